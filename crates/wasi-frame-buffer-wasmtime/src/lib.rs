@@ -6,12 +6,15 @@ use raw_window_handle::{DisplayHandle, WindowHandle};
 use wasmtime::component::Resource;
 use wasmtime_wasi::WasiView;
 
-use crate::wasi::webgpu::frame_buffer;
-use wasi_graphics_context_wasmtime::{DisplayApi, DrawApi, GraphicsContext, GraphicsContextBuffer};
+use crate::wasi::webgpu::frame_buffer::{self, AlphaMode, ScalingMode};
+use wasi_graphics_context_wasmtime::{
+    Color, DisplayApi, DrawApi, GraphicsContext, GraphicsContextBuffer, PresentResult,
+};
 
 wasmtime::component::bindgen!({
     path: "../../wit/",
     world: "example",
+    tracing: true,
     async: {
         only_imports: [],
     },
@@ -24,18 +27,155 @@ wasmtime::component::bindgen!({
 
 pub struct FBSurface {
     pub(crate) surface: Option<softbuffer::Surface<DisplayHandle<'static>, WindowHandle<'static>>>,
+    window_size: (u32, u32),
+    scaled: Option<ScaledBuffer>,
+    alpha_mode: AlphaMode,
+}
+
+/// The largest side `set-buffer-size` takes, so a guest can't have the host
+/// allocate gigabytes of pixels.
+pub const MAX_BUFFER_SIZE: u32 = 16384;
+
+/// Turns `pixel` into the 0RGB softbuffer wants, composited over black.
+fn flatten(pixel: u32, alpha_mode: AlphaMode) -> u32 {
+    let alpha = pixel >> 24;
+    let channel = |shift: u32| {
+        let c = (pixel >> shift) & 0xff;
+        let c = match alpha_mode {
+            AlphaMode::Opaque => c,
+            AlphaMode::Straight => (c * alpha + 127) / 255,
+            AlphaMode::Premultiplied => c.min(alpha),
+        };
+        c << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+/// A frame buffer with its own size, that gets scaled to the window on present.
+struct ScaledBuffer {
+    width: u32,
+    height: u32,
+    scaling: ScalingMode,
+    background: u32,
+    pixels: Arc<Mutex<Vec<u32>>>,
+}
+
+impl ScaledBuffer {
+    /// Copies the buffer into `target`, a `target_width` x `target_height` window buffer.
+    fn blit(
+        &self,
+        target: &mut [u32],
+        target_width: u32,
+        target_height: u32,
+        alpha_mode: AlphaMode,
+    ) {
+        let (dest_width, dest_height) = self.dest_size(target_width, target_height);
+        // Can be negative in `Center` mode, when the buffer is larger than the window.
+        let dest_x = (target_width as i64 - dest_width as i64) / 2;
+        let dest_y = (target_height as i64 - dest_height as i64) / 2;
+
+        let pixels = self.pixels.lock().unwrap();
+        for y in 0..target_height {
+            for x in 0..target_width {
+                let local_x = x as i64 - dest_x;
+                let local_y = y as i64 - dest_y;
+                let inside = (0..dest_width as i64).contains(&local_x)
+                    && (0..dest_height as i64).contains(&local_y);
+                let color = if inside {
+                    // Nearest neighbour.
+                    let src_x = local_x as u64 * self.width as u64 / dest_width as u64;
+                    let src_y = local_y as u64 * self.height as u64 / dest_height as u64;
+                    flatten(
+                        pixels[(src_y * self.width as u64 + src_x) as usize],
+                        alpha_mode,
+                    )
+                } else {
+                    self.background
+                };
+                if let Some(pixel) = target.get_mut((y * target_width + x) as usize) {
+                    *pixel = color;
+                }
+            }
+        }
+    }
+
+    /// The size the buffer is drawn at in a `target_width` x `target_height` window.
+    fn dest_size(&self, target_width: u32, target_height: u32) -> (u32, u32) {
+        match self.scaling {
+            ScalingMode::Stretch => (target_width, target_height),
+            ScalingMode::PreserveAspect => {
+                let scale = f64::min(
+                    target_width as f64 / self.width as f64,
+                    target_height as f64 / self.height as f64,
+                );
+                (
+                    (self.width as f64 * scale) as u32,
+                    (self.height as f64 * scale) as u32,
+                )
+            }
+            ScalingMode::Center => (self.width, self.height),
+        }
+    }
 }
 // TODO: actually ensure safety
 unsafe impl Send for FBSurface {}
 unsafe impl Sync for FBSurface {}
+impl Default for FBSurface {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FBSurface {
     pub fn new() -> Self {
-        Self { surface: None }
+        Self {
+            surface: None,
+            window_size: (0, 0),
+            scaled: None,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
+    pub fn set_buffer_size(
+        &mut self,
+        width: u32,
+        height: u32,
+        scaling: ScalingMode,
+        background: u32,
+    ) -> Result<(), String> {
+        for (name, size) in [("width", width), ("height", height)] {
+            if !(1..=MAX_BUFFER_SIZE).contains(&size) {
+                return Err(format!(
+                    "buffer {name} of {size} has to be from 1 to {MAX_BUFFER_SIZE}"
+                ));
+            }
+        }
+        self.scaled = Some(ScaledBuffer {
+            width,
+            height,
+            scaling,
+            background,
+            pixels: Arc::new(Mutex::new(vec![
+                background;
+                width as usize * height as usize
+            ])),
+        });
+        Ok(())
     }
 }
 
 // TODO: can we avoid the Mutex here?
 pub struct FBSurfaceArc(pub Arc<Mutex<FBSurface>>);
+impl Default for FBSurfaceArc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FBSurfaceArc {
     pub fn new() -> Self {
         FBSurfaceArc(Arc::new(Mutex::new(FBSurface::new())))
@@ -46,13 +186,25 @@ impl DrawApi for FBSurfaceArc {
         self.0.lock().unwrap().get_current_buffer()
     }
 
-    fn present(&mut self) -> wasmtime::Result<()> {
+    fn present(&mut self) -> wasmtime::Result<PresentResult> {
         self.0.lock().unwrap().present()
     }
 
-    fn display_api_ready(&mut self, display_api: &Box<dyn DisplayApi + Send + Sync>) {
+    fn display_api_ready(&mut self, display_api: &(dyn DisplayApi + Send + Sync)) {
         self.0.lock().unwrap().display_api_ready(display_api)
     }
+
+    fn display_api_released(&mut self) {
+        self.0.lock().unwrap().display_api_released()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.0.lock().unwrap().resize(width, height)
+    }
+
+    fn clear(&mut self, color: Color) -> wasmtime::Result<()> {
+        self.0.lock().unwrap().clear(color)
+    }
 }
 
 // impl Surface {
@@ -63,6 +215,11 @@ impl DrawApi for FBSurfaceArc {
 
 impl DrawApi for FBSurface {
     fn get_current_buffer(&mut self) -> wasmtime::Result<GraphicsContextBuffer> {
+        if let Some(scaled) = &self.scaled {
+            let buff = FBBuffer::new(FBBufferData::Scaled(Arc::clone(&scaled.pixels)));
+            let buff: GraphicsContextBuffer = Box::new(buff).into();
+            return Ok(buff);
+        }
         let surface = self.surface.as_mut().unwrap();
         let buff = surface.buffer_mut().unwrap();
         // TODO: use ouroboros?
@@ -74,18 +231,46 @@ impl DrawApi for FBSurface {
         Ok(buff)
     }
 
-    fn present(&mut self) -> wasmtime::Result<()> {
-        self.surface
-            .as_mut()
-            .unwrap()
-            .buffer_mut()
-            .unwrap()
-            .present()
-            .unwrap();
-        Ok(())
+    fn present(&mut self) -> wasmtime::Result<PresentResult> {
+        let mut buffer = self.surface.as_mut().unwrap().buffer_mut().unwrap();
+        match &self.scaled {
+            Some(scaled) => {
+                let (width, height) = self.window_size;
+                scaled.blit(&mut buffer, width, height, self.alpha_mode);
+            }
+            None if self.alpha_mode != AlphaMode::Opaque => {
+                for pixel in buffer.iter_mut() {
+                    *pixel = flatten(*pixel, self.alpha_mode);
+                }
+            }
+            None => {}
+        }
+        Ok(match buffer.present() {
+            Ok(()) => PresentResult::Presented,
+            Err(e) => PresentResult::Failed(e.to_string()),
+        })
     }
 
-    fn display_api_ready(&mut self, display: &Box<dyn DisplayApi + Send + Sync>) {
+    fn resize(&mut self, width: u32, height: u32) {
+        // Kept even while there's no surface or the window is minimized, so the next
+        // present blits to the right size.
+        self.window_size = (width, height);
+        let (Some(surface), Some(width_nz), Some(height_nz)) = (
+            &mut self.surface,
+            NonZeroU32::new(width),
+            NonZeroU32::new(height),
+        ) else {
+            return;
+        };
+        let _ = surface.resize(width_nz, height_nz);
+    }
+
+    fn display_api_released(&mut self) {
+        // The surface borrows the display's handles, so it has to go first.
+        self.surface = None;
+    }
+
+    fn display_api_ready(&mut self, display: &(dyn DisplayApi + Send + Sync)) {
         let context = softbuffer::Context::new(display.display_handle().unwrap()).unwrap();
         let surface = softbuffer::Surface::new(&context, display.window_handle().unwrap()).unwrap();
 
@@ -94,32 +279,70 @@ impl DrawApi for FBSurface {
             unsafe { mem::transmute(surface) };
 
         // softbuffer requires setting the size before presenting.
-        let _ = surface.resize(
-            display
-                .width()
-                .try_into()
-                .unwrap_or(NonZeroU32::new(1).unwrap()),
-            display
-                .height()
-                .try_into()
-                .unwrap_or(NonZeroU32::new(1).unwrap()),
-        );
+        let width = display
+            .width()
+            .try_into()
+            .unwrap_or(NonZeroU32::new(1).unwrap());
+        let height = display
+            .height()
+            .try_into()
+            .unwrap_or(NonZeroU32::new(1).unwrap());
+        let _ = surface.resize(width, height);
+        self.window_size = (width.get(), height.get());
         self.surface = Some(surface);
     }
+
+    fn clear(&mut self, color: Color) -> wasmtime::Result<()> {
+        // Frame buffer pixels are 0RGB, and can't be transparent.
+        let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+        let pixel = channel(color.r) << 16 | channel(color.g) << 8 | channel(color.b);
+        let surface = self
+            .surface
+            .as_mut()
+            .ok_or_else(|| wasmtime::Error::msg("frame buffer has no surface yet"))?;
+        let mut buffer = surface
+            .buffer_mut()
+            .map_err(|e| wasmtime::Error::msg(format!("can't get frame buffer: {e}")))?;
+        buffer.fill(pixel);
+        buffer
+            .present()
+            .map_err(|e| wasmtime::Error::msg(format!("can't present frame buffer: {e}")))?;
+        Ok(())
+    }
 }
 
 pub struct FBBuffer {
     // Never none
-    buffer: Arc<Mutex<Option<softbuffer::Buffer<'static, GraphicsContext, GraphicsContext>>>>,
+    buffer: Arc<Mutex<Option<FBBufferData>>>,
+}
+
+enum FBBufferData {
+    Surface(softbuffer::Buffer<'static, GraphicsContext, GraphicsContext>),
+    Scaled(Arc<Mutex<Vec<u32>>>),
+}
+
+impl FBBuffer {
+    // Send and Sync are implemented manually on FBBuffer above.
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn new(data: FBBufferData) -> Self {
+        FBBuffer {
+            buffer: Arc::new(Mutex::new(Some(data))),
+        }
+    }
+
+    fn with_pixels<R>(&self, f: impl FnOnce(&mut [u32]) -> R) -> R {
+        match self.buffer.lock().unwrap().as_mut().unwrap() {
+            FBBufferData::Surface(buffer) => f(buffer),
+            FBBufferData::Scaled(pixels) => f(&mut pixels.lock().unwrap()),
+        }
+    }
 }
 // TODO: ensure safety
 unsafe impl Send for FBBuffer {}
 unsafe impl Sync for FBBuffer {}
 impl From<softbuffer::Buffer<'static, GraphicsContext, GraphicsContext>> for FBBuffer {
     fn from(buffer: softbuffer::Buffer<'static, GraphicsContext, GraphicsContext>) -> Self {
-        FBBuffer {
-            buffer: Arc::new(Mutex::new(Some(buffer))),
-        }
+        FBBuffer::new(FBBufferData::Surface(buffer))
     }
 }
 
@@ -158,6 +381,27 @@ impl frame_buffer::HostSurface for dyn WasiFrameBufferView + '_ {
         graphics_context.connect_draw_api(Box::new(surface));
     }
 
+    fn set_buffer_size(
+        &mut self,
+        surface: Resource<FBSurfaceArc>,
+        width: u32,
+        height: u32,
+        scaling: ScalingMode,
+        background: u32,
+    ) -> Result<(), String> {
+        let surface = self.table().get(&surface).unwrap();
+        surface
+            .0
+            .lock()
+            .unwrap()
+            .set_buffer_size(width, height, scaling, background)
+    }
+
+    fn set_alpha_mode(&mut self, surface: Resource<FBSurfaceArc>, mode: AlphaMode) {
+        let surface = self.table().get(&surface).unwrap();
+        surface.0.lock().unwrap().set_alpha_mode(mode);
+    }
+
     fn drop(&mut self, _rep: Resource<FBSurfaceArc>) -> wasmtime::Result<()> {
         todo!()
     }
@@ -175,25 +419,18 @@ impl frame_buffer::HostFrameBuffer for dyn WasiFrameBufferView + '_ {
 
     fn length(&mut self, buffer: Resource<FBBuffer>) -> u32 {
         let buffer = self.table().get(&buffer).unwrap();
-        let len = buffer.buffer.lock().unwrap().as_ref().unwrap().len();
+        let len = buffer.with_pixels(|pixels| pixels.len());
         len as u32
     }
 
     fn get(&mut self, buffer: Resource<FBBuffer>, i: u32) -> u32 {
         let buffer = self.table().get(&buffer).unwrap();
-        *buffer
-            .buffer
-            .lock()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .get(i as usize)
-            .unwrap()
+        buffer.with_pixels(|pixels| *pixels.get(i as usize).unwrap())
     }
 
     fn set(&mut self, buffer: Resource<FBBuffer>, i: u32, val: u32) {
         let buffer = self.table().get_mut(&buffer).unwrap();
-        buffer.buffer.lock().unwrap().as_mut().unwrap()[i as usize] = val as u32;
+        buffer.with_pixels(|pixels| pixels[i as usize] = val);
     }
 
     fn drop(&mut self, frame_buffer: Resource<FBBuffer>) -> wasmtime::Result<()> {
@@ -202,3 +439,93 @@ impl frame_buffer::HostFrameBuffer for dyn WasiFrameBufferView + '_ {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: u32 = 0x00ff00;
+
+    /// A `width` x `height` buffer with every pixel set to its own index.
+    fn buffer(width: u32, height: u32, scaling: ScalingMode) -> ScaledBuffer {
+        ScaledBuffer {
+            width,
+            height,
+            scaling,
+            background: BACKGROUND,
+            pixels: Arc::new(Mutex::new((0..width * height).collect())),
+        }
+    }
+
+    fn blit(buffer: &ScaledBuffer, width: u32, height: u32) -> Vec<u32> {
+        let mut target = vec![0xdead; (width * height) as usize];
+        buffer.blit(&mut target, width, height, AlphaMode::Opaque);
+        target
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        let buffer = buffer(2, 1, ScalingMode::Stretch);
+        assert_eq!(buffer.dest_size(4, 3), (4, 3));
+        assert_eq!(blit(&buffer, 4, 2), [0, 0, 1, 1, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn preserve_aspect_adds_bars() {
+        let buffer = buffer(2, 1, ScalingMode::PreserveAspect);
+        // Too tall for the buffer, so there are bars above and below.
+        assert_eq!(buffer.dest_size(4, 4), (4, 2));
+        let b = BACKGROUND;
+        #[rustfmt::skip]
+        assert_eq!(blit(&buffer, 4, 4), [
+            b, b, b, b,
+            0, 0, 1, 1,
+            0, 0, 1, 1,
+            b, b, b, b,
+        ]);
+    }
+
+    #[test]
+    fn center_crops_buffers_larger_than_the_window() {
+        let buffer = buffer(4, 1, ScalingMode::Center);
+        assert_eq!(buffer.dest_size(2, 1), (4, 1));
+        assert_eq!(blit(&buffer, 2, 1), [1, 2]);
+        let b = BACKGROUND;
+        assert_eq!(blit(&buffer, 6, 1), [b, 0, 1, 2, 3, b]);
+    }
+
+    #[test]
+    fn blitting_to_an_empty_window_does_nothing() {
+        let buffer = buffer(2, 2, ScalingMode::PreserveAspect);
+        assert!(blit(&buffer, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn buffer_size_has_to_be_in_range() {
+        let mut surface = FBSurface::new();
+        let set = |surface: &mut FBSurface, width, height| {
+            surface.set_buffer_size(width, height, ScalingMode::Stretch, 0)
+        };
+        assert!(set(&mut surface, 0, 10).is_err());
+        assert!(set(&mut surface, 10, 0).is_err());
+        assert!(set(&mut surface, MAX_BUFFER_SIZE + 1, 10).is_err());
+        assert!(surface.scaled.is_none());
+        assert!(set(&mut surface, MAX_BUFFER_SIZE, 1).is_ok());
+        assert!(surface.scaled.is_some());
+    }
+
+    #[test]
+    fn semi_transparent_pixels_are_composited_over_black() {
+        // Half transparent orange, as straight and as premultiplied alpha.
+        let straight = 0x80ff4000;
+        let premultiplied = 0x80802000;
+        assert_eq!(flatten(straight, AlphaMode::Straight), 0x802000);
+        assert_eq!(flatten(premultiplied, AlphaMode::Premultiplied), 0x802000);
+        // Premultiplied channels can't be brighter than alpha.
+        assert_eq!(flatten(0x40ff0000, AlphaMode::Premultiplied), 0x400000);
+        assert_eq!(flatten(0xff123456, AlphaMode::Straight), 0x123456);
+        assert_eq!(flatten(0x00123456, AlphaMode::Straight), 0);
+        // Opaque buffers don't have an alpha channel to go by.
+        assert_eq!(flatten(0x00123456, AlphaMode::Opaque), 0x123456);
+    }
+}
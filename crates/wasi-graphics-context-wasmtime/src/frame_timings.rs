@@ -0,0 +1,226 @@
+use std::{fmt, io, sync::Mutex, time::Duration};
+
+/// Frame timings collected while profiling, shared by all graphics contexts.
+#[derive(Default)]
+pub struct FrameTimings {
+    present_intervals: Mutex<TimingStats>,
+    gpu_times: Mutex<TimingStats>,
+    input_latencies: Mutex<TimingStats>,
+    // Each present interval in order, one per frame, for `write_csv`.
+    frame_times: Mutex<Vec<Duration>>,
+    frame_limit: Option<FrameLimit>,
+}
+
+struct FrameLimit {
+    frames: usize,
+    on_reached: Box<dyn Fn() + Send + Sync>,
+}
+
+impl FrameTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only records the first `frames` frames, and calls `on_reached` once
+    /// the last of them is recorded.
+    pub fn with_frame_limit(frames: usize, on_reached: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            frame_limit: Some(FrameLimit {
+                frames,
+                on_reached: Box::new(on_reached),
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Time between two presents of the same graphics context.
+    pub fn record_present_interval(&self, interval: Duration) {
+        let frames = {
+            let mut frame_times = self.frame_times.lock().unwrap();
+            if let Some(limit) = &self.frame_limit {
+                if frame_times.len() >= limit.frames {
+                    return;
+                }
+            }
+            frame_times.push(interval);
+            frame_times.len()
+        };
+        self.present_intervals.lock().unwrap().record(interval);
+        if let Some(limit) = self.frame_limit.as_ref().filter(|l| l.frames == frames) {
+            (limit.on_reached)();
+        }
+    }
+
+    /// Time from a queue submit until the GPU finished its work.
+    pub fn record_gpu_time(&self, time: Duration) {
+        self.gpu_times.lock().unwrap().record(time);
+    }
+
+    /// Time from input arriving to the present of the frame reflecting it.
+    pub fn record_input_latency(&self, latency: Duration) {
+        self.input_latencies.lock().unwrap().record(latency);
+    }
+
+    /// A table of the timings recorded so far.
+    pub fn summary(&self) -> FrameTimingsSummary {
+        FrameTimingsSummary {
+            present_intervals: *self.present_intervals.lock().unwrap(),
+            gpu_times: *self.gpu_times.lock().unwrap(),
+            input_latencies: *self.input_latencies.lock().unwrap(),
+        }
+    }
+
+    /// Writes a `frame,ms` row per frame recorded so far, with the time from
+    /// the present before it, for looking at the distribution offline.
+    pub fn write_csv(&self, mut out: impl io::Write) -> io::Result<()> {
+        writeln!(out, "frame,ms")?;
+        for (frame, time) in self.frame_times.lock().unwrap().iter().enumerate() {
+            writeln!(out, "{frame},{:.3}", time.as_secs_f64() * 1000.0)?;
+        }
+        out.flush()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimingStats {
+    pub count: u64,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl TimingStats {
+    fn record(&mut self, time: Duration) {
+        if self.count == 0 || time < self.min {
+            self.min = time;
+        }
+        self.max = self.max.max(time);
+        self.total += time;
+        self.count += 1;
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total.div_f64(self.count as f64))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FrameTimingsSummary {
+    pub present_intervals: TimingStats,
+    pub gpu_times: TimingStats,
+    pub input_latencies: TimingStats,
+}
+
+impl fmt::Display for FrameTimingsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "{:<22}{:>10}{:>10}{:>10}{:>10}",
+            "frame timings (ms)", "samples", "avg", "min", "max"
+        )?;
+        for (name, stats) in [
+            ("present to present", self.present_intervals),
+            ("submit to work done", self.gpu_times),
+            ("input to present", self.input_latencies),
+        ] {
+            match stats.average() {
+                Some(average) => writeln!(
+                    f,
+                    "{name:<22}{:>10}{:>10.2}{:>10.2}{:>10.2}",
+                    stats.count,
+                    ms(average),
+                    ms(stats.min),
+                    ms(stats.max)
+                )?,
+                None => writeln!(f, "{name:<22}{:>10}{:>10}{:>10}{:>10}", 0, "-", "-", "-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn stats_track_min_max_and_average() {
+        let mut stats = TimingStats::default();
+        assert_eq!(stats.average(), None);
+        for time in [ms(20), ms(10), ms(30)] {
+            stats.record(time);
+        }
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min, ms(10));
+        assert_eq!(stats.max, ms(30));
+        assert_eq!(stats.average(), Some(ms(20)));
+    }
+
+    #[test]
+    fn frame_limit_stops_recording_and_fires_once() {
+        let reached = Arc::new(AtomicUsize::new(0));
+        let timings = FrameTimings::with_frame_limit(3, {
+            let reached = Arc::clone(&reached);
+            move || {
+                reached.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        for _ in 0..5 {
+            timings.record_present_interval(ms(16));
+        }
+        assert_eq!(reached.load(Ordering::Relaxed), 1);
+        assert_eq!(timings.summary().present_intervals.count, 3);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_recorded_frame() {
+        let timings = FrameTimings::with_frame_limit(5, || {});
+        for frame in 1..=7 {
+            timings.record_present_interval(ms(frame));
+        }
+        let mut csv = Vec::new();
+        timings.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("frame,ms"));
+        let rows: Vec<_> = lines.collect();
+        assert_eq!(
+            rows,
+            ["0,1.000", "1,2.000", "2,3.000", "3,4.000", "4,5.000"]
+        );
+    }
+
+    #[test]
+    fn summary_has_a_row_per_timing() {
+        let timings = FrameTimings::new();
+        timings.record_present_interval(ms(16));
+        timings.record_present_interval(ms(18));
+        timings.record_gpu_time(ms(4));
+        let summary = timings.summary().to_string();
+        let rows: Vec<_> = summary.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[1].starts_with("present to present"));
+        assert_eq!(
+            rows[1].split_whitespace().skip(3).collect::<Vec<_>>(),
+            ["2", "17.00", "16.00", "18.00"]
+        );
+        assert_eq!(
+            rows[2].split_whitespace().skip(4).collect::<Vec<_>>(),
+            ["1", "4.00", "4.00", "4.00"]
+        );
+        assert_eq!(
+            rows[3].split_whitespace().skip(3).collect::<Vec<_>>(),
+            ["0", "-", "-", "-"]
+        );
+    }
+}
@@ -1,15 +1,28 @@
-use std::any::Any;
+use std::{
+    any::Any,
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::wasi::webgpu::graphics_context::{self, ConfigureContextDesc};
+pub use crate::wasi::webgpu::graphics_context::{
+    Color, ColorSpace, CompositeAlphaMode, PresentResult,
+};
 use raw_window_handle::{
     DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
 };
 use wasmtime::component::Resource;
 use wasmtime_wasi::WasiView;
 
+pub use crate::frame_timings::{FrameTimings, FrameTimingsSummary, TimingStats};
+
+mod frame_timings;
+
 wasmtime::component::bindgen!({
     path: "../../wit/",
     world: "example",
+    tracing: true,
     async: false,
     with: {
         "wasi:webgpu/graphics-context/graphics-context": GraphicsContext,
@@ -17,9 +30,39 @@ wasmtime::component::bindgen!({
     },
 });
 
+/// How many of the latest input latencies `input_latency` averages.
+const INPUT_LATENCY_WINDOW: usize = 60;
+
 pub struct GraphicsContext {
     draw_api: Option<Box<dyn DrawApi + Send + Sync>>,
     display_api: Option<Box<dyn DisplayApi + Send + Sync>>,
+    clear_color: Option<Color>,
+    /// Color space asked for, applied to draw apis as they connect.
+    color_space: ColorSpace,
+    has_presented: bool,
+    /// Extra displays presented frames are mirrored to.
+    mirrors: Vec<Mirror>,
+    last_present: Option<Instant>,
+    /// Time from input to the present of the frame reflecting it, newest last.
+    input_latencies: VecDeque<Duration>,
+    /// Whether the draw api follows the display's size on its own.
+    auto_reconfigure: bool,
+    /// The display size the draw api was last resized to.
+    display_size: Option<(u32, u32)>,
+}
+
+/// A display frames are mirrored to, see `GraphicsContext::attach_display_api`.
+struct Mirror {
+    key: usize,
+    display_api: Box<dyn DisplayApi + Send + Sync>,
+    /// The display size the draw api last saw for it.
+    size: (u32, u32),
+}
+
+impl Default for GraphicsContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GraphicsContext {
@@ -27,6 +70,38 @@ impl GraphicsContext {
         Self {
             display_api: None,
             draw_api: None,
+            clear_color: None,
+            color_space: ColorSpace::Srgb,
+            has_presented: false,
+            mirrors: Vec::new(),
+            last_present: None,
+            input_latencies: VecDeque::new(),
+            auto_reconfigure: true,
+            display_size: None,
+        }
+    }
+
+    pub fn with_clear_color(clear_color: Option<Color>) -> Self {
+        Self {
+            clear_color,
+            ..Self::new()
+        }
+    }
+
+    pub fn set_default_clear_color(&mut self, color: Option<Color>) {
+        self.clear_color = color;
+        self.clear_if_not_presented();
+    }
+
+    /// Shows the clear color until the guest presents its first frame.
+    fn clear_if_not_presented(&mut self) {
+        if self.has_presented || self.display_api.is_none() {
+            return;
+        }
+        if let (Some(color), Some(draw_api)) = (self.clear_color, &mut self.draw_api) {
+            if let Err(e) = draw_api.clear(color) {
+                tracing::warn!("can't show the clear color: {e}");
+            }
         }
     }
 
@@ -34,11 +109,229 @@ impl GraphicsContext {
         Ok(())
     }
 
+    /// Renders to `display_api` from now on. A display connected before is let go
+    /// of, and shows whatever it last got.
     pub fn connect_display_api(&mut self, display_api: Box<dyn DisplayApi + Send + Sync>) {
         if let Some(draw_api) = &mut self.draw_api {
-            draw_api.display_api_ready(&display_api)
+            if self.display_api.is_some() {
+                draw_api.display_api_released();
+            }
+            draw_api.display_api_ready(display_api.as_ref())
         }
         self.display_api = Some(display_api);
+        self.display_size = None;
+        // The new display still needs clearing until something is presented to it.
+        self.has_presented = false;
+        self.clear_if_not_presented();
+    }
+
+    /// With auto reconfigure off, the draw api keeps its size when the display
+    /// resizes, until it's reconfigured with `reconfigure`.
+    pub fn set_auto_reconfigure(&mut self, enabled: bool) {
+        self.auto_reconfigure = enabled;
+        // Catches up with resizes that happened while it was off.
+        self.display_size = None;
+    }
+
+    /// Resizes the draw api if the display changed size since it last was, and
+    /// auto reconfigure is on.
+    fn follow_display_size(&mut self) {
+        let (Some(display_api), Some(draw_api)) = (&self.display_api, &mut self.draw_api) else {
+            return;
+        };
+        if !self.auto_reconfigure {
+            return;
+        }
+        let size = (display_api.width(), display_api.height());
+        if self.display_size == Some(size) {
+            return;
+        }
+        self.display_size = Some(size);
+        // Minimized windows are 0x0, which surfaces can't be.
+        if size.0 > 0 && size.1 > 0 {
+            draw_api.resize(size.0, size.1);
+        }
+    }
+
+    /// Reconfigures the draw api's surface with a configuration it understands,
+    /// e.g. a webgpu surface configuration.
+    pub fn reconfigure(&mut self, configuration: Box<dyn Any + Send + Sync>) -> Result<(), String> {
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.reconfigure(configuration),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    pub fn set_buffer_count(&mut self, count: u32) -> Result<(), String> {
+        if !(2..=3).contains(&count) {
+            return Err(format!("buffer count must be 2 or 3, got {count}"));
+        }
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_buffer_count(count),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    pub fn set_desired_maximum_frame_latency(&mut self, frames: u32) -> Result<(), String> {
+        if frames == 0 {
+            return Err("frame latency must be at least 1".to_string());
+        }
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_desired_maximum_frame_latency(frames),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    /// Sets the resolution frames are rendered at, relative to the window.
+    /// Clamped to 0.1..=2.0. Takes effect from the next frame, so the one
+    /// being drawn is still presented at the old scale.
+    pub fn set_render_scale(&mut self, scale: f64) -> Result<(), String> {
+        if !scale.is_finite() {
+            return Err(format!("render scale must be a finite number, got {scale}"));
+        }
+        let scale = scale.clamp(0.1, 2.0);
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_render_scale(scale),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    /// Renders frames with `count` samples per pixel, resolving them on present.
+    /// 1 turns multisampling off.
+    pub fn set_sample_count(&mut self, count: u32) -> Result<(), String> {
+        if !matches!(count, 1 | 2 | 4 | 8) {
+            return Err(format!("sample count must be 1, 2, 4 or 8, got {count}"));
+        }
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_sample_count(count),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    /// Shows frames in `space` if the draw api's surface supports it, or sRGB
+    /// if not. Returns the color space chosen.
+    pub fn set_color_space(&mut self, space: ColorSpace) -> Result<ColorSpace, String> {
+        self.color_space = space;
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_color_space(space),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    /// Also shows presented frames on `display_api`, scaled to its size, until
+    /// it's detached with the same `key`.
+    pub fn attach_display_api(
+        &mut self,
+        key: usize,
+        display_api: Box<dyn DisplayApi + Send + Sync>,
+    ) -> Result<(), String> {
+        if self.mirrors.iter().any(|mirror| mirror.key == key) {
+            return Err("display is already attached to this graphics context".to_string());
+        }
+        if self.display_api.is_none() {
+            return Err("graphics context has no display api connected".to_string());
+        }
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.attach_display_api(key, display_api.as_ref())?,
+            None => return Err("graphics context has no draw api connected".to_string()),
+        }
+        let size = (display_api.width(), display_api.height());
+        self.mirrors.push(Mirror {
+            key,
+            display_api,
+            size,
+        });
+        Ok(())
+    }
+
+    pub fn detach_display_api(&mut self, key: usize) -> Result<(), String> {
+        let Some(index) = self.mirrors.iter().position(|mirror| mirror.key == key) else {
+            return Err("display isn't attached to this graphics context".to_string());
+        };
+        if let Some(draw_api) = &mut self.draw_api {
+            draw_api.detach_display_api(key)?;
+        }
+        self.mirrors.remove(index);
+        Ok(())
+    }
+
+    /// Stops mirroring to displays the guest let go of, and resizes the
+    /// mirrors of the others that changed size.
+    fn follow_mirrors(&mut self) {
+        let Some(draw_api) = &mut self.draw_api else {
+            return;
+        };
+        self.mirrors.retain_mut(|mirror| {
+            if mirror.display_api.released() {
+                if let Err(e) = draw_api.detach_display_api(mirror.key) {
+                    tracing::warn!("can't stop mirroring to a released display: {e}");
+                }
+                return false;
+            }
+            let size = (mirror.display_api.width(), mirror.display_api.height());
+            // Minimized windows are 0x0, which surfaces can't be.
+            if mirror.size != size && size.0 > 0 && size.1 > 0 {
+                mirror.size = size;
+                draw_api.resize_display_api(mirror.key, size.0, size.1);
+            }
+            true
+        });
+    }
+
+    /// Copies every presented frame into `buffer`, a buffer of the connected
+    /// draw api, or stops copying if `None`.
+    pub fn set_frame_readback(
+        &mut self,
+        buffer: Option<Box<dyn Any + Send + Sync>>,
+    ) -> Result<(), String> {
+        match &mut self.draw_api {
+            Some(draw_api) => draw_api.set_frame_readback(buffer),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
+    }
+
+    /// Average time from input arriving to the frame reflecting it being
+    /// presented, over the last few frames that had input.
+    pub fn input_latency(&self) -> Option<Duration> {
+        let count = self.input_latencies.len() as u32;
+        (count > 0).then(|| self.input_latencies.iter().sum::<Duration>() / count)
+    }
+
+    fn record_input_latency(&mut self, latency: Duration) {
+        if self.input_latencies.len() == INPUT_LATENCY_WINDOW {
+            self.input_latencies.pop_front();
+        }
+        self.input_latencies.push_back(latency);
+    }
+
+    /// Lets the display know a frame was presented, and records the time
+    /// since the input it reflects and since the last present.
+    fn presented(&mut self, frame_timings: Option<&FrameTimings>) {
+        let now = Instant::now();
+        let input_arrival = self.display_api.as_ref().and_then(|display_api| {
+            display_api.presented();
+            display_api.take_input_arrival()
+        });
+        if let Some(arrival) = input_arrival {
+            let latency = now.saturating_duration_since(arrival);
+            self.record_input_latency(latency);
+            if let Some(frame_timings) = frame_timings {
+                frame_timings.record_input_latency(latency);
+            }
+        }
+        self.has_presented = true;
+        if let (Some(frame_timings), Some(last_present)) = (frame_timings, self.last_present) {
+            frame_timings.record_present_interval(now - last_present);
+        }
+        self.last_present = Some(now);
+    }
+
+    /// What the draw api's surface supports, in the draw api's own types.
+    pub fn surface_capabilities(&self) -> Result<Box<dyn Any + Send + Sync>, String> {
+        match &self.draw_api {
+            Some(draw_api) => draw_api.surface_capabilities(),
+            None => Err("graphics context has no draw api connected".to_string()),
+        }
     }
 
     // pub fn resize(&mut self, height: u32, width: u32) {
@@ -47,10 +340,17 @@ impl GraphicsContext {
     // }
 
     pub fn connect_draw_api(&mut self, mut draw_api: Box<dyn DrawApi + Send + Sync>) {
+        if self.color_space != ColorSpace::Srgb {
+            // Picked before the display is ready, so the first configuration has it.
+            if let Err(e) = draw_api.set_color_space(self.color_space) {
+                tracing::warn!("falling back to srgb: {e}");
+            }
+        }
         if let Some(display_api) = &self.display_api {
-            draw_api.display_api_ready(&*display_api)
+            draw_api.display_api_ready(display_api.as_ref())
         }
         self.draw_api = Some(draw_api);
+        self.clear_if_not_presented();
     }
 }
 
@@ -75,13 +375,82 @@ impl HasWindowHandle for GraphicsContext {
 // TODO: rename to FrameProvider? since this isn't necessarily implemented on the whole api?
 pub trait DrawApi {
     fn get_current_buffer(&mut self) -> wasmtime::Result<GraphicsContextBuffer>;
-    fn present(&mut self) -> wasmtime::Result<()>;
-    fn display_api_ready(&mut self, display_api: &Box<dyn DisplayApi + Send + Sync>);
+    /// Errors are reported to the guest as `PresentResult::Failed`.
+    fn present(&mut self) -> wasmtime::Result<PresentResult>;
+    fn display_api_ready(&mut self, display_api: &(dyn DisplayApi + Send + Sync));
+    /// The display from `display_api_ready` is being replaced by another one, and
+    /// anything made for it should be let go of.
+    fn display_api_released(&mut self) {}
+    /// The display is now `width` x `height`, and the context follows its size.
+    fn resize(&mut self, _width: u32, _height: u32) {}
+    /// Applies a configuration from the api the draw api belongs to.
+    fn reconfigure(&mut self, _configuration: Box<dyn Any + Send + Sync>) -> Result<(), String> {
+        Err("surface can't be reconfigured for this draw api".to_string())
+    }
+    fn set_buffer_count(&mut self, _count: u32) -> Result<(), String> {
+        Err("buffer count can't be changed for this draw api".to_string())
+    }
+    fn set_desired_maximum_frame_latency(&mut self, _frames: u32) -> Result<(), String> {
+        Err("frame latency can't be changed for this draw api".to_string())
+    }
+    /// Renders frames at `scale` times the window size, scaling them to fit on present.
+    fn set_render_scale(&mut self, _scale: f64) -> Result<(), String> {
+        Err("render scale can't be changed for this draw api".to_string())
+    }
+    /// Renders frames with `count` samples per pixel, resolving them on present.
+    fn set_sample_count(&mut self, _count: u32) -> Result<(), String> {
+        Err("multisampling isn't supported by this draw api".to_string())
+    }
+    /// Also presents frames to `display_api`, until detached with the same `key`.
+    fn attach_display_api(
+        &mut self,
+        _key: usize,
+        _display_api: &(dyn DisplayApi + Send + Sync),
+    ) -> Result<(), String> {
+        Err("frames can't be mirrored for this draw api".to_string())
+    }
+    fn detach_display_api(&mut self, _key: usize) -> Result<(), String> {
+        Err("frames can't be mirrored for this draw api".to_string())
+    }
+    /// The display attached with `key` is now `width` x `height`.
+    fn resize_display_api(&mut self, _key: usize, _width: u32, _height: u32) {}
+    /// Shows frames in `space`, or sRGB if unsupported. Returns the color space
+    /// chosen. Until a display is ready, it's the one asked for.
+    fn set_color_space(&mut self, _space: ColorSpace) -> Result<ColorSpace, String> {
+        Err("color space can't be changed for this draw api".to_string())
+    }
+    /// Copies every presented frame into `buffer`, or stops copying if `None`.
+    fn set_frame_readback(
+        &mut self,
+        _buffer: Option<Box<dyn Any + Send + Sync>>,
+    ) -> Result<(), String> {
+        Err("frames can't be read back for this draw api".to_string())
+    }
+    /// Formats, present modes and so on the surface supports.
+    fn surface_capabilities(&self) -> Result<Box<dyn Any + Send + Sync>, String> {
+        Err("surface capabilities can't be queried for this draw api".to_string())
+    }
+    /// Fills the next frame with `color` and presents it.
+    fn clear(&mut self, _color: Color) -> wasmtime::Result<()> {
+        Ok(())
+    }
 }
 
 pub trait DisplayApi: HasDisplayHandle + HasWindowHandle {
     fn height(&self) -> u32;
     fn width(&self) -> u32;
+    /// Called after each frame presented to this display.
+    fn presented(&self) {}
+    /// When the newest input the frame just presented reflects arrived, unless
+    /// no input arrived since the frame before. Called after `presented`.
+    fn take_input_arrival(&self) -> Option<Instant> {
+        None
+    }
+    /// Whether the guest dropped this display. Frames stop being mirrored to
+    /// it then, though a context it's connected to keeps presenting to it.
+    fn released(&self) -> bool {
+        false
+    }
 }
 
 pub struct GraphicsContextBuffer {
@@ -123,13 +492,33 @@ where
     Ok(())
 }
 
-pub trait WasiGraphicsContextView: WasiView {}
+pub trait WasiGraphicsContextView: WasiView {
+    /// Color new graphics contexts show until their first frame is presented.
+    fn default_clear_color(&self) -> Option<Color> {
+        None
+    }
+
+    /// Color space new graphics contexts ask for until the guest picks one.
+    fn default_color_space(&self) -> Option<ColorSpace> {
+        None
+    }
+
+    /// Where to record frame timings, if profiling.
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        None
+    }
+}
 
 impl graphics_context::Host for dyn WasiGraphicsContextView + '_ {}
 
 impl graphics_context::HostGraphicsContext for dyn WasiGraphicsContextView + '_ {
     fn new(&mut self) -> Resource<GraphicsContext> {
-        self.table().push(GraphicsContext::new()).unwrap()
+        let clear_color = self.default_clear_color();
+        let mut context = GraphicsContext::with_clear_color(clear_color);
+        if let Some(space) = self.default_color_space() {
+            context.color_space = space;
+        }
+        self.table().push(context).unwrap()
     }
 
     fn configure(&mut self, context: Resource<GraphicsContext>, desc: ConfigureContextDesc) {
@@ -141,7 +530,13 @@ impl graphics_context::HostGraphicsContext for dyn WasiGraphicsContextView + '_
         &mut self,
         context: Resource<GraphicsContext>,
     ) -> Resource<GraphicsContextBuffer> {
+        let _span = tracing::info_span!(
+            "graphics_context_get_current_buffer",
+            context = context.rep()
+        )
+        .entered();
         let context_kind = self.table().get_mut(&context).unwrap();
+        context_kind.follow_display_size();
         let next_frame = context_kind
             .draw_api
             .as_mut()
@@ -152,14 +547,96 @@ impl graphics_context::HostGraphicsContext for dyn WasiGraphicsContextView + '_
         next_frame
     }
 
-    fn present(&mut self, context: Resource<GraphicsContext>) {
+    fn present(&mut self, context: Resource<GraphicsContext>) -> PresentResult {
+        let _span =
+            tracing::info_span!("graphics_context_present", context = context.rep()).entered();
+        let frame_timings = self.frame_timings();
         let context = self.table().get_mut(&context).unwrap();
-        // context.display_api.as_mut().unwrap().present().unwrap();
-        context.draw_api.as_mut().unwrap().present().unwrap();
+        context.follow_mirrors();
+        let Some(draw_api) = &mut context.draw_api else {
+            return PresentResult::Failed("graphics context has no draw api connected".to_string());
+        };
+        let result = match draw_api.present() {
+            Ok(result) => result,
+            Err(e) => PresentResult::Failed(e.to_string()),
+        };
+        if let PresentResult::Failed(reason) = &result {
+            tracing::warn!("present failed: {reason}");
+            return result;
+        }
+        context.presented(frame_timings.as_deref());
+        result
     }
 
-    fn drop(&mut self, _graphics_context: Resource<GraphicsContext>) -> wasmtime::Result<()> {
-        // todo!()
+    fn set_buffer_count(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        count: u32,
+    ) -> Result<(), String> {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_buffer_count(count)
+    }
+
+    fn set_desired_maximum_frame_latency(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        frames: u32,
+    ) -> Result<(), String> {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_desired_maximum_frame_latency(frames)
+    }
+
+    fn set_render_scale(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        scale: f64,
+    ) -> Result<(), String> {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_render_scale(scale)
+    }
+
+    fn set_sample_count(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        count: u32,
+    ) -> Result<(), String> {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_sample_count(count)
+    }
+
+    fn set_color_space(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        space: ColorSpace,
+    ) -> Result<ColorSpace, String> {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_color_space(space)
+    }
+
+    fn get_input_latency_ms(&mut self, context: Resource<GraphicsContext>) -> Option<f64> {
+        let context = self.table().get(&context).unwrap();
+        context
+            .input_latency()
+            .map(|latency| latency.as_secs_f64() * 1000.0)
+    }
+
+    fn set_auto_reconfigure(&mut self, context: Resource<GraphicsContext>, enabled: bool) {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_auto_reconfigure(enabled);
+    }
+
+    fn set_default_clear_color(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        color: Option<Color>,
+    ) {
+        let context = self.table().get_mut(&context).unwrap();
+        context.set_default_clear_color(color);
+    }
+
+    fn drop(&mut self, graphics_context: Resource<GraphicsContext>) -> wasmtime::Result<()> {
+        // Drops the draw api too, which lets go of its mirrors.
+        self.table().delete(graphics_context)?;
         Ok(())
     }
 }
@@ -169,3 +646,291 @@ impl graphics_context::HostGraphicsContextBuffer for dyn WasiGraphicsContextView
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+
+    /// A display of a size that can change, with no window behind it.
+    #[derive(Clone, Default)]
+    struct FakeDisplay {
+        width: Arc<AtomicU32>,
+        height: Arc<AtomicU32>,
+        released: Arc<AtomicBool>,
+        input_arrival: Arc<Mutex<Option<Instant>>>,
+    }
+
+    impl FakeDisplay {
+        fn sized(width: u32, height: u32) -> Self {
+            let display = Self::default();
+            display.resize(width, height);
+            display
+        }
+
+        fn resize(&self, width: u32, height: u32) {
+            self.width.store(width, Ordering::Relaxed);
+            self.height.store(height, Ordering::Relaxed);
+        }
+    }
+
+    impl HasDisplayHandle for FakeDisplay {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            Err(HandleError::Unavailable)
+        }
+    }
+
+    impl HasWindowHandle for FakeDisplay {
+        fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+            Err(HandleError::Unavailable)
+        }
+    }
+
+    impl DisplayApi for FakeDisplay {
+        fn height(&self) -> u32 {
+            self.height.load(Ordering::Relaxed)
+        }
+
+        fn width(&self) -> u32 {
+            self.width.load(Ordering::Relaxed)
+        }
+
+        fn released(&self) -> bool {
+            self.released.load(Ordering::Relaxed)
+        }
+
+        fn take_input_arrival(&self) -> Option<Instant> {
+            self.input_arrival.lock().unwrap().take()
+        }
+    }
+
+    /// Records what's done to the displays attached to it, and separately,
+    /// which display it's connected to and presents to.
+    #[derive(Default)]
+    struct FakeDrawApi {
+        calls: Arc<Mutex<Vec<String>>>,
+        displays: Arc<Mutex<Vec<String>>>,
+        display_size: Option<(u32, u32)>,
+    }
+
+    impl DrawApi for FakeDrawApi {
+        fn get_current_buffer(&mut self) -> wasmtime::Result<GraphicsContextBuffer> {
+            Ok(Box::new(()).into())
+        }
+
+        fn present(&mut self) -> wasmtime::Result<PresentResult> {
+            if let Some((width, height)) = self.display_size {
+                self.displays
+                    .lock()
+                    .unwrap()
+                    .push(format!("present {width}x{height}"));
+            }
+            Ok(PresentResult::Presented)
+        }
+
+        fn display_api_ready(&mut self, display_api: &(dyn DisplayApi + Send + Sync)) {
+            let (width, height) = (display_api.width(), display_api.height());
+            self.display_size = Some((width, height));
+            self.displays
+                .lock()
+                .unwrap()
+                .push(format!("ready {width}x{height}"));
+        }
+
+        fn display_api_released(&mut self) {
+            self.display_size = None;
+            self.displays.lock().unwrap().push("released".to_string());
+        }
+
+        fn resize(&mut self, width: u32, height: u32) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("resize {width}x{height}"));
+        }
+
+        fn reconfigure(&mut self, configuration: Box<dyn Any + Send + Sync>) -> Result<(), String> {
+            let (width, height) = *configuration.downcast::<(u32, u32)>().unwrap();
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("reconfigure {width}x{height}"));
+            Ok(())
+        }
+
+        fn attach_display_api(
+            &mut self,
+            key: usize,
+            _display_api: &(dyn DisplayApi + Send + Sync),
+        ) -> Result<(), String> {
+            self.calls.lock().unwrap().push(format!("attach {key}"));
+            Ok(())
+        }
+
+        fn detach_display_api(&mut self, key: usize) -> Result<(), String> {
+            self.calls.lock().unwrap().push(format!("detach {key}"));
+            Ok(())
+        }
+
+        fn resize_display_api(&mut self, key: usize, width: u32, height: u32) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("resize {key} {width}x{height}"));
+        }
+
+        fn clear(&mut self, _color: Color) -> wasmtime::Result<()> {
+            self.calls.lock().unwrap().push("clear".to_string());
+            Err(wasmtime::Error::msg("nothing to clear"))
+        }
+    }
+
+    fn mirroring_context(mirror: &FakeDisplay) -> (GraphicsContext, Arc<Mutex<Vec<String>>>) {
+        let draw_api = FakeDrawApi::default();
+        let calls = Arc::clone(&draw_api.calls);
+        let mut context = GraphicsContext::new();
+        context.connect_draw_api(Box::new(draw_api));
+        context.connect_display_api(Box::new(FakeDisplay::sized(100, 100)));
+        context
+            .attach_display_api(7, Box::new(mirror.clone()))
+            .unwrap();
+        calls.lock().unwrap().clear();
+        (context, calls)
+    }
+
+    #[test]
+    fn mirrors_follow_their_display_size() {
+        let mirror = FakeDisplay::sized(50, 40);
+        let (mut context, calls) = mirroring_context(&mirror);
+        context.follow_mirrors();
+        assert!(calls.lock().unwrap().is_empty());
+        mirror.resize(80, 60);
+        context.follow_mirrors();
+        context.follow_mirrors();
+        assert_eq!(*calls.lock().unwrap(), ["resize 7 80x60"]);
+    }
+
+    #[test]
+    fn minimized_mirrors_keep_their_size() {
+        let mirror = FakeDisplay::sized(50, 40);
+        let (mut context, calls) = mirroring_context(&mirror);
+        mirror.resize(0, 0);
+        context.follow_mirrors();
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn released_mirrors_are_detached() {
+        let mirror = FakeDisplay::sized(50, 40);
+        let (mut context, calls) = mirroring_context(&mirror);
+        mirror.released.store(true, Ordering::Relaxed);
+        context.follow_mirrors();
+        assert_eq!(*calls.lock().unwrap(), ["detach 7"]);
+        assert!(context.mirrors.is_empty());
+        // Nothing is left to detach by hand.
+        assert!(context.detach_display_api(7).is_err());
+    }
+
+    #[test]
+    fn canvases_are_cleared_only_with_a_clear_color() {
+        let draw_api = FakeDrawApi::default();
+        let calls = Arc::clone(&draw_api.calls);
+        let mut context = GraphicsContext::new();
+        context.connect_draw_api(Box::new(draw_api));
+        context.connect_display_api(Box::new(FakeDisplay::sized(100, 100)));
+        assert!(calls.lock().unwrap().is_empty());
+        // The fake's clear fails, which is only logged.
+        context.set_default_clear_color(Some(Color {
+            r: 0.1,
+            g: 0.1,
+            b: 0.1,
+            a: 1.0,
+        }));
+        assert_eq!(*calls.lock().unwrap(), ["clear"]);
+    }
+
+    #[test]
+    fn contexts_move_from_one_display_to_another() {
+        let draw_api = FakeDrawApi::default();
+        let displays = Arc::clone(&draw_api.displays);
+        let mut context = GraphicsContext::new();
+        context.connect_draw_api(Box::new(draw_api));
+        let present = |context: &mut GraphicsContext| {
+            context.draw_api.as_mut().unwrap().present().unwrap();
+        };
+        // With no display before, there's nothing to release.
+        context.connect_display_api(Box::new(FakeDisplay::sized(100, 100)));
+        present(&mut context);
+        context.connect_display_api(Box::new(FakeDisplay::sized(50, 40)));
+        present(&mut context);
+        assert_eq!(
+            *displays.lock().unwrap(),
+            [
+                "ready 100x100",
+                "present 100x100",
+                "released",
+                "ready 50x40",
+                "present 50x40"
+            ]
+        );
+    }
+
+    #[test]
+    fn manual_mode_waits_for_reconfigure_after_a_resize() {
+        let draw_api = FakeDrawApi::default();
+        let calls = Arc::clone(&draw_api.calls);
+        let display = FakeDisplay::sized(100, 100);
+        let mut context = GraphicsContext::new();
+        context.connect_draw_api(Box::new(draw_api));
+        context.connect_display_api(Box::new(display.clone()));
+        context.follow_display_size();
+        assert_eq!(*calls.lock().unwrap(), ["resize 100x100"]);
+        calls.lock().unwrap().clear();
+
+        context.set_auto_reconfigure(false);
+        display.resize(80, 60);
+        context.follow_display_size();
+        assert!(calls.lock().unwrap().is_empty());
+        context.reconfigure(Box::new((80u32, 60u32))).unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["reconfigure 80x60"]);
+        calls.lock().unwrap().clear();
+
+        // Back in auto mode, the next frame catches up with the display.
+        context.set_auto_reconfigure(true);
+        context.follow_display_size();
+        assert_eq!(*calls.lock().unwrap(), ["resize 80x60"]);
+    }
+
+    #[test]
+    fn input_latency_is_measured_to_the_present_reflecting_it() {
+        let display = FakeDisplay::sized(100, 100);
+        let frame_timings = FrameTimings::new();
+        let mut context = GraphicsContext::new();
+        context.connect_draw_api(Box::new(FakeDrawApi::default()));
+        context.connect_display_api(Box::new(display.clone()));
+        context.presented(Some(&frame_timings));
+        assert_eq!(context.input_latency(), None);
+
+        *display.input_arrival.lock().unwrap() = Some(Instant::now());
+        context.presented(Some(&frame_timings));
+        assert!(context.input_latency().is_some());
+        assert!(frame_timings.summary().input_latencies.average().is_some());
+        // Frames without new input leave the average as it was.
+        let latency = context.input_latency();
+        context.presented(Some(&frame_timings));
+        assert_eq!(context.input_latency(), latency);
+    }
+
+    #[test]
+    fn displays_are_attached_once() {
+        let mirror = FakeDisplay::sized(50, 40);
+        let (mut context, _) = mirroring_context(&mirror);
+        assert!(context
+            .attach_display_api(7, Box::new(mirror.clone()))
+            .is_err());
+    }
+}
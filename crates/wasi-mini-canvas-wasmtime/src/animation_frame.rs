@@ -1,8 +1,11 @@
-use std::sync::Mutex;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use crate::{
     wasi::webgpu::animation_frame::{self, FrameEvent, Pollable},
-    MiniCanvasArc, WasiMiniCanvasView,
+    FrameTick, MiniCanvasArc, WasiMiniCanvasView,
 };
 use async_broadcast::Receiver;
 use wasmtime::component::Resource;
@@ -15,10 +18,27 @@ impl animation_frame::Host for dyn WasiMiniCanvasView + '_ {
     ) -> Resource<AnimationFrameListener> {
         let canvas = &self.table().get(&mini_canvas).unwrap().0;
         let receiver = canvas.frame_sender.new_receiver();
+        let budget = self.frame_budget();
         self.table()
             .push(AnimationFrameListener {
                 receiver,
                 data: Default::default(),
+                budget,
+                last_taken: Default::default(),
+            })
+            .unwrap()
+    }
+
+    async fn present_listener(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+    ) -> Resource<PresentNowListener> {
+        let canvas = &self.table().get(&mini_canvas).unwrap().0;
+        let receiver = canvas.present_sender.new_receiver();
+        self.table()
+            .push(PresentNowListener {
+                receiver,
+                data: Default::default(),
             })
             .unwrap()
     }
@@ -33,7 +53,11 @@ impl animation_frame::HostFrameListener for dyn WasiMiniCanvasView + '_ {
     }
     fn get(&mut self, frame_listener: Resource<AnimationFrameListener>) -> Option<FrameEvent> {
         let frame_listener = self.table().get(&frame_listener).unwrap();
-        frame_listener.data.lock().unwrap().take()
+        let tick = frame_listener.data.lock().unwrap().take()?;
+        frame_listener.check_budget(tick.sent_at);
+        Some(FrameEvent {
+            time: tick.time.as_secs_f64() * 1000.0,
+        })
     }
     fn drop(&mut self, _self_: Resource<AnimationFrameListener>) -> wasmtime::Result<()> {
         Ok(())
@@ -42,14 +66,104 @@ impl animation_frame::HostFrameListener for dyn WasiMiniCanvasView + '_ {
 
 #[derive(Debug)]
 pub struct AnimationFrameListener {
-    receiver: Receiver<()>,
-    data: Mutex<Option<FrameEvent>>,
+    receiver: Receiver<FrameTick>,
+    data: Mutex<Option<FrameTick>>,
+    budget: Option<Duration>,
+    last_taken: Mutex<Option<Instant>>,
+}
+
+impl AnimationFrameListener {
+    /// Warns if the guest spent longer than the budget on the last frame, and
+    /// returns how long it took if so. Time spent waiting for a frame to be
+    /// sent doesn't count towards it.
+    fn check_budget(&self, sent_at: Instant) -> Option<Duration> {
+        let now = Instant::now();
+        let last_taken = self.last_taken.lock().unwrap().replace(now);
+        let frame_time = now - last_taken?.max(sent_at);
+        let budget = self.budget?;
+        if frame_time <= budget {
+            return None;
+        }
+        tracing::warn!(
+            ?frame_time,
+            ?budget,
+            "frame took longer than the frame budget"
+        );
+        Some(frame_time)
+    }
 }
 
 #[async_trait::async_trait]
 impl wasmtime_wasi::Subscribe for AnimationFrameListener {
     async fn ready(&mut self) {
-        let _ = self.receiver.recv().await.unwrap();
-        *self.data.lock().unwrap() = Some(FrameEvent { nothing: false });
+        let tick = self.receiver.recv().await.unwrap();
+        *self.data.lock().unwrap() = Some(tick);
+    }
+}
+
+impl animation_frame::HostPresentNowListener for dyn WasiMiniCanvasView + '_ {
+    fn subscribe(&mut self, present_listener: Resource<PresentNowListener>) -> Resource<Pollable> {
+        wasmtime_wasi::subscribe(self.table(), present_listener).unwrap()
+    }
+    fn get(&mut self, present_listener: Resource<PresentNowListener>) -> Option<FrameEvent> {
+        let present_listener = self.table().get(&present_listener).unwrap();
+        present_listener.data.lock().unwrap().take()
+    }
+    fn drop(&mut self, _self_: Resource<PresentNowListener>) -> wasmtime::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PresentNowListener {
+    receiver: Receiver<Duration>,
+    data: Mutex<Option<FrameEvent>>,
+}
+
+#[async_trait::async_trait]
+impl wasmtime_wasi::Subscribe for PresentNowListener {
+    async fn ready(&mut self) {
+        let time = self.receiver.recv().await.unwrap();
+        *self.data.lock().unwrap() = Some(FrameEvent {
+            time: time.as_secs_f64() * 1000.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    fn listener(budget: Duration) -> AnimationFrameListener {
+        let (_, receiver) = async_broadcast::broadcast(1);
+        AnimationFrameListener {
+            receiver,
+            data: Default::default(),
+            budget: Some(budget),
+            last_taken: Default::default(),
+        }
+    }
+
+    #[test]
+    fn frames_over_budget_warn() {
+        let listener = listener(Duration::from_millis(20));
+        assert_eq!(listener.check_budget(Instant::now()), None);
+        // The next frame was sent while the guest was still on this one.
+        let sent_at = Instant::now();
+        sleep(Duration::from_millis(40));
+        let frame_time = listener.check_budget(sent_at);
+        assert!(frame_time.is_some_and(|time| time >= Duration::from_millis(40)));
+        assert_eq!(listener.check_budget(Instant::now()), None);
+    }
+
+    #[test]
+    fn waiting_for_the_next_frame_isnt_counted() {
+        let listener = listener(Duration::from_millis(20));
+        listener.check_budget(Instant::now());
+        // The guest was idle until the next frame was sent.
+        sleep(Duration::from_millis(40));
+        assert_eq!(listener.check_budget(Instant::now()), None);
     }
 }
@@ -0,0 +1,94 @@
+//! Gamepad rumble, dispatched to whatever gamepad backend the embedder has.
+//!
+//! The runtime doesn't ship one, so by default rumbling fails, saying the host
+//! has no gamepad support.
+
+use std::time::Duration;
+
+/// Controllers the guest can rumble.
+///
+/// Implementations that have to talk to controllers from the main thread do
+/// the routing themselves; `rumble` is called from the guest's thread.
+pub trait GamepadBackend: Send + Sync {
+    /// Indices of the controllers connected right now.
+    fn connected(&self) -> Vec<u32>;
+
+    fn rumble(&self, index: u32, rumble: Rumble) -> Result<(), String>;
+}
+
+/// Strengths of the strong (low frequency) and weak (high frequency) motors,
+/// from 0 to 1, and how long they run for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rumble {
+    pub strong: f32,
+    pub weak: f32,
+    pub duration: Duration,
+}
+
+/// Rumbles the controller at `index`, if it's connected.
+pub(crate) fn set_rumble(
+    backend: Option<&dyn GamepadBackend>,
+    index: u32,
+    rumble: Rumble,
+) -> Result<(), String> {
+    for strength in [rumble.strong, rumble.weak] {
+        if !(0.0..=1.0).contains(&strength) {
+            return Err(format!("rumble strength {strength} isn't between 0 and 1"));
+        }
+    }
+    let Some(backend) = backend else {
+        return Err("this host has no gamepad support".to_string());
+    };
+    match backend.connected().contains(&index) {
+        true => backend.rumble(index, rumble),
+        false => Err(format!("no gamepad connected at index {index}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockBackend {
+        rumbled: Mutex<Vec<(u32, Rumble)>>,
+    }
+
+    impl GamepadBackend for MockBackend {
+        fn connected(&self) -> Vec<u32> {
+            vec![0, 2]
+        }
+
+        fn rumble(&self, index: u32, rumble: Rumble) -> Result<(), String> {
+            self.rumbled.lock().unwrap().push((index, rumble));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rumble_reaches_connected_gamepads_only() {
+        let backend = MockBackend::default();
+        let rumble = Rumble {
+            strong: 1.0,
+            weak: 0.25,
+            duration: Duration::from_millis(200),
+        };
+        set_rumble(Some(&backend), 2, rumble).unwrap();
+        assert_eq!(
+            set_rumble(Some(&backend), 1, rumble),
+            Err("no gamepad connected at index 1".to_string())
+        );
+        assert_eq!(
+            set_rumble(None, 0, rumble),
+            Err("this host has no gamepad support".to_string())
+        );
+        let too_strong = Rumble {
+            strong: 1.5,
+            ..rumble
+        };
+        assert!(set_rumble(Some(&backend), 0, too_strong).is_err());
+        assert_eq!(*backend.rumbled.lock().unwrap(), vec![(2, rumble)]);
+    }
+}
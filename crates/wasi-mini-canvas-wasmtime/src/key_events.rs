@@ -36,6 +36,21 @@ impl key_events::Host for dyn WasiMiniCanvasView + '_ {
             })
             .unwrap()
     }
+
+    fn get_pressed_scancodes(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Vec<u32> {
+        let canvas = &self.table().get(&mini_canvas).unwrap().0;
+        canvas.pressed_scancodes()
+    }
+
+    fn get_pressed_keys(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Vec<String> {
+        let canvas = &self.table().get(&mini_canvas).unwrap().0;
+        canvas.pressed_keys()
+    }
+
+    fn get_keyboard_layout(&mut self) -> String {
+        // winit doesn't expose the active layout on any platform yet.
+        "unknown".to_string()
+    }
 }
 
 impl key_events::HostKeyUpListener for dyn WasiMiniCanvasView + '_ {
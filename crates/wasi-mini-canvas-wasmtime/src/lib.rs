@@ -1,9 +1,15 @@
 use std::{
+    collections::BTreeSet,
     fmt::Debug,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
 };
 use wasi_graphics_context_wasmtime::DisplayApi;
 
+use crate::pointer_events::RawMotion;
 use crate::wasi::webgpu::mini_canvas::{self, GraphicsContext, Pollable};
 use async_broadcast::{Receiver, TrySendError};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
@@ -11,27 +17,116 @@ use wasmtime::component::Resource;
 use wasmtime_wasi::WasiView;
 
 mod animation_frame;
+mod gamepads;
 mod key_events;
 mod pointer_events;
 
+#[cfg(test)]
+mod test_canvas;
+
+#[cfg(feature = "winit")]
+mod opacity;
 #[cfg(feature = "winit")]
 mod winit;
 
+#[cfg(all(feature = "winit", feature = "inject-input"))]
+pub use ::winit::window::WindowId;
 #[cfg(feature = "winit")]
 pub use winit::{create_wasi_winit_event_loop, WasiWinitEventLoop, WasiWinitEventLoopProxy};
 
+pub use gamepads::{GamepadBackend, Rumble};
+
 pub trait HasDisplayAndWindowHandle: HasDisplayHandle + HasWindowHandle {}
 
 impl<T: HasDisplayHandle + HasWindowHandle> HasDisplayAndWindowHandle for T {}
 
 pub use crate::wasi::webgpu::{
-    key_events::KeyEvent,
-    mini_canvas::{CreateDesc as MiniCanvasDesc, ResizeEvent},
-    pointer_events::PointerEvent,
+    key_events::{KeyEvent, KeyLocation},
+    mini_canvas::{
+        CanvasSize, CreateDesc as MiniCanvasDesc, CursorPosition, DroppedEventStats, MonitorInfo,
+        ResizeEvent, Theme, VideoMode, WindowSize,
+    },
+    pointer_events::{PointerEvent, RawMotionEvent},
 };
 
+/// The window backing a `MiniCanvas`.
+///
+/// Window controls default to doing nothing, so backends without a real window
+/// only have to provide the `DisplayApi` part.
+pub trait CanvasWindow: DisplayApi {
+    fn set_title(&self, _title: String) {}
+    fn set_resizable(&self, _resizable: bool) {}
+    fn set_decorations(&self, _decorations: bool) {}
+    fn set_min_size(&self, _size: Option<CanvasSize>) {}
+    fn set_max_size(&self, _size: Option<CanvasSize>) {}
+    fn set_aspect_ratio(&self, _ratio: Option<f32>) {}
+    fn set_pointer_lock(&self, _locked: bool) {}
+    /// Physical pixels per logical pixel.
+    fn scale_factor(&self) -> f64 {
+        1.0
+    }
+    fn inner_size(&self) -> WindowSize {
+        WindowSize {
+            width: self.width(),
+            height: self.height(),
+            scale_factor: self.scale_factor(),
+        }
+    }
+    /// Same as the inner size for windows without decorations.
+    fn outer_size(&self) -> WindowSize {
+        self.inner_size()
+    }
+    /// In millihertz.
+    fn refresh_rate(&self) -> Option<u32> {
+        None
+    }
+    /// From 0 to 1.
+    fn set_opacity(&self, _opacity: f64) -> Result<(), String> {
+        Err("window opacity isn't supported by this canvas".to_string())
+    }
+    fn opacity(&self) -> Option<f64> {
+        None
+    }
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        Vec::new()
+    }
+    /// `monitor` and `mode` index into `monitors`.
+    fn set_exclusive_fullscreen(&self, _monitor: u32, _mode: u32) -> Result<(), String> {
+        Err("exclusive fullscreen isn't supported by this canvas".to_string())
+    }
+    fn exit_fullscreen(&self) {}
+    /// Where the pointer last was over the window, in physical pixels, or
+    /// `None` if it hasn't been over it yet.
+    fn cursor_position(&self) -> Option<CursorPosition> {
+        None
+    }
+    /// `rgba` has been checked to hold `width` * `height` pixels.
+    fn set_custom_cursor(
+        &self,
+        _rgba: Vec<u8>,
+        _width: u16,
+        _height: u16,
+        _hotspot_x: u16,
+        _hotspot_y: u16,
+    ) -> Result<(), String> {
+        Err("custom cursors aren't supported by this canvas".to_string())
+    }
+}
+
 pub trait WasiMiniCanvasView: WasiView {
     fn create_canvas(&self, desc: MiniCanvasDesc) -> MiniCanvas;
+
+    /// Warn about frames the guest spends longer than this on, measured from
+    /// when it picks up one frame event to when it picks up the next. Only
+    /// observes, frames are sent the same either way.
+    fn frame_budget(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Controllers `set-gamepad-rumble` drives. Without one, it always fails.
+    fn gamepads(&self) -> Option<Arc<dyn GamepadBackend>> {
+        None
+    }
 }
 
 pub fn add_to_linker<T>(l: &mut wasmtime::component::Linker<T>) -> wasmtime::Result<()>
@@ -57,13 +152,16 @@ where
 wasmtime::component::bindgen!({
     path: "../../wit/",
     world: "example",
+    tracing: true,
     async: {
         only_imports: [
             "poll",
             "up-listener",
             "down-listener",
             "move-listener",
+            "raw-motion-listener",
             "listener",
+            "present-listener",
             // "resize-listener",
         ],
     },
@@ -72,17 +170,24 @@ wasmtime::component::bindgen!({
         "wasi:webgpu/pointer-events/pointer-up-listener": pointer_events::PointerUpListener,
         "wasi:webgpu/pointer-events/pointer-down-listener": pointer_events::PointerDownListener,
         "wasi:webgpu/pointer-events/pointer-move-listener": pointer_events::PointerMoveListener,
+        "wasi:webgpu/pointer-events/pointer-raw-motion-listener": pointer_events::PointerRawMotionListener,
         "wasi:webgpu/key-events/key-up-listener": key_events::KeyUpListener,
         "wasi:webgpu/key-events/key-down-listener": key_events::KeyDownListener,
         "wasi:webgpu/animation-frame/frame-listener": animation_frame::AnimationFrameListener,
+        "wasi:webgpu/animation-frame/present-now-listener": animation_frame::PresentNowListener,
         "wasi:webgpu/graphics-context": wasi_graphics_context_wasmtime,
         "wasi:webgpu/mini-canvas/mini-canvas": MiniCanvasArc,
         "wasi:webgpu/mini-canvas/resize-listener": ResizeListener,
+        "wasi:webgpu/mini-canvas/theme-listener": ThemeListener,
     },
 });
 
 pub struct MiniCanvas {
-    pub window: Box<dyn DisplayApi + Send + Sync + 'static>,
+    pub window: Box<dyn CanvasWindow + Send + Sync + 'static>,
+    /// Tells canvases apart, unlike their address, which a later one can reuse.
+    id: usize,
+    /// Set once the guest drops the canvas.
+    released: AtomicBool,
 
     // Keeping inactive receivers to keep channels open.
     // See https://docs.rs/async-broadcast/0.7.1/async_broadcast/struct.InactiveReceiver.html
@@ -92,14 +197,190 @@ pub struct MiniCanvas {
     _pointer_down_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
     pointer_move_sender: async_broadcast::Sender<PointerEvent>,
     _pointer_move_receiver: async_broadcast::InactiveReceiver<PointerEvent>,
+    /// Wakes raw motion listeners up, the motion itself is in `raw_motion`.
+    pointer_raw_motion_sender: async_broadcast::Sender<()>,
+    _pointer_raw_motion_receiver: async_broadcast::InactiveReceiver<()>,
     key_up_sender: async_broadcast::Sender<KeyEvent>,
     _key_up_receiver: async_broadcast::InactiveReceiver<KeyEvent>,
     key_down_sender: async_broadcast::Sender<KeyEvent>,
     _key_down_receiver: async_broadcast::InactiveReceiver<KeyEvent>,
     canvas_resize_sender: async_broadcast::Sender<ResizeEvent>,
     _canvas_resize_receiver: async_broadcast::InactiveReceiver<ResizeEvent>,
-    frame_sender: async_broadcast::Sender<()>,
-    _frame_receiver: async_broadcast::InactiveReceiver<()>,
+    frame_sender: async_broadcast::Sender<FrameTick>,
+    _frame_receiver: async_broadcast::InactiveReceiver<FrameTick>,
+    /// Sends the frame clock's time.
+    present_sender: async_broadcast::Sender<Duration>,
+    _present_receiver: async_broadcast::InactiveReceiver<Duration>,
+    theme_sender: async_broadcast::Sender<Theme>,
+    _theme_receiver: async_broadcast::InactiveReceiver<Theme>,
+    theme: Arc<Mutex<Theme>>,
+    dropped_events: Arc<DroppedEvents>,
+    pointer_moves: Arc<PointerMoves>,
+    raw_motion: Arc<RawMotion>,
+    pressed_keys: Arc<Mutex<PressedKeys>>,
+    pointer_locked: Arc<AtomicBool>,
+    redraw_requested: Arc<AtomicBool>,
+    input_arrivals: Arc<InputArrivals>,
+    frame_clock: Arc<Mutex<FrameClock>>,
+}
+
+/// How many events a canvas holds for the guest per channel before dropping
+/// new ones, counted in `get-dropped-event-stats`.
+///
+/// Larger queues drop less under bursty input, but a guest that falls behind
+/// then works through older events first, which adds to the input latency. The
+/// frame queue also takes present-now events; with more than one, a slow guest
+/// renders frames that are already late instead of skipping to the latest.
+#[derive(Clone, Copy, Debug)]
+pub struct EventQueueCapacities {
+    /// For each kind of input event, resizes and theme changes.
+    pub input: usize,
+    /// For frame and present-now events.
+    pub frame: usize,
+}
+
+impl Default for EventQueueCapacities {
+    fn default() -> Self {
+        Self { input: 5, frame: 1 }
+    }
+}
+
+/// Pointer moves waiting for the next frame, unless raw moves were requested.
+#[derive(Debug, Default)]
+struct PointerMoves {
+    raw: AtomicBool,
+    latest: Mutex<Option<PointerEvent>>,
+}
+
+/// A frame event on its way to the guest.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FrameTick {
+    /// When it was sent, to tell slow frames apart from idle ones.
+    pub(crate) sent_at: Instant,
+    /// The frame clock's time.
+    pub(crate) time: Duration,
+}
+
+/// When the runtime started, which frame event times count from. Set on first
+/// use and kept for the life of the process, so new canvases and reloaded
+/// guests share it.
+pub fn time_origin() -> Instant {
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    *ORIGIN.get_or_init(Instant::now)
+}
+
+/// Time since the runtime started, less the time the canvas's frames spent
+/// paused. Stands still while they're paused.
+#[derive(Debug)]
+struct FrameClock {
+    started: Instant,
+    /// When frames were paused, if they are.
+    paused_at: Option<Instant>,
+    /// Time spent paused before `paused_at`.
+    paused_for: Duration,
+}
+
+impl FrameClock {
+    fn new() -> Self {
+        Self::started_at(time_origin())
+    }
+
+    fn started_at(started: Instant) -> Self {
+        Self {
+            started,
+            paused_at: None,
+            paused_for: Duration::ZERO,
+        }
+    }
+
+    fn pause(&mut self) {
+        self.pause_at(Instant::now());
+    }
+
+    fn pause_at(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    fn resume(&mut self) {
+        self.resume_at(Instant::now());
+    }
+
+    fn resume_at(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_for += now - paused_at;
+        }
+    }
+
+    fn time(&self) -> Duration {
+        self.time_at(Instant::now())
+    }
+
+    fn time_at(&self, now: Instant) -> Duration {
+        let now = self.paused_at.unwrap_or(now);
+        now - self.started - self.paused_for
+    }
+}
+
+/// Keys held down, by scancode and by `code`.
+#[derive(Debug, Default)]
+struct PressedKeys {
+    scancodes: BTreeSet<u32>,
+    codes: BTreeSet<String>,
+}
+
+impl PressedKeys {
+    fn press(&mut self, event: &KeyEvent) {
+        if event.scancode != 0 {
+            self.scancodes.insert(event.scancode);
+        }
+        self.codes.insert(event.code.clone());
+    }
+
+    fn release(&mut self, event: &KeyEvent) {
+        self.scancodes.remove(&event.scancode);
+        self.codes.remove(&event.code);
+    }
+}
+
+/// When input arrived, for measuring how long it takes to show up on screen.
+#[derive(Debug, Default)]
+struct InputArrivals {
+    /// The newest input not yet followed by a frame.
+    pending: Mutex<Option<Instant>>,
+    /// The newest input before the last frame, taken when that frame is presented.
+    framed: Mutex<Option<Instant>>,
+}
+
+/// Per channel count of events dropped because the channel was full. Raw
+/// motion adds up while the guest is busy instead, so none of it is dropped.
+#[derive(Debug, Default)]
+struct DroppedEvents {
+    pointer_up: AtomicU64,
+    pointer_down: AtomicU64,
+    pointer_move: AtomicU64,
+    key_up: AtomicU64,
+    key_down: AtomicU64,
+    resize: AtomicU64,
+    frame: AtomicU64,
+    present: AtomicU64,
+    theme: AtomicU64,
+}
+
+impl DroppedEvents {
+    fn stats(&self) -> DroppedEventStats {
+        DroppedEventStats {
+            pointer_up: self.pointer_up.load(Ordering::Relaxed),
+            pointer_down: self.pointer_down.load(Ordering::Relaxed),
+            pointer_move: self.pointer_move.load(Ordering::Relaxed),
+            pointer_raw_motion: 0,
+            key_up: self.key_up.load(Ordering::Relaxed),
+            key_down: self.key_down.load(Ordering::Relaxed),
+            resize: self.resize.load(Ordering::Relaxed),
+            frame: self.frame.load(Ordering::Relaxed),
+            present: self.present.load(Ordering::Relaxed),
+            theme: self.theme.load(Ordering::Relaxed),
+        }
+    }
 }
 impl Debug for MiniCanvas {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -111,6 +392,11 @@ impl Debug for MiniCanvas {
             .field("_pointer_down_receiver", &self._pointer_down_receiver)
             .field("pointer_move_sender", &self.pointer_move_sender)
             .field("_pointer_move_receiver", &self._pointer_move_receiver)
+            .field("pointer_raw_motion_sender", &self.pointer_raw_motion_sender)
+            .field(
+                "_pointer_raw_motion_receiver",
+                &self._pointer_raw_motion_receiver,
+            )
             .field("key_up_sender", &self.key_up_sender)
             .field("_key_up_receiver", &self._key_up_receiver)
             .field("key_down_sender", &self.key_down_sender)
@@ -119,34 +405,67 @@ impl Debug for MiniCanvas {
             .field("_canvas_resize_receiver", &self._canvas_resize_receiver)
             .field("frame_sender", &self.frame_sender)
             .field("_frame_receiver", &self._frame_receiver)
+            .field("present_sender", &self.present_sender)
+            .field("_present_receiver", &self._present_receiver)
+            .field("theme_sender", &self.theme_sender)
+            .field("_theme_receiver", &self._theme_receiver)
+            .field("theme", &self.theme)
+            .field("dropped_events", &self.dropped_events)
+            .field("pointer_moves", &self.pointer_moves)
+            .field("pressed_keys", &self.pressed_keys)
+            .field("pointer_locked", &self.pointer_locked)
+            .field("redraw_requested", &self.redraw_requested)
+            .field("input_arrivals", &self.input_arrivals)
             .finish()
     }
 }
 
 impl MiniCanvas {
-    pub fn new(window: Box<dyn DisplayApi + Send + Sync + 'static>) -> Self {
-        let (pointer_up_sender, pointer_up_receiver) = async_broadcast::broadcast(5);
+    pub fn new(window: Box<dyn CanvasWindow + Send + Sync + 'static>) -> Self {
+        Self::with_queue_capacities(window, EventQueueCapacities::default())
+    }
+
+    pub fn with_queue_capacities(
+        window: Box<dyn CanvasWindow + Send + Sync + 'static>,
+        capacities: EventQueueCapacities,
+    ) -> Self {
+        let (pointer_up_sender, pointer_up_receiver) = async_broadcast::broadcast(capacities.input);
         let pointer_up_receiver = pointer_up_receiver.deactivate();
-        let (pointer_down_sender, pointer_down_receiver) = async_broadcast::broadcast(5);
+        let (pointer_down_sender, pointer_down_receiver) =
+            async_broadcast::broadcast(capacities.input);
         let pointer_down_receiver = pointer_down_receiver.deactivate();
-        let (pointer_move_sender, pointer_move_receiver) = async_broadcast::broadcast(5);
+        let (pointer_move_sender, pointer_move_receiver) =
+            async_broadcast::broadcast(capacities.input);
         let pointer_move_receiver = pointer_move_receiver.deactivate();
-        let (key_up_sender, key_up_receiver) = async_broadcast::broadcast(5);
+        let (pointer_raw_motion_sender, pointer_raw_motion_receiver) =
+            async_broadcast::broadcast(capacities.input);
+        let pointer_raw_motion_receiver = pointer_raw_motion_receiver.deactivate();
+        let (key_up_sender, key_up_receiver) = async_broadcast::broadcast(capacities.input);
         let key_up_receiver = key_up_receiver.deactivate();
-        let (key_down_sender, key_down_receiver) = async_broadcast::broadcast(5);
+        let (key_down_sender, key_down_receiver) = async_broadcast::broadcast(capacities.input);
         let key_down_receiver = key_down_receiver.deactivate();
-        let (canvas_resize_sender, canvas_resize_receiver) = async_broadcast::broadcast(5);
+        let (canvas_resize_sender, canvas_resize_receiver) =
+            async_broadcast::broadcast(capacities.input);
         let canvas_resize_receiver = canvas_resize_receiver.deactivate();
-        let (frame_sender, frame_receiver) = async_broadcast::broadcast(1);
+        let (frame_sender, frame_receiver) = async_broadcast::broadcast(capacities.frame);
         let frame_receiver = frame_receiver.deactivate();
+        let (present_sender, present_receiver) = async_broadcast::broadcast(capacities.frame);
+        let present_receiver = present_receiver.deactivate();
+        let (theme_sender, theme_receiver) = async_broadcast::broadcast(capacities.input);
+        let theme_receiver = theme_receiver.deactivate();
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
         Self {
             window,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            released: AtomicBool::new(false),
             pointer_up_sender,
             _pointer_up_receiver: pointer_up_receiver,
             pointer_down_sender,
             _pointer_down_receiver: pointer_down_receiver,
             pointer_move_sender,
             _pointer_move_receiver: pointer_move_receiver,
+            pointer_raw_motion_sender,
+            _pointer_raw_motion_receiver: pointer_raw_motion_receiver,
             key_up_sender,
             _key_up_receiver: key_up_receiver,
             key_down_sender,
@@ -155,68 +474,291 @@ impl MiniCanvas {
             _canvas_resize_receiver: canvas_resize_receiver,
             frame_sender,
             _frame_receiver: frame_receiver,
+            present_sender,
+            _present_receiver: present_receiver,
+            theme_sender,
+            _theme_receiver: theme_receiver,
+            theme: Arc::new(Mutex::new(Theme::Light)),
+            dropped_events: Default::default(),
+            pointer_moves: Default::default(),
+            raw_motion: Default::default(),
+            pressed_keys: Default::default(),
+            pointer_locked: Default::default(),
+            redraw_requested: Default::default(),
+            input_arrivals: Default::default(),
+            frame_clock: Arc::new(Mutex::new(FrameClock::new())),
         }
     }
 
+    pub fn dropped_event_stats(&self) -> DroppedEventStats {
+        self.dropped_events.stats()
+    }
+
+    pub fn set_raw_pointer_moves(&self, raw: bool) {
+        self.pointer_moves.raw.store(raw, Ordering::Relaxed);
+    }
+
+    pub fn request_redraw(&self) {
+        self.redraw_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops frame and present-now events, and the frame clock, until
+    /// `resume_frames`. Input keeps being delivered.
+    pub fn pause_frames(&self) {
+        self.frame_clock.lock().unwrap().pause();
+    }
+
+    pub fn resume_frames(&self) {
+        self.frame_clock.lock().unwrap().resume();
+        // So on demand runtimes send a frame right away too.
+        self.request_redraw();
+    }
+
+    /// Raw motion is only delivered while the pointer is locked.
+    pub fn set_pointer_lock(&self, locked: bool) {
+        self.pointer_locked.store(locked, Ordering::Relaxed);
+        self.window.set_pointer_lock(locked);
+    }
+
+    pub fn theme(&self) -> Theme {
+        *self.theme.lock().unwrap()
+    }
+
+    /// Sets the theme without notifying listeners, to seed it from the window.
+    pub fn set_theme(&self, theme: Theme) {
+        *self.theme.lock().unwrap() = theme;
+    }
+
+    pub fn cursor_position(&self) -> Option<CursorPosition> {
+        self.window.cursor_position()
+    }
+
+    pub fn pressed_scancodes(&self) -> Vec<u32> {
+        let pressed_keys = self.pressed_keys.lock().unwrap();
+        pressed_keys.scancodes.iter().copied().collect()
+    }
+
+    pub fn pressed_keys(&self) -> Vec<String> {
+        let pressed_keys = self.pressed_keys.lock().unwrap();
+        pressed_keys.codes.iter().cloned().collect()
+    }
+
     pub fn proxy(&self) -> MiniCanvasProxy {
         MiniCanvasProxy {
             pointer_up_sender: self.pointer_up_sender.clone(),
             pointer_down_sender: self.pointer_down_sender.clone(),
             pointer_move_sender: self.pointer_move_sender.clone(),
+            pointer_raw_motion_sender: self.pointer_raw_motion_sender.clone(),
             key_up_sender: self.key_up_sender.clone(),
             key_down_sender: self.key_down_sender.clone(),
             canvas_resize_sender: self.canvas_resize_sender.clone(),
             frame_sender: self.frame_sender.clone(),
+            present_sender: self.present_sender.clone(),
+            theme_sender: self.theme_sender.clone(),
+            theme: Arc::clone(&self.theme),
+            dropped_events: Arc::clone(&self.dropped_events),
+            pointer_moves: Arc::clone(&self.pointer_moves),
+            raw_motion: Arc::clone(&self.raw_motion),
+            pressed_keys: Arc::clone(&self.pressed_keys),
+            pointer_locked: Arc::clone(&self.pointer_locked),
+            redraw_requested: Arc::clone(&self.redraw_requested),
+            input_arrivals: Arc::clone(&self.input_arrivals),
+            frame_clock: Arc::clone(&self.frame_clock),
         }
     }
 }
 
+/// Input for `MiniCanvasProxy::inject`, delivered as if the window got it.
+#[cfg(feature = "inject-input")]
+#[derive(Debug, Clone)]
+pub enum InjectedInput {
+    PointerUp(PointerEvent),
+    PointerDown(PointerEvent),
+    PointerMove(PointerEvent),
+    KeyUp(KeyEvent),
+    KeyDown(KeyEvent),
+    Resize(ResizeEvent),
+}
+
 #[derive(Debug, Clone)]
 pub struct MiniCanvasProxy {
     pointer_up_sender: async_broadcast::Sender<PointerEvent>,
     pointer_down_sender: async_broadcast::Sender<PointerEvent>,
     pointer_move_sender: async_broadcast::Sender<PointerEvent>,
+    pointer_raw_motion_sender: async_broadcast::Sender<()>,
     key_up_sender: async_broadcast::Sender<KeyEvent>,
     key_down_sender: async_broadcast::Sender<KeyEvent>,
     canvas_resize_sender: async_broadcast::Sender<ResizeEvent>,
-    frame_sender: async_broadcast::Sender<()>,
+    frame_sender: async_broadcast::Sender<FrameTick>,
+    present_sender: async_broadcast::Sender<Duration>,
+    theme_sender: async_broadcast::Sender<Theme>,
+    theme: Arc<Mutex<Theme>>,
+    dropped_events: Arc<DroppedEvents>,
+    pointer_moves: Arc<PointerMoves>,
+    raw_motion: Arc<RawMotion>,
+    pressed_keys: Arc<Mutex<PressedKeys>>,
+    pointer_locked: Arc<AtomicBool>,
+    redraw_requested: Arc<AtomicBool>,
+    input_arrivals: Arc<InputArrivals>,
+    frame_clock: Arc<Mutex<FrameClock>>,
 }
 
 impl MiniCanvasProxy {
     pub fn pointer_up(&self, event: PointerEvent) {
-        unwrap_unless_inactive(self.pointer_up_sender.try_broadcast(event));
+        self.input_arrived();
+        self.request_redraw();
+        unwrap_unless_inactive_or_full(
+            self.pointer_up_sender.try_broadcast(event),
+            &self.dropped_events.pointer_up,
+        );
     }
     pub fn pointer_down(&self, event: PointerEvent) {
-        unwrap_unless_inactive(self.pointer_down_sender.try_broadcast(event));
+        self.input_arrived();
+        self.request_redraw();
+        unwrap_unless_inactive_or_full(
+            self.pointer_down_sender.try_broadcast(event),
+            &self.dropped_events.pointer_down,
+        );
     }
     pub fn pointer_move(&self, event: PointerEvent) {
-        unwrap_unless_inactive_or_full(self.pointer_move_sender.try_broadcast(event));
+        self.input_arrived();
+        self.request_redraw();
+        if self.pointer_moves.raw.load(Ordering::Relaxed) {
+            self.broadcast_pointer_move(event);
+        } else {
+            *self.pointer_moves.latest.lock().unwrap() = Some(event);
+        }
+    }
+    /// Dropped unless the canvas holds the pointer lock.
+    pub fn pointer_raw_motion(&self, event: RawMotionEvent) {
+        if !self.pointer_locked.load(Ordering::Relaxed) {
+            return;
+        }
+        self.input_arrived();
+        self.request_redraw();
+        self.raw_motion.add(event);
+        // A full channel means the listeners are already due to wake up.
+        let _ = self.pointer_raw_motion_sender.try_broadcast(());
+    }
+    fn broadcast_pointer_move(&self, event: PointerEvent) {
+        unwrap_unless_inactive_or_full(
+            self.pointer_move_sender.try_broadcast(event),
+            &self.dropped_events.pointer_move,
+        );
     }
     pub fn key_up(&self, event: KeyEvent) {
-        unwrap_unless_inactive(self.key_up_sender.try_broadcast(event));
+        self.input_arrived();
+        self.request_redraw();
+        self.pressed_keys.lock().unwrap().release(&event);
+        unwrap_unless_inactive_or_full(
+            self.key_up_sender.try_broadcast(event),
+            &self.dropped_events.key_up,
+        );
     }
     pub fn key_down(&self, event: KeyEvent) {
-        unwrap_unless_inactive(self.key_down_sender.try_broadcast(event));
+        self.input_arrived();
+        self.request_redraw();
+        self.pressed_keys.lock().unwrap().press(&event);
+        unwrap_unless_inactive_or_full(
+            self.key_down_sender.try_broadcast(event),
+            &self.dropped_events.key_down,
+        );
+    }
+    /// Keys released while the window is out of focus don't send key up, so none
+    /// count as held down anymore.
+    pub fn focus_lost(&self) {
+        *self.pressed_keys.lock().unwrap() = PressedKeys::default();
     }
     pub fn canvas_resize(&self, event: ResizeEvent) {
-        unwrap_unless_inactive(self.canvas_resize_sender.try_broadcast(event));
+        self.request_redraw();
+        unwrap_unless_inactive_or_full(
+            self.canvas_resize_sender.try_broadcast(event),
+            &self.dropped_events.resize,
+        );
+    }
+    pub fn theme_changed(&self, theme: Theme) {
+        self.request_redraw();
+        *self.theme.lock().unwrap() = theme;
+        unwrap_unless_inactive_or_full(
+            self.theme_sender.try_broadcast(theme),
+            &self.dropped_events.theme,
+        );
+    }
+    /// Sends `input` to the canvas's listeners the same way real input is.
+    #[cfg(feature = "inject-input")]
+    pub fn inject(&self, input: InjectedInput) {
+        match input {
+            InjectedInput::PointerUp(event) => self.pointer_up(event),
+            InjectedInput::PointerDown(event) => self.pointer_down(event),
+            InjectedInput::PointerMove(event) => self.pointer_move(event),
+            InjectedInput::KeyUp(event) => self.key_up(event),
+            InjectedInput::KeyDown(event) => self.key_down(event),
+            InjectedInput::Resize(event) => self.canvas_resize(event),
+        }
     }
+
+    fn input_arrived(&self) {
+        *self.input_arrivals.pending.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Input events count as a redraw request, so on demand guests still react to them.
+    pub fn request_redraw(&self) {
+        self.redraw_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears the pending redraw request, returning whether there was one.
+    pub fn take_redraw_request(&self) -> bool {
+        self.redraw_requested.swap(false, Ordering::Relaxed)
+    }
+
     pub fn animation_frame(&self) {
-        unwrap_unless_inactive_or_full(self.frame_sender.try_broadcast(()));
+        // Coalesced moves are delivered right before the frame they belong to.
+        let latest_pointer_move = self.pointer_moves.latest.lock().unwrap().take();
+        if let Some(event) = latest_pointer_move {
+            self.broadcast_pointer_move(event);
+        }
+        let clock = self.frame_clock.lock().unwrap();
+        if clock.paused_at.is_some() {
+            return;
+        }
+        // The frame reflects the input that arrived before it, so its latency
+        // is measured from the newest of that on present.
+        if let Some(arrival) = self.input_arrivals.pending.lock().unwrap().take() {
+            *self.input_arrivals.framed.lock().unwrap() = Some(arrival);
+        }
+        unwrap_unless_inactive_or_full(
+            self.frame_sender.try_broadcast(FrameTick {
+                sent_at: Instant::now(),
+                time: clock.time(),
+            }),
+            &self.dropped_events.frame,
+        );
+    }
+
+    /// The window system is ready for the next frame to be presented.
+    pub fn present_now(&self) {
+        let clock = self.frame_clock.lock().unwrap();
+        if clock.paused_at.is_some() {
+            return;
+        }
+        unwrap_unless_inactive_or_full(
+            self.present_sender.try_broadcast(clock.time()),
+            &self.dropped_events.present,
+        );
     }
 }
 
 impl HasDisplayHandle for MiniCanvas {
     fn display_handle(
         &self,
-    ) -> Result<raw_window_handle::DisplayHandle, raw_window_handle::HandleError> {
+    ) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
         self.window.display_handle()
     }
 }
 impl HasWindowHandle for MiniCanvas {
     fn window_handle(
         &self,
-    ) -> Result<raw_window_handle::WindowHandle, raw_window_handle::HandleError> {
+    ) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
         self.window.window_handle()
     }
 }
@@ -229,12 +771,36 @@ impl DisplayApi for MiniCanvas {
     fn width(&self) -> u32 {
         self.window.width()
     }
+
+    fn presented(&self) {
+        self.window.presented();
+    }
+
+    fn take_input_arrival(&self) -> Option<Instant> {
+        self.input_arrivals.framed.lock().unwrap().take()
+    }
 }
 
 // TODO: instead of Arc, maybe have a global list of windows and ids? That ways it's same as webgpu, but might be harder to handle? Would likely also require a Mutex.
 #[derive(Clone)]
 pub struct MiniCanvasArc(pub Arc<MiniCanvas>);
 
+/// Host-side access to the native window, for embedding the runtime into an
+/// existing UI. Not exposed to the guest.
+impl MiniCanvasArc {
+    pub fn raw_window_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawWindowHandle, raw_window_handle::HandleError> {
+        Ok(self.window_handle()?.as_raw())
+    }
+
+    pub fn raw_display_handle(
+        &self,
+    ) -> Result<raw_window_handle::RawDisplayHandle, raw_window_handle::HandleError> {
+        Ok(self.display_handle()?.as_raw())
+    }
+}
+
 impl HasDisplayHandle for MiniCanvasArc {
     fn display_handle(
         &self,
@@ -258,22 +824,29 @@ impl DisplayApi for MiniCanvasArc {
     fn width(&self) -> u32 {
         self.0.width()
     }
-}
 
-fn unwrap_unless_inactive<T>(res: Result<Option<T>, TrySendError<T>>) {
-    if let Err(e) = &res {
-        if let TrySendError::Inactive(_) = e {
-            return;
-        }
+    fn presented(&self) {
+        self.0.presented();
+    }
+
+    fn take_input_arrival(&self) -> Option<Instant> {
+        self.0.take_input_arrival()
+    }
+
+    fn released(&self) -> bool {
+        self.0.released.load(Ordering::Relaxed)
     }
-    res.unwrap();
 }
 
-fn unwrap_unless_inactive_or_full<T>(res: Result<Option<T>, TrySendError<T>>) {
-    if let Err(e) = &res {
-        if matches!(e, TrySendError::Inactive(_) | TrySendError::Full(_)) {
+/// Events sent while the channel is full are dropped, and counted in `dropped`.
+fn unwrap_unless_inactive_or_full<T>(res: Result<Option<T>, TrySendError<T>>, dropped: &AtomicU64) {
+    match &res {
+        Err(TrySendError::Inactive(_)) => return,
+        Err(TrySendError::Full(_)) => {
+            dropped.fetch_add(1, Ordering::Relaxed);
             return;
         }
+        _ => {}
     }
     res.unwrap();
 }
@@ -292,8 +865,61 @@ impl wasmtime_wasi::Subscribe for ResizeListener {
     }
 }
 
+#[derive(Debug)]
+pub struct ThemeListener {
+    receiver: Receiver<Theme>,
+    data: Mutex<Option<Theme>>,
+}
+
+#[async_trait::async_trait]
+impl wasmtime_wasi::Subscribe for ThemeListener {
+    async fn ready(&mut self) {
+        let event = self.receiver.recv().await.unwrap();
+        *self.data.lock().unwrap() = Some(event);
+    }
+}
+
 // wasmtime
-impl mini_canvas::Host for dyn WasiMiniCanvasView + '_ {}
+impl mini_canvas::Host for dyn WasiMiniCanvasView + '_ {
+    fn attach_canvas(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        canvas: Resource<MiniCanvasArc>,
+    ) -> Result<(), String> {
+        let canvas = self
+            .table()
+            .get(&canvas)
+            .map_err(|e| e.to_string())?
+            .clone();
+        let graphics_context = self.table().get_mut(&context).map_err(|e| e.to_string())?;
+        graphics_context.attach_display_api(canvas.0.id, Box::new(canvas))
+    }
+
+    fn detach_canvas(
+        &mut self,
+        context: Resource<GraphicsContext>,
+        canvas: Resource<MiniCanvasArc>,
+    ) -> Result<(), String> {
+        let key = self.table().get(&canvas).map_err(|e| e.to_string())?.0.id;
+        let graphics_context = self.table().get_mut(&context).map_err(|e| e.to_string())?;
+        graphics_context.detach_display_api(key)
+    }
+
+    fn set_gamepad_rumble(
+        &mut self,
+        index: u32,
+        strong: f32,
+        weak: f32,
+        duration_ms: u32,
+    ) -> Result<(), String> {
+        let rumble = Rumble {
+            strong,
+            weak,
+            duration: Duration::from_millis(duration_ms.into()),
+        };
+        gamepads::set_rumble(self.gamepads().as_deref(), index, rumble)
+    }
+}
 
 #[async_trait::async_trait]
 impl mini_canvas::HostMiniCanvas for dyn WasiMiniCanvasView + '_ {
@@ -338,7 +964,182 @@ impl mini_canvas::HostMiniCanvas for dyn WasiMiniCanvasView + '_ {
         mini_canvas.width()
     }
 
-    fn drop(&mut self, _self_: Resource<MiniCanvasArc>) -> wasmtime::Result<()> {
+    fn set_title(&mut self, mini_canvas: Resource<MiniCanvasArc>, title: String) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_title(title);
+    }
+
+    fn set_resizable(&mut self, mini_canvas: Resource<MiniCanvasArc>, resizable: bool) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_resizable(resizable);
+    }
+
+    fn set_decorations(&mut self, mini_canvas: Resource<MiniCanvasArc>, decorations: bool) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_decorations(decorations);
+    }
+
+    fn set_min_size(&mut self, mini_canvas: Resource<MiniCanvasArc>, size: Option<CanvasSize>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_min_size(size);
+    }
+
+    fn set_max_size(&mut self, mini_canvas: Resource<MiniCanvasArc>, size: Option<CanvasSize>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_max_size(size);
+    }
+
+    fn set_aspect_ratio(&mut self, mini_canvas: Resource<MiniCanvasArc>, ratio: Option<f32>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_aspect_ratio(ratio);
+    }
+
+    fn set_raw_pointer_moves(&mut self, mini_canvas: Resource<MiniCanvasArc>, raw: bool) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.set_raw_pointer_moves(raw);
+    }
+
+    fn request_pointer_lock(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.set_pointer_lock(true);
+    }
+
+    fn exit_pointer_lock(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.set_pointer_lock(false);
+    }
+
+    fn request_redraw(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.request_redraw();
+    }
+
+    fn pause_frames(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.pause_frames();
+    }
+
+    fn resume_frames(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.resume_frames();
+    }
+
+    fn get_scale_factor(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> f64 {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.scale_factor()
+    }
+
+    fn get_inner_size(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> WindowSize {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.inner_size()
+    }
+
+    fn get_outer_size(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> WindowSize {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.outer_size()
+    }
+
+    fn get_refresh_rate(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Option<u32> {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.refresh_rate()
+    }
+
+    fn set_window_opacity(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+        opacity: f64,
+    ) -> Result<(), String> {
+        if opacity.is_nan() {
+            return Err("opacity can't be NaN".to_string());
+        }
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_opacity(opacity.clamp(0.0, 1.0))
+    }
+
+    fn get_window_opacity(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Option<f64> {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.opacity()
+    }
+
+    fn get_monitors(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Vec<MonitorInfo> {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.monitors()
+    }
+
+    fn set_exclusive_fullscreen(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+        monitor: u32,
+        mode: u32,
+    ) -> Result<(), String> {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.set_exclusive_fullscreen(monitor, mode)
+    }
+
+    fn exit_fullscreen(&mut self, mini_canvas: Resource<MiniCanvasArc>) {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.window.exit_fullscreen();
+    }
+
+    fn set_custom_cursor(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+        rgba: Vec<u8>,
+        width: u16,
+        height: u16,
+        hotspot_x: u16,
+        hotspot_y: u16,
+    ) -> Result<(), String> {
+        let expected = width as usize * height as usize * 4;
+        if rgba.len() != expected {
+            return Err(format!(
+                "a {width}x{height} cursor needs {expected} bytes of rgba, got {}",
+                rgba.len()
+            ));
+        }
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas
+            .0
+            .window
+            .set_custom_cursor(rgba, width, height, hotspot_x, hotspot_y)
+    }
+
+    fn get_dropped_event_stats(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+    ) -> DroppedEventStats {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.dropped_event_stats()
+    }
+
+    fn get_theme(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Theme {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.theme()
+    }
+
+    fn theme_listener(&mut self, mini_canvas: Resource<MiniCanvasArc>) -> Resource<ThemeListener> {
+        let canvas = &self.table().get(&mini_canvas).unwrap().0;
+        let receiver = canvas.theme_sender.new_receiver();
+        self.table()
+            .push(ThemeListener {
+                receiver,
+                data: Default::default(),
+            })
+            .unwrap()
+    }
+
+    fn get_cursor_position(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+    ) -> Option<CursorPosition> {
+        let mini_canvas = self.table().get(&mini_canvas).unwrap();
+        mini_canvas.0.cursor_position()
+    }
+
+    fn drop(&mut self, self_: Resource<MiniCanvasArc>) -> wasmtime::Result<()> {
+        // Contexts it's connected to keep it alive, but stop mirroring to it.
+        let canvas = self.table().delete(self_)?;
+        canvas.0.released.store(true, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -355,3 +1156,291 @@ impl mini_canvas::HostResizeListener for dyn WasiMiniCanvasView + '_ {
         Ok(())
     }
 }
+
+impl mini_canvas::HostThemeListener for dyn WasiMiniCanvasView + '_ {
+    fn subscribe(&mut self, theme: Resource<ThemeListener>) -> Resource<Pollable> {
+        wasmtime_wasi::subscribe(self.table(), theme).unwrap()
+    }
+    fn get(&mut self, theme: Resource<ThemeListener>) -> Option<Theme> {
+        let theme = self.table().get(&theme).unwrap();
+        theme.data.lock().unwrap().take()
+    }
+    fn drop(&mut self, _self_: Resource<ThemeListener>) -> wasmtime::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_canvas::{FakeWindow, TestView};
+
+    fn pointer_at(x: f64) -> PointerEvent {
+        PointerEvent {
+            x,
+            y: 0.0,
+            logical_x: x,
+            logical_y: 0.0,
+        }
+    }
+
+    #[test]
+    fn pointer_moves_between_frames_are_coalesced_to_the_latest() {
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        let mut moves = canvas.pointer_move_sender.new_receiver();
+        let proxy = canvas.proxy();
+        for x in 0..100 {
+            proxy.pointer_move(pointer_at(x as f64));
+        }
+        assert!(moves.try_recv().is_err());
+        proxy.animation_frame();
+        assert_eq!(moves.try_recv().unwrap().x, 99.0);
+        assert!(moves.try_recv().is_err());
+        // Nothing moved since, so the next frame delivers no move.
+        proxy.animation_frame();
+        assert!(moves.try_recv().is_err());
+        assert_eq!(canvas.dropped_event_stats().pointer_move, 0);
+    }
+
+    #[test]
+    fn raw_pointer_moves_are_delivered_right_away() {
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        let mut moves = canvas.pointer_move_sender.new_receiver();
+        let proxy = canvas.proxy();
+        canvas.set_raw_pointer_moves(true);
+        for x in 0..3 {
+            proxy.pointer_move(pointer_at(x as f64));
+        }
+        for x in 0..3 {
+            assert_eq!(moves.try_recv().unwrap().x, x as f64);
+        }
+        proxy.animation_frame();
+        assert!(moves.try_recv().is_err());
+    }
+
+    #[test]
+    fn redraws_signal_present_now_apart_from_frames() {
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        let mut presents = canvas.present_sender.new_receiver();
+        let mut frames = canvas.frame_sender.new_receiver();
+        let proxy = canvas.proxy();
+        proxy.present_now();
+        assert!(presents.try_recv().is_ok());
+        assert!(frames.try_recv().is_err());
+        proxy.animation_frame();
+        assert!(presents.try_recv().is_err());
+        assert!(frames.try_recv().is_ok());
+    }
+
+    #[test]
+    fn pressed_keys_are_those_not_released_yet() {
+        let key = |code: &str, scancode| KeyEvent {
+            code: code.into(),
+            key: code.into(),
+            location: KeyLocation::Standard,
+            alt_key: false,
+            ctrl_key: false,
+            meta_key: false,
+            shift_key: false,
+            scancode,
+        };
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        let proxy = canvas.proxy();
+        proxy.key_down(key("KeyW", 17));
+        proxy.key_down(key("KeyA", 30));
+        proxy.key_up(key("KeyW", 17));
+        assert_eq!(canvas.pressed_keys(), ["KeyA"]);
+        assert_eq!(canvas.pressed_scancodes(), [30]);
+        // Key ups get lost while out of focus.
+        proxy.focus_lost();
+        assert!(canvas.pressed_keys().is_empty());
+        assert!(canvas.pressed_scancodes().is_empty());
+    }
+
+    #[cfg(feature = "inject-input")]
+    #[test]
+    fn injected_input_arrives_in_order() {
+        use crate::wasi::webgpu::{
+            key_events::{self, HostKeyDownListener},
+            pointer_events::{self, HostPointerDownListener},
+        };
+        use futures::{executor::block_on, FutureExt};
+        use mini_canvas::{HostMiniCanvas, HostResizeListener};
+        use wasmtime_wasi::Subscribe;
+
+        fn nothing_left(listener: &mut impl Subscribe) -> bool {
+            listener.ready().now_or_never().is_none()
+        }
+
+        let key = |code: &str| KeyEvent {
+            code: code.into(),
+            key: code.into(),
+            location: KeyLocation::Standard,
+            alt_key: false,
+            ctrl_key: false,
+            meta_key: false,
+            shift_key: false,
+            scancode: 0,
+        };
+        let mut view = TestView::new();
+        let canvas = view.canvas();
+        let host = view.host();
+        let downs = block_on(pointer_events::Host::down_listener(
+            host,
+            Resource::new_borrow(canvas.rep()),
+        ));
+        let keys = block_on(key_events::Host::down_listener(
+            host,
+            Resource::new_borrow(canvas.rep()),
+        ));
+        let resizes = host.resize_listener(Resource::new_borrow(canvas.rep()));
+        let proxy = host.table().get(&canvas).unwrap().0.proxy();
+        for x in 0..3 {
+            proxy.inject(InjectedInput::PointerDown(pointer_at(x as f64)));
+        }
+        for code in ["KeyA", "KeyB", "KeyC"] {
+            proxy.inject(InjectedInput::KeyDown(key(code)));
+        }
+        for width in [100, 200] {
+            proxy.inject(InjectedInput::Resize(ResizeEvent { height: 50, width }));
+        }
+
+        for x in 0..3 {
+            block_on(host.table().get_mut(&downs).unwrap().ready());
+            let down = HostPointerDownListener::get(host, Resource::new_borrow(downs.rep()));
+            assert_eq!(down.unwrap().x, x as f64);
+        }
+        for code in ["KeyA", "KeyB", "KeyC"] {
+            block_on(host.table().get_mut(&keys).unwrap().ready());
+            let key = HostKeyDownListener::get(host, Resource::new_borrow(keys.rep()));
+            assert_eq!(key.unwrap().code, code);
+        }
+        for width in [100, 200] {
+            block_on(host.table().get_mut(&resizes).unwrap().ready());
+            let resize = HostResizeListener::get(host, Resource::new_borrow(resizes.rep()));
+            assert_eq!(resize.unwrap().width, width);
+        }
+        assert!(nothing_left(host.table().get_mut(&downs).unwrap()));
+        assert!(nothing_left(host.table().get_mut(&keys).unwrap()));
+        assert!(nothing_left(host.table().get_mut(&resizes).unwrap()));
+    }
+
+    #[test]
+    fn theme_listeners_get_theme_changes() {
+        use mini_canvas::{HostMiniCanvas, HostThemeListener};
+
+        let mut view = TestView::new();
+        let canvas = view.canvas();
+        let host = view.host();
+        let listener = host.theme_listener(Resource::new_borrow(canvas.rep()));
+        assert_eq!(host.get(Resource::new_borrow(listener.rep())), None);
+        let proxy = host.table().get(&canvas).unwrap().0.proxy();
+        proxy.theme_changed(Theme::Dark);
+        futures::executor::block_on(wasmtime_wasi::Subscribe::ready(
+            host.table().get_mut(&listener).unwrap(),
+        ));
+        assert_eq!(host.get(listener), Some(Theme::Dark));
+        assert_eq!(host.get_theme(canvas), Theme::Dark);
+    }
+
+    #[test]
+    fn custom_cursors_need_4_bytes_per_pixel() {
+        use mini_canvas::HostMiniCanvas;
+
+        let mut view = TestView::new();
+        let canvas = view.canvas();
+        let host = view.host();
+        let mut set_cursor = |rgba: Vec<u8>| {
+            host.set_custom_cursor(Resource::new_borrow(canvas.rep()), rgba, 4, 2, 1, 1)
+        };
+        assert_eq!(
+            set_cursor(vec![255; 31]),
+            Err("a 4x2 cursor needs 32 bytes of rgba, got 31".to_string())
+        );
+        assert!(set_cursor(vec![255; 33]).is_err());
+        // The right size gets through to the window, which has no cursors.
+        assert_eq!(
+            set_cursor(vec![255; 32]),
+            Err("custom cursors aren't supported by this canvas".to_string())
+        );
+    }
+
+    #[test]
+    fn window_sizes_match_the_size_the_canvas_was_created_with() {
+        use mini_canvas::HostMiniCanvas;
+
+        let mut view = TestView::new();
+        let canvas = view.canvas();
+        let host = view.host();
+        let inner = host.get_inner_size(Resource::new_borrow(canvas.rep()));
+        assert_eq!((inner.width, inner.height), (640, 480));
+        assert_eq!(inner.scale_factor, 1.0);
+        // The fake window has no decorations.
+        let outer = host.get_outer_size(canvas);
+        assert_eq!((outer.width, outer.height), (640, 480));
+    }
+
+    #[test]
+    fn events_dropped_on_a_full_channel_are_counted() {
+        let dropped = AtomicU64::new(0);
+        let (sender, _receiver) = async_broadcast::broadcast(2);
+        for event in 0..5 {
+            unwrap_unless_inactive_or_full(sender.try_broadcast(event), &dropped);
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        // Without an active receiver, nobody misses the events.
+        let (sender, receiver) = async_broadcast::broadcast(1);
+        let _receiver = receiver.deactivate();
+        for event in 0..5 {
+            unwrap_unless_inactive_or_full(sender.try_broadcast(event), &dropped);
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn queues_hold_as_many_events_as_their_capacity() {
+        let capacities = EventQueueCapacities { input: 8, frame: 3 };
+        let canvas = MiniCanvas::with_queue_capacities(Box::new(FakeWindow), capacities);
+        let _downs = canvas.pointer_down_sender.new_receiver();
+        let _presents = canvas.present_sender.new_receiver();
+        let proxy = canvas.proxy();
+        for x in 0..8 {
+            proxy.pointer_down(pointer_at(x as f64));
+        }
+        for _ in 0..3 {
+            proxy.present_now();
+        }
+        assert_eq!(canvas.dropped_event_stats().pointer_down, 0);
+        assert_eq!(canvas.dropped_event_stats().present, 0);
+        proxy.pointer_down(pointer_at(8.0));
+        proxy.present_now();
+        assert_eq!(canvas.dropped_event_stats().pointer_down, 1);
+        assert_eq!(canvas.dropped_event_stats().present, 1);
+    }
+
+    #[test]
+    fn frame_clock_leaves_out_paused_time() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut clock = FrameClock::started_at(start);
+        assert_eq!(clock.time_at(at(1)), Duration::from_secs(1));
+        clock.pause_at(at(1));
+        // Stands still while paused, and pausing again changes nothing.
+        assert_eq!(clock.time_at(at(3)), Duration::from_secs(1));
+        clock.pause_at(at(3));
+        clock.resume_at(at(4));
+        assert_eq!(clock.time_at(at(5)), Duration::from_secs(2));
+        // Resuming a running clock changes nothing either.
+        clock.resume_at(at(6));
+        assert_eq!(clock.time_at(at(7)), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn frame_clocks_start_at_the_time_origin() {
+        let first = FrameClock::new();
+        std::thread::sleep(Duration::from_millis(2));
+        let second = FrameClock::new();
+        assert_eq!(first.started, time_origin());
+        assert_eq!(second.started, time_origin());
+    }
+}
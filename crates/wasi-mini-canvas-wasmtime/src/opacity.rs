@@ -0,0 +1,142 @@
+//! Window opacity, which winit has no api for.
+//!
+//! Only X11 is supported, through the `_NET_WM_WINDOW_OPACITY` hint that
+//! compositors read. Elsewhere setting it fails and it can't be queried.
+
+use winit::window::Window;
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+mod x11 {
+    use std::sync::OnceLock;
+
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, PropMode, Window};
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    use super::OPAQUE;
+
+    /// The X11 id of `window`, if it's an X11 window.
+    pub(super) fn window_id(window: &winit::window::Window) -> Option<Window> {
+        match window.window_handle().ok()?.as_raw() {
+            RawWindowHandle::Xlib(handle) => Some(handle.window as Window),
+            RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+            _ => None,
+        }
+    }
+
+    /// The connection to the X server and the opacity hint's atom, made on
+    /// first use and kept for the rest of the run.
+    fn connection() -> Result<&'static (RustConnection, u32), String> {
+        static CONNECTION: OnceLock<Result<(RustConnection, u32), String>> = OnceLock::new();
+        let connect = || {
+            let (connection, _) = x11rb::connect(None).map_err(|e| e.to_string())?;
+            let atom = connection
+                .intern_atom(false, b"_NET_WM_WINDOW_OPACITY")
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?
+                .atom;
+            Ok((connection, atom))
+        };
+        CONNECTION
+            .get_or_init(connect)
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    pub(super) fn set_opacity(window: Window, value: Option<u32>) -> Result<(), String> {
+        let (connection, atom) = connection()?;
+        if let Some(value) = value {
+            connection
+                .change_property32(
+                    PropMode::REPLACE,
+                    window,
+                    *atom,
+                    AtomEnum::CARDINAL,
+                    &[value],
+                )
+                .map_err(|e| e.to_string())?;
+        } else {
+            // Compositors treat a window without the hint as opaque.
+            connection
+                .delete_property(window, *atom)
+                .map_err(|e| e.to_string())?;
+        }
+        connection.flush().map_err(|e| e.to_string())
+    }
+
+    pub(super) fn opacity(window: Window) -> Option<f64> {
+        let (connection, atom) = connection().ok()?;
+        let reply = connection
+            .get_property(false, window, *atom, AtomEnum::CARDINAL, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let value = reply.value32().and_then(|mut values| values.next());
+        Some(value.unwrap_or(OPAQUE) as f64 / OPAQUE as f64)
+    }
+}
+
+/// `_NET_WM_WINDOW_OPACITY` for a fully opaque window.
+const OPAQUE: u32 = u32::MAX;
+
+/// The opacity hint for `opacity`, which is clamped to 0 to 1. None for fully
+/// opaque, which is shown by leaving the hint out.
+fn opacity_hint(opacity: f64) -> Result<Option<u32>, String> {
+    if opacity.is_nan() {
+        return Err("opacity can't be NaN".to_string());
+    }
+    let opacity = opacity.clamp(0.0, 1.0);
+    if opacity == 1.0 {
+        return Ok(None);
+    }
+    Ok(Some((opacity * OPAQUE as f64).round() as u32))
+}
+
+/// Sets how opaque `window` is, from 0 to 1. Values outside that are clamped.
+pub(crate) fn set_opacity(window: &Window, opacity: f64) -> Result<(), String> {
+    let hint = opacity_hint(opacity)?;
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    if let Some(window) = x11::window_id(window) {
+        return x11::set_opacity(window, hint);
+    }
+    let _ = (window, hint);
+    Err("window opacity isn't supported on this platform".to_string())
+}
+
+/// How opaque `window` is, from 0 to 1. None where it can't be queried.
+pub(crate) fn opacity(window: &Window) -> Option<f64> {
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    if let Some(window) = x11::window_id(window) {
+        return x11::opacity(window);
+    }
+    let _ = window;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opacity_hints_are_clamped() {
+        assert_eq!(opacity_hint(0.0), Ok(Some(0)));
+        assert_eq!(opacity_hint(-1.0), Ok(Some(0)));
+        assert_eq!(opacity_hint(0.5), Ok(Some(OPAQUE / 2 + 1)));
+        assert_eq!(opacity_hint(1.0), Ok(None));
+        assert_eq!(opacity_hint(2.0), Ok(None));
+        assert_eq!(opacity_hint(f64::INFINITY), Ok(None));
+        assert!(opacity_hint(f64::NAN).is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use crate::{
-    wasi::webgpu::pointer_events::{self, PointerEvent, Pollable},
+    wasi::webgpu::pointer_events::{self, PointerEvent, Pollable, RawMotionEvent},
     MiniCanvasArc, WasiMiniCanvasView,
 };
 use async_broadcast::Receiver;
@@ -50,6 +50,18 @@ impl pointer_events::Host for dyn WasiMiniCanvasView + '_ {
             })
             .unwrap()
     }
+
+    async fn raw_motion_listener(
+        &mut self,
+        mini_canvas: Resource<MiniCanvasArc>,
+    ) -> Resource<PointerRawMotionListener> {
+        let canvas = &self.table().get(&mini_canvas).unwrap().0;
+        let receiver = canvas.pointer_raw_motion_sender.new_receiver();
+        let data = canvas.raw_motion.listen();
+        self.table()
+            .push(PointerRawMotionListener { receiver, data })
+            .unwrap()
+    }
 }
 
 impl pointer_events::HostPointerUpListener for dyn WasiMiniCanvasView + '_ {
@@ -132,3 +144,113 @@ impl wasmtime_wasi::Subscribe for PointerMoveListener {
         *self.data.lock().unwrap() = Some(event);
     }
 }
+
+impl pointer_events::HostPointerRawMotionListener for dyn WasiMiniCanvasView + '_ {
+    fn subscribe(&mut self, raw_motion: Resource<PointerRawMotionListener>) -> Resource<Pollable> {
+        wasmtime_wasi::subscribe(self.table(), raw_motion).unwrap()
+    }
+    fn get(&mut self, raw_motion: Resource<PointerRawMotionListener>) -> Option<RawMotionEvent> {
+        let raw_motion = self.table().get(&raw_motion).unwrap();
+        raw_motion.data.lock().unwrap().take()
+    }
+    fn drop(&mut self, _self_: Resource<PointerRawMotionListener>) -> wasmtime::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PointerRawMotionListener {
+    receiver: Receiver<()>,
+    /// Motion since the guest last got it, added to by `RawMotion`.
+    data: Arc<Mutex<Option<RawMotionEvent>>>,
+}
+
+#[async_trait::async_trait]
+impl wasmtime_wasi::Subscribe for PointerRawMotionListener {
+    async fn ready(&mut self) {
+        // Wake ups can be left over from motion the guest already got.
+        while self.data.lock().unwrap().is_none() {
+            self.receiver.recv().await.unwrap();
+        }
+    }
+}
+
+/// Relative pointer motion not yet read, per raw motion listener. Motion is
+/// summed up rather than queued, so none is lost while the guest is busy.
+#[derive(Debug, Default)]
+pub(crate) struct RawMotion {
+    listeners: Mutex<Vec<Weak<Mutex<Option<RawMotionEvent>>>>>,
+}
+
+impl RawMotion {
+    fn listen(&self) -> Arc<Mutex<Option<RawMotionEvent>>> {
+        let data = Arc::default();
+        self.listeners.lock().unwrap().push(Arc::downgrade(&data));
+        data
+    }
+
+    /// Adds `event` to the motion pending for every listener still around.
+    pub(crate) fn add(&self, event: RawMotionEvent) {
+        self.listeners.lock().unwrap().retain(|listener| {
+            let Some(data) = listener.upgrade() else {
+                return false;
+            };
+            let mut data = data.lock().unwrap();
+            let pending = data.get_or_insert(RawMotionEvent { dx: 0.0, dy: 0.0 });
+            pending.dx += event.dx;
+            pending.dy += event.dy;
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motion(dx: f64, dy: f64) -> RawMotionEvent {
+        RawMotionEvent { dx, dy }
+    }
+
+    fn take(data: &Mutex<Option<RawMotionEvent>>) -> Option<(f64, f64)> {
+        data.lock()
+            .unwrap()
+            .take()
+            .map(|event| (event.dx, event.dy))
+    }
+
+    #[test]
+    fn motion_adds_up_until_read() {
+        let raw_motion = RawMotion::default();
+        let data = raw_motion.listen();
+        assert_eq!(take(&data), None);
+        for _ in 0..100 {
+            raw_motion.add(motion(1.5, -2.0));
+        }
+        assert_eq!(take(&data), Some((150.0, -200.0)));
+        assert_eq!(take(&data), None);
+        raw_motion.add(motion(0.5, 0.5));
+        assert_eq!(take(&data), Some((0.5, 0.5)));
+    }
+
+    #[test]
+    fn each_listener_gets_all_motion() {
+        let raw_motion = RawMotion::default();
+        let first = raw_motion.listen();
+        raw_motion.add(motion(1.0, 1.0));
+        let second = raw_motion.listen();
+        raw_motion.add(motion(2.0, 3.0));
+        assert_eq!(take(&first), Some((3.0, 4.0)));
+        assert_eq!(take(&second), Some((2.0, 3.0)));
+    }
+
+    #[test]
+    fn dropped_listeners_are_forgotten() {
+        let raw_motion = RawMotion::default();
+        let kept = raw_motion.listen();
+        drop(raw_motion.listen());
+        raw_motion.add(motion(1.0, 0.0));
+        assert_eq!(raw_motion.listeners.lock().unwrap().len(), 1);
+        assert_eq!(take(&kept), Some((1.0, 0.0)));
+    }
+}
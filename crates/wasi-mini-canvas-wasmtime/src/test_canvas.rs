@@ -0,0 +1,87 @@
+//! A host for calling the mini-canvas host functions directly, without a
+//! guest, with canvases on windows that need no window system.
+
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+use wasi_graphics_context_wasmtime::DisplayApi;
+use wasmtime::component::Resource;
+use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+
+use crate::{
+    mini_canvas, CanvasWindow, MiniCanvas, MiniCanvasArc, MiniCanvasDesc, WasiMiniCanvasView,
+};
+
+/// A 640 by 480 window with no window system behind it.
+pub(crate) struct FakeWindow;
+
+impl HasDisplayHandle for FakeWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl HasWindowHandle for FakeWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl DisplayApi for FakeWindow {
+    fn height(&self) -> u32 {
+        480
+    }
+
+    fn width(&self) -> u32 {
+        640
+    }
+}
+
+impl CanvasWindow for FakeWindow {}
+
+pub(crate) struct TestView {
+    table: ResourceTable,
+    ctx: WasiCtx,
+}
+
+impl TestView {
+    pub(crate) fn new() -> Self {
+        Self {
+            table: ResourceTable::new(),
+            ctx: WasiCtxBuilder::new().build(),
+        }
+    }
+
+    /// The host functions, called as if from a guest.
+    pub(crate) fn host(&mut self) -> &mut dyn WasiMiniCanvasView {
+        self
+    }
+
+    /// A canvas on a `FakeWindow`, as the guest would hold it.
+    pub(crate) fn canvas(&mut self) -> Resource<MiniCanvasArc> {
+        mini_canvas::HostMiniCanvas::new(
+            self.host(),
+            MiniCanvasDesc {
+                height: 480,
+                width: 640,
+                offscreen: false,
+            },
+        )
+    }
+}
+
+impl WasiView for TestView {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+impl WasiMiniCanvasView for TestView {
+    fn create_canvas(&self, _desc: MiniCanvasDesc) -> MiniCanvas {
+        MiniCanvas::new(Box::new(FakeWindow))
+    }
+}
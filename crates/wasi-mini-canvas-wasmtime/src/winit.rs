@@ -7,25 +7,97 @@ use std::{
     time::Duration,
 };
 
-use crate::{MiniCanvas, MiniCanvasDesc, MiniCanvasProxy};
+use crate::{
+    CanvasSize, CanvasWindow, CursorPosition, EventQueueCapacities, MiniCanvas, MiniCanvasDesc,
+    MiniCanvasProxy, MonitorInfo, Theme, VideoMode, WindowSize,
+};
 use raw_window_handle::{
     DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
 };
 use wasi_graphics_context_wasmtime::DisplayApi;
 use winit::{
     application::ApplicationHandler,
-    dpi::Size,
-    event::{ElementState, WindowEvent},
+    dpi::{LogicalSize, PhysicalSize, Size},
+    event::{DeviceEvent, DeviceId, ElementState, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
-    keyboard::ModifiersState,
-    window::{Window, WindowAttributes, WindowId},
+    keyboard::{Key, KeyLocation, ModifiersState, PhysicalKey},
+    window::{
+        CursorGrabMode, CursorIcon, CustomCursor, CustomCursorSource, Fullscreen, Window,
+        WindowAttributes, WindowId,
+    },
 };
 
+/// What the guest gets for a key press or release.
+fn key_event(
+    physical_key: PhysicalKey,
+    logical_key: &Key,
+    location: KeyLocation,
+    modifiers: ModifiersState,
+) -> crate::KeyEvent {
+    crate::KeyEvent {
+        code: match physical_key {
+            PhysicalKey::Code(code) => format!("{code:?}"),
+            PhysicalKey::Unidentified(_) => "Unidentified".to_string(),
+        },
+        // Named keys are spelled the way the DOM's `KeyboardEvent.key` has them,
+        // e.g. "Shift" or "ArrowLeft".
+        key: match logical_key {
+            Key::Character(char) => char.to_string(),
+            Key::Named(named) => format!("{named:?}"),
+            Key::Unidentified(_) | Key::Dead(_) => "Unidentified".to_string(),
+        },
+        location: match location {
+            KeyLocation::Standard => crate::KeyLocation::Standard,
+            KeyLocation::Left => crate::KeyLocation::Left,
+            KeyLocation::Right => crate::KeyLocation::Right,
+            KeyLocation::Numpad => crate::KeyLocation::Numpad,
+        },
+        alt_key: modifiers.alt_key(),
+        ctrl_key: modifiers.control_key(),
+        meta_key: modifiers.super_key(),
+        shift_key: modifiers.shift_key(),
+        scancode: to_scancode(physical_key),
+    }
+}
+
+/// The platform scancode of `key`, or 0 where winit doesn't expose one.
+fn to_scancode(key: PhysicalKey) -> u32 {
+    #[cfg(any(
+        windows,
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    {
+        use winit::platform::scancode::PhysicalKeyExtScancode;
+        key.to_scancode().unwrap_or(0)
+    }
+    #[cfg(not(any(
+        windows,
+        target_os = "macos",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )))]
+    {
+        let _ = key;
+        0
+    }
+}
+
 pub fn create_wasi_winit_event_loop() -> (WasiWinitEventLoop, WasiWinitEventLoopProxy) {
     let event_loop = WasiWinitEventLoop {
         event_loop: winit::event_loop::EventLoop::<MainThreadAction>::with_user_event()
             .build()
             .unwrap(),
+        on_demand: false,
+        canvas_size: None,
+        queue_capacities: EventQueueCapacities::default(),
     };
     let message_sender = WasiWinitEventLoopProxy {
         proxy: event_loop.event_loop.create_proxy(),
@@ -33,11 +105,171 @@ pub fn create_wasi_winit_event_loop() -> (WasiWinitEventLoop, WasiWinitEventLoop
     (event_loop, message_sender)
 }
 
+/// The min and max sizes a guest set on its canvas's window, in logical pixels.
+#[derive(Clone, Copy, Default)]
+struct SizeLimits {
+    min: Option<CanvasSize>,
+    max: Option<CanvasSize>,
+}
+
+/// `size` clamped to `limits`, at `scale_factor` physical pixels per logical pixel.
+fn clamp_to_size_limits(
+    size: PhysicalSize<u32>,
+    limits: SizeLimits,
+    scale_factor: f64,
+) -> PhysicalSize<u32> {
+    let physical = |size: CanvasSize| {
+        LogicalSize::new(size.width as f64, size.height as f64).to_physical::<u32>(scale_factor)
+    };
+    let mut clamped = size;
+    if let Some(max) = limits.max.map(physical) {
+        clamped.width = clamped.width.min(max.width);
+        clamped.height = clamped.height.min(max.height);
+    }
+    if let Some(min) = limits.min.map(physical) {
+        clamped.width = clamped.width.max(min.width);
+        clamped.height = clamped.height.max(min.height);
+    }
+    clamped
+}
+
+/// A pointer event at `x`, `y` in physical pixels, with the logical position
+/// at `scale_factor` physical pixels per logical pixel.
+fn pointer_event(x: f64, y: f64, scale_factor: f64) -> crate::PointerEvent {
+    crate::PointerEvent {
+        x,
+        y,
+        logical_x: x / scale_factor,
+        logical_y: y / scale_factor,
+    }
+}
+
+/// Where the pointer last was over each window, in physical pixels. Shared by
+/// the event loop, which tracks it, and the windows' canvases.
+#[derive(Clone, Default)]
+struct PointerPositions(Arc<Mutex<HashMap<WindowId, CursorPosition>>>);
+
+impl PointerPositions {
+    fn moved(&self, window_id: WindowId, x: f64, y: f64) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(window_id, CursorPosition { x, y });
+    }
+
+    fn get(&self, window_id: WindowId) -> Option<CursorPosition> {
+        self.0.lock().unwrap().get(&window_id).copied()
+    }
+
+    fn remove(&self, window_id: WindowId) {
+        self.0.lock().unwrap().remove(&window_id);
+    }
+}
+
+/// Refresh rate of the monitor `window` is on, in millihertz.
+fn refresh_rate(window: &Window) -> Option<u32> {
+    window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+}
+
+/// The monitors `window` could go fullscreen on, with their video modes.
+fn monitors(window: &Window) -> Vec<MonitorInfo> {
+    window
+        .available_monitors()
+        .map(|monitor| MonitorInfo {
+            name: monitor.name(),
+            video_modes: monitor
+                .video_modes()
+                .map(|mode| VideoMode {
+                    width: mode.size().width,
+                    height: mode.size().height,
+                    bit_depth: mode.bit_depth(),
+                    refresh_rate: mode.refresh_rate_millihertz(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// `monitor` and `mode` index into `monitors`.
+fn set_exclusive_fullscreen(window: &Window, monitor: u32, mode: u32) -> Result<(), String> {
+    let monitor = window
+        .available_monitors()
+        .nth(monitor as usize)
+        .ok_or_else(|| format!("there's no monitor {monitor}"))?;
+    let mode = monitor
+        .video_modes()
+        .nth(mode as usize)
+        .ok_or_else(|| format!("the monitor has no video mode {mode}"))?;
+    window.set_fullscreen(Some(Fullscreen::Exclusive(mode)));
+    // Where exclusive fullscreen isn't supported, e.g. on Wayland, winit
+    // only logs a warning and leaves the window as it was.
+    match window.fullscreen() {
+        Some(Fullscreen::Exclusive(_)) => Ok(()),
+        _ => Err("exclusive fullscreen isn't supported on this platform".to_string()),
+    }
+}
+
+fn to_theme(theme: winit::window::Theme) -> Theme {
+    match theme {
+        winit::window::Theme::Light => Theme::Light,
+        winit::window::Theme::Dark => Theme::Dark,
+    }
+}
+
+/// Passes `event` from the window `window_id` on to the canvas on that window,
+/// for the events that need nothing from the window itself.
+fn route_window_event(
+    proxies: &HashMap<WindowId, MiniCanvasProxy>,
+    window_id: WindowId,
+    event: &WindowEvent,
+) {
+    let Some(proxy) = proxies.get(&window_id) else {
+        return;
+    };
+    match event {
+        WindowEvent::RedrawRequested => proxy.present_now(),
+        WindowEvent::Focused(false) => proxy.focus_lost(),
+        WindowEvent::ThemeChanged(theme) => proxy.theme_changed(to_theme(*theme)),
+        _ => {}
+    }
+}
+
+/// Sends `proxy` its frame for this tick. On demand, only if a redraw was
+/// requested or input arrived since its last one.
+fn frame_tick(proxy: &MiniCanvasProxy, on_demand: bool) {
+    if on_demand && !proxy.take_redraw_request() {
+        return;
+    }
+    proxy.animation_frame();
+}
+
 pub struct WasiWinitEventLoop {
     event_loop: EventLoop<MainThreadAction>,
+    on_demand: bool,
+    canvas_size: Option<CanvasSize>,
+    queue_capacities: EventQueueCapacities,
 }
 
 impl WasiWinitEventLoop {
+    /// Only send frames to canvases that requested a redraw or got input since
+    /// the last frame, instead of on every tick.
+    pub fn set_on_demand(&mut self, on_demand: bool) {
+        self.on_demand = on_demand;
+    }
+
+    /// Opens every window at exactly this size in physical pixels, instead of
+    /// the size the guest asks for.
+    pub fn set_canvas_size(&mut self, size: Option<CanvasSize>) {
+        self.canvas_size = size;
+    }
+
+    /// How many events each new canvas holds for its guest before dropping more.
+    pub fn set_queue_capacities(&mut self, capacities: EventQueueCapacities) {
+        self.queue_capacities = capacities;
+    }
+
     /// This has to be run on the main thread.
     /// This call will block the thread.
     pub fn run(self) {
@@ -45,41 +277,264 @@ impl WasiWinitEventLoop {
 
         {
             let proxies = Arc::clone(&proxies);
+            let on_demand = self.on_demand;
             thread::spawn(move || loop {
                 for (_, proxy) in proxies.lock().unwrap().iter() {
-                    proxy.animation_frame();
+                    frame_tick(proxy, on_demand);
                 }
                 sleep(Duration::from_millis(16));
             });
         }
 
-        struct MyWindow(pub Window);
+        struct MyWindow {
+            window: Arc<Window>,
+            main_thread_proxy: EventLoopProxy<MainThreadAction>,
+            on_demand: bool,
+            pointer_pos: PointerPositions,
+        }
+        impl MyWindow {
+            fn send(&self, action: WindowAction) {
+                // Fails once the event loop exited, when there's no window left to change.
+                let _ = self
+                    .main_thread_proxy
+                    .send_event(MainThreadAction::Window(self.window.id(), action));
+            }
+        }
         impl HasDisplayHandle for MyWindow {
             fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
-                self.0.display_handle()
+                self.window.display_handle()
             }
         }
         impl HasWindowHandle for MyWindow {
             fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
-                self.0.window_handle()
+                self.window.window_handle()
             }
         }
         impl DisplayApi for MyWindow {
             fn height(&self) -> u32 {
-                self.0.inner_size().height
+                self.window.inner_size().height
             }
 
             fn width(&self) -> u32 {
-                self.0.inner_size().width
+                self.window.inner_size().width
+            }
+
+            fn presented(&self) {
+                // Asks for the next `RedrawRequested`, so presents keep following the
+                // compositor. On demand, only input and guest requests cause redraws.
+                if !self.on_demand {
+                    self.window.request_redraw();
+                }
+            }
+        }
+        impl CanvasWindow for MyWindow {
+            fn cursor_position(&self) -> Option<CursorPosition> {
+                self.pointer_pos.get(self.window.id())
+            }
+            fn set_title(&self, title: String) {
+                self.send(WindowAction::Title(title));
+            }
+
+            fn set_resizable(&self, resizable: bool) {
+                self.send(WindowAction::Resizable(resizable));
+            }
+
+            fn set_decorations(&self, decorations: bool) {
+                self.send(WindowAction::Decorations(decorations));
+            }
+
+            fn set_min_size(&self, size: Option<CanvasSize>) {
+                self.send(WindowAction::MinSize(size));
+            }
+
+            fn set_max_size(&self, size: Option<CanvasSize>) {
+                self.send(WindowAction::MaxSize(size));
+            }
+
+            fn set_aspect_ratio(&self, ratio: Option<f32>) {
+                self.send(WindowAction::AspectRatio(ratio));
+            }
+
+            fn set_pointer_lock(&self, locked: bool) {
+                self.send(WindowAction::PointerLock(locked));
+            }
+
+            fn scale_factor(&self) -> f64 {
+                self.window.scale_factor()
+            }
+
+            fn inner_size(&self) -> WindowSize {
+                let (sender, receiver) = oneshot::channel();
+                let _ = self.main_thread_proxy.send_event(MainThreadAction::Window(
+                    self.window.id(),
+                    WindowAction::InnerSize(sender),
+                ));
+                // Once the event loop is gone, the window can still answer itself.
+                receiver
+                    .recv()
+                    .unwrap_or_else(|_| window_size(&self.window, self.window.inner_size()))
+            }
+
+            fn outer_size(&self) -> WindowSize {
+                let (sender, receiver) = oneshot::channel();
+                let _ = self.main_thread_proxy.send_event(MainThreadAction::Window(
+                    self.window.id(),
+                    WindowAction::OuterSize(sender),
+                ));
+                receiver
+                    .recv()
+                    .unwrap_or_else(|_| window_size(&self.window, self.window.outer_size()))
+            }
+
+            fn refresh_rate(&self) -> Option<u32> {
+                // Monitors can only be queried from the main thread on some platforms.
+                let (sender, receiver) = oneshot::channel();
+                self.main_thread_proxy
+                    .send_event(MainThreadAction::Window(
+                        self.window.id(),
+                        WindowAction::RefreshRate(sender),
+                    ))
+                    .ok()?;
+                receiver.recv().ok().flatten()
+            }
+
+            fn set_opacity(&self, opacity: f64) -> Result<(), String> {
+                let (sender, receiver) = oneshot::channel();
+                self.main_thread_proxy
+                    .send_event(MainThreadAction::Window(
+                        self.window.id(),
+                        WindowAction::SetOpacity(opacity, sender),
+                    ))
+                    .map_err(|_| "the window is gone".to_string())?;
+                receiver
+                    .recv()
+                    .map_err(|_| "the window is gone".to_string())?
+            }
+
+            fn opacity(&self) -> Option<f64> {
+                let (sender, receiver) = oneshot::channel();
+                self.main_thread_proxy
+                    .send_event(MainThreadAction::Window(
+                        self.window.id(),
+                        WindowAction::Opacity(sender),
+                    ))
+                    .ok()?;
+                receiver.recv().ok().flatten()
+            }
+
+            fn monitors(&self) -> Vec<MonitorInfo> {
+                let (sender, receiver) = oneshot::channel();
+                let sent = self.main_thread_proxy.send_event(MainThreadAction::Window(
+                    self.window.id(),
+                    WindowAction::Monitors(sender),
+                ));
+                if sent.is_err() {
+                    return Vec::new();
+                }
+                receiver.recv().unwrap_or_default()
+            }
+
+            fn set_exclusive_fullscreen(&self, monitor: u32, mode: u32) -> Result<(), String> {
+                let (sender, receiver) = oneshot::channel();
+                self.main_thread_proxy
+                    .send_event(MainThreadAction::Window(
+                        self.window.id(),
+                        WindowAction::ExclusiveFullscreen(monitor, mode, sender),
+                    ))
+                    .map_err(|_| "the window is gone".to_string())?;
+                receiver
+                    .recv()
+                    .map_err(|_| "the window is gone".to_string())?
+            }
+
+            fn exit_fullscreen(&self) {
+                self.send(WindowAction::ExitFullscreen);
+            }
+
+            fn set_custom_cursor(
+                &self,
+                rgba: Vec<u8>,
+                width: u16,
+                height: u16,
+                hotspot_x: u16,
+                hotspot_y: u16,
+            ) -> Result<(), String> {
+                let cursor = CustomCursor::from_rgba(rgba, width, height, hotspot_x, hotspot_y)
+                    .map_err(|e| e.to_string())?;
+                // Only the event loop can turn the image into a cursor.
+                self.send(WindowAction::CustomCursor(cursor));
+                Ok(())
+            }
+        }
+
+        fn logical_size(size: Option<CanvasSize>) -> Option<Size> {
+            size.map(|size| Size::Logical((size.width as f64, size.height as f64).into()))
+        }
+
+        fn window_size(window: &Window, size: PhysicalSize<u32>) -> WindowSize {
+            WindowSize {
+                width: size.width,
+                height: size.height,
+                scale_factor: window.scale_factor(),
             }
         }
 
-        #[derive(Default)]
+        fn set_pointer_lock(window: &Window, locked: bool) {
+            if locked {
+                // Not every platform can lock the cursor in place, confining it is the next best thing.
+                let grabbed = window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+                if let Err(e) = grabbed {
+                    tracing::warn!("couldn't grab the cursor for the pointer lock: {e}");
+                }
+            } else {
+                let _ = window.set_cursor_grab(CursorGrabMode::None);
+            }
+            window.set_cursor_visible(!locked);
+        }
+
         struct App {
-            pointer_pos: HashMap<WindowId, (f64, f64)>,
+            main_thread_proxy: EventLoopProxy<MainThreadAction>,
+            on_demand: bool,
+            canvas_size: Option<CanvasSize>,
+            queue_capacities: EventQueueCapacities,
+            windows: HashMap<WindowId, Arc<Window>>,
+            aspect_ratios: HashMap<WindowId, f32>,
+            size_limits: HashMap<WindowId, SizeLimits>,
+            pointer_pos: PointerPositions,
             modifiers: HashMap<WindowId, ModifiersState>,
             proxies: HashMap<WindowId, MiniCanvasProxy>,
             arc_proxies: Arc<Mutex<HashMap<WindowId, MiniCanvasProxy>>>,
+            /// Released windows, handed out again before creating new ones.
+            spare_windows: Vec<Arc<Window>>,
+        }
+
+        impl App {
+            /// A pointer event at `x`, `y` in physical pixels, with the logical
+            /// position at the window's current scale factor.
+            fn pointer_event(&self, window_id: WindowId, x: f64, y: f64) -> crate::PointerEvent {
+                let scale_factor = self
+                    .windows
+                    .get(&window_id)
+                    .map_or(1.0, |window| window.scale_factor());
+                pointer_event(x, y, scale_factor)
+            }
+
+            /// Returns the size the window should have to keep its aspect ratio,
+            /// or `None` if it already does.
+            fn clamp_to_aspect_ratio(
+                &self,
+                window_id: WindowId,
+                size: PhysicalSize<u32>,
+            ) -> Option<PhysicalSize<u32>> {
+                let ratio = *self.aspect_ratios.get(&window_id)?;
+                let height = (size.width as f32 / ratio).round() as u32;
+                if height.abs_diff(size.height) <= 1 {
+                    return None;
+                }
+                Some(PhysicalSize::new(size.width, height))
+            }
         }
 
         impl ApplicationHandler<MainThreadAction> for App {
@@ -89,16 +544,39 @@ impl WasiWinitEventLoop {
 
             fn user_event(&mut self, event_loop: &ActiveEventLoop, event: MainThreadAction) {
                 match event {
+                    MainThreadAction::Exit => event_loop.exit(),
                     MainThreadAction::CreateWindow(desc, response_channel) => {
-                        let window_options = WindowAttributes::default().with_inner_size(
-                            Size::Logical((desc.width as f64, desc.height as f64).into()),
-                        );
-                        let window = event_loop.create_window(window_options).unwrap();
-                        // TODO: remove when window is drooped.
-                        self.pointer_pos.insert(window.id(), (0.0, 0.0));
+                        let size = match self.canvas_size {
+                            Some(size) => Size::Physical((size.width, size.height).into()),
+                            None => Size::Logical((desc.width as f64, desc.height as f64).into()),
+                        };
+                        let window = match self.spare_windows.pop() {
+                            Some(window) => {
+                                let _ = window.request_inner_size(size);
+                                window
+                            }
+                            None => {
+                                let window_options =
+                                    WindowAttributes::default().with_inner_size(size);
+                                Arc::new(event_loop.create_window(window_options).unwrap())
+                            }
+                        };
                         let window_id = window.id();
+                        self.windows.insert(window_id, Arc::clone(&window));
 
-                        let canvas = MiniCanvas::new(Box::new(MyWindow(window)));
+                        let theme = window.theme();
+                        let canvas = MiniCanvas::with_queue_capacities(
+                            Box::new(MyWindow {
+                                window,
+                                main_thread_proxy: self.main_thread_proxy.clone(),
+                                on_demand: self.on_demand,
+                                pointer_pos: self.pointer_pos.clone(),
+                            }),
+                            self.queue_capacities,
+                        );
+                        if let Some(theme) = theme {
+                            canvas.set_theme(to_theme(theme));
+                        }
 
                         self.proxies.insert(window_id, canvas.proxy());
                         self.arc_proxies
@@ -111,25 +589,146 @@ impl WasiWinitEventLoop {
                     MainThreadAction::Spawn(f, res) => {
                         res.send(f()).unwrap();
                     }
+                    MainThreadAction::ReleaseWindows => {
+                        let mut arc_proxies = self.arc_proxies.lock().unwrap();
+                        for (window_id, window) in self.windows.drain() {
+                            self.proxies.remove(&window_id);
+                            arc_proxies.remove(&window_id);
+                            self.aspect_ratios.remove(&window_id);
+                            self.size_limits.remove(&window_id);
+                            self.pointer_pos.remove(window_id);
+                            window.set_title(&WindowAttributes::default().title);
+                            window.set_resizable(true);
+                            window.set_decorations(true);
+                            window.set_min_inner_size(None::<Size>);
+                            window.set_max_inner_size(None::<Size>);
+                            set_pointer_lock(&window, false);
+                            window.set_fullscreen(None);
+                            window.set_cursor(CursorIcon::Default);
+                            self.spare_windows.push(window);
+                        }
+                    }
+                    #[cfg(feature = "inject-input")]
+                    MainThreadAction::WindowIds(response_channel) => {
+                        let _ = response_channel.send(self.proxies.keys().copied().collect());
+                    }
+                    #[cfg(feature = "inject-input")]
+                    MainThreadAction::InjectInput(window_id, input) => {
+                        if let Some(proxy) = self.proxies.get(&window_id) {
+                            if let crate::InjectedInput::PointerMove(event) = &input {
+                                self.pointer_pos.moved(window_id, event.x, event.y);
+                            }
+                            proxy.inject(input);
+                        }
+                    }
+                    MainThreadAction::Window(window_id, action) => {
+                        let Some(window) = self.windows.get(&window_id) else {
+                            // Dropping the action tells anyone waiting on a response.
+                            return;
+                        };
+                        match action {
+                            WindowAction::Title(title) => {
+                                window.set_title(&title);
+                            }
+                            WindowAction::Resizable(resizable) => {
+                                window.set_resizable(resizable);
+                            }
+                            WindowAction::Decorations(decorations) => {
+                                // winit reports the resize, if the inner size changes.
+                                window.set_decorations(decorations);
+                            }
+                            WindowAction::MinSize(size) => {
+                                window.set_min_inner_size(logical_size(size));
+                                self.size_limits.entry(window_id).or_default().min = size;
+                            }
+                            WindowAction::MaxSize(size) => {
+                                window.set_max_inner_size(logical_size(size));
+                                self.size_limits.entry(window_id).or_default().max = size;
+                            }
+                            WindowAction::AspectRatio(ratio) => {
+                                match ratio.filter(|ratio| *ratio > 0.0) {
+                                    Some(ratio) => {
+                                        self.aspect_ratios.insert(window_id, ratio);
+                                        let size = window.inner_size();
+                                        if let Some(size) =
+                                            self.clamp_to_aspect_ratio(window_id, size)
+                                        {
+                                            let _ = window.request_inner_size(size);
+                                        }
+                                    }
+                                    None => {
+                                        self.aspect_ratios.remove(&window_id);
+                                    }
+                                }
+                            }
+                            WindowAction::PointerLock(locked) => {
+                                set_pointer_lock(window, locked);
+                            }
+                            WindowAction::RefreshRate(response_channel) => {
+                                let _ = response_channel.send(refresh_rate(window));
+                            }
+                            WindowAction::SetOpacity(opacity, response_channel) => {
+                                let _ = response_channel
+                                    .send(crate::opacity::set_opacity(window, opacity));
+                            }
+                            WindowAction::Opacity(response_channel) => {
+                                let _ = response_channel.send(crate::opacity::opacity(window));
+                            }
+                            WindowAction::InnerSize(response_channel) => {
+                                let _ =
+                                    response_channel.send(window_size(window, window.inner_size()));
+                            }
+                            WindowAction::OuterSize(response_channel) => {
+                                let _ =
+                                    response_channel.send(window_size(window, window.outer_size()));
+                            }
+                            WindowAction::Monitors(response_channel) => {
+                                let _ = response_channel.send(monitors(window));
+                            }
+                            WindowAction::ExclusiveFullscreen(monitor, mode, response_channel) => {
+                                let _ = response_channel
+                                    .send(set_exclusive_fullscreen(window, monitor, mode));
+                            }
+                            WindowAction::ExitFullscreen => {
+                                window.set_fullscreen(None);
+                            }
+                            WindowAction::CustomCursor(cursor) => {
+                                window.set_cursor(event_loop.create_custom_cursor(cursor));
+                            }
+                        }
+                    }
                 }
             }
 
             fn window_event(
                 &mut self,
-                _event_loop: &ActiveEventLoop,
+                event_loop: &ActiveEventLoop,
                 window_id: WindowId,
                 event: WindowEvent,
             ) {
                 match event {
+                    WindowEvent::CloseRequested => {
+                        // The guest may still hold the canvas, so the window is
+                        // hidden rather than dropped. The others keep running.
+                        if let Some(window) = self.windows.remove(&window_id) {
+                            window.set_visible(false);
+                            self.proxies.remove(&window_id);
+                            self.arc_proxies.lock().unwrap().remove(&window_id);
+                            self.aspect_ratios.remove(&window_id);
+                            self.size_limits.remove(&window_id);
+                            self.modifiers.remove(&window_id);
+                            self.pointer_pos.remove(window_id);
+                        }
+                        if self.windows.is_empty() {
+                            event_loop.exit();
+                        }
+                    }
                     WindowEvent::CursorMoved { position, .. } => {
-                        self.pointer_pos
-                            .insert(window_id, (position.x, position.y))
-                            .unwrap();
+                        self.pointer_pos.moved(window_id, position.x, position.y);
                         if let Some(proxy) = self.proxies.get(&window_id) {
-                            proxy.pointer_move(crate::PointerEvent {
-                                x: position.x,
-                                y: position.y,
-                            });
+                            proxy.pointer_move(
+                                self.pointer_event(window_id, position.x, position.y),
+                            );
                         }
                     }
                     WindowEvent::ModifiersChanged(modifiers) => {
@@ -137,20 +736,12 @@ impl WasiWinitEventLoop {
                     }
                     WindowEvent::KeyboardInput { event: input, .. } => {
                         let modifiers = self.modifiers.get(&window_id).unwrap();
-                        let event = crate::KeyEvent {
-                            code: match input.physical_key {
-                                winit::keyboard::PhysicalKey::Code(code) => format!("{code:?}"),
-                                winit::keyboard::PhysicalKey::Unidentified(_) => todo!(),
-                            },
-                            key: match input.logical_key {
-                                winit::keyboard::Key::Character(char) => char.to_string(),
-                                _ => todo!(),
-                            },
-                            alt_key: modifiers.alt_key(),
-                            ctrl_key: modifiers.control_key(),
-                            meta_key: modifiers.super_key(),
-                            shift_key: modifiers.shift_key(),
-                        };
+                        let event = key_event(
+                            input.physical_key,
+                            &input.logical_key,
+                            input.location,
+                            *modifiers,
+                        );
                         if let Some(proxy) = self.proxies.get(&window_id) {
                             match input.state {
                                 ElementState::Pressed => {
@@ -163,11 +754,11 @@ impl WasiWinitEventLoop {
                         }
                     }
                     WindowEvent::MouseInput { state, .. } => {
-                        let (pointer_x, pointer_y) = self.pointer_pos.get(&window_id).unwrap();
-                        let event = crate::PointerEvent {
-                            x: *pointer_x,
-                            y: *pointer_y,
-                        };
+                        let (pointer_x, pointer_y) = self
+                            .pointer_pos
+                            .get(window_id)
+                            .map_or((0.0, 0.0), |position| (position.x, position.y));
+                        let event = self.pointer_event(window_id, pointer_x, pointer_y);
                         if let Some(proxy) = self.proxies.get(&window_id) {
                             match state {
                                 ElementState::Pressed => {
@@ -179,7 +770,28 @@ impl WasiWinitEventLoop {
                             }
                         }
                     }
-                    WindowEvent::Resized(new_size) => {
+                    WindowEvent::Resized(mut new_size) => {
+                        // Not every platform enforces the size limits, e.g. tiling
+                        // window managers, so resizes past them are undone here.
+                        if let Some(limits) = self.size_limits.get(&window_id) {
+                            let window = &self.windows[&window_id];
+                            let limited =
+                                clamp_to_size_limits(new_size, *limits, window.scale_factor());
+                            if limited != new_size {
+                                match window.request_inner_size(limited) {
+                                    Some(size) => new_size = size,
+                                    // A new `Resized` event with the limited size will follow.
+                                    None => return,
+                                }
+                            }
+                        }
+                        if let Some(clamped) = self.clamp_to_aspect_ratio(window_id, new_size) {
+                            match self.windows[&window_id].request_inner_size(clamped) {
+                                Some(size) => new_size = size,
+                                // A new `Resized` event with the clamped size will follow.
+                                None => return,
+                            }
+                        }
                         if let Some(proxy) = self.proxies.get(&window_id) {
                             proxy.canvas_resize(crate::ResizeEvent {
                                 height: new_size.height,
@@ -187,13 +799,39 @@ impl WasiWinitEventLoop {
                             });
                         }
                     }
-                    _ => {}
+                    event => route_window_event(&self.proxies, window_id, &event),
+                }
+            }
+
+            fn device_event(
+                &mut self,
+                _event_loop: &ActiveEventLoop,
+                _device_id: DeviceId,
+                event: DeviceEvent,
+            ) {
+                if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+                    // Not tied to a window, canvases without the pointer lock ignore it.
+                    for proxy in self.proxies.values() {
+                        proxy.pointer_raw_motion(crate::RawMotionEvent { dx, dy });
+                    }
                 }
             }
         }
 
-        let mut app = App::default();
-        app.arc_proxies = Arc::clone(&proxies);
+        let mut app = App {
+            main_thread_proxy: self.event_loop.create_proxy(),
+            on_demand: self.on_demand,
+            canvas_size: self.canvas_size,
+            queue_capacities: self.queue_capacities,
+            windows: Default::default(),
+            aspect_ratios: Default::default(),
+            size_limits: Default::default(),
+            pointer_pos: Default::default(),
+            modifiers: Default::default(),
+            proxies: Default::default(),
+            arc_proxies: Arc::clone(&proxies),
+            spare_windows: Vec::new(),
+        };
         self.event_loop.run_app(&mut app).unwrap();
     }
 }
@@ -227,25 +865,508 @@ impl WasiWinitEventLoopProxy {
             .unwrap();
         *receiver.await.unwrap().downcast().unwrap()
     }
+
+    /// Disconnects all windows from their canvases, and keeps them open to be
+    /// reused by the next `create_window` calls, e.g. when reloading the guest.
+    pub fn release_windows(&self) {
+        self.proxy
+            .send_event(MainThreadAction::ReleaseWindows)
+            .unwrap();
+    }
+
+    /// Windows that are connected to a canvas, in no particular order.
+    #[cfg(feature = "inject-input")]
+    pub async fn window_ids(&self) -> Vec<WindowId> {
+        let (sender, receiver) = oneshot::channel();
+        self.proxy
+            .send_event(MainThreadAction::WindowIds(sender))
+            .unwrap();
+        receiver.await.unwrap()
+    }
+
+    /// Delivers `input` to the canvas of `window` on the main thread, ordered
+    /// with the window's real events.
+    #[cfg(feature = "inject-input")]
+    pub fn inject_input(&self, window: WindowId, input: crate::InjectedInput) {
+        // The loop is already gone if this fails, and nothing is listening.
+        let _ = self
+            .proxy
+            .send_event(MainThreadAction::InjectInput(window, input));
+    }
+
+    /// Makes `WasiWinitEventLoop::run` return.
+    pub fn exit(&self) {
+        // The loop is already gone if this fails.
+        let _ = self.proxy.send_event(MainThreadAction::Exit);
+    }
 }
 
 enum MainThreadAction {
+    Exit,
     CreateWindow(MiniCanvasDesc, oneshot::Sender<MiniCanvas>),
     Spawn(
         Box<dyn FnOnce() -> Box<dyn Any + Send + Sync> + Send + Sync>,
         oneshot::Sender<Box<dyn Any + Send + Sync>>,
     ),
+    Window(WindowId, WindowAction),
+    ReleaseWindows,
+    #[cfg(feature = "inject-input")]
+    WindowIds(oneshot::Sender<Vec<WindowId>>),
+    #[cfg(feature = "inject-input")]
+    InjectInput(WindowId, crate::InjectedInput),
+}
+
+#[derive(Debug)]
+enum WindowAction {
+    Title(String),
+    Resizable(bool),
+    Decorations(bool),
+    MinSize(Option<CanvasSize>),
+    MaxSize(Option<CanvasSize>),
+    AspectRatio(Option<f32>),
+    PointerLock(bool),
+    RefreshRate(oneshot::Sender<Option<u32>>),
+    SetOpacity(f64, oneshot::Sender<Result<(), String>>),
+    Opacity(oneshot::Sender<Option<f64>>),
+    InnerSize(oneshot::Sender<WindowSize>),
+    OuterSize(oneshot::Sender<WindowSize>),
+    Monitors(oneshot::Sender<Vec<MonitorInfo>>),
+    ExclusiveFullscreen(u32, u32, oneshot::Sender<Result<(), String>>),
+    ExitFullscreen,
+    CustomCursor(CustomCursorSource),
 }
 
 impl Debug for MainThreadAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Exit => f.debug_tuple("Exit").finish(),
             Self::CreateWindow(arg0, arg1) => f
                 .debug_tuple("CreateWindow")
                 .field(arg0)
                 .field(arg1)
                 .finish(),
             Self::Spawn(_, _) => f.debug_tuple("Spawn").finish(),
+            Self::Window(arg0, arg1) => f.debug_tuple("Window").field(arg0).field(arg1).finish(),
+            Self::ReleaseWindows => f.debug_tuple("ReleaseWindows").finish(),
+            #[cfg(feature = "inject-input")]
+            Self::WindowIds(arg0) => f.debug_tuple("WindowIds").field(arg0).finish(),
+            #[cfg(feature = "inject-input")]
+            Self::InjectInput(arg0, arg1) => f
+                .debug_tuple("InjectInput")
+                .field(arg0)
+                .field(arg1)
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, OnceLock};
+
+    use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+    use winit::keyboard::{KeyCode, NamedKey, NativeKey, NativeKeyCode};
+
+    use super::*;
+    use crate::{test_canvas::FakeWindow, MiniCanvasArc};
+
+    /// A window of the test run's own, or `None` if there's no display to
+    /// open it on. Its event loop runs on a thread of its own, since winit
+    /// only lets a process create one and tests don't get the main thread.
+    fn test_window() -> Option<Arc<Window>> {
+        static WINDOW: OnceLock<Option<Arc<Window>>> = OnceLock::new();
+        let window = WINDOW.get_or_init(open_test_window).clone();
+        if window.is_none() {
+            eprintln!("skipping: no display");
+        }
+        window
+    }
+
+    /// Sizes the test window was resized to, in the order winit reported them.
+    static TEST_WINDOW_RESIZES: Mutex<Vec<PhysicalSize<u32>>> = Mutex::new(Vec::new());
+
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    fn open_test_window() -> Option<Arc<Window>> {
+        #[cfg(target_os = "windows")]
+        use winit::platform::windows::EventLoopBuilderExtWindows;
+        #[cfg(target_os = "linux")]
+        use winit::platform::x11::EventLoopBuilderExtX11;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let Ok(event_loop) = EventLoop::builder().with_any_thread(true).build() else {
+                sender.send(None).unwrap();
+                return;
+            };
+            #[allow(deprecated)]
+            let window = event_loop
+                .create_window(Window::default_attributes().with_title("mini-canvas test"))
+                .ok()
+                .map(Arc::new);
+            let opened = window.is_some();
+            sender.send(window).unwrap();
+            if opened {
+                #[allow(deprecated)]
+                let _ = event_loop.run(|event, _| {
+                    if let winit::event::Event::WindowEvent {
+                        event: WindowEvent::Resized(size),
+                        ..
+                    } = event
+                    {
+                        TEST_WINDOW_RESIZES.lock().unwrap().push(size);
+                    }
+                });
+            }
+        });
+        receiver.recv().unwrap()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    fn open_test_window() -> Option<Arc<Window>> {
+        None
+    }
+
+    /// A canvas window with only the title and decorations of the window controls.
+    struct TestWindow(Arc<Window>);
+
+    impl HasDisplayHandle for TestWindow {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            self.0.display_handle()
         }
     }
+
+    impl HasWindowHandle for TestWindow {
+        fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+            self.0.window_handle()
+        }
+    }
+
+    impl DisplayApi for TestWindow {
+        fn height(&self) -> u32 {
+            self.0.inner_size().height
+        }
+
+        fn width(&self) -> u32 {
+            self.0.inner_size().width
+        }
+    }
+
+    impl CanvasWindow for TestWindow {
+        fn set_title(&self, title: String) {
+            self.0.set_title(&title);
+        }
+
+        fn set_decorations(&self, decorations: bool) {
+            self.0.set_decorations(decorations);
+        }
+    }
+
+    #[test]
+    fn canvases_give_embedders_the_platform_window_handles() {
+        let Some(window) = test_window() else {
+            return;
+        };
+        let canvas = MiniCanvasArc(Arc::new(MiniCanvas::new(Box::new(TestWindow(window)))));
+        let window_handle = canvas.raw_window_handle().unwrap();
+        let display_handle = canvas.raw_display_handle().unwrap();
+        #[cfg(target_os = "linux")]
+        assert!(
+            matches!(
+                (window_handle, display_handle),
+                (RawWindowHandle::Xlib(_), RawDisplayHandle::Xlib(_))
+                    | (RawWindowHandle::Xcb(_), RawDisplayHandle::Xcb(_))
+                    | (RawWindowHandle::Wayland(_), RawDisplayHandle::Wayland(_))
+            ),
+            "{window_handle:?}, {display_handle:?}"
+        );
+        #[cfg(target_os = "windows")]
+        assert!(
+            matches!(
+                (window_handle, display_handle),
+                (RawWindowHandle::Win32(_), RawDisplayHandle::Windows(_))
+            ),
+            "{window_handle:?}, {display_handle:?}"
+        );
+    }
+
+    #[test]
+    fn refresh_rates_are_positive_where_reported() {
+        let Some(window) = test_window() else {
+            return;
+        };
+        if let Some(rate) = refresh_rate(&window) {
+            assert!(rate > 0);
+        }
+        let primary = window
+            .primary_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz());
+        if let Some(rate) = primary {
+            assert!(rate > 0);
+        }
+    }
+
+    #[test]
+    fn windows_enter_and_exit_exclusive_fullscreen_where_supported() {
+        let Some(window) = test_window() else {
+            return;
+        };
+        let monitors = monitors(&window);
+        for mode in monitors.iter().flat_map(|monitor| &monitor.video_modes) {
+            assert!(mode.width > 0 && mode.height > 0, "{mode:?}");
+        }
+        assert!(set_exclusive_fullscreen(&window, monitors.len() as u32, 0).is_err());
+        let Some(monitor) = monitors
+            .iter()
+            .position(|monitor| !monitor.video_modes.is_empty())
+        else {
+            eprintln!("skipping: no monitor with video modes");
+            return;
+        };
+        if let Err(e) = set_exclusive_fullscreen(&window, monitor as u32, 0) {
+            eprintln!("skipping: {e}");
+            return;
+        }
+        assert!(matches!(
+            window.fullscreen(),
+            Some(Fullscreen::Exclusive(_))
+        ));
+        window.set_fullscreen(None);
+        assert!(window.fullscreen().is_none());
+    }
+
+    #[test]
+    fn guests_set_the_window_title() {
+        use wasmtime::component::Resource;
+
+        use crate::{mini_canvas::HostMiniCanvas, test_canvas::TestView};
+
+        let Some(window) = test_window() else {
+            return;
+        };
+        let mut view = TestView::new();
+        let host = view.host();
+        let canvas = host
+            .table()
+            .push(MiniCanvasArc(Arc::new(MiniCanvas::new(Box::new(
+                TestWindow(Arc::clone(&window)),
+            )))))
+            .unwrap();
+        host.set_title(Resource::new_borrow(canvas.rep()), "set-title test".into());
+        // Some platforms, like X11, can't read titles back.
+        let title = window.title();
+        if !title.is_empty() {
+            assert_eq!(title, "set-title test");
+        }
+        window.set_title("mini-canvas test");
+    }
+
+    #[test]
+    fn decorations_toggle_with_a_resize_where_the_size_changes() {
+        use wasmtime::component::Resource;
+
+        use crate::{mini_canvas::HostMiniCanvas, test_canvas::TestView};
+
+        let Some(window) = test_window() else {
+            return;
+        };
+        let mut view = TestView::new();
+        let host = view.host();
+        let canvas = host
+            .table()
+            .push(MiniCanvasArc(Arc::new(MiniCanvas::new(Box::new(
+                TestWindow(Arc::clone(&window)),
+            )))))
+            .unwrap();
+        for decorations in [false, true] {
+            let size = window.inner_size();
+            host.set_decorations(Resource::new_borrow(canvas.rep()), decorations);
+            // The window manager takes its time, if there is one.
+            thread::sleep(Duration::from_millis(200));
+            assert_eq!(window.is_decorated(), decorations);
+            let new_size = window.inner_size();
+            if new_size != size {
+                assert!(TEST_WINDOW_RESIZES.lock().unwrap().contains(&new_size));
+            }
+        }
+    }
+
+    #[test]
+    fn window_events_go_to_the_canvas_on_their_window() {
+        let first = MiniCanvas::new(Box::new(FakeWindow));
+        let second = MiniCanvas::new(Box::new(FakeWindow));
+        let (first_id, second_id) = (WindowId::from(1), WindowId::from(2));
+        let proxies = HashMap::from([(first_id, first.proxy()), (second_id, second.proxy())]);
+        let mut first_themes = first.theme_sender.new_receiver();
+        let mut second_themes = second.theme_sender.new_receiver();
+        let mut first_presents = first.present_sender.new_receiver();
+        let mut second_presents = second.present_sender.new_receiver();
+
+        let dark = WindowEvent::ThemeChanged(winit::window::Theme::Dark);
+        route_window_event(&proxies, first_id, &dark);
+        assert_eq!(first_themes.try_recv(), Ok(Theme::Dark));
+        assert!(second_themes.try_recv().is_err());
+
+        route_window_event(&proxies, second_id, &WindowEvent::RedrawRequested);
+        assert!(second_presents.try_recv().is_ok());
+        assert!(first_presents.try_recv().is_err());
+
+        // Events from windows without a canvas go nowhere.
+        route_window_event(&proxies, WindowId::from(3), &dark);
+        assert!(first_themes.try_recv().is_err());
+        assert!(second_themes.try_recv().is_err());
+    }
+
+    #[test]
+    fn on_demand_frames_wait_for_a_redraw_request() {
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        let mut frames = canvas.frame_sender.new_receiver();
+        let proxy = canvas.proxy();
+        for _ in 0..3 {
+            frame_tick(&proxy, true);
+        }
+        assert!(frames.try_recv().is_err());
+        canvas.request_redraw();
+        frame_tick(&proxy, true);
+        frame_tick(&proxy, true);
+        assert!(frames.try_recv().is_ok());
+        assert!(frames.try_recv().is_err());
+        // Without on demand, every tick is a frame.
+        frame_tick(&proxy, false);
+        assert!(frames.try_recv().is_ok());
+    }
+
+    #[test]
+    fn pointer_positions_follow_moves() {
+        let positions = PointerPositions::default();
+        // Only compared against itself.
+        let window_id = unsafe { WindowId::dummy() };
+        let position = || positions.get(window_id).map(|p| (p.x, p.y));
+        assert_eq!(position(), None);
+        positions.moved(window_id, 12.0, 34.5);
+        assert_eq!(position(), Some((12.0, 34.5)));
+        positions.moved(window_id, 20.0, 10.0);
+        assert_eq!(position(), Some((20.0, 10.0)));
+        positions.remove(window_id);
+        assert_eq!(position(), None);
+    }
+
+    #[test]
+    fn logical_pointer_positions_are_scaled_down() {
+        let event = pointer_event(300.0, 120.5, 2.0);
+        assert_eq!((event.x, event.y), (300.0, 120.5));
+        assert_eq!((event.logical_x, event.logical_y), (150.0, 60.25));
+        let event = pointer_event(300.0, 120.5, 1.0);
+        assert_eq!((event.logical_x, event.logical_y), (300.0, 120.5));
+    }
+
+    #[test]
+    fn resizes_below_the_min_size_are_clamped_to_it() {
+        let limits = SizeLimits {
+            min: Some(CanvasSize {
+                width: 320,
+                height: 240,
+            }),
+            max: Some(CanvasSize {
+                width: 1920,
+                height: 1080,
+            }),
+        };
+        let clamp = |width, height, scale_factor| {
+            let size = clamp_to_size_limits(PhysicalSize::new(width, height), limits, scale_factor);
+            (size.width, size.height)
+        };
+        assert_eq!(clamp(100, 50, 1.0), (320, 240));
+        assert_eq!(clamp(100, 500, 1.0), (320, 500));
+        assert_eq!(clamp(800, 600, 1.0), (800, 600));
+        assert_eq!(clamp(4000, 600, 1.0), (1920, 600));
+        // The limits are logical, sizes physical.
+        assert_eq!(clamp(400, 400, 2.0), (640, 480));
+        assert_eq!(
+            clamp_to_size_limits(PhysicalSize::new(10, 10), SizeLimits::default(), 1.0),
+            PhysicalSize::new(10, 10)
+        );
+    }
+
+    #[test]
+    fn numpad_digits_report_the_numpad_location() {
+        let event = key_event(
+            PhysicalKey::Code(KeyCode::Numpad7),
+            &Key::Character("7".into()),
+            KeyLocation::Numpad,
+            ModifiersState::empty(),
+        );
+        assert_eq!(event.code, "Numpad7");
+        assert_eq!(event.key, "7");
+        assert_eq!(event.location, crate::KeyLocation::Numpad);
+    }
+
+    #[test]
+    fn left_and_right_shift_keep_their_location() {
+        let shift = |code, location| {
+            key_event(
+                PhysicalKey::Code(code),
+                &Key::Named(NamedKey::Shift),
+                location,
+                ModifiersState::SHIFT,
+            )
+        };
+        let left = shift(KeyCode::ShiftLeft, KeyLocation::Left);
+        let right = shift(KeyCode::ShiftRight, KeyLocation::Right);
+        assert_eq!(
+            (left.key.as_str(), left.location),
+            ("Shift", crate::KeyLocation::Left)
+        );
+        assert_eq!(
+            (right.key.as_str(), right.location),
+            ("Shift", crate::KeyLocation::Right)
+        );
+        assert!(left.shift_key);
+    }
+
+    #[test]
+    fn game_keys_are_named() {
+        let key = |logical_key: Key| {
+            key_event(
+                PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+                &logical_key,
+                KeyLocation::Standard,
+                ModifiersState::empty(),
+            )
+            .key
+        };
+        assert_eq!(key(Key::Named(NamedKey::ArrowLeft)), "ArrowLeft");
+        assert_eq!(key(Key::Named(NamedKey::Space)), "Space");
+        assert_eq!(key(Key::Named(NamedKey::Escape)), "Escape");
+        assert_eq!(key(Key::Dead(Some('`'))), "Unidentified");
+        assert_eq!(
+            key(Key::Unidentified(NativeKey::Unidentified)),
+            "Unidentified"
+        );
+    }
+
+    /// The same physical key, A on QWERTY, is Q on AZERTY.
+    #[test]
+    fn scancodes_are_the_same_across_layouts() {
+        let press = |logical_key: &str| {
+            key_event(
+                PhysicalKey::Code(KeyCode::KeyA),
+                &Key::Character(logical_key.into()),
+                KeyLocation::Standard,
+                ModifiersState::empty(),
+            )
+        };
+        let (qwerty, azerty) = (press("a"), press("q"));
+        assert_ne!(qwerty.key, azerty.key);
+        assert_eq!(qwerty.code, azerty.code);
+        assert_eq!(qwerty.scancode, azerty.scancode);
+        #[cfg(target_os = "linux")]
+        assert_eq!(qwerty.scancode, 30);
+        let unidentified = key_event(
+            PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+            &Key::Character("a".into()),
+            KeyLocation::Standard,
+            ModifiersState::empty(),
+        );
+        assert_eq!(unidentified.code, "Unidentified");
+    }
 }
@@ -0,0 +1,149 @@
+//! Input injected through `WasiWinitEventLoopProxy::inject_input` reaches the
+//! guest's listeners in order, through the event loop of a real window.
+//! Machines without a display skip it.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    thread,
+};
+
+use futures::executor::block_on;
+use wasi_mini_canvas_wasmtime::{
+    create_wasi_winit_event_loop,
+    wasi::webgpu::{
+        key_events::{self, HostKeyDownListener},
+        mini_canvas::{HostMiniCanvas, HostResizeListener},
+        pointer_events::{self, HostPointerDownListener},
+    },
+    InjectedInput, KeyEvent, KeyLocation, MiniCanvas, MiniCanvasArc, MiniCanvasDesc, PointerEvent,
+    ResizeEvent, WasiMiniCanvasView, WasiWinitEventLoopProxy,
+};
+use wasmtime::component::Resource;
+use wasmtime_wasi::{ResourceTable, Subscribe, WasiCtx, WasiCtxBuilder, WasiView};
+
+/// A host for calling the mini-canvas host functions directly, without a guest.
+struct TestView {
+    table: ResourceTable,
+    ctx: WasiCtx,
+}
+
+impl WasiView for TestView {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+impl WasiMiniCanvasView for TestView {
+    fn create_canvas(&self, _desc: MiniCanvasDesc) -> MiniCanvas {
+        unreachable!("canvases come from the event loop")
+    }
+}
+
+fn key(code: &str) -> KeyEvent {
+    KeyEvent {
+        code: code.into(),
+        key: code.into(),
+        location: KeyLocation::Standard,
+        alt_key: false,
+        ctrl_key: false,
+        meta_key: false,
+        shift_key: false,
+        scancode: 0,
+    }
+}
+
+fn pointer_at(x: f64) -> PointerEvent {
+    PointerEvent {
+        x,
+        y: 0.0,
+        logical_x: x,
+        logical_y: 0.0,
+    }
+}
+
+fn injected_input_arrives_in_order(event_loop: &WasiWinitEventLoopProxy) {
+    let canvas = block_on(event_loop.create_window(MiniCanvasDesc {
+        height: 480,
+        width: 640,
+        offscreen: false,
+    }));
+    let [window] = block_on(event_loop.window_ids())[..] else {
+        panic!("expected just the canvas's window");
+    };
+    let mut view = TestView {
+        table: ResourceTable::new(),
+        ctx: WasiCtxBuilder::new().build(),
+    };
+    let canvas = view.table.push(MiniCanvasArc(Arc::new(canvas))).unwrap();
+    let host: &mut dyn WasiMiniCanvasView = &mut view;
+    let downs = block_on(pointer_events::Host::down_listener(
+        host,
+        Resource::new_borrow(canvas.rep()),
+    ));
+    let keys = block_on(key_events::Host::down_listener(
+        host,
+        Resource::new_borrow(canvas.rep()),
+    ));
+    let resizes = host.resize_listener(Resource::new_borrow(canvas.rep()));
+
+    for x in 0..3 {
+        event_loop.inject_input(window, InjectedInput::PointerDown(pointer_at(x as f64)));
+    }
+    for code in ["KeyA", "KeyB", "KeyC"] {
+        event_loop.inject_input(window, InjectedInput::KeyDown(key(code)));
+    }
+    for width in [100, 200] {
+        let resize = ResizeEvent { height: 50, width };
+        event_loop.inject_input(window, InjectedInput::Resize(resize));
+    }
+
+    for x in 0..3 {
+        block_on(host.table().get_mut(&downs).unwrap().ready());
+        let down = HostPointerDownListener::get(host, Resource::new_borrow(downs.rep()));
+        assert_eq!(down.unwrap().x, x as f64);
+    }
+    for code in ["KeyA", "KeyB", "KeyC"] {
+        block_on(host.table().get_mut(&keys).unwrap().ready());
+        let key = HostKeyDownListener::get(host, Resource::new_borrow(keys.rep()));
+        assert_eq!(key.unwrap().code, code);
+    }
+    let mut widths = Vec::new();
+    while widths.len() < 2 {
+        block_on(host.table().get_mut(&resizes).unwrap().ready());
+        let resize = HostResizeListener::get(host, Resource::new_borrow(resizes.rep()));
+        let resize = resize.unwrap();
+        // The window system can resize the new window in between.
+        if resize.height == 50 {
+            widths.push(resize.width);
+        }
+    }
+    assert_eq!(widths, [100, 200]);
+}
+
+fn main() {
+    // Creating the event loop panics without a display, quietly here.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let created = panic::catch_unwind(create_wasi_winit_event_loop);
+    panic::set_hook(hook);
+    let Ok((event_loop, proxy)) = created else {
+        eprintln!("skipping: no display");
+        return;
+    };
+    let test = thread::spawn(move || {
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| injected_input_arrives_in_order(&proxy)));
+        proxy.exit();
+        result
+    });
+    event_loop.run();
+    if let Err(panic) = test.join().unwrap() {
+        panic::resume_unwind(panic);
+    }
+    println!("test injected_input_arrives_in_order ... ok");
+}
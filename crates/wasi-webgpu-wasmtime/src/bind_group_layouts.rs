@@ -0,0 +1,157 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use wgpu_core::id::{BindGroupLayoutId, DeviceId};
+
+type LayoutKey = (DeviceId, Vec<wgpu_types::BindGroupLayoutEntry>);
+
+/// Hands out one bind group layout for each set of structurally identical ones.
+///
+/// Guests often create the same layout again and again, e.g. once per pipeline,
+/// and wgpu-core makes a new one every time. With the cache, layouts with the
+/// same device and entries share one wgpu layout, labelled after the first of
+/// them. It's dropped once the guest dropped every handle to it.
+#[derive(Default)]
+pub struct BindGroupLayoutCache {
+    layouts: Mutex<Layouts>,
+}
+
+#[derive(Default)]
+struct Layouts {
+    by_key: HashMap<LayoutKey, BindGroupLayoutId>,
+    // Guest handles to each layout, and the key to remove it by once there are none.
+    handles: HashMap<BindGroupLayoutId, (usize, LayoutKey)>,
+}
+
+impl BindGroupLayoutCache {
+    /// The layout with these entries, made with `create` if there's none yet.
+    /// Each call takes a handle, given back with `release`.
+    pub(crate) fn get_or_create<E>(
+        &self,
+        device: DeviceId,
+        entries: &[wgpu_types::BindGroupLayoutEntry],
+        create: impl FnOnce() -> Result<BindGroupLayoutId, E>,
+    ) -> Result<BindGroupLayoutId, E> {
+        let mut entries = entries.to_vec();
+        // Entries are looked up by binding, so their order doesn't matter.
+        entries.sort_by_key(|entry| entry.binding);
+        let key = (device, entries);
+        let mut layouts = self.layouts.lock().unwrap();
+        let layout = match layouts.by_key.get(&key) {
+            Some(layout) => *layout,
+            None => {
+                let layout = create()?;
+                layouts.by_key.insert(key.clone(), layout);
+                layout
+            }
+        };
+        layouts.handles.entry(layout).or_insert((0, key)).0 += 1;
+        Ok(layout)
+    }
+
+    /// Gives back a handle to `layout`. Returns whether it was the last one, and
+    /// the layout should be dropped. Layouts the cache didn't hand out have no
+    /// other handles.
+    pub(crate) fn release(&self, layout: BindGroupLayoutId) -> bool {
+        let mut layouts = self.layouts.lock().unwrap();
+        let Some((handles, _)) = layouts.handles.get_mut(&layout) else {
+            return true;
+        };
+        *handles -= 1;
+        if *handles > 0 {
+            return false;
+        }
+        let (_, key) = layouts.handles.remove(&layout).unwrap();
+        layouts.by_key.remove(&key);
+        true
+    }
+
+    /// How many distinct layouts are cached.
+    pub fn len(&self) -> usize {
+        self.layouts.lock().unwrap().by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use wgpu_core::id::Id;
+
+    use super::*;
+
+    fn entry(binding: u32) -> wgpu_types::BindGroupLayoutEntry {
+        wgpu_types::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu_types::ShaderStages::FRAGMENT,
+            ty: wgpu_types::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+            count: None,
+        }
+    }
+
+    fn id<T: wgpu_core::id::Marker>(index: u32) -> Id<T> {
+        Id::zip(index, 1, wgpu_types::Backend::Empty)
+    }
+
+    /// Gets a layout from `cache`, made with the next id in `next` if it's new.
+    fn get(
+        cache: &BindGroupLayoutCache,
+        device: DeviceId,
+        entries: &[wgpu_types::BindGroupLayoutEntry],
+        next: &Cell<u32>,
+    ) -> BindGroupLayoutId {
+        let create = || {
+            next.set(next.get() + 1);
+            Ok::<_, ()>(id(next.get()))
+        };
+        cache.get_or_create(device, entries, create).unwrap()
+    }
+
+    #[test]
+    fn identical_layouts_share_one() {
+        let cache = BindGroupLayoutCache::default();
+        let next = Cell::new(0);
+        let first = get(&cache, id(1), &[entry(0), entry(1)], &next);
+        // Entries in another order are the same layout.
+        let second = get(&cache, id(1), &[entry(1), entry(0)], &next);
+        assert_eq!(first, second);
+        assert_eq!(next.get(), 1);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn layouts_differ_by_entries_and_device() {
+        let cache = BindGroupLayoutCache::default();
+        let next = Cell::new(0);
+        let layout = get(&cache, id(1), &[entry(0)], &next);
+        assert_ne!(get(&cache, id(1), &[entry(1)], &next), layout);
+        assert_ne!(get(&cache, id(2), &[entry(0)], &next), layout);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn layouts_are_dropped_with_their_last_handle() {
+        let cache = BindGroupLayoutCache::default();
+        let next = Cell::new(0);
+        let layout = get(&cache, id(1), &[entry(0)], &next);
+        get(&cache, id(1), &[entry(0)], &next);
+        assert!(!cache.release(layout));
+        assert!(cache.release(layout));
+        assert!(cache.is_empty());
+        // A new one is made the next time it's asked for.
+        assert_ne!(get(&cache, id(1), &[entry(0)], &next), layout);
+    }
+
+    #[test]
+    fn failed_layouts_are_not_cached() {
+        let cache = BindGroupLayoutCache::default();
+        assert_eq!(
+            cache.get_or_create(id(1), &[entry(0)], || Err("invalid")),
+            Err("invalid")
+        );
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Reading part of a buffer back for `read-buffer`.
+
+use std::sync::{Arc, Mutex};
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{BufferId, DeviceId, QueueId};
+
+use crate::{core_result, Buffer};
+
+/// The range has to be inside the buffer, and copyable: 4 byte aligned, from a
+/// buffer with copy-src usage.
+pub(crate) fn validate_buffer_read(
+    buffer: &Buffer,
+    offset: u64,
+    length: u64,
+) -> Result<(), String> {
+    if !buffer.usage.contains(wgpu_types::BufferUsages::COPY_SRC) {
+        return Err("buffer needs copy-src usage to be read back".to_string());
+    }
+    let alignment = wgpu_types::COPY_BUFFER_ALIGNMENT;
+    if !offset.is_multiple_of(alignment) {
+        return Err(format!(
+            "offset of {offset} must be a multiple of {alignment}"
+        ));
+    }
+    if !length.is_multiple_of(alignment) {
+        return Err(format!(
+            "length of {length} must be a multiple of {alignment}"
+        ));
+    }
+    if offset
+        .checked_add(length)
+        .is_none_or(|end| end > buffer.size)
+    {
+        return Err(format!(
+            "{length} bytes at offset {offset} are past the end of the {} byte buffer",
+            buffer.size
+        ));
+    }
+    Ok(())
+}
+
+/// Copies `length` bytes of `buffer` from `offset` on into a buffer that can be
+/// mapped, and waits for the GPU to get them.
+pub(crate) fn read_buffer(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    buffer: BufferId,
+    offset: u64,
+    length: u64,
+) -> wasmtime::Result<Vec<u8>> {
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+    let staging = core_result(instance.device_create_buffer::<crate::Backend>(
+        device,
+        &wgpu_types::BufferDescriptor {
+            label: Some("buffer readback".into()),
+            size: length,
+            usage: wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        },
+        None,
+    ))?;
+    let bytes = read_into(instance, device, queue, buffer, offset, length, staging);
+    instance.buffer_drop::<crate::Backend>(staging, false);
+    bytes
+}
+
+fn read_into(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    buffer: BufferId,
+    offset: u64,
+    length: u64,
+    staging: BufferId,
+) -> wasmtime::Result<Vec<u8>> {
+    let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+        device,
+        &Default::default(),
+        None,
+    ))?;
+    instance.command_encoder_copy_buffer_to_buffer::<crate::Backend>(
+        encoder, buffer, offset, staging, 0, length,
+    )?;
+    let command_buffer = core_result(
+        instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+    )?;
+    instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+
+    let mapped = Arc::new(Mutex::new(None));
+    let op = wgpu_core::resource::BufferMapOperation {
+        host: wgpu_core::device::HostMap::Read,
+        callback: Some(wgpu_core::resource::BufferMapCallback::from_rust(Box::new(
+            {
+                let mapped = Arc::clone(&mapped);
+                move |result| *mapped.lock().unwrap() = Some(result)
+            },
+        ))),
+    };
+    instance.buffer_map_async::<crate::Backend>(staging, 0, Some(length), op)?;
+    instance.device_poll::<crate::Backend>(device, wgpu_types::Maintain::wait())?;
+    let mapped = mapped.lock().unwrap().take();
+    mapped.ok_or_else(|| wasmtime::Error::msg("buffer readback wasn't mapped"))??;
+
+    let (ptr, len) =
+        instance.buffer_get_mapped_range::<crate::Backend>(staging, 0, Some(length))?;
+    // Safety: the buffer stays mapped, and isn't written to, until the unmap below.
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len as usize) }.to_vec();
+    instance.buffer_unmap::<crate::Backend>(staging)?;
+    Ok(bytes)
+}
@@ -0,0 +1,183 @@
+//! Reads the depth values of a texture back to the host, e.g. to debug shadow maps.
+//!
+//! The depth aspect is copied into a mappable buffer with rows padded to
+//! `COPY_BYTES_PER_ROW_ALIGNMENT`, and unpacked into floats once the GPU is done.
+
+use std::sync::{Arc, Mutex};
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{DeviceId, QueueId, TextureId};
+
+use crate::core_result;
+use crate::texture_descriptors::TextureDescriptor;
+use crate::wasi::webgpu::webgpu::GpuTextureRegion;
+
+/// Checks that `region` of the texture described by `descriptor` can be read,
+/// returning the size of each depth value in bytes.
+pub(crate) fn validate_depth_read(
+    descriptor: &TextureDescriptor,
+    region: &GpuTextureRegion,
+) -> Result<u32, String> {
+    let format = descriptor.format;
+    if !format.has_depth_aspect() {
+        return Err(format!("{format:?} isn't a depth format"));
+    }
+    if !descriptor
+        .usage
+        .contains(wgpu_types::TextureUsages::COPY_SRC)
+    {
+        return Err("texture needs copy-src usage to be read".to_string());
+    }
+    if descriptor.sample_count != 1 {
+        return Err("multisampled textures can't be read".to_string());
+    }
+    let Some(bytes_per_value) = format.block_copy_size(Some(wgpu_types::TextureAspect::DepthOnly))
+    else {
+        return Err(format!(
+            "depth of {format:?} can't be copied, use depth16unorm, depth32float or depth32float-stencil8 instead"
+        ));
+    };
+    let Some(size) = descriptor.mip_level_size(region.mip_level) else {
+        return Err(format!(
+            "mip-level {} is out of range, the texture has {}",
+            region.mip_level, descriptor.mip_level_count
+        ));
+    };
+    if region.width == 0 || region.height == 0 {
+        return Err("region can't be empty".to_string());
+    }
+    let fits =
+        |start: u32, length: u32, max: u32| start.checked_add(length).is_some_and(|end| end <= max);
+    if !fits(region.x, region.width, size.width) || !fits(region.y, region.height, size.height) {
+        return Err(format!(
+            "region doesn't fit in mip-level {}, which is {}x{}",
+            region.mip_level, size.width, size.height
+        ));
+    }
+    if region.array_layer >= size.depth_or_array_layers {
+        return Err(format!(
+            "array-layer {} is out of range, the texture has {}",
+            region.array_layer, size.depth_or_array_layers
+        ));
+    }
+    Ok(bytes_per_value)
+}
+
+/// Copies `region` of `texture` into a buffer and waits for the GPU to get its
+/// depth values, row by row. `bytes_per_value` comes from `validate_depth_read`.
+pub(crate) fn read_depth(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    texture: TextureId,
+    region: GpuTextureRegion,
+    bytes_per_value: u32,
+) -> wasmtime::Result<Vec<f32>> {
+    let row_size = region.width as u64 * bytes_per_value as u64;
+    let padded_row_size =
+        row_size.next_multiple_of(wgpu_types::COPY_BYTES_PER_ROW_ALIGNMENT as u64);
+    let size = padded_row_size * region.height as u64;
+    let buffer = core_result(instance.device_create_buffer::<crate::Backend>(
+        device,
+        &wgpu_types::BufferDescriptor {
+            label: Some("depth readback".into()),
+            size,
+            usage: wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        },
+        None,
+    ))?;
+    let depths = read_into(
+        instance,
+        device,
+        queue,
+        texture,
+        &region,
+        bytes_per_value,
+        buffer,
+        padded_row_size,
+    );
+    instance.buffer_drop::<crate::Backend>(buffer, false);
+    depths
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_into(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    texture: TextureId,
+    region: &GpuTextureRegion,
+    bytes_per_value: u32,
+    buffer: wgpu_core::id::BufferId,
+    padded_row_size: u64,
+) -> wasmtime::Result<Vec<f32>> {
+    let size = padded_row_size * region.height as u64;
+    let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+        device,
+        &Default::default(),
+        None,
+    ))?;
+    instance.command_encoder_copy_texture_to_buffer::<crate::Backend>(
+        encoder,
+        &wgpu_types::ImageCopyTexture {
+            texture,
+            mip_level: region.mip_level,
+            origin: wgpu_types::Origin3d {
+                x: region.x,
+                y: region.y,
+                z: region.array_layer,
+            },
+            aspect: wgpu_types::TextureAspect::DepthOnly,
+        },
+        &wgpu_types::ImageCopyBuffer {
+            buffer,
+            layout: wgpu_types::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size as u32),
+                rows_per_image: Some(region.height),
+            },
+        },
+        &wgpu_types::Extent3d {
+            width: region.width,
+            height: region.height,
+            depth_or_array_layers: 1,
+        },
+    )?;
+    let command_buffer = core_result(
+        instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+    )?;
+    instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+
+    let mapped = Arc::new(Mutex::new(None));
+    let op = wgpu_core::resource::BufferMapOperation {
+        host: wgpu_core::device::HostMap::Read,
+        callback: Some(wgpu_core::resource::BufferMapCallback::from_rust(Box::new(
+            {
+                let mapped = Arc::clone(&mapped);
+                move |result| *mapped.lock().unwrap() = Some(result)
+            },
+        ))),
+    };
+    instance.buffer_map_async::<crate::Backend>(buffer, 0, Some(size), op)?;
+    instance.device_poll::<crate::Backend>(device, wgpu_types::Maintain::wait())?;
+    let mapped = mapped.lock().unwrap().take();
+    mapped.ok_or_else(|| wasmtime::Error::msg("depth readback wasn't mapped"))??;
+
+    let (ptr, len) = instance.buffer_get_mapped_range::<crate::Backend>(buffer, 0, Some(size))?;
+    // Safety: the buffer stays mapped, and isn't written to, until the unmap below.
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len as usize) };
+    let depths = bytes
+        .chunks(padded_row_size as usize)
+        .flat_map(|row| {
+            row[..(region.width * bytes_per_value) as usize].chunks_exact(bytes_per_value as usize)
+        })
+        .map(|value| match *value {
+            [a, b] => u16::from_le_bytes([a, b]) as f32 / u16::MAX as f32,
+            [a, b, c, d] => f32::from_le_bytes([a, b, c, d]),
+            _ => unreachable!("depth values are 2 or 4 bytes"),
+        })
+        .collect();
+    instance.buffer_unmap::<crate::Backend>(buffer)?;
+    Ok(depths)
+}
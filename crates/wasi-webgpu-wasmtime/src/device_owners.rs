@@ -0,0 +1,103 @@
+use std::{any::TypeId, collections::HashMap, sync::Mutex};
+
+use wgpu_core::id::{DeviceId, Id, Marker, RawId};
+
+/// Which device each resource was created on.
+///
+/// wgpu-core only reports mixing resources from different devices as a
+/// generic validation error deep inside the call, so resources are checked
+/// here first to tell the guest which one was the problem.
+#[derive(Default)]
+pub struct DeviceOwners {
+    owners: Mutex<HashMap<(TypeId, RawId), DeviceId>>,
+}
+
+impl DeviceOwners {
+    pub fn insert<T: Marker + 'static>(&self, id: Id<T>, device: DeviceId) {
+        self.owners
+            .lock()
+            .unwrap()
+            .insert((TypeId::of::<T>(), id.into_raw()), device);
+    }
+
+    pub fn get<T: Marker + 'static>(&self, id: Id<T>) -> Option<DeviceId> {
+        self.owners
+            .lock()
+            .unwrap()
+            .get(&(TypeId::of::<T>(), id.into_raw()))
+            .copied()
+    }
+
+    /// Forgets the resources of `device`, once it's gone.
+    pub fn forget_device(&self, device: DeviceId) {
+        self.owners
+            .lock()
+            .unwrap()
+            .retain(|_, owner| *owner != device);
+    }
+
+    /// Fails if `id` is known to belong to a device other than `device`.
+    /// `what` names the resource in the error.
+    pub fn check<T: Marker + 'static>(
+        &self,
+        id: Id<T>,
+        device: DeviceId,
+        what: &str,
+    ) -> wasmtime::Result<()> {
+        match self.get(id) {
+            Some(owner) if owner != device => Err(wasmtime::Error::msg(format!(
+                "{what} was created on device {owner:?}, but is used with device {device:?}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu_core::id::{BufferId, TextureId};
+
+    use super::*;
+
+    fn device(index: u32) -> DeviceId {
+        DeviceId::zip(index, 1, wgpu_types::Backend::Empty)
+    }
+
+    #[test]
+    fn check_rejects_other_devices() {
+        let owners = DeviceOwners::default();
+        let buffer = BufferId::zip(0, 1, wgpu_types::Backend::Empty);
+        owners.insert(buffer, device(0));
+        assert!(owners.check(buffer, device(0), "buffer").is_ok());
+        assert!(owners.check(buffer, device(1), "buffer").is_err());
+        // Unknown resources are left to wgpu.
+        let other = BufferId::zip(1, 1, wgpu_types::Backend::Empty);
+        assert!(owners.check(other, device(1), "buffer").is_ok());
+    }
+
+    #[test]
+    fn ids_of_different_types_are_kept_apart() {
+        let owners = DeviceOwners::default();
+        owners.insert(BufferId::zip(0, 1, wgpu_types::Backend::Empty), device(0));
+        owners.insert(TextureId::zip(0, 1, wgpu_types::Backend::Empty), device(1));
+        assert_eq!(
+            owners.get(BufferId::zip(0, 1, wgpu_types::Backend::Empty)),
+            Some(device(0))
+        );
+        assert_eq!(
+            owners.get(TextureId::zip(0, 1, wgpu_types::Backend::Empty)),
+            Some(device(1))
+        );
+    }
+
+    #[test]
+    fn forgetting_a_device_leaves_the_others() {
+        let owners = DeviceOwners::default();
+        let buffer = |index| BufferId::zip(index, 1, wgpu_types::Backend::Empty);
+        owners.insert(buffer(0), device(0));
+        owners.insert(buffer(1), device(1));
+        owners.forget_device(device(0));
+        assert_eq!(owners.get(buffer(0)), None);
+        assert_eq!(owners.get(buffer(1)), Some(device(1)));
+    }
+}
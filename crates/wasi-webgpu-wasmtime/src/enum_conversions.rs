@@ -1,84 +1,163 @@
 use crate::wasi::webgpu::webgpu;
+use wasi_graphics_context_wasmtime::CompositeAlphaMode;
+use wgpu_core::{command::TransferError, device::queue::QueueWriteError};
+
+/// `format` as a WebGPU texture format, or `None` if WebGPU doesn't have it.
+pub(crate) fn webgpu_texture_format(
+    format: wgpu_types::TextureFormat,
+) -> Option<webgpu::GpuTextureFormat> {
+    Some(match format {
+        wgpu_types::TextureFormat::Bgra8UnormSrgb => webgpu::GpuTextureFormat::Bgra8unormSrgb,
+        wgpu_types::TextureFormat::R8Unorm => webgpu::GpuTextureFormat::R8unorm,
+        wgpu_types::TextureFormat::R8Snorm => webgpu::GpuTextureFormat::R8snorm,
+        wgpu_types::TextureFormat::R8Uint => webgpu::GpuTextureFormat::R8uint,
+        wgpu_types::TextureFormat::R8Sint => webgpu::GpuTextureFormat::R8sint,
+        wgpu_types::TextureFormat::R16Uint => webgpu::GpuTextureFormat::R16uint,
+        wgpu_types::TextureFormat::R16Sint => webgpu::GpuTextureFormat::R16sint,
+        wgpu_types::TextureFormat::R16Float => webgpu::GpuTextureFormat::R16float,
+        wgpu_types::TextureFormat::Rg8Unorm => webgpu::GpuTextureFormat::Rg8unorm,
+        wgpu_types::TextureFormat::Rg8Snorm => webgpu::GpuTextureFormat::Rg8snorm,
+        wgpu_types::TextureFormat::Rg8Uint => webgpu::GpuTextureFormat::Rg8uint,
+        wgpu_types::TextureFormat::Rg8Sint => webgpu::GpuTextureFormat::Rg8sint,
+        wgpu_types::TextureFormat::R32Uint => webgpu::GpuTextureFormat::R32uint,
+        wgpu_types::TextureFormat::R32Sint => webgpu::GpuTextureFormat::R32sint,
+        wgpu_types::TextureFormat::R32Float => webgpu::GpuTextureFormat::R32float,
+        wgpu_types::TextureFormat::Rg16Uint => webgpu::GpuTextureFormat::Rg16uint,
+        wgpu_types::TextureFormat::Rg16Sint => webgpu::GpuTextureFormat::Rg16sint,
+        wgpu_types::TextureFormat::Rg16Float => webgpu::GpuTextureFormat::Rg16float,
+        wgpu_types::TextureFormat::Rgba8Unorm => webgpu::GpuTextureFormat::Rgba8unorm,
+        wgpu_types::TextureFormat::Rgba8UnormSrgb => webgpu::GpuTextureFormat::Rgba8unormSrgb,
+        wgpu_types::TextureFormat::Rgba8Snorm => webgpu::GpuTextureFormat::Rgba8snorm,
+        wgpu_types::TextureFormat::Rgba8Uint => webgpu::GpuTextureFormat::Rgba8uint,
+        wgpu_types::TextureFormat::Rgba8Sint => webgpu::GpuTextureFormat::Rgba8sint,
+        wgpu_types::TextureFormat::Bgra8Unorm => webgpu::GpuTextureFormat::Bgra8unorm,
+        wgpu_types::TextureFormat::Rgb9e5Ufloat => webgpu::GpuTextureFormat::Rgb9e5ufloat,
+        wgpu_types::TextureFormat::Rgb10a2Uint => webgpu::GpuTextureFormat::Rgb10a2uint,
+        wgpu_types::TextureFormat::Rgb10a2Unorm => webgpu::GpuTextureFormat::Rgb10a2unorm,
+        wgpu_types::TextureFormat::Rg11b10Float => webgpu::GpuTextureFormat::Rg11b10ufloat,
+        wgpu_types::TextureFormat::Rg32Uint => webgpu::GpuTextureFormat::Rg32uint,
+        wgpu_types::TextureFormat::Rg32Sint => webgpu::GpuTextureFormat::Rg32sint,
+        wgpu_types::TextureFormat::Rg32Float => webgpu::GpuTextureFormat::Rg32float,
+        wgpu_types::TextureFormat::Rgba16Uint => webgpu::GpuTextureFormat::Rgba16uint,
+        wgpu_types::TextureFormat::Rgba16Sint => webgpu::GpuTextureFormat::Rgba16sint,
+        wgpu_types::TextureFormat::Rgba16Float => webgpu::GpuTextureFormat::Rgba16float,
+        wgpu_types::TextureFormat::Rgba32Uint => webgpu::GpuTextureFormat::Rgba32uint,
+        wgpu_types::TextureFormat::Rgba32Sint => webgpu::GpuTextureFormat::Rgba32sint,
+        wgpu_types::TextureFormat::Rgba32Float => webgpu::GpuTextureFormat::Rgba32float,
+        wgpu_types::TextureFormat::Stencil8 => webgpu::GpuTextureFormat::Stencil8,
+        wgpu_types::TextureFormat::Depth16Unorm => webgpu::GpuTextureFormat::Depth16unorm,
+        wgpu_types::TextureFormat::Depth24Plus => webgpu::GpuTextureFormat::Depth24plus,
+        wgpu_types::TextureFormat::Depth24PlusStencil8 => {
+            webgpu::GpuTextureFormat::Depth24plusStencil8
+        }
+        wgpu_types::TextureFormat::Depth32Float => webgpu::GpuTextureFormat::Depth32float,
+        wgpu_types::TextureFormat::Depth32FloatStencil8 => {
+            webgpu::GpuTextureFormat::Depth32floatStencil8
+        }
+        wgpu_types::TextureFormat::Bc1RgbaUnorm => webgpu::GpuTextureFormat::Bc1RgbaUnorm,
+        wgpu_types::TextureFormat::Bc1RgbaUnormSrgb => webgpu::GpuTextureFormat::Bc1RgbaUnormSrgb,
+        wgpu_types::TextureFormat::Bc2RgbaUnorm => webgpu::GpuTextureFormat::Bc2RgbaUnorm,
+        wgpu_types::TextureFormat::Bc2RgbaUnormSrgb => webgpu::GpuTextureFormat::Bc2RgbaUnormSrgb,
+        wgpu_types::TextureFormat::Bc3RgbaUnorm => webgpu::GpuTextureFormat::Bc3RgbaUnorm,
+        wgpu_types::TextureFormat::Bc3RgbaUnormSrgb => webgpu::GpuTextureFormat::Bc3RgbaUnormSrgb,
+        wgpu_types::TextureFormat::Bc4RUnorm => webgpu::GpuTextureFormat::Bc4RUnorm,
+        wgpu_types::TextureFormat::Bc4RSnorm => webgpu::GpuTextureFormat::Bc4RSnorm,
+        wgpu_types::TextureFormat::Bc5RgUnorm => webgpu::GpuTextureFormat::Bc5RgUnorm,
+        wgpu_types::TextureFormat::Bc5RgSnorm => webgpu::GpuTextureFormat::Bc5RgSnorm,
+        wgpu_types::TextureFormat::Bc6hRgbUfloat => webgpu::GpuTextureFormat::Bc6hRgbUfloat,
+        wgpu_types::TextureFormat::Bc6hRgbFloat => webgpu::GpuTextureFormat::Bc6hRgbFloat,
+        wgpu_types::TextureFormat::Bc7RgbaUnorm => webgpu::GpuTextureFormat::Bc7RgbaUnorm,
+        wgpu_types::TextureFormat::Bc7RgbaUnormSrgb => webgpu::GpuTextureFormat::Bc7RgbaUnormSrgb,
+        wgpu_types::TextureFormat::Etc2Rgb8Unorm => webgpu::GpuTextureFormat::Etc2Rgb8unorm,
+        wgpu_types::TextureFormat::Etc2Rgb8UnormSrgb => webgpu::GpuTextureFormat::Etc2Rgb8unormSrgb,
+        wgpu_types::TextureFormat::Etc2Rgb8A1Unorm => webgpu::GpuTextureFormat::Etc2Rgb8a1unorm,
+        wgpu_types::TextureFormat::Etc2Rgb8A1UnormSrgb => {
+            webgpu::GpuTextureFormat::Etc2Rgb8a1unormSrgb
+        }
+        wgpu_types::TextureFormat::Etc2Rgba8Unorm => webgpu::GpuTextureFormat::Etc2Rgba8unorm,
+        wgpu_types::TextureFormat::Etc2Rgba8UnormSrgb => {
+            webgpu::GpuTextureFormat::Etc2Rgba8unormSrgb
+        }
+        wgpu_types::TextureFormat::EacR11Unorm => webgpu::GpuTextureFormat::EacR11unorm,
+        wgpu_types::TextureFormat::EacR11Snorm => webgpu::GpuTextureFormat::EacR11snorm,
+        wgpu_types::TextureFormat::EacRg11Unorm => webgpu::GpuTextureFormat::EacRg11unorm,
+        wgpu_types::TextureFormat::EacRg11Snorm => webgpu::GpuTextureFormat::EacRg11snorm,
+        wgpu_types::TextureFormat::Astc { block, channel } => {
+            use wgpu_types::{AstcBlock, AstcChannel};
+            match (block, channel) {
+                (AstcBlock::B4x4, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc4x4Unorm,
+                (AstcBlock::B4x4, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc4x4UnormSrgb
+                }
+                (AstcBlock::B5x4, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc5x4Unorm,
+                (AstcBlock::B5x4, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc5x4UnormSrgb
+                }
+                (AstcBlock::B5x5, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc5x5Unorm,
+                (AstcBlock::B5x5, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc5x5UnormSrgb
+                }
+                (AstcBlock::B6x5, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc6x5Unorm,
+                (AstcBlock::B6x5, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc6x5UnormSrgb
+                }
+                (AstcBlock::B6x6, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc6x6Unorm,
+                (AstcBlock::B6x6, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc6x6UnormSrgb
+                }
+                (AstcBlock::B8x5, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc8x5Unorm,
+                (AstcBlock::B8x5, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc8x5UnormSrgb
+                }
+                (AstcBlock::B8x6, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc8x6Unorm,
+                (AstcBlock::B8x6, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc8x6UnormSrgb
+                }
+                (AstcBlock::B8x8, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc8x8Unorm,
+                (AstcBlock::B8x8, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc8x8UnormSrgb
+                }
+                (AstcBlock::B10x5, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc10x5Unorm,
+                (AstcBlock::B10x5, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc10x5UnormSrgb
+                }
+                (AstcBlock::B10x6, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc10x6Unorm,
+                (AstcBlock::B10x6, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc10x6UnormSrgb
+                }
+                (AstcBlock::B10x8, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc10x8Unorm,
+                (AstcBlock::B10x8, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc10x8UnormSrgb
+                }
+                (AstcBlock::B10x10, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc10x10Unorm,
+                (AstcBlock::B10x10, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc10x10UnormSrgb
+                }
+                (AstcBlock::B12x10, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc12x10Unorm,
+                (AstcBlock::B12x10, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc12x10UnormSrgb
+                }
+                (AstcBlock::B12x12, AstcChannel::Unorm) => webgpu::GpuTextureFormat::Astc12x12Unorm,
+                (AstcBlock::B12x12, AstcChannel::UnormSrgb) => {
+                    webgpu::GpuTextureFormat::Astc12x12UnormSrgb
+                }
+                (_, AstcChannel::Hdr) => return None,
+            }
+        }
+        // Not in WebGPU.
+        wgpu_types::TextureFormat::R16Unorm
+        | wgpu_types::TextureFormat::R16Snorm
+        | wgpu_types::TextureFormat::Rg16Unorm
+        | wgpu_types::TextureFormat::Rg16Snorm
+        | wgpu_types::TextureFormat::Rgba16Unorm
+        | wgpu_types::TextureFormat::Rgba16Snorm
+        | wgpu_types::TextureFormat::NV12 => return None,
+    })
+}
 
 impl From<wgpu_types::TextureFormat> for webgpu::GpuTextureFormat {
     fn from(value: wgpu_types::TextureFormat) -> Self {
-        match value {
-            wgpu_types::TextureFormat::Bgra8UnormSrgb => webgpu::GpuTextureFormat::Bgra8unormSrgb,
-            wgpu_types::TextureFormat::R8Unorm => todo!(),
-            wgpu_types::TextureFormat::R8Snorm => todo!(),
-            wgpu_types::TextureFormat::R8Uint => todo!(),
-            wgpu_types::TextureFormat::R8Sint => todo!(),
-            wgpu_types::TextureFormat::R16Uint => todo!(),
-            wgpu_types::TextureFormat::R16Sint => todo!(),
-            wgpu_types::TextureFormat::R16Unorm => todo!(),
-            wgpu_types::TextureFormat::R16Snorm => todo!(),
-            wgpu_types::TextureFormat::R16Float => todo!(),
-            wgpu_types::TextureFormat::Rg8Unorm => todo!(),
-            wgpu_types::TextureFormat::Rg8Snorm => todo!(),
-            wgpu_types::TextureFormat::Rg8Uint => todo!(),
-            wgpu_types::TextureFormat::Rg8Sint => todo!(),
-            wgpu_types::TextureFormat::R32Uint => todo!(),
-            wgpu_types::TextureFormat::R32Sint => todo!(),
-            wgpu_types::TextureFormat::R32Float => todo!(),
-            wgpu_types::TextureFormat::Rg16Uint => todo!(),
-            wgpu_types::TextureFormat::Rg16Sint => todo!(),
-            wgpu_types::TextureFormat::Rg16Unorm => todo!(),
-            wgpu_types::TextureFormat::Rg16Snorm => todo!(),
-            wgpu_types::TextureFormat::Rg16Float => todo!(),
-            wgpu_types::TextureFormat::Rgba8Unorm => todo!(),
-            wgpu_types::TextureFormat::Rgba8UnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Rgba8Snorm => todo!(),
-            wgpu_types::TextureFormat::Rgba8Uint => todo!(),
-            wgpu_types::TextureFormat::Rgba8Sint => todo!(),
-            wgpu_types::TextureFormat::Bgra8Unorm => todo!(),
-            wgpu_types::TextureFormat::Rgb9e5Ufloat => todo!(),
-            wgpu_types::TextureFormat::Rgb10a2Uint => todo!(),
-            wgpu_types::TextureFormat::Rgb10a2Unorm => todo!(),
-            wgpu_types::TextureFormat::Rg11b10Float => todo!(),
-            wgpu_types::TextureFormat::Rg32Uint => todo!(),
-            wgpu_types::TextureFormat::Rg32Sint => todo!(),
-            wgpu_types::TextureFormat::Rg32Float => todo!(),
-            wgpu_types::TextureFormat::Rgba16Uint => todo!(),
-            wgpu_types::TextureFormat::Rgba16Sint => todo!(),
-            wgpu_types::TextureFormat::Rgba16Unorm => todo!(),
-            wgpu_types::TextureFormat::Rgba16Snorm => todo!(),
-            wgpu_types::TextureFormat::Rgba16Float => todo!(),
-            wgpu_types::TextureFormat::Rgba32Uint => todo!(),
-            wgpu_types::TextureFormat::Rgba32Sint => todo!(),
-            wgpu_types::TextureFormat::Rgba32Float => todo!(),
-            wgpu_types::TextureFormat::Stencil8 => todo!(),
-            wgpu_types::TextureFormat::Depth16Unorm => todo!(),
-            wgpu_types::TextureFormat::Depth24Plus => todo!(),
-            wgpu_types::TextureFormat::Depth24PlusStencil8 => todo!(),
-            wgpu_types::TextureFormat::Depth32Float => todo!(),
-            wgpu_types::TextureFormat::Depth32FloatStencil8 => todo!(),
-            wgpu_types::TextureFormat::Bc1RgbaUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc1RgbaUnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Bc2RgbaUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc2RgbaUnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Bc3RgbaUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc3RgbaUnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Bc4RUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc4RSnorm => todo!(),
-            wgpu_types::TextureFormat::Bc5RgUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc5RgSnorm => todo!(),
-            wgpu_types::TextureFormat::Bc6hRgbUfloat => todo!(),
-            wgpu_types::TextureFormat::Bc6hRgbFloat => todo!(),
-            wgpu_types::TextureFormat::Bc7RgbaUnorm => todo!(),
-            wgpu_types::TextureFormat::Bc7RgbaUnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgb8Unorm => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgb8UnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgb8A1Unorm => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgb8A1UnormSrgb => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgba8Unorm => todo!(),
-            wgpu_types::TextureFormat::Etc2Rgba8UnormSrgb => todo!(),
-            wgpu_types::TextureFormat::EacR11Unorm => todo!(),
-            wgpu_types::TextureFormat::EacR11Snorm => todo!(),
-            wgpu_types::TextureFormat::EacRg11Unorm => todo!(),
-            wgpu_types::TextureFormat::EacRg11Snorm => todo!(),
-            wgpu_types::TextureFormat::Astc { .. } => todo!(),
-            wgpu_types::TextureFormat::NV12 => todo!(),
-        }
+        webgpu_texture_format(value).unwrap_or_else(|| panic!("{value:?} has no WebGPU equivalent"))
     }
 }
 
@@ -170,38 +249,159 @@ impl From<webgpu::GpuTextureFormat> for wgpu_types::TextureFormat {
             webgpu::GpuTextureFormat::EacR11snorm => wgpu_types::TextureFormat::EacR11Snorm,
             webgpu::GpuTextureFormat::EacRg11unorm => wgpu_types::TextureFormat::EacRg11Unorm,
             webgpu::GpuTextureFormat::EacRg11snorm => wgpu_types::TextureFormat::EacRg11Snorm,
-            webgpu::GpuTextureFormat::Astc4x4Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc4x4UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc5x4Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc5x4UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc5x5Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc5x5UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc6x5Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc6x5UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc6x6Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc6x6UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc8x5Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc8x5UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc8x6Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc8x6UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc8x8Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc8x8UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc10x5Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc10x5UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc10x6Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc10x6UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc10x8Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc10x8UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc10x10Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc10x10UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc12x10Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc12x10UnormSrgb => todo!(),
-            webgpu::GpuTextureFormat::Astc12x12Unorm => todo!(),
-            webgpu::GpuTextureFormat::Astc12x12UnormSrgb => todo!(),
+            webgpu::GpuTextureFormat::Astc4x4Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B4x4,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc4x4UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B4x4,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc5x4Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B5x4,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc5x4UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B5x4,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc5x5Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B5x5,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc5x5UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B5x5,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc6x5Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B6x5,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc6x5UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B6x5,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc6x6Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B6x6,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc6x6UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B6x6,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc8x5Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x5,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc8x5UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x5,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc8x6Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x6,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc8x6UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x6,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc8x8Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x8,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc8x8UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B8x8,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc10x5Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x5,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc10x5UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x5,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc10x6Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x6,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc10x6UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x6,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc10x8Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x8,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc10x8UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x8,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc10x10Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x10,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc10x10UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B10x10,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc12x10Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B12x10,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc12x10UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B12x10,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+            webgpu::GpuTextureFormat::Astc12x12Unorm => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B12x12,
+                channel: wgpu_types::AstcChannel::Unorm,
+            },
+            webgpu::GpuTextureFormat::Astc12x12UnormSrgb => wgpu_types::TextureFormat::Astc {
+                block: wgpu_types::AstcBlock::B12x12,
+                channel: wgpu_types::AstcChannel::UnormSrgb,
+            },
+        }
+    }
+}
+
+impl TryFrom<wgpu_types::PresentMode> for webgpu::GpuPresentMode {
+    type Error = ();
+
+    /// Fails for the `Auto*` modes, which surfaces never report as supported.
+    fn try_from(value: wgpu_types::PresentMode) -> Result<Self, Self::Error> {
+        match value {
+            wgpu_types::PresentMode::Fifo => Ok(webgpu::GpuPresentMode::Fifo),
+            wgpu_types::PresentMode::FifoRelaxed => Ok(webgpu::GpuPresentMode::FifoRelaxed),
+            wgpu_types::PresentMode::Immediate => Ok(webgpu::GpuPresentMode::Immediate),
+            wgpu_types::PresentMode::Mailbox => Ok(webgpu::GpuPresentMode::Mailbox),
+            wgpu_types::PresentMode::AutoVsync | wgpu_types::PresentMode::AutoNoVsync => Err(()),
         }
     }
 }
 
+impl From<webgpu::GpuPresentMode> for wgpu_types::PresentMode {
+    fn from(value: webgpu::GpuPresentMode) -> Self {
+        match value {
+            webgpu::GpuPresentMode::Fifo => wgpu_types::PresentMode::Fifo,
+            webgpu::GpuPresentMode::FifoRelaxed => wgpu_types::PresentMode::FifoRelaxed,
+            webgpu::GpuPresentMode::Immediate => wgpu_types::PresentMode::Immediate,
+            webgpu::GpuPresentMode::Mailbox => wgpu_types::PresentMode::Mailbox,
+        }
+    }
+}
+
+// A function because neither type is defined in this crate.
+pub(crate) fn composite_alpha_mode(value: wgpu_types::CompositeAlphaMode) -> CompositeAlphaMode {
+    match value {
+        wgpu_types::CompositeAlphaMode::Auto => CompositeAlphaMode::Auto,
+        wgpu_types::CompositeAlphaMode::Opaque => CompositeAlphaMode::Opaque,
+        wgpu_types::CompositeAlphaMode::PreMultiplied => CompositeAlphaMode::PreMultiplied,
+        wgpu_types::CompositeAlphaMode::PostMultiplied => CompositeAlphaMode::PostMultiplied,
+        wgpu_types::CompositeAlphaMode::Inherit => CompositeAlphaMode::Inherit,
+    }
+}
+
 impl From<webgpu::GpuPrimitiveTopology> for wgpu_types::PrimitiveTopology {
     fn from(value: webgpu::GpuPrimitiveTopology) -> Self {
         match value {
@@ -519,3 +719,81 @@ impl From<webgpu::GpuTextureAspect> for wgpu_types::TextureAspect {
         }
     }
 }
+
+impl From<webgpu::GpuQueryType> for wgpu_types::QueryType {
+    fn from(value: webgpu::GpuQueryType) -> Self {
+        match value {
+            webgpu::GpuQueryType::Occlusion => wgpu_types::QueryType::Occlusion,
+            webgpu::GpuQueryType::Timestamp => wgpu_types::QueryType::Timestamp,
+        }
+    }
+}
+
+impl From<QueueWriteError> for webgpu::GpuWriteBufferError {
+    fn from(value: QueueWriteError) -> Self {
+        match value {
+            QueueWriteError::Transfer(TransferError::UnalignedBufferOffset(_)) => {
+                webgpu::GpuWriteBufferError::UnalignedOffset
+            }
+            QueueWriteError::Transfer(TransferError::UnalignedCopySize(_)) => {
+                webgpu::GpuWriteBufferError::UnalignedSize
+            }
+            QueueWriteError::Transfer(TransferError::BufferOverrun { .. }) => {
+                webgpu::GpuWriteBufferError::OutOfBounds
+            }
+            QueueWriteError::Transfer(TransferError::MissingCopyDstUsageFlag(..)) => {
+                webgpu::GpuWriteBufferError::MissingCopyDstUsage
+            }
+            _ => webgpu::GpuWriteBufferError::InvalidBuffer,
+        }
+    }
+}
+
+impl From<webgpu::GpuFeatureName> for wgpu_types::Features {
+    fn from(value: webgpu::GpuFeatureName) -> Self {
+        match value {
+            webgpu::GpuFeatureName::DepthClipControl => wgpu_types::Features::DEPTH_CLIP_CONTROL,
+            webgpu::GpuFeatureName::Depth32floatStencil8 => {
+                wgpu_types::Features::DEPTH32FLOAT_STENCIL8
+            }
+            webgpu::GpuFeatureName::TextureCompressionBc => {
+                wgpu_types::Features::TEXTURE_COMPRESSION_BC
+            }
+            webgpu::GpuFeatureName::TextureCompressionEtc2 => {
+                wgpu_types::Features::TEXTURE_COMPRESSION_ETC2
+            }
+            webgpu::GpuFeatureName::TextureCompressionAstc => {
+                wgpu_types::Features::TEXTURE_COMPRESSION_ASTC
+            }
+            webgpu::GpuFeatureName::TimestampQuery => wgpu_types::Features::TIMESTAMP_QUERY,
+            webgpu::GpuFeatureName::IndirectFirstInstance => {
+                wgpu_types::Features::INDIRECT_FIRST_INSTANCE
+            }
+            webgpu::GpuFeatureName::ShaderF16 => wgpu_types::Features::SHADER_F16,
+            webgpu::GpuFeatureName::Rg11b10ufloatRenderable => {
+                wgpu_types::Features::RG11B10UFLOAT_RENDERABLE
+            }
+            webgpu::GpuFeatureName::Bgra8unormStorage => wgpu_types::Features::BGRA8UNORM_STORAGE,
+            webgpu::GpuFeatureName::Float32Filterable => wgpu_types::Features::FLOAT32_FILTERABLE,
+            webgpu::GpuFeatureName::TextureBindingArray => {
+                wgpu_types::Features::TEXTURE_BINDING_ARRAY
+            }
+            webgpu::GpuFeatureName::SampledTextureAndStorageBufferArrayNonUniformIndexing => {
+                wgpu_types::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+            }
+            webgpu::GpuFeatureName::Multiview => wgpu_types::Features::MULTIVIEW,
+        }
+    }
+}
+
+impl From<wgpu_types::DeviceType> for webgpu::GpuDeviceType {
+    fn from(value: wgpu_types::DeviceType) -> Self {
+        match value {
+            wgpu_types::DeviceType::Other => webgpu::GpuDeviceType::Other,
+            wgpu_types::DeviceType::IntegratedGpu => webgpu::GpuDeviceType::IntegratedGpu,
+            wgpu_types::DeviceType::DiscreteGpu => webgpu::GpuDeviceType::DiscreteGpu,
+            wgpu_types::DeviceType::VirtualGpu => webgpu::GpuDeviceType::VirtualGpu,
+            wgpu_types::DeviceType::Cpu => webgpu::GpuDeviceType::Cpu,
+        }
+    }
+}
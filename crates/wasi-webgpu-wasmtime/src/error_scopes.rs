@@ -0,0 +1,103 @@
+use crate::wasi::webgpu::webgpu::GpuErrorFilter;
+
+/// What `pop-error-scope` hands out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuError {
+    pub(crate) kind: GpuErrorFilter,
+    pub(crate) message: String,
+}
+
+impl GpuError {
+    pub(crate) fn validation(message: impl Into<String>) -> Self {
+        Self {
+            kind: GpuErrorFilter::Validation,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn out_of_memory(message: impl Into<String>) -> Self {
+        Self {
+            kind: GpuErrorFilter::OutOfMemory,
+            message: message.into(),
+        }
+    }
+}
+
+/// A device's error scopes, innermost last.
+///
+/// As in WebGPU, an error goes to the innermost scope catching its kind, which
+/// keeps only the first one it gets. Errors no scope catches are logged.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorScopes {
+    scopes: Vec<(GpuErrorFilter, Option<GpuError>)>,
+}
+
+impl ErrorScopes {
+    pub(crate) fn push(&mut self, filter: GpuErrorFilter) {
+        self.scopes.push((filter, None));
+    }
+
+    /// The first error the innermost scope caught, if any.
+    pub(crate) fn pop(&mut self) -> Result<Option<GpuError>, String> {
+        match self.scopes.pop() {
+            Some((_, error)) => Ok(error),
+            None => Err("there's no error scope to pop".to_string()),
+        }
+    }
+
+    pub(crate) fn report(&mut self, error: GpuError) {
+        let scope = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|(filter, _)| *filter == error.kind);
+        match scope {
+            Some((_, caught)) => {
+                caught.get_or_insert(error);
+            }
+            None => tracing::error!("uncaptured {:?} error: {}", error.kind, error.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popping_without_a_scope_fails() {
+        let mut scopes = ErrorScopes::default();
+        assert!(scopes.pop().is_err());
+    }
+
+    #[test]
+    fn scopes_keep_the_first_error() {
+        let mut scopes = ErrorScopes::default();
+        scopes.push(GpuErrorFilter::Validation);
+        scopes.report(GpuError::validation("first"));
+        scopes.report(GpuError::validation("second"));
+        assert_eq!(scopes.pop(), Ok(Some(GpuError::validation("first"))));
+        assert!(scopes.pop().is_err());
+    }
+
+    #[test]
+    fn errors_go_to_the_innermost_scope_of_their_kind() {
+        let mut scopes = ErrorScopes::default();
+        scopes.push(GpuErrorFilter::Validation);
+        scopes.push(GpuErrorFilter::OutOfMemory);
+        scopes.push(GpuErrorFilter::Internal);
+        scopes.report(GpuError::validation("invalid"));
+        scopes.report(GpuError::out_of_memory("too big"));
+        assert_eq!(scopes.pop(), Ok(None));
+        assert_eq!(scopes.pop(), Ok(Some(GpuError::out_of_memory("too big"))));
+        assert_eq!(scopes.pop(), Ok(Some(GpuError::validation("invalid"))));
+    }
+
+    #[test]
+    fn uncaught_errors_are_dropped() {
+        let mut scopes = ErrorScopes::default();
+        scopes.push(GpuErrorFilter::OutOfMemory);
+        scopes.report(GpuError::validation("invalid"));
+        assert_eq!(scopes.pop(), Ok(None));
+    }
+}
@@ -0,0 +1,236 @@
+//! Copies every presented frame into a guest buffer, e.g. to encode or stream it.
+//!
+//! Frames are copied straight from the texture they were rendered into, in its
+//! format, with each row padded to the 256 bytes `copy_texture_to_buffer` needs.
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{BufferId, DeviceId, QueueId, TextureId};
+
+use crate::core_result;
+
+/// The guest buffer frames get read back to.
+pub(crate) struct FrameReadback {
+    buffer: BufferId,
+    size: u64,
+    usage: wgpu_types::BufferUsages,
+}
+
+impl FrameReadback {
+    pub(crate) fn new(buffer: BufferId, size: u64, usage: wgpu_types::BufferUsages) -> Self {
+        Self {
+            buffer,
+            size,
+            usage,
+        }
+    }
+
+    /// Whether `width` x `height` frames in `format` fit in the buffer.
+    pub(crate) fn validate(
+        &self,
+        format: wgpu_types::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<(), String> {
+        if !self.usage.contains(wgpu_types::BufferUsages::COPY_DST) {
+            return Err("frames can only be read back to a buffer with copy-dst usage".to_string());
+        }
+        let Some(bytes_per_row) = bytes_per_row(format, width) else {
+            return Err(format!("frames in {format:?} can't be read back"));
+        };
+        let needed = bytes_per_row as u64 * height as u64;
+        if self.size < needed {
+            return Err(format!(
+                "buffer of {} bytes is too small for {width}x{height} frames in {format:?}, \
+                 which take {needed}",
+                self.size
+            ));
+        }
+        Ok(())
+    }
+
+    /// Queues a copy of `texture`, a `width` x `height` frame in `format`, into
+    /// the guest's buffer. Check the frame fits with `validate` first.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn copy(
+        &self,
+        instance: &Global,
+        device: DeviceId,
+        queue: QueueId,
+        texture: TextureId,
+        format: wgpu_types::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> wasmtime::Result<()> {
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            device,
+            &Default::default(),
+            None,
+        ))?;
+        instance.command_encoder_copy_texture_to_buffer::<crate::Backend>(
+            encoder,
+            &wgpu_types::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Default::default(),
+                aspect: wgpu_types::TextureAspect::All,
+            },
+            &wgpu_types::ImageCopyBuffer {
+                buffer: self.buffer,
+                layout: wgpu_types::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: bytes_per_row(format, width),
+                    rows_per_image: Some(height),
+                },
+            },
+            &wgpu_types::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        )?;
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )?;
+        instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+        Ok(())
+    }
+}
+
+/// The bytes a row of a `width` wide frame in `format` takes in the buffer,
+/// padding included.
+pub(crate) fn bytes_per_row(format: wgpu_types::TextureFormat, width: u32) -> Option<u32> {
+    let pixel_size = format.block_copy_size(None)?;
+    Some((width * pixel_size).next_multiple_of(wgpu_types::COPY_BYTES_PER_ROW_ALIGNMENT))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use wgpu_core::id::Id;
+
+    use super::*;
+    use crate::test_gpu::TestGpu;
+
+    fn readback(size: u64, usage: wgpu_types::BufferUsages) -> FrameReadback {
+        FrameReadback::new(Id::zip(1, 1, wgpu_types::Backend::Empty), size, usage)
+    }
+
+    #[test]
+    fn rows_are_padded_to_the_copy_alignment() {
+        let format = wgpu_types::TextureFormat::Bgra8Unorm;
+        assert_eq!(bytes_per_row(format, 64), Some(256));
+        assert_eq!(bytes_per_row(format, 65), Some(512));
+        assert_eq!(
+            bytes_per_row(wgpu_types::TextureFormat::Rgba16Float, 64),
+            Some(512)
+        );
+        assert_eq!(
+            bytes_per_row(wgpu_types::TextureFormat::Depth24Plus, 64),
+            None
+        );
+    }
+
+    #[test]
+    fn buffers_need_room_for_the_padded_frame() {
+        let format = wgpu_types::TextureFormat::Bgra8Unorm;
+        let usage = wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::MAP_READ;
+        assert_eq!(readback(512 * 10, usage).validate(format, 65, 10), Ok(()));
+        assert_eq!(
+            readback(65 * 4 * 10, usage).validate(format, 65, 10),
+            Err(
+                "buffer of 2600 bytes is too small for 65x10 frames in Bgra8Unorm, which \
+                 take 5120"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn buffers_need_copy_dst_usage() {
+        let format = wgpu_types::TextureFormat::Bgra8Unorm;
+        let usage = wgpu_types::BufferUsages::MAP_READ;
+        assert!(readback(512 * 10, usage).validate(format, 65, 10).is_err());
+    }
+
+    #[test]
+    fn frames_read_back_match_their_clear_color() {
+        let Some(gpu) = TestGpu::new() else {
+            return;
+        };
+        let instance = &gpu.instance;
+        let format = wgpu_types::TextureFormat::Rgba8Unorm;
+        let (width, height) = (65, 3);
+        let texture = gpu.create_texture(
+            format,
+            width,
+            height,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC,
+        );
+        let view = core_result(instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            gpu.device,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let pass = wgpu_core::command::RenderPass::new(
+            encoder,
+            &wgpu_core::command::RenderPassDescriptor {
+                label: None,
+                color_attachments: Cow::Borrowed(&[Some(
+                    wgpu_core::command::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        channel: wgpu_core::command::PassChannel {
+                            load_op: wgpu_core::command::LoadOp::Clear,
+                            store_op: wgpu_core::command::StoreOp::Store,
+                            clear_value: wgpu_types::Color {
+                                r: 1.0,
+                                g: 0.0,
+                                b: 1.0,
+                                a: 1.0,
+                            },
+                            read_only: false,
+                        },
+                    },
+                )]),
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        );
+        instance
+            .command_encoder_run_render_pass::<crate::Backend>(encoder, &pass)
+            .unwrap();
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )
+        .unwrap();
+        instance
+            .queue_submit::<crate::Backend>(gpu.queue, &[command_buffer])
+            .unwrap();
+
+        let size = 512 * height as u64;
+        let usage = wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::COPY_SRC;
+        let buffer = gpu.create_buffer(size, usage);
+        let readback = FrameReadback::new(buffer, size, usage);
+        readback.validate(format, width, height).unwrap();
+        readback
+            .copy(
+                instance, gpu.device, gpu.queue, texture, format, width, height,
+            )
+            .unwrap();
+
+        let bytes = gpu.read(buffer, 0, size);
+        for row in bytes.chunks(512) {
+            let pixels = &row[..width as usize * 4];
+            assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 255, 255]));
+        }
+    }
+}
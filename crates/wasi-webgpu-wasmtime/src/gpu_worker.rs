@@ -0,0 +1,271 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A thread that GPU submission and polling run on, so that waiting on the GPU
+/// doesn't hold up the thread that called in, nor the event loop.
+///
+/// Jobs run one at a time, in the order they were sent. A job that panics is
+/// logged and skipped; the worker carries on with the next one.
+#[derive(Clone)]
+pub struct GpuWorker {
+    sender: Sender<Job>,
+}
+
+impl GpuWorker {
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("gpu-worker".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        tracing::error!("a GPU worker job panicked");
+                    }
+                }
+            })
+            .unwrap();
+        Self { sender }
+    }
+
+    /// Queues `f` without waiting for it to run. If the worker is gone, `f`
+    /// runs in place instead.
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Err(mpsc::SendError(job)) = self.sender.send(Box::new(f)) {
+            tracing::warn!("GPU worker is gone, running its job in place");
+            job();
+        }
+    }
+
+    /// Runs `f` on the worker and waits for its result. A panic in `f` is
+    /// returned as an error.
+    pub async fn run<F, T>(&self, f: F) -> wasmtime::Result<T>
+    where
+        F: FnOnce() -> wasmtime::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.execute(move || {
+            let _ = sender.send(f());
+        });
+        // The sender is only dropped unsent if `f` panicked.
+        receiver
+            .await
+            .unwrap_or_else(|_| Err(wasmtime::Error::msg("GPU worker job panicked")))
+    }
+}
+
+/// Runs `f` on `worker`, or in place if there's no worker.
+pub(crate) async fn run_on_worker<F, T>(worker: Option<GpuWorker>, f: F) -> wasmtime::Result<T>
+where
+    F: FnOnce() -> wasmtime::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match worker {
+        Some(worker) => worker.run(f).await,
+        None => f(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use futures::executor::block_on;
+    use wasi_mini_canvas_wasmtime::{
+        wasi::webgpu::pointer_events::{self, HostPointerDownListener},
+        PointerEvent,
+    };
+    use wasmtime::component::Resource;
+    use wasmtime_wasi::WasiView;
+    use wgpu_core::id::CommandEncoderId;
+
+    use super::*;
+    use crate::{
+        core_result,
+        test_gpu::{TestGpu, TestView},
+        wasi::webgpu::webgpu,
+    };
+
+    #[test]
+    fn panicking_jobs_are_errors_and_the_worker_keeps_going() {
+        let worker = GpuWorker::spawn();
+        let panicked = block_on(worker.run(|| -> wasmtime::Result<()> { panic!("lost device") }));
+        assert_eq!(panicked.unwrap_err().to_string(), "GPU worker job panicked");
+        assert_eq!(block_on(worker.run(|| Ok(4))).unwrap(), 4);
+    }
+
+    /// Records a compute pass that keeps the GPU busy for a while, hashing
+    /// each of 64 * 64 values 100000 times.
+    fn record_heavy_compute(gpu: &TestGpu, view: &mut TestView) -> CommandEncoderId {
+        use webgpu::{
+            HostGpuCommandEncoder, HostGpuComputePassEncoder, HostGpuComputePipeline, HostGpuDevice,
+        };
+
+        let (device, _queue) = view.device(gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let values = host
+            .create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size: 64 * 64 * 4,
+                    usage: wgpu_types::BufferUsages::STORAGE.bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @group(0) @binding(0) var<storage, read_write> values: array<u32>;
+
+                    @compute @workgroup_size(64)
+                    fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                        var value = id.x;
+                        for (var i = 0u; i < 100000u; i++) {
+                            value = value * 1664525u + 1013904223u;
+                        }
+                        values[id.x] = value;
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_compute_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuComputePipelineDescriptor {
+                    compute: webgpu::GpuProgrammableStage {
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: Some("main".to_string()),
+                    },
+                    layout: webgpu::GpuPipelineLayoutOrGpuAutoLayoutMode::GpuAutoLayoutMode(
+                        webgpu::GpuAutoLayoutMode::Auto,
+                    ),
+                    label: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let layout = host.get_bind_group_layout(Resource::new_borrow(pipeline.rep()), 0);
+        let bind_group = host
+            .create_bind_group(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupDescriptor {
+                    layout,
+                    entries: vec![webgpu::GpuBindGroupEntry {
+                        binding: 0,
+                        resource: webgpu::GpuBindingResource::GpuBufferBinding(
+                            webgpu::GpuBufferBinding {
+                                buffer: Resource::new_borrow(values.rep()),
+                                offset: None,
+                                size: None,
+                            },
+                        ),
+                    }],
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_compute_pass(Resource::new_borrow(encoder_resource.rep()), None)
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.set_bind_group(Resource::new_borrow(pass.rep()), 0, bind_group, None)
+            .unwrap();
+        host.dispatch_workgroups(Resource::new_borrow(pass.rep()), 64, None, None);
+        host.end(pass, encoder_resource).unwrap();
+        encoder
+    }
+
+    /// Input goes through the canvas on the guest's thread, so a heavy compute
+    /// job submitted to the worker doesn't hold it up.
+    #[test]
+    fn input_latency_stays_bounded_while_a_heavy_compute_job_runs() {
+        let Some(gpu) = TestGpu::new() else {
+            return;
+        };
+        let mut view = TestView::new(&gpu);
+        let encoder = record_heavy_compute(&gpu, &mut view);
+        let canvas = view.canvas();
+        let host = view.canvas_host();
+        let listener = block_on(pointer_events::Host::down_listener(
+            host,
+            Resource::new_borrow(canvas.rep()),
+        ));
+        let proxy = host.table().get(&canvas).unwrap().0.proxy();
+
+        let worker = GpuWorker::spawn();
+        let (done_sender, done) = mpsc::channel();
+        let instance = Arc::clone(&gpu.instance);
+        let (device, queue) = (gpu.device, gpu.queue);
+        worker.execute(move || {
+            let command_buffer = core_result(
+                instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+            )
+            .unwrap();
+            instance
+                .queue_submit::<crate::Backend>(queue, &[command_buffer])
+                .unwrap();
+            instance
+                .device_poll::<crate::Backend>(device, wgpu_types::Maintain::wait())
+                .unwrap();
+            let _ = done_sender.send(());
+        });
+
+        // Until the job is done, with at least one event sent while it runs.
+        let mut latencies = Vec::new();
+        loop {
+            let x = latencies.len() as f64;
+            let sent = Instant::now();
+            proxy.pointer_down(PointerEvent {
+                x,
+                y: 0.0,
+                logical_x: x,
+                logical_y: 0.0,
+            });
+            block_on(wasmtime_wasi::Subscribe::ready(
+                host.table().get_mut(&listener).unwrap(),
+            ));
+            let event = host.get(Resource::new_borrow(listener.rep())).unwrap();
+            latencies.push(sent.elapsed());
+            assert_eq!(event.x, x);
+            if done.try_recv().is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let slowest = latencies.iter().max().unwrap();
+        assert!(
+            *slowest < Duration::from_millis(50),
+            "slowest of {} events took {slowest:?}",
+            latencies.len()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use wgpu_core::{device::queue::SubmittedWorkDoneClosure, global::Global, id::QueueId};
+
+/// Bounds how many queue submissions can be on the GPU at once.
+///
+/// Without a bound, a guest that submits faster than the GPU can keep up
+/// queues up command buffers, and the memory they hold, without limit.
+pub struct InFlightLimit {
+    max: u32,
+    in_flight: Arc<AtomicU32>,
+}
+
+impl InFlightLimit {
+    pub fn new(max: u32) -> Self {
+        assert!(max > 0, "in-flight limit must be at least 1");
+        Self {
+            max,
+            in_flight: Default::default(),
+        }
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// Submissions the GPU hasn't finished yet.
+    pub fn in_flight(&self) -> u32 {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Blocks until another submission fits under the limit.
+    pub(crate) fn wait_for_slot(&self, instance: &Global) -> wasmtime::Result<()> {
+        while self.in_flight() >= self.max {
+            let _span =
+                tracing::info_span!("wait_for_in_flight", in_flight = self.in_flight()).entered();
+            // Waits for the GPU and runs the work done callbacks of everything that
+            // finished.
+            let idle = instance.poll_all_devices(true)?;
+            // With every queue empty, every callback has run, so a count still at
+            // the limit would never go down.
+            if idle && self.in_flight() >= self.max {
+                return Err(wasmtime::Error::msg(format!(
+                    "{} submissions are still counted as in flight, but the GPU is idle",
+                    self.in_flight()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a submission to `queue` until the GPU is done with it.
+    pub(crate) fn submitted(&self, instance: &Global, queue: QueueId) -> wasmtime::Result<()> {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let in_flight = Arc::clone(&self.in_flight);
+        let registered = instance.queue_on_submitted_work_done::<crate::Backend>(
+            queue,
+            SubmittedWorkDoneClosure::from_rust(Box::new(move || {
+                in_flight.fetch_sub(1, Ordering::AcqRel);
+            })),
+        );
+        if let Err(e) = registered {
+            // The callback won't run, so nothing else takes the submission back out.
+            self.in_flight.fetch_sub(1, Ordering::AcqRel);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core_result, test_gpu::TestGpu};
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn limits_below_1_are_rejected() {
+        InFlightLimit::new(0);
+    }
+
+    #[test]
+    fn submissions_in_flight_never_exceed_the_limit() {
+        let Some(gpu) = TestGpu::new() else {
+            return;
+        };
+        let limit = InFlightLimit::new(2);
+        // Big enough copies that the GPU falls behind the submissions.
+        let size = 64 << 20;
+        let source = gpu.create_buffer(size, wgpu_types::BufferUsages::COPY_SRC);
+        let destination = gpu.create_buffer(size, wgpu_types::BufferUsages::COPY_DST);
+        let mut most_in_flight = 0;
+        for _ in 0..8 {
+            limit.wait_for_slot(&gpu.instance).unwrap();
+            let encoder = core_result(
+                gpu.instance
+                    .device_create_command_encoder::<crate::Backend>(
+                        gpu.device,
+                        &Default::default(),
+                        None,
+                    ),
+            )
+            .unwrap();
+            gpu.instance
+                .command_encoder_copy_buffer_to_buffer::<crate::Backend>(
+                    encoder,
+                    source,
+                    0,
+                    destination,
+                    0,
+                    size,
+                )
+                .unwrap();
+            gpu.submit(encoder);
+            limit.submitted(&gpu.instance, gpu.queue).unwrap();
+            most_in_flight = most_in_flight.max(limit.in_flight());
+        }
+        assert!(most_in_flight <= limit.max(), "{most_in_flight} in flight");
+        gpu.instance.poll_all_devices(true).unwrap();
+        assert_eq!(limit.in_flight(), 0);
+    }
+}
@@ -8,16 +8,49 @@ use callback_future::CallbackFuture;
 use core::slice;
 use futures::executor::block_on;
 use std::borrow::Cow;
-use std::sync::Arc;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
 use std::{future::Future, mem};
+use tracing::Instrument;
 use wasmtime::component::Resource;
 use wasmtime_wasi::WasiView;
-use wgpu_core::id::SurfaceId;
+use wgpu_core::id::{Id, Marker, SurfaceId};
 
 use crate::wasi::webgpu::webgpu;
-use wasi_graphics_context_wasmtime::{DisplayApi, DrawApi, GraphicsContext, GraphicsContextBuffer};
-
+use wasi_graphics_context_wasmtime::{
+    Color, ColorSpace, DisplayApi, DrawApi, FrameTimings, GraphicsContext, GraphicsContextBuffer,
+    PresentResult,
+};
+
+use self::buffer_readback::{read_buffer, validate_buffer_read};
+use self::depth_readback::{read_depth, validate_depth_read};
+use self::enum_conversions::{composite_alpha_mode, webgpu_texture_format};
+use self::error_scopes::ErrorScopes;
+use self::frame_readback::FrameReadback;
+use self::gpu_worker::run_on_worker;
+use self::memory_budget::{texture_size, Allocation};
+use self::msaa::MsaaTarget;
+use self::query_readback::{read_queries, validate_query_range};
+use self::render_scale::{Blitter, ScaledTarget};
 use self::to_core_conversions::ToCore;
+use self::validation::{
+    explain_bind_group_error, validate_buffer_binding, validate_buffer_descriptor,
+    validate_clear_buffer, validate_color, validate_extent, validate_image_copy_texture,
+    validate_indirect_dispatch, validate_multiview, validate_origin, validate_primitive_state,
+    validate_sampler_descriptor, validate_texture_descriptor, validate_timestamp_writes,
+    validate_vertex_buffer_slot, validate_vertex_buffers_bound,
+};
+
+pub use self::bind_group_layouts::BindGroupLayoutCache;
+pub use self::device_owners::DeviceOwners;
+pub use self::error_scopes::GpuError;
+pub use self::gpu_worker::GpuWorker;
+pub use self::in_flight::InFlightLimit;
+pub use self::memory_budget::MemoryBudget;
+#[cfg(feature = "resource-counts")]
+pub use self::resource_counts::{ResourceCounts, ResourceKind};
+pub use self::texture_descriptors::TextureDescriptors;
+pub use self::write_batches::WriteBatches;
 
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub(crate) type Backend = wgpu_core::api::Vulkan;
@@ -37,41 +70,88 @@ pub(crate) type Backend = wgpu_core::api::Metal;
 ))]
 pub(crate) type Backend = wgpu_core::api::Gl;
 
-// needed for wasmtime::component::bindgen! as it only looks in the current crate.
-pub(crate) use wgpu_core;
-pub(crate) use wgpu_types;
 wasmtime::component::bindgen!({
     path: "../../wit/",
     world: "example",
+    tracing: true,
     async: {
         only_imports: [
             "[method]gpu-buffer.map-async",
+            "[method]gpu-device.read-query-results",
+            "[method]gpu-device.read-buffer",
+            "[method]gpu-device.read-depth-texture",
+            "[method]gpu-queue.submit",
         ],
     },
+    trappable_imports: [
+        "[static]gpu-render-pass-encoder.end",
+        "[static]gpu-compute-pass-encoder.end",
+        "[method]gpu-command-encoder.begin-render-pass",
+        "[method]gpu-command-encoder.begin-compute-pass",
+        "[static]gpu-command-encoder.finish",
+        "[method]gpu-command-encoder.copy-buffer-to-buffer",
+        "[method]gpu-queue.write-buffer",
+        "[method]gpu-queue.submit",
+        "[method]gpu-queue.write-texture",
+        "[method]gpu-command-encoder.copy-buffer-to-texture",
+        "[method]gpu-command-encoder.copy-texture-to-buffer",
+        "[method]gpu-command-encoder.copy-texture-to-texture",
+        "[method]gpu-command-encoder.clear-buffer",
+        "[method]gpu-command-encoder.resolve-query-set",
+        "[method]gpu-command-encoder.label",
+        "[method]gpu-command-encoder.push-debug-group",
+        "[method]gpu-command-encoder.pop-debug-group",
+        "[method]gpu-command-encoder.insert-debug-marker",
+        "[method]gpu-device.create-bind-group-layout",
+        "[method]gpu-device.create-bind-group",
+        "[method]gpu-device.create-pipeline-layout",
+        "[method]gpu-device.create-render-pipeline",
+        "[method]gpu-device.create-compute-pipeline",
+        "[method]gpu-device.create-query-set",
+        "[method]gpu-device.create-buffer",
+        "[method]gpu-device.create-texture",
+        "[method]gpu-device.create-sampler",
+        "[method]gpu-texture.create-view",
+        "[method]gpu-texture.destroy",
+        "[method]gpu-buffer.destroy",
+        "[method]gpu-render-pass-encoder.set-pipeline",
+        "[method]gpu-render-pass-encoder.draw",
+        "[method]gpu-render-pass-encoder.set-bind-group",
+        "[method]gpu-render-pass-encoder.set-vertex-buffer",
+        "[method]gpu-render-pass-encoder.set-blend-constant",
+        "[method]gpu-compute-pass-encoder.set-pipeline",
+        "[method]gpu-compute-pass-encoder.set-bind-group",
+        "[method]gpu-compute-pass-encoder.dispatch-workgroups-indirect",
+        "[method]remote-buffer.length",
+        "[method]remote-buffer.get",
+        "[method]remote-buffer.set",
+    ],
     with: {
         "wasi:webgpu/webgpu/gpu-adapter": wgpu_core::id::AdapterId,
         "wasi:webgpu/webgpu/gpu-device": Device,
         "wasi:webgpu/webgpu/gpu-queue": wgpu_core::id::QueueId,
         "wasi:webgpu/webgpu/gpu-command-encoder": wgpu_core::id::CommandEncoderId,
-        "wasi:webgpu/webgpu/gpu-render-pass-encoder": wgpu_core::command::RenderPass,
-        "wasi:webgpu/webgpu/gpu-compute-pass-encoder": wgpu_core::command::ComputePass,
+        "wasi:webgpu/webgpu/gpu-render-pass-encoder": RenderPass,
+        "wasi:webgpu/webgpu/gpu-compute-pass-encoder": ComputePass,
         "wasi:webgpu/webgpu/gpu-shader-module": wgpu_core::id::ShaderModuleId,
-        "wasi:webgpu/webgpu/gpu-render-pipeline": wgpu_core::id::RenderPipelineId,
+        "wasi:webgpu/webgpu/gpu-render-pipeline": RenderPipeline,
         "wasi:webgpu/webgpu/gpu-command-buffer": wgpu_core::id::CommandBufferId,
         // "wasi:webgpu/webgpu/gpu-buffer": wgpu_core::id::BufferId,
         "wasi:webgpu/webgpu/gpu-buffer": Buffer,
-        "wasi:webgpu/webgpu/remote-buffer": Buffer,
+        "wasi:webgpu/webgpu/remote-buffer": RemoteBuffer,
         "wasi:webgpu/webgpu/gpu-pipeline-layout": wgpu_core::id::PipelineLayoutId,
         "wasi:webgpu/webgpu/gpu-bind-group-layout": wgpu_core::id::BindGroupLayoutId,
         "wasi:webgpu/webgpu/gpu-sampler": wgpu_core::id::SamplerId,
         "wasi:webgpu/webgpu/gpu-supported-features": wgpu_types::Features,
-        "wasi:webgpu/webgpu/gpu-texture": wgpu_core::id::TextureId,
+        "wasi:webgpu/webgpu/gpu-texture": Texture,
         "wasi:webgpu/webgpu/gpu-compute-pipeline": wgpu_core::id::ComputePipelineId,
-        "wasi:webgpu/webgpu/gpu-bind-group": wgpu_core::id::BindGroupId,
+        "wasi:webgpu/webgpu/gpu-bind-group": BindGroup,
         "wasi:webgpu/webgpu/gpu-texture-view": wgpu_core::id::TextureViewId,
         "wasi:webgpu/webgpu/gpu-adapter-info": wgpu_types::AdapterInfo,
-        "wasi:webgpu/webgpu/gpu-query-set": wgpu_core::id::QuerySetId,
-        "wasi:webgpu/webgpu/gpu-supported-limits": wgpu_types::Limits,
+        "wasi:webgpu/webgpu/gpu-query-set": QuerySet,
+        "wasi:webgpu/webgpu/gpu-supported-limits": SupportedLimits,
+        "wasi:webgpu/webgpu/gpu-pipeline-cache": PipelineCache,
+        "wasi:webgpu/webgpu/gpu-error": GpuError,
         "wasi:webgpu/graphics-context": wasi_graphics_context_wasmtime,
     },
 });
@@ -97,10 +177,413 @@ pub trait WasiWebGpuView: WasiView {
     /// Provide the ability to run closure on the UI thread.
     /// On platforms that don't require UI to run on the UI thread, this can just execute in place.
     fn ui_thread_spawner(&self) -> Box<impl MainThreadSpawner>;
+
+    /// Adapter to return from `request_adapter`, instead of the one wgpu picks.
+    fn adapter(&self) -> Option<wgpu_core::id::AdapterId> {
+        None
+    }
+
+    /// Worker to run queue submission and device polling on.
+    /// Without one, they run on the thread of the calling guest.
+    fn gpu_worker(&self) -> Option<GpuWorker> {
+        None
+    }
+
+    /// Tracks which device created each resource, to report resources from
+    /// different devices being mixed. Without it, only wgpu validates this.
+    fn device_owners(&self) -> Option<Arc<DeviceOwners>> {
+        None
+    }
+
+    /// Limits how many queue submissions may be unfinished at once. Submitting
+    /// past the limit blocks until the GPU catches up. Unbounded by default.
+    fn in_flight_limit(&self) -> Option<Arc<InFlightLimit>> {
+        None
+    }
+
+    /// Remembers what each texture was created with, which reading textures
+    /// back with `read-depth-texture` needs. Texture copies are also checked
+    /// against it, to name the out of range mip level or origin.
+    fn texture_descriptors(&self) -> Option<Arc<TextureDescriptors>> {
+        None
+    }
+
+    /// Caps the memory taken up by the guest's buffers and textures. Creating
+    /// one past the budget fails with an out of memory error. Unbounded by default.
+    fn memory_budget(&self) -> Option<Arc<MemoryBudget>> {
+        None
+    }
+
+    /// Batches buffer writes until the queue is submitted, merging adjacent
+    /// ones. Without it, every write is copied on its own.
+    fn write_batches(&self) -> Option<Arc<WriteBatches>> {
+        None
+    }
+
+    /// Shares one bind group layout between structurally identical ones.
+    /// Without it, each `create-bind-group-layout` makes a new one.
+    fn bind_group_layout_cache(&self) -> Option<Arc<BindGroupLayoutCache>> {
+        None
+    }
+
+    /// Where to record how long the GPU takes for each submit, if profiling.
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        None
+    }
+
+    /// Counts the resources the guest holds, to check for leaks in tests.
+    #[cfg(feature = "resource-counts")]
+    fn resource_counts(&self) -> Option<Arc<ResourceCounts>> {
+        None
+    }
+}
+
+/// The backend this crate drives wgpu with on the current platform. Probing only
+/// this one when creating the instance keeps startup fast.
+pub fn primary_backends() -> wgpu_types::Backends {
+    <Backend as wgpu_core::hal_api::HalApi>::VARIANT.into()
+}
+
+/// Finds an adapter by its index in enumeration order, or by a case-insensitive
+/// substring of its name. Only adapters of this crate's backend are counted.
+pub fn find_adapter(
+    instance: &wgpu_core::global::Global,
+    selector: &str,
+) -> wasmtime::Result<wgpu_core::id::AdapterId> {
+    let adapters = instance.enumerate_adapters(wgpu_core::instance::AdapterInputs::Mask(
+        primary_backends(),
+        |_| None,
+    ));
+    let infos = adapters
+        .iter()
+        .map(|adapter| instance.adapter_get_info::<crate::Backend>(*adapter))
+        .collect::<Result<Vec<_>, _>>()?;
+    let names: Vec<_> = infos.iter().map(|info| info.name.as_str()).collect();
+
+    match match_adapter(selector, &names) {
+        Some(index) => Ok(adapters[index]),
+        None => {
+            let available = infos
+                .iter()
+                .enumerate()
+                .map(|(i, info)| format!("{i}: {} ({:?})", info.name, info.backend))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(wasmtime::Error::msg(format!(
+                "no adapter matches {selector:?}, available adapters: [{available}]"
+            )))
+        }
+    }
+}
+
+/// The index of the adapter `selector` picks out of `names`.
+fn match_adapter(selector: &str, names: &[&str]) -> Option<usize> {
+    match selector.parse::<usize>() {
+        Ok(index) => (index < names.len()).then_some(index),
+        Err(_) => {
+            let selector = selector.to_lowercase();
+            names
+                .iter()
+                .position(|name| name.to_lowercase().contains(&selector))
+        }
+    }
+}
+
+/// The adapter a guest gets from `request-adapter` when the host doesn't pick one.
+pub fn default_adapter(
+    instance: &wgpu_core::global::Global,
+) -> wasmtime::Result<wgpu_core::id::AdapterId> {
+    Ok(instance.request_adapter(
+        &Default::default(),
+        wgpu_core::instance::AdapterInputs::Mask(primary_backends(), |_| None),
+    )?)
+}
+
+/// What `adapter` is and supports, as `key: value` lines, for bug reports.
+pub fn capability_report(
+    instance: &wgpu_core::global::Global,
+    adapter: wgpu_core::id::AdapterId,
+) -> wasmtime::Result<String> {
+    let info = instance.adapter_get_info::<crate::Backend>(adapter)?;
+    let features = instance.adapter_features::<crate::Backend>(adapter)?;
+    let limits = instance.adapter_limits::<crate::Backend>(adapter)?;
+    let downlevel = instance.adapter_downlevel_capabilities::<crate::Backend>(adapter)?;
+
+    let names = |names: Vec<&str>| match names.is_empty() {
+        true => "none".to_string(),
+        false => names.join(", "),
+    };
+    let mut report = vec![
+        format!("adapter: {}", info.name),
+        format!("backend: {:?}", info.backend),
+        format!("device type: {:?}", info.device_type),
+        format!("vendor id: {:#06x}", info.vendor),
+        format!("device id: {:#06x}", info.device),
+        format!("driver: {} {}", info.driver, info.driver_info),
+        format!(
+            "features: {}",
+            names(features.iter_names().map(|(name, _)| name).collect())
+        ),
+        format!(
+            "downlevel flags: {}",
+            names(downlevel.flags.iter_names().map(|(name, _)| name).collect())
+        ),
+        format!("shader model: {:?}", downlevel.shader_model),
+    ];
+    let key_limits = [
+        (
+            "max texture dimension 2d",
+            limits.max_texture_dimension_2d as u64,
+        ),
+        (
+            "max texture dimension 3d",
+            limits.max_texture_dimension_3d as u64,
+        ),
+        (
+            "max texture array layers",
+            limits.max_texture_array_layers as u64,
+        ),
+        ("max bind groups", limits.max_bind_groups as u64),
+        (
+            "max bindings per bind group",
+            limits.max_bindings_per_bind_group as u64,
+        ),
+        (
+            "max uniform buffer binding size",
+            limits.max_uniform_buffer_binding_size as u64,
+        ),
+        (
+            "max storage buffer binding size",
+            limits.max_storage_buffer_binding_size as u64,
+        ),
+        ("max buffer size", limits.max_buffer_size),
+        ("max vertex buffers", limits.max_vertex_buffers as u64),
+        ("max vertex attributes", limits.max_vertex_attributes as u64),
+        ("max color attachments", limits.max_color_attachments as u64),
+        (
+            "max compute workgroup size x",
+            limits.max_compute_workgroup_size_x as u64,
+        ),
+        (
+            "max compute invocations per workgroup",
+            limits.max_compute_invocations_per_workgroup as u64,
+        ),
+        (
+            "max compute workgroups per dimension",
+            limits.max_compute_workgroups_per_dimension as u64,
+        ),
+        (
+            "max push constant size",
+            limits.max_push_constant_size as u64,
+        ),
+    ];
+    report.extend(
+        key_limits
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}")),
+    );
+    Ok(report.join("\n"))
 }
 
 pub struct WasiWebGpuImpl<T>(pub T);
 
+impl<T: WasiWebGpuView> WasiWebGpuImpl<T> {
+    fn record_owner<M: Marker + 'static>(&self, id: Id<M>, device: wgpu_core::id::DeviceId) {
+        if let Some(owners) = self.0.device_owners() {
+            owners.insert(id, device);
+        }
+    }
+
+    #[cfg(feature = "resource-counts")]
+    fn resource_created(&self, kind: ResourceKind) {
+        if let Some(counts) = self.0.resource_counts() {
+            counts.created(kind);
+        }
+    }
+
+    /// Counts a `kind` as dropped once `freed` frees its id.
+    #[cfg(feature = "resource-counts")]
+    fn count_drop<I: Copy>(&self, mut freed: Freed<I>, kind: ResourceKind) -> Freed<I> {
+        if let Some(counts) = self.0.resource_counts() {
+            freed.on_free = Some(Box::new(move || counts.dropped(kind)));
+        }
+        freed
+    }
+
+    fn owner<M: Marker + 'static>(&self, id: Id<M>) -> Option<wgpu_core::id::DeviceId> {
+        self.0.device_owners()?.get(id)
+    }
+
+    fn check_owner<M: Marker + 'static>(
+        &self,
+        id: Id<M>,
+        device: wgpu_core::id::DeviceId,
+        what: &str,
+    ) -> wasmtime::Result<()> {
+        match self.0.device_owners() {
+            Some(owners) => owners.check(id, device, what),
+            None => Ok(()),
+        }
+    }
+
+    /// A pipeline cache, if given, has to be from the pipeline's device.
+    fn check_pipeline_cache(
+        &mut self,
+        cache: Option<&Resource<PipelineCache>>,
+        device: wgpu_core::id::DeviceId,
+    ) -> wasmtime::Result<()> {
+        let Some(cache) = cache else {
+            return Ok(());
+        };
+        let owner = self.0.table().get(cache)?.device;
+        if owner != device {
+            return Err(wasmtime::Error::msg(format!(
+                "pipeline cache was created on device {owner:?}, but is used with device {device:?}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reports `error` to `device`'s error scopes and hands the guest an invalid
+    /// buffer instead, as WebGPU does, so the mistake doesn't end the guest.
+    /// `buffer` is the invalid buffer wgpu made, if it got that far.
+    fn invalid_buffer(
+        &mut self,
+        device: &Resource<webgpu::GpuDevice>,
+        buffer: Option<wgpu_core::id::BufferId>,
+        error: GpuError,
+    ) -> wasmtime::Result<Resource<webgpu::GpuBuffer>> {
+        let device = self.0.table().get_mut(device)?;
+        device.error_scopes.report(error);
+        let device = device.device;
+        let instance = self.0.instance();
+        // wgpu makes an invalid buffer for any creation that fails, and empty
+        // usage always does.
+        let buffer = buffer.unwrap_or_else(|| {
+            let descriptor = wgpu_core::resource::BufferDescriptor {
+                label: None,
+                size: 0,
+                usage: wgpu_types::BufferUsages::empty(),
+                mapped_at_creation: false,
+            };
+            instance
+                .device_create_buffer::<crate::Backend>(device, &descriptor, None)
+                .0
+        });
+        let buffer = Buffer {
+            buffer,
+            size: 0,
+            usage: wgpu_types::BufferUsages::empty(),
+            mapping: Arc::new(BufferMapping {
+                buffer: Freed::new(instance, buffer, |instance, buffer| {
+                    instance.buffer_drop::<crate::Backend>(buffer, false)
+                }),
+                range: Mutex::new(None),
+            }),
+            destroyed: false,
+        };
+        Ok(self.0.table().push(buffer)?)
+    }
+
+    /// Like `invalid_buffer`, for textures.
+    fn invalid_texture(
+        &mut self,
+        device: &Resource<webgpu::GpuDevice>,
+        texture: Option<wgpu_core::id::TextureId>,
+        error: GpuError,
+    ) -> wasmtime::Result<Resource<webgpu::GpuTexture>> {
+        let device = self.0.table().get_mut(device)?;
+        device.error_scopes.report(error);
+        let device = device.device;
+        let texture = texture.unwrap_or_else(|| {
+            let descriptor = wgpu_types::TextureDescriptor {
+                label: None,
+                size: wgpu_types::Extent3d::default(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu_types::TextureDimension::D2,
+                format: wgpu_types::TextureFormat::Rgba8Unorm,
+                usage: wgpu_types::TextureUsages::empty(),
+                view_formats: vec![],
+            };
+            self.0
+                .instance()
+                .device_create_texture::<crate::Backend>(device, &descriptor, None)
+                .0
+        });
+        Ok(self.0.table().push(Texture {
+            id: texture,
+            owned: true,
+            destroyed: false,
+        })?)
+    }
+
+    /// The encoder behind `command_encoder`. There's no finished state to check,
+    /// `finish` takes the guest's handle, so it can't be used afterwards.
+    fn command_encoder(
+        &mut self,
+        command_encoder: &Resource<wgpu_core::id::CommandEncoderId>,
+    ) -> wasmtime::Result<wgpu_core::id::CommandEncoderId> {
+        Ok(*self.0.table().get(command_encoder)?)
+    }
+
+    /// The buffer behind `buffer`, unless `destroy` freed it already.
+    fn live_buffer(&mut self, buffer: &Resource<webgpu::GpuBuffer>) -> wasmtime::Result<&Buffer> {
+        let buffer = self.0.table().get(buffer)?;
+        match buffer.destroyed {
+            true => Err(wasmtime::Error::msg(
+                "buffer was destroyed, so it can't be used anymore",
+            )),
+            false => Ok(buffer),
+        }
+    }
+
+    /// The texture behind `texture`, unless `destroy` freed it already.
+    fn live_texture(
+        &mut self,
+        texture: &Resource<webgpu::GpuTexture>,
+    ) -> wasmtime::Result<Texture> {
+        let texture = *self.0.table().get(texture)?;
+        match texture.destroyed {
+            true => Err(wasmtime::Error::msg(
+                "texture was destroyed, so it can't be used anymore",
+            )),
+            false => Ok(texture),
+        }
+    }
+
+    /// Checks the texture side of a copy against what the texture was created
+    /// with. Textures that aren't known, e.g. surface textures, are left to wgpu.
+    fn validate_image_copy(
+        &self,
+        name: &str,
+        copy: &wgpu_types::ImageCopyTexture<wgpu_core::id::TextureId>,
+        copy_size: wgpu_types::Extent3d,
+    ) -> Result<(), String> {
+        match self
+            .0
+            .texture_descriptors()
+            .and_then(|descriptors| descriptors.get(copy.texture))
+        {
+            Some(descriptor) => validate_image_copy_texture(name, copy, copy_size, &descriptor),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that `id` belongs to the device `encoder` was created on.
+    fn check_encoder_owner<M: Marker + 'static>(
+        &self,
+        id: Id<M>,
+        encoder: wgpu_core::id::CommandEncoderId,
+        what: &str,
+    ) -> wasmtime::Result<()> {
+        match self.owner(encoder) {
+            Some(device) => self.check_owner(id, device, what),
+            None => Ok(()),
+        }
+    }
+}
+
 impl<T: WasiView> WasiView for WasiWebGpuImpl<T> {
     fn table(&mut self) -> &mut wasmtime_wasi::ResourceTable {
         self.0.table()
@@ -119,6 +602,47 @@ impl<T: WasiWebGpuView> WasiWebGpuView for WasiWebGpuImpl<T> {
     fn ui_thread_spawner(&self) -> Box<impl MainThreadSpawner + 'static> {
         self.0.ui_thread_spawner()
     }
+
+    fn adapter(&self) -> Option<wgpu_core::id::AdapterId> {
+        self.0.adapter()
+    }
+
+    fn gpu_worker(&self) -> Option<GpuWorker> {
+        self.0.gpu_worker()
+    }
+
+    fn device_owners(&self) -> Option<Arc<DeviceOwners>> {
+        self.0.device_owners()
+    }
+
+    fn in_flight_limit(&self) -> Option<Arc<InFlightLimit>> {
+        self.0.in_flight_limit()
+    }
+
+    fn texture_descriptors(&self) -> Option<Arc<TextureDescriptors>> {
+        self.0.texture_descriptors()
+    }
+
+    fn memory_budget(&self) -> Option<Arc<MemoryBudget>> {
+        self.0.memory_budget()
+    }
+
+    fn write_batches(&self) -> Option<Arc<WriteBatches>> {
+        self.0.write_batches()
+    }
+
+    fn bind_group_layout_cache(&self) -> Option<Arc<BindGroupLayoutCache>> {
+        self.0.bind_group_layout_cache()
+    }
+
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        self.0.frame_timings()
+    }
+
+    #[cfg(feature = "resource-counts")]
+    fn resource_counts(&self) -> Option<Arc<ResourceCounts>> {
+        self.0.resource_counts()
+    }
 }
 
 impl<T: ?Sized + WasiWebGpuView> WasiWebGpuView for &mut T {
@@ -129,6 +653,47 @@ impl<T: ?Sized + WasiWebGpuView> WasiWebGpuView for &mut T {
     fn ui_thread_spawner(&self) -> Box<impl MainThreadSpawner + 'static> {
         T::ui_thread_spawner(self)
     }
+
+    fn adapter(&self) -> Option<wgpu_core::id::AdapterId> {
+        T::adapter(self)
+    }
+
+    fn gpu_worker(&self) -> Option<GpuWorker> {
+        T::gpu_worker(self)
+    }
+
+    fn device_owners(&self) -> Option<Arc<DeviceOwners>> {
+        T::device_owners(self)
+    }
+
+    fn in_flight_limit(&self) -> Option<Arc<InFlightLimit>> {
+        T::in_flight_limit(self)
+    }
+
+    fn texture_descriptors(&self) -> Option<Arc<TextureDescriptors>> {
+        T::texture_descriptors(self)
+    }
+
+    fn memory_budget(&self) -> Option<Arc<MemoryBudget>> {
+        T::memory_budget(self)
+    }
+
+    fn write_batches(&self) -> Option<Arc<WriteBatches>> {
+        T::write_batches(self)
+    }
+
+    fn bind_group_layout_cache(&self) -> Option<Arc<BindGroupLayoutCache>> {
+        T::bind_group_layout_cache(self)
+    }
+
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        T::frame_timings(self)
+    }
+
+    #[cfg(feature = "resource-counts")]
+    fn resource_counts(&self) -> Option<Arc<ResourceCounts>> {
+        T::resource_counts(self)
+    }
 }
 
 pub trait MainThreadSpawner: Send + Sync + 'static {
@@ -147,8 +712,340 @@ where
     get_instance: GI,
     create_surface: CS,
     device_id: wgpu_core::id::DeviceId,
+    queue_id: wgpu_core::id::QueueId,
     adapter_id: wgpu_core::id::AdapterId,
     surface_id: Option<wgpu_core::id::SurfaceId>,
+    config: Option<wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>>,
+    frame_latency: u32,
+    render_scale: f64,
+    blitter: Option<Blitter>,
+    scaled_target: Option<ScaledTarget>,
+    sample_count: u32,
+    msaa_target: Option<MsaaTarget>,
+    mirrors: Vec<Mirror>,
+    readback: Option<FrameReadback>,
+    color_space: ColorSpace,
+    /// The surface texture acquired for the frame being rendered, until it's
+    /// presented. wgpu won't hand it out twice.
+    acquired: Option<wgpu_core::id::TextureId>,
+    /// Whether the surface was out of date and reconfigured since the last present.
+    reconfigured: bool,
+}
+
+/// What `gpu-device.reconfigure-surface` hands a `WebGpuSurface`.
+struct SurfaceConfiguration {
+    width: u32,
+    height: u32,
+    format: wgpu_types::TextureFormat,
+    present_mode: wgpu_types::PresentMode,
+}
+
+/// The frame latency that has the swapchain cycle through `count` buffers.
+///
+/// wgpu-core doesn't expose the surface's swapchain size range, and clamps the
+/// latency to it when configuring, so only the counts every backend supports
+/// are accepted.
+fn frame_latency_for_buffer_count(count: u32) -> Result<u32, String> {
+    match count {
+        // The frame latency doesn't count the buffer currently on screen.
+        2 | 3 => Ok(count - 1),
+        _ => Err(format!("buffer count must be 2 or 3, not {count}")),
+    }
+}
+
+/// The format to show frames in `color_space` with on a surface with
+/// `capabilities`, and the color space that ends up being.
+///
+/// wgpu has no color space setting; its backends show rgba16float surfaces in
+/// extended sRGB linear and every other format in sRGB, so the format is what
+/// picks it. Wider gamuts like Rec. 2020 can't be asked for.
+fn pick_format(
+    color_space: ColorSpace,
+    capabilities: &wgpu_types::SurfaceCapabilities,
+) -> (wgpu_types::TextureFormat, ColorSpace) {
+    let hdr_format = wgpu_types::TextureFormat::Rgba16Float;
+    if color_space == ColorSpace::ExtendedSrgbLinear && capabilities.formats.contains(&hdr_format) {
+        return (hdr_format, ColorSpace::ExtendedSrgbLinear);
+    }
+    match capabilities
+        .formats
+        .iter()
+        .find(|format| **format != hdr_format)
+    {
+        Some(format) => (*format, ColorSpace::Srgb),
+        // Only rgba16float, which can't be shown in sRGB.
+        None => (hdr_format, ColorSpace::ExtendedSrgbLinear),
+    }
+}
+
+/// Another window that frames get copied to on present.
+struct Mirror {
+    key: usize,
+    surface_id: SurfaceId,
+    config: wgpu_types::SurfaceConfiguration<Vec<wgpu_types::TextureFormat>>,
+}
+
+impl<GI, CS, I> WebGpuSurface<GI, CS, I>
+where
+    I: AsRef<wgpu_core::global::Global>,
+    GI: Fn() -> I,
+    CS: Fn(&(dyn DisplayApi + Send + Sync)) -> SurfaceId,
+{
+    fn configure(&mut self) {
+        let instance = (self.get_instance)();
+        if let (Some(surface_id), Some(config)) = (self.surface_id, &mut self.config) {
+            config.desired_maximum_frame_latency = self.frame_latency;
+            // Frames read back are copied from the surface texture when there's
+            // no scaled target.
+            config.usage = match self.readback {
+                Some(_) => {
+                    wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu_types::TextureUsages::COPY_SRC
+                }
+                None => wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+            };
+            instance.as_ref().surface_configure::<crate::Backend>(
+                surface_id,
+                self.device_id,
+                config,
+            );
+        }
+        for mirror in &mut self.mirrors {
+            mirror.config.desired_maximum_frame_latency = self.frame_latency;
+            instance.as_ref().surface_configure::<crate::Backend>(
+                mirror.surface_id,
+                self.device_id,
+                &mirror.config,
+            );
+        }
+    }
+
+    /// The texture frames are rendered into when a render scale is set or frames
+    /// are mirrored, (re)created to match the current window size and scale.
+    fn scaled_target(&mut self) -> wasmtime::Result<Option<&ScaledTarget>> {
+        let Some(config) = &self.config else {
+            return Ok(None);
+        };
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        let (width, height) =
+            render_scale::scaled_size(config.width, config.height, self.render_scale);
+        let needed = self.render_scale != 1.0 || !self.mirrors.is_empty();
+        if let Some(target) = self.scaled_target.take() {
+            if needed && (target.width, target.height) == (width, height) {
+                self.scaled_target = Some(target);
+            } else {
+                target.destroy(instance);
+            }
+        }
+        if !needed {
+            return Ok(None);
+        }
+        if self.scaled_target.is_none() {
+            let blitter = match self.blitter.take() {
+                Some(blitter) => blitter,
+                None => Blitter::new(instance, self.device_id)?,
+            };
+            let blitter = self.blitter.insert(blitter);
+            self.scaled_target = Some(ScaledTarget::new(
+                instance,
+                self.device_id,
+                blitter,
+                config.format,
+                width,
+                height,
+            )?);
+        }
+        Ok(self.scaled_target.as_ref())
+    }
+
+    /// The multisampled texture frames are rendered into when a sample count is
+    /// set, (re)created to match the texture it resolves into.
+    fn msaa_target(&mut self, width: u32, height: u32) -> wasmtime::Result<Option<&MsaaTarget>> {
+        let Some(config) = &self.config else {
+            return Ok(None);
+        };
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        if let Some(target) = self.msaa_target.take() {
+            if (target.sample_count, target.width, target.height)
+                == (self.sample_count, width, height)
+            {
+                self.msaa_target = Some(target);
+            } else {
+                target.destroy(instance);
+            }
+        }
+        if self.sample_count == 1 {
+            return Ok(None);
+        }
+        if self.msaa_target.is_none() {
+            self.msaa_target = Some(MsaaTarget::new(
+                instance,
+                self.device_id,
+                config.format,
+                self.sample_count,
+                width,
+                height,
+            )?);
+        }
+        Ok(self.msaa_target.as_ref())
+    }
+
+    fn surface_id(&self) -> wasmtime::Result<SurfaceId> {
+        self.surface_id
+            .ok_or_else(|| wasmtime::Error::msg("surface isn't connected to a display yet"))
+    }
+
+    /// The surface's texture for this frame, acquired the first time it's asked
+    /// for. A surface that's out of date, e.g. after a resize, is reconfigured and
+    /// asked again.
+    fn current_texture(&mut self) -> wasmtime::Result<wgpu_core::id::TextureId> {
+        if let Some(texture) = self.acquired {
+            return Ok(texture);
+        }
+        let texture = self.acquire_texture()?;
+        self.acquired = Some(texture);
+        Ok(texture)
+    }
+
+    fn acquire_texture(&mut self) -> wasmtime::Result<wgpu_core::id::TextureId> {
+        let surface_id = self.surface_id()?;
+        let instance = (self.get_instance)();
+        let output = instance
+            .as_ref()
+            .surface_get_current_texture::<crate::Backend>(surface_id, None)?;
+        if let Some(texture) = output.texture_id {
+            return Ok(texture);
+        }
+        self.configure();
+        self.reconfigured = true;
+        let output = instance
+            .as_ref()
+            .surface_get_current_texture::<crate::Backend>(surface_id, None)?;
+        output.texture_id.ok_or_else(|| {
+            wasmtime::Error::msg(format!(
+                "surface has no texture even after reconfiguring: {:?}",
+                output.status
+            ))
+        })
+    }
+
+    /// The size of the frames the guest renders, which are scaled on present.
+    fn frame_size(&self) -> Option<(u32, u32)> {
+        let config = self.config.as_ref()?;
+        Some(render_scale::scaled_size(
+            config.width,
+            config.height,
+            self.render_scale,
+        ))
+    }
+
+    /// Copies `frame`, the texture the guest rendered into and its size, into
+    /// the frame readback buffer. Stops reading back once frames no longer fit,
+    /// e.g. after the window grew.
+    fn read_back(&mut self, (texture, width, height): (wgpu_core::id::TextureId, u32, u32)) {
+        let (Some(readback), Some(config)) = (&self.readback, &self.config) else {
+            return;
+        };
+        if let Err(e) = readback.validate(config.format, width, height) {
+            tracing::warn!("stopped frame readback: {e}");
+            self.readback = None;
+            return;
+        }
+        let result = readback.copy(
+            (self.get_instance)().as_ref(),
+            self.device_id,
+            self.queue_id,
+            texture,
+            config.format,
+            width,
+            height,
+        );
+        if let Err(e) = result {
+            // Likely the guest still has the buffer mapped.
+            tracing::debug!("skipped frame readback: {e}");
+        }
+    }
+
+    /// What presenting with `status` means for the guest. Surfaces that are out
+    /// of date are reconfigured for the next frame.
+    fn present_result(&mut self, status: wgpu_types::SurfaceStatus) -> PresentResult {
+        let reconfigured = mem::take(&mut self.reconfigured);
+        match status {
+            wgpu_types::SurfaceStatus::Good if !reconfigured => PresentResult::Presented,
+            wgpu_types::SurfaceStatus::Good => PresentResult::Reconfigured,
+            wgpu_types::SurfaceStatus::Suboptimal
+            | wgpu_types::SurfaceStatus::Outdated
+            | wgpu_types::SurfaceStatus::Lost => {
+                self.configure();
+                PresentResult::Reconfigured
+            }
+            wgpu_types::SurfaceStatus::Timeout => {
+                PresentResult::Failed("timed out waiting for the surface".to_string())
+            }
+        }
+    }
+
+    /// Submits a render pass clearing `view` to `color`.
+    fn clear_view(
+        &self,
+        instance: &wgpu_core::global::Global,
+        view: wgpu_core::id::TextureViewId,
+        color: Color,
+    ) -> wasmtime::Result<()> {
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            self.device_id,
+            &Default::default(),
+            None,
+        ))?;
+        let render_pass = wgpu_core::command::RenderPass::new(
+            encoder,
+            &wgpu_core::command::RenderPassDescriptor {
+                label: None,
+                color_attachments: Cow::Borrowed(&[Some(
+                    wgpu_core::command::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        channel: wgpu_core::command::PassChannel {
+                            load_op: wgpu_core::command::LoadOp::Clear,
+                            store_op: wgpu_core::command::StoreOp::Store,
+                            clear_value: wgpu_types::Color {
+                                r: color.r,
+                                g: color.g,
+                                b: color.b,
+                                a: color.a,
+                            },
+                            read_only: false,
+                        },
+                    },
+                )]),
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        );
+        instance.command_encoder_run_render_pass::<crate::Backend>(encoder, &render_pass)?;
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )?;
+        instance.queue_submit::<crate::Backend>(self.queue_id, &[command_buffer])?;
+        Ok(())
+    }
+}
+
+impl<GI, CS, I> Drop for WebGpuSurface<GI, CS, I>
+where
+    I: AsRef<wgpu_core::global::Global>,
+    GI: Fn() -> I,
+    CS: Fn(&(dyn DisplayApi + Send + Sync)) -> SurfaceId,
+{
+    fn drop(&mut self) {
+        let instance = (self.get_instance)();
+        for mirror in self.mirrors.drain(..) {
+            instance.as_ref().surface_drop(mirror.surface_id);
+        }
+    }
 }
 
 impl<GI, CS, I> DrawApi for WebGpuSurface<GI, CS, I>
@@ -158,33 +1055,105 @@ where
     CS: Fn(&(dyn DisplayApi + Send + Sync)) -> SurfaceId,
 {
     fn get_current_buffer(&mut self) -> wasmtime::Result<GraphicsContextBuffer> {
-        let texture: wgpu_core::id::TextureId = (self.get_instance)()
-            .as_ref()
-            .surface_get_current_texture::<crate::Backend>(self.surface_id.unwrap(), None)
-            .unwrap()
-            .texture_id
-            .unwrap();
+        let _span = tracing::info_span!("surface_acquire", surface = ?self.surface_id).entered();
+        let scaled = self
+            .scaled_target()?
+            .map(|target| (target.texture, target.width, target.height));
+        let size = match (scaled, &self.config) {
+            (Some((_, width, height)), _) => Some((width, height)),
+            (None, Some(config)) => Some((config.width, config.height)),
+            (None, None) => None,
+        };
+        if let Some((width, height)) = size {
+            if let Some(target) = self.msaa_target(width, height)? {
+                let buff = Box::new(target.texture);
+                let buff: GraphicsContextBuffer = buff.into();
+                return Ok(buff);
+            }
+        }
+        if let Some((texture, _, _)) = scaled {
+            let buff = Box::new(texture);
+            let buff: GraphicsContextBuffer = buff.into();
+            return Ok(buff);
+        }
+        let texture = self.current_texture()?;
         let buff = Box::new(texture);
         let buff: GraphicsContextBuffer = buff.into();
         Ok(buff)
     }
 
-    fn present(&mut self) -> wasmtime::Result<()> {
-        (self.get_instance)()
+    fn present(&mut self) -> wasmtime::Result<PresentResult> {
+        let _span = tracing::info_span!("surface_present", surface = ?self.surface_id).entered();
+        if self.msaa_target.is_some() {
+            let destination = match &self.scaled_target {
+                Some(target) => target.texture,
+                None => self.current_texture()?,
+            };
+            let instance = (self.get_instance)();
+            let msaa_target = self.msaa_target.as_ref().unwrap();
+            msaa_target.resolve(
+                instance.as_ref(),
+                self.device_id,
+                self.queue_id,
+                destination,
+            )?;
+        }
+        if self.readback.is_some() {
+            let frame = match &self.scaled_target {
+                Some(target) => (target.texture, target.width, target.height),
+                None => {
+                    let (width, height) = self.frame_size().unwrap_or_default();
+                    (self.current_texture()?, width, height)
+                }
+            };
+            self.read_back(frame);
+        }
+        // Presenting gives the texture back to the surface, even if it fails.
+        self.acquired = None;
+        if let (Some(target), Some(blitter), Some(config)) =
+            (&self.scaled_target, &mut self.blitter, &self.config)
+        {
+            let instance = (self.get_instance)();
+            let mirrors = self.mirrors.iter().map(|mirror| mirror.surface_id);
+            // Reconfiguring covers the mirrors too, so one out of date is enough.
+            let mut status = wgpu_types::SurfaceStatus::Good;
+            for surface_id in self.surface_id.into_iter().chain(mirrors) {
+                let surface_status = target.present(
+                    instance.as_ref(),
+                    self.device_id,
+                    self.queue_id,
+                    surface_id,
+                    config.format,
+                    blitter,
+                )?;
+                if !matches!(surface_status, wgpu_types::SurfaceStatus::Good) {
+                    status = surface_status;
+                }
+            }
+            return Ok(self.present_result(status));
+        }
+        let status = (self.get_instance)()
             .as_ref()
-            .surface_present::<crate::Backend>(self.surface_id.unwrap())
-            .unwrap();
-        Ok(())
+            .surface_present::<crate::Backend>(self.surface_id()?)?;
+        Ok(self.present_result(status))
     }
 
-    fn display_api_ready(&mut self, display: &Box<dyn DisplayApi + Send + Sync>) {
-        let surface_id = (self.create_surface)(display.as_ref());
+    fn display_api_ready(&mut self, display: &(dyn DisplayApi + Send + Sync)) {
+        let surface_id = (self.create_surface)(display);
 
         let swapchain_capabilities = (self.get_instance)()
             .as_ref()
             .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
             .unwrap();
-        let swapchain_format = swapchain_capabilities.formats[0];
+        let (swapchain_format, color_space) =
+            pick_format(self.color_space, &swapchain_capabilities);
+        if color_space != self.color_space {
+            tracing::warn!(
+                "surface doesn't support {:?}, showing frames in {color_space:?}",
+                self.color_space
+            );
+        }
+        tracing::info!("surface format {swapchain_format:?}, color space {color_space:?}");
 
         let config = wgpu_types::SurfaceConfiguration {
             usage: wgpu_types::TextureUsages::RENDER_ATTACHMENT,
@@ -194,50 +1163,587 @@ where
             present_mode: wgpu_types::PresentMode::Fifo,
             alpha_mode: swapchain_capabilities.alpha_modes[0],
             view_formats: vec![swapchain_format],
-            // TODO: not sure what the correct value is
-            desired_maximum_frame_latency: 2,
+            // Set from `frame_latency` in `configure`.
+            desired_maximum_frame_latency: 0,
         };
 
-        (self.get_instance)()
-            .as_ref()
-            .surface_configure::<crate::Backend>(surface_id, self.device_id, &config);
-
         self.surface_id = Some(surface_id);
+        self.config = Some(config);
+        self.configure();
     }
-}
-
-// ToCore trait used for resources, records, and variants.
-// Into trait used for enums, since they never need table access.
-mod enum_conversions;
-mod to_core_conversions;
 
-pub struct BufferPtr {
-    // See https://bytecodealliance.zulipchat.com/#narrow/stream/206238-general/topic/Should.20wasi.20resources.20be.20stored.20behind.20a.20mutex.3F
-    pub(crate) ptr: *mut u8,
-    pub(crate) len: u64,
-}
-impl BufferPtr {
-    pub fn slice(&self) -> &[u8] {
-        unsafe { slice::from_raw_parts(self.ptr, self.len as usize) }
+    fn resize(&mut self, width: u32, height: u32) {
+        let Some(config) = &mut self.config else {
+            return;
+        };
+        if (config.width, config.height) == (width, height) {
+            return;
+        }
+        config.width = width;
+        config.height = height;
+        self.configure();
     }
-    pub fn slice_mut(&mut self) -> &mut [u8] {
-        unsafe { slice::from_raw_parts_mut(self.ptr, self.len as usize) }
+
+    fn reconfigure(
+        &mut self,
+        configuration: Box<dyn std::any::Any + Send + Sync>,
+    ) -> Result<(), String> {
+        let configuration = configuration
+            .downcast::<SurfaceConfiguration>()
+            .map_err(|_| "not a webgpu surface configuration".to_string())?;
+        let (Some(surface_id), Some(config)) = (self.surface_id, &self.config) else {
+            return Err("surface isn't connected to a display yet".to_string());
+        };
+        if configuration.width == 0 || configuration.height == 0 {
+            return Err("width and height must be greater than 0".to_string());
+        }
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        let capabilities = instance
+            .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
+            .map_err(|e| e.to_string())?;
+        if !capabilities.formats.contains(&configuration.format) {
+            return Err(format!(
+                "surface doesn't support format {:?}",
+                configuration.format
+            ));
+        }
+        if !capabilities
+            .present_modes
+            .contains(&configuration.present_mode)
+        {
+            return Err(format!(
+                "surface doesn't support present mode {:?}",
+                configuration.present_mode
+            ));
+        }
+        if configuration.format != config.format {
+            // Made in the old format, recreated in the new one when needed.
+            if let Some(target) = self.scaled_target.take() {
+                target.destroy(instance);
+            }
+            if let Some(target) = self.msaa_target.take() {
+                target.destroy(instance);
+            }
+        }
+        self.config = Some(wgpu_types::SurfaceConfiguration {
+            width: configuration.width,
+            height: configuration.height,
+            format: configuration.format,
+            present_mode: configuration.present_mode,
+            view_formats: vec![configuration.format],
+            ..config.clone()
+        });
+        self.configure();
+        Ok(())
     }
-}
-unsafe impl Send for BufferPtr {}
-unsafe impl Sync for BufferPtr {}
 
-pub struct Buffer {
-    buffer: wgpu_core::id::BufferId,
-    mapped: Option<BufferPtr>,
-}
+    fn display_api_released(&mut self) {
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        // Recreated for the next display's size and format when needed.
+        if let Some(target) = self.scaled_target.take() {
+            target.destroy(instance);
+        }
+        if let Some(blitter) = self.blitter.take() {
+            blitter.destroy(instance);
+        }
+        if let Some(target) = self.msaa_target.take() {
+            target.destroy(instance);
+        }
+        if let Some(surface_id) = self.surface_id.take() {
+            instance.surface_drop(surface_id);
+        }
+        self.config = None;
+    }
+
+    fn set_buffer_count(&mut self, count: u32) -> Result<(), String> {
+        // The buffer count and `set_desired_maximum_frame_latency` are the same
+        // swapchain setting, so whichever was called last wins.
+        self.frame_latency = frame_latency_for_buffer_count(count)?;
+        self.configure();
+        Ok(())
+    }
+
+    fn set_desired_maximum_frame_latency(&mut self, frames: u32) -> Result<(), String> {
+        self.frame_latency = frames;
+        self.configure();
+        Ok(())
+    }
+
+    fn set_render_scale(&mut self, scale: f64) -> Result<(), String> {
+        // Takes effect on the next `get_current_buffer`, so the current frame still
+        // gets presented from where it was rendered.
+        self.render_scale = scale;
+        Ok(())
+    }
+
+    fn set_sample_count(&mut self, count: u32) -> Result<(), String> {
+        if let Some(config) = &self.config {
+            let features = (self.get_instance)()
+                .as_ref()
+                .adapter_get_texture_format_features::<crate::Backend>(
+                    self.adapter_id,
+                    config.format,
+                )
+                .map_err(|e| e.to_string())?;
+            if !features.flags.sample_count_supported(count) {
+                return Err(format!(
+                    "{:?} can't be rendered with {count} samples on this adapter",
+                    config.format
+                ));
+            }
+        }
+        // Like the render scale, takes effect on the next `get_current_buffer`.
+        self.sample_count = count;
+        Ok(())
+    }
+
+    fn attach_display_api(
+        &mut self,
+        key: usize,
+        display: &(dyn DisplayApi + Send + Sync),
+    ) -> Result<(), String> {
+        let Some(config) = &self.config else {
+            return Err("surface isn't connected to a display yet".to_string());
+        };
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        let surface_id = (self.create_surface)(display);
+        let capabilities = instance
+            .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
+            .map_err(|e| e.to_string());
+        let capabilities = match capabilities {
+            Ok(capabilities) if capabilities.formats.contains(&config.format) => capabilities,
+            Ok(_) => {
+                instance.surface_drop(surface_id);
+                return Err(format!(
+                    "display doesn't support format {:?}",
+                    config.format
+                ));
+            }
+            Err(e) => {
+                instance.surface_drop(surface_id);
+                return Err(e);
+            }
+        };
+        let config = wgpu_types::SurfaceConfiguration {
+            width: display.width(),
+            height: display.height(),
+            alpha_mode: capabilities.alpha_modes[0],
+            ..config.clone()
+        };
+        if let Some(e) =
+            instance.surface_configure::<crate::Backend>(surface_id, self.device_id, &config)
+        {
+            instance.surface_drop(surface_id);
+            return Err(e.to_string());
+        }
+        self.mirrors.push(Mirror {
+            key,
+            surface_id,
+            config,
+        });
+        Ok(())
+    }
+
+    fn detach_display_api(&mut self, key: usize) -> Result<(), String> {
+        let Some(index) = self.mirrors.iter().position(|mirror| mirror.key == key) else {
+            return Err("display isn't attached to this surface".to_string());
+        };
+        let mirror = self.mirrors.remove(index);
+        (self.get_instance)()
+            .as_ref()
+            .surface_drop(mirror.surface_id);
+        Ok(())
+    }
+
+    fn resize_display_api(&mut self, key: usize, width: u32, height: u32) {
+        let Some(mirror) = self.mirrors.iter_mut().find(|mirror| mirror.key == key) else {
+            return;
+        };
+        mirror.config.width = width;
+        mirror.config.height = height;
+        if let Some(e) = (self.get_instance)()
+            .as_ref()
+            .surface_configure::<crate::Backend>(mirror.surface_id, self.device_id, &mirror.config)
+        {
+            tracing::warn!("can't resize a mirrored display: {e}");
+        }
+    }
+
+    fn surface_capabilities(&self) -> Result<Box<dyn std::any::Any + Send + Sync>, String> {
+        let Some(surface_id) = self.surface_id else {
+            return Err("surface isn't connected to a display yet".to_string());
+        };
+        let capabilities = (self.get_instance)()
+            .as_ref()
+            .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
+            .map_err(|e| e.to_string())?;
+        Ok(Box::new(capabilities))
+    }
+
+    fn set_color_space(&mut self, space: ColorSpace) -> Result<ColorSpace, String> {
+        let (Some(surface_id), Some(config)) = (self.surface_id, &self.config) else {
+            // Picked from when the display is ready.
+            self.color_space = space;
+            return Ok(space);
+        };
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        let capabilities = instance
+            .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
+            .map_err(|e| e.to_string())?;
+        let previous = mem::replace(&mut self.color_space, space);
+        let (format, chosen) = pick_format(self.color_space, &capabilities);
+        if format == config.format {
+            return Ok(chosen);
+        }
+        if !self.mirrors.is_empty() {
+            self.color_space = previous;
+            return Err("color space can't be changed while frames are mirrored".to_string());
+        }
+        // Made in the old format, recreated in the new one when needed.
+        if let Some(target) = self.scaled_target.take() {
+            target.destroy(instance);
+        }
+        if let Some(target) = self.msaa_target.take() {
+            target.destroy(instance);
+        }
+        self.config = Some(wgpu_types::SurfaceConfiguration {
+            format,
+            view_formats: vec![format],
+            ..config.clone()
+        });
+        self.configure();
+        tracing::info!("surface format {format:?}, color space {chosen:?}");
+        Ok(chosen)
+    }
+
+    fn set_frame_readback(
+        &mut self,
+        buffer: Option<Box<dyn std::any::Any + Send + Sync>>,
+    ) -> Result<(), String> {
+        let readback = match buffer.map(|buffer| buffer.downcast::<FrameReadback>()) {
+            Some(Ok(readback)) => Some(*readback),
+            Some(Err(_)) => return Err("frames can only be read back to a gpu-buffer".to_string()),
+            None => None,
+        };
+        if let Some(readback) = &readback {
+            let (Some(surface_id), Some(config), Some((width, height))) =
+                (self.surface_id, &self.config, self.frame_size())
+            else {
+                return Err("surface isn't connected to a display yet".to_string());
+            };
+            readback.validate(config.format, width, height)?;
+            let capabilities = (self.get_instance)()
+                .as_ref()
+                .surface_get_capabilities::<crate::Backend>(surface_id, self.adapter_id)
+                .map_err(|e| e.to_string())?;
+            if !capabilities
+                .usages
+                .contains(wgpu_types::TextureUsages::COPY_SRC)
+            {
+                return Err("this surface's frames can't be copied".to_string());
+            }
+        }
+        self.readback = readback;
+        self.configure();
+        Ok(())
+    }
 
+    fn clear(&mut self, color: Color) -> wasmtime::Result<()> {
+        let texture = self.current_texture()?;
+        let instance = (self.get_instance)();
+        let instance = instance.as_ref();
+        let view = core_result(instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))?;
+        let cleared = self.clear_view(instance, view, color);
+        instance.texture_view_drop::<crate::Backend>(view, false)?;
+        self.acquired = None;
+        cleared?;
+        instance.surface_present::<crate::Backend>(self.surface_id()?)?;
+        Ok(())
+    }
+}
+
+// ToCore trait used for resources, records, and variants.
+// Into trait used for enums, since they never need table access.
+mod bind_group_layouts;
+mod buffer_readback;
+mod depth_readback;
+mod device_owners;
+mod enum_conversions;
+mod error_scopes;
+mod frame_readback;
+mod gpu_worker;
+mod in_flight;
+mod memory_budget;
+mod msaa;
+mod query_readback;
+mod render_scale;
+#[cfg(feature = "resource-counts")]
+mod resource_counts;
+#[cfg(test)]
+mod test_gpu;
+mod texture_descriptors;
+mod to_core_conversions;
+mod validation;
+mod write_batches;
+
+pub struct BufferPtr {
+    // See https://bytecodealliance.zulipchat.com/#narrow/stream/206238-general/topic/Should.20wasi.20resources.20be.20stored.20behind.20a.20mutex.3F
+    pub(crate) ptr: *mut u8,
+    pub(crate) len: u64,
+}
+impl BufferPtr {
+    pub fn slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len as usize) }
+    }
+    pub fn slice_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len as usize) }
+    }
+}
+unsafe impl Send for BufferPtr {}
+unsafe impl Sync for BufferPtr {}
+
+pub struct Buffer {
+    buffer: wgpu_core::id::BufferId,
+    size: u64,
+    usage: wgpu_types::BufferUsages,
+    mapping: Arc<BufferMapping>,
+    // Set by `destroy`, which frees the wgpu buffer but leaves the handle.
+    destroyed: bool,
+}
+
+/// Frees a wgpu id when dropped. Shared through an `Arc` by the guest's handle
+/// and whatever else still needs the id, like a pass that isn't run until `end`.
+pub struct Freed<I: Copy> {
+    instance: Arc<wgpu_core::global::Global>,
+    id: I,
+    free: fn(&wgpu_core::global::Global, I),
+    /// Runs after the id is freed.
+    on_free: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl<I: Copy> Freed<I> {
+    fn new(
+        instance: Arc<wgpu_core::global::Global>,
+        id: I,
+        free: fn(&wgpu_core::global::Global, I),
+    ) -> Self {
+        Self {
+            instance,
+            id,
+            free,
+            on_free: None,
+        }
+    }
+}
+
+impl<I: Copy> Drop for Freed<I> {
+    fn drop(&mut self) {
+        (self.free)(&self.instance, self.id);
+        if let Some(on_free) = self.on_free.take() {
+            on_free();
+        }
+    }
+}
+
+/// A buffer's wgpu id, shared by its handle, the remote buffers viewing its
+/// mapped range and passes using it. The wgpu buffer is dropped with the last
+/// of them, so a remote buffer never outlives the memory it points into.
+pub struct BufferMapping {
+    buffer: Freed<wgpu_core::id::BufferId>,
+    // Cleared on unmap and destroy, after which remote buffers have nothing to show.
+    range: Mutex<Option<BufferPtr>>,
+}
+
+/// What `get-mapped-range` hands out.
+pub struct RemoteBuffer(Arc<BufferMapping>);
+
+impl RemoteBuffer {
+    fn with_range<R>(&self, f: impl FnOnce(&mut BufferPtr) -> R) -> wasmtime::Result<R> {
+        match self.0.range.lock().unwrap().as_mut() {
+            Some(range) => Ok(f(range)),
+            None => Err(wasmtime::Error::msg(
+                "buffer was unmapped or destroyed, so its mapped range is gone",
+            )),
+        }
+    }
+}
+
+/// A texture, with whether it's the guest's to free.
 #[derive(Clone, Copy)]
+pub struct Texture {
+    id: wgpu_core::id::TextureId,
+    /// False for graphics context textures, which their surface frees.
+    owned: bool,
+    destroyed: bool,
+}
+
+/// A query set, with what it was created with.
+#[derive(Clone)]
+pub struct QuerySet {
+    id: wgpu_core::id::QuerySetId,
+    type_: webgpu::GpuQueryType,
+    count: u32,
+    freed: Arc<Freed<wgpu_core::id::QuerySetId>>,
+}
+
+/// A render pipeline, with how many vertex buffers it takes.
+pub struct RenderPipeline {
+    id: wgpu_core::id::RenderPipelineId,
+    vertex_buffers: u32,
+    freed: Arc<Freed<wgpu_core::id::RenderPipelineId>>,
+}
+
+pub struct BindGroup {
+    id: wgpu_core::id::BindGroupId,
+    freed: Arc<Freed<wgpu_core::id::BindGroupId>>,
+}
+
+/// Keeps what a pass uses from being freed before `end`, which is when wgpu-core
+/// looks up the ids recorded in the pass.
+type KeepAlive = Vec<Arc<dyn std::any::Any + Send + Sync>>;
+
+/// A render pass, with what's been set on it that's checked before wgpu sees it.
+pub struct RenderPass {
+    pass: wgpu_core::command::RenderPass,
+    /// The device's `max_vertex_buffers`, if the device is known.
+    max_vertex_buffers: Option<u32>,
+    /// How many vertex buffers the current pipeline takes.
+    pipeline_vertex_buffers: Option<u32>,
+    /// Slots that have a vertex buffer bound.
+    vertex_buffer_slots: BTreeSet<u32>,
+    keep_alive: KeepAlive,
+}
+
+impl std::ops::Deref for RenderPass {
+    type Target = wgpu_core::command::RenderPass;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pass
+    }
+}
+
+impl std::ops::DerefMut for RenderPass {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pass
+    }
+}
+
+pub struct ComputePass {
+    pass: wgpu_core::command::ComputePass,
+    keep_alive: KeepAlive,
+}
+
+impl std::ops::Deref for ComputePass {
+    type Target = wgpu_core::command::ComputePass;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pass
+    }
+}
+
+impl std::ops::DerefMut for ComputePass {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pass
+    }
+}
+
+/// wgpu-core 0.20 has no pipeline cache, so this only remembers its device
+/// until it does. Pipelines created with it are compiled as usual.
+pub struct PipelineCache {
+    device: wgpu_core::id::DeviceId,
+}
+
+/// A device's or adapter's limits, plus the ones wgpu doesn't count as limits.
+pub struct SupportedLimits {
+    limits: wgpu_types::Limits,
+    max_anisotropy: u16,
+}
+
+impl SupportedLimits {
+    fn new(limits: wgpu_types::Limits, downlevel: &wgpu_types::DownlevelCapabilities) -> Self {
+        Self {
+            limits,
+            max_anisotropy: max_anisotropy(downlevel),
+        }
+    }
+}
+
+impl std::ops::Deref for SupportedLimits {
+    type Target = wgpu_types::Limits;
+
+    fn deref(&self) -> &Self::Target {
+        &self.limits
+    }
+}
+
+/// Zeroes a buffer that was just created. One created mapped is zeroed through
+/// its mapping, and stays mapped only if `keep_mapped`; any other is cleared on
+/// the GPU, which needs copy-dst usage.
+fn zero_new_buffer(
+    instance: &wgpu_core::global::Global,
+    device: wgpu_core::id::DeviceId,
+    queue: wgpu_core::id::QueueId,
+    buffer: wgpu_core::id::BufferId,
+    mapped: bool,
+    keep_mapped: bool,
+) -> wasmtime::Result<()> {
+    if mapped {
+        let (ptr, len) = instance.buffer_get_mapped_range::<crate::Backend>(buffer, 0, None)?;
+        // Safety: the buffer was just created mapped, and nothing else has its range.
+        unsafe { std::ptr::write_bytes(ptr, 0, len as usize) };
+        if !keep_mapped {
+            instance.buffer_unmap::<crate::Backend>(buffer)?;
+        }
+        return Ok(());
+    }
+    let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+        device,
+        &Default::default(),
+        None,
+    ))?;
+    instance.command_encoder_clear_buffer::<crate::Backend>(encoder, buffer, 0, None)?;
+    let command_buffer = core_result(
+        instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+    )?;
+    instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+    Ok(())
+}
+
+/// wgpu clamps anisotropy to 16, or ignores it where it isn't supported.
+fn max_anisotropy(downlevel: &wgpu_types::DownlevelCapabilities) -> u16 {
+    if downlevel
+        .flags
+        .contains(wgpu_types::DownlevelFlags::ANISOTROPIC_FILTERING)
+    {
+        16
+    } else {
+        1
+    }
+}
+
+/// Like WebGPU, asking for more anisotropy than the device has isn't an error,
+/// it's clamped to the device's maximum.
+fn clamp_anisotropy(
+    requested: Option<u16>,
+    downlevel: &wgpu_types::DownlevelCapabilities,
+) -> Option<u16> {
+    requested.map(|max| max.min(max_anisotropy(downlevel)))
+}
+
 pub struct Device {
     pub device: wgpu_core::id::DeviceId,
     pub queue: wgpu_core::id::QueueId,
     // only needed when calling surface.get_capabilities in connect_graphics_context. If table would have a way to get parent from child, we could get it from device.
     pub adapter: wgpu_core::id::AdapterId,
+    error_scopes: ErrorScopes,
 }
 
 impl<T: WasiWebGpuView> webgpu::Host for WasiWebGpuImpl<T> {
@@ -247,30 +1753,37 @@ impl<T: WasiWebGpuView> webgpu::Host for WasiWebGpuImpl<T> {
 }
 
 impl<T: WasiWebGpuView> webgpu::HostRemoteBuffer for WasiWebGpuImpl<T> {
-    fn length(&mut self, buffer: Resource<webgpu::RemoteBuffer>) -> u32 {
-        let buffer = self.0.table().get(&buffer).unwrap();
-        let len = buffer.mapped.as_ref().unwrap().len;
-        len as u32
-    }
-
-    fn get(&mut self, buffer: Resource<webgpu::RemoteBuffer>, i: u32) -> u8 {
-        let buffer = self.0.table().get(&buffer).unwrap();
-        let remote_buffer = buffer.mapped.as_ref().unwrap();
-        let val = remote_buffer.slice()[i as usize];
-        val
+    fn length(&mut self, buffer: Resource<webgpu::RemoteBuffer>) -> wasmtime::Result<u32> {
+        let buffer = self.0.table().get(&buffer)?;
+        buffer.with_range(|range| range.len as u32)
     }
 
-    fn set(&mut self, buffer: Resource<webgpu::RemoteBuffer>, i: u32, val: u8) {
-        let buffer = self.0.table().get_mut(&buffer).unwrap();
-        let remote_buffer = buffer.mapped.as_mut().unwrap();
-        remote_buffer.slice_mut()[i as usize] = val;
+    fn get(&mut self, buffer: Resource<webgpu::RemoteBuffer>, i: u32) -> wasmtime::Result<u8> {
+        let buffer = self.0.table().get(&buffer)?;
+        buffer
+            .with_range(|range| range.slice().get(i as usize).copied())?
+            .ok_or_else(|| wasmtime::Error::msg(format!("index {i} is past the mapped range")))
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::RemoteBuffer>) -> wasmtime::Result<()> {
+    fn set(
+        &mut self,
+        buffer: Resource<webgpu::RemoteBuffer>,
+        i: u32,
+        val: u8,
+    ) -> wasmtime::Result<()> {
+        let buffer = self.0.table().get(&buffer)?;
+        buffer
+            .with_range(|range| range.slice_mut().get_mut(i as usize).map(|v| *v = val))?
+            .ok_or_else(|| wasmtime::Error::msg(format!("index {i} is past the mapped range")))
+    }
+
+    fn drop(&mut self, rep: Resource<webgpu::RemoteBuffer>) -> wasmtime::Result<()> {
+        self.0.table().delete(rep)?;
         Ok(())
     }
 }
 
+#[async_trait::async_trait]
 impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
     fn connect_graphics_context(
         &mut self,
@@ -279,6 +1792,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
     ) {
         let device = self.0.table().get(&device).unwrap();
         let device_id = device.device;
+        let queue_id = device.queue;
         let adapter_id = device.adapter;
 
         let instance = Arc::downgrade(&self.0.instance());
@@ -327,13 +1841,225 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
                 }
             },
             device_id,
+            queue_id,
             adapter_id,
             surface_id: None,
+            config: None,
+            frame_latency: 2,
+            render_scale: 1.0,
+            blitter: None,
+            scaled_target: None,
+            sample_count: 1,
+            msaa_target: None,
+            mirrors: Vec::new(),
+            readback: None,
+            color_space: ColorSpace::Srgb,
+            acquired: None,
+            reconfigured: false,
         };
 
         context.connect_draw_api(Box::new(surface));
     }
 
+    fn get_surface_capabilities(
+        &mut self,
+        _device: Resource<Device>,
+        context: Resource<GraphicsContext>,
+    ) -> Result<webgpu::GpuSurfaceCapabilities, String> {
+        let context = self.0.table().get(&context).unwrap();
+        let capabilities = context
+            .surface_capabilities()?
+            .downcast::<wgpu_types::SurfaceCapabilities>()
+            .map_err(|_| "graphics context isn't connected to a webgpu device".to_string())?;
+        Ok(webgpu::GpuSurfaceCapabilities {
+            formats: capabilities
+                .formats
+                .into_iter()
+                .filter_map(webgpu_texture_format)
+                .collect(),
+            present_modes: capabilities
+                .present_modes
+                .into_iter()
+                .filter_map(|mode| mode.try_into().ok())
+                .collect(),
+            alpha_modes: capabilities
+                .alpha_modes
+                .into_iter()
+                .map(composite_alpha_mode)
+                .collect(),
+        })
+    }
+
+    fn reconfigure_surface(
+        &mut self,
+        _device: Resource<Device>,
+        context: Resource<GraphicsContext>,
+        configuration: webgpu::GpuSurfaceConfiguration,
+    ) -> Result<(), String> {
+        let context = self.0.table().get_mut(&context).unwrap();
+        context.reconfigure(Box::new(SurfaceConfiguration {
+            width: configuration.width,
+            height: configuration.height,
+            format: configuration.format.into(),
+            present_mode: configuration.present_mode.into(),
+        }))
+    }
+
+    async fn read_depth_texture(
+        &mut self,
+        device: Resource<Device>,
+        texture: Resource<webgpu::GpuTexture>,
+        region: webgpu::GpuTextureRegion,
+    ) -> Result<Vec<f32>, String> {
+        let device = self.0.table().get(&device).unwrap();
+        let (device, queue) = (device.device, device.queue);
+        let texture = self.live_texture(&texture).map_err(|e| e.to_string())?.id;
+        let descriptor = self
+            .0
+            .texture_descriptors()
+            .and_then(|descriptors| descriptors.get(texture))
+            .ok_or_else(|| {
+                "texture's format isn't known, only textures from create-texture can be read"
+                    .to_string()
+            })?;
+        let bytes_per_value = validate_depth_read(&descriptor, &region)?;
+        let instance = self.0.instance();
+        // Waits on the GPU, which is the worker's job if there is one.
+        run_on_worker(self.0.gpu_worker(), move || {
+            read_depth(&instance, device, queue, texture, region, bytes_per_value)
+        })
+        .await
+        .map_err(|e| format!("can't read depth texture: {e}"))
+    }
+
+    fn get_texture_format_features(
+        &mut self,
+        device: Resource<Device>,
+        format: webgpu::GpuTextureFormat,
+    ) -> webgpu::GpuTextureFormatFeatures {
+        let device = self.0.table().get(&device).unwrap();
+        let (adapter, device) = (device.adapter, device.device);
+        let instance = self.0.instance();
+        let features = instance.device_features::<crate::Backend>(device).unwrap();
+        let format: wgpu_types::TextureFormat = format.into();
+        let supported = features.contains(format.required_features());
+        let format_features = match supported {
+            false => wgpu_types::TextureFormatFeatures {
+                allowed_usages: wgpu_types::TextureUsages::empty(),
+                flags: wgpu_types::TextureFormatFeatureFlags::empty(),
+            },
+            true if features
+                .contains(wgpu_types::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) =>
+            {
+                instance
+                    .adapter_get_texture_format_features::<crate::Backend>(adapter, format)
+                    .unwrap()
+            }
+            true => format.guaranteed_format_features(features),
+        };
+        let (usages, flags) = (format_features.allowed_usages, format_features.flags);
+        webgpu::GpuTextureFormatFeatures {
+            supported,
+            allowed_usages: usages.bits(),
+            renderable: usages.contains(wgpu_types::TextureUsages::RENDER_ATTACHMENT),
+            storage: usages.contains(wgpu_types::TextureUsages::STORAGE_BINDING),
+            storage_read_write: flags
+                .contains(wgpu_types::TextureFormatFeatureFlags::STORAGE_READ_WRITE),
+            filterable: flags.contains(wgpu_types::TextureFormatFeatureFlags::FILTERABLE),
+            blendable: flags.contains(wgpu_types::TextureFormatFeatureFlags::BLENDABLE),
+            multisample_resolve: flags
+                .contains(wgpu_types::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE),
+            sample_counts: match supported {
+                true => flags.supported_sample_counts(),
+                false => Vec::new(),
+            },
+        }
+    }
+
+    async fn read_query_results(
+        &mut self,
+        device: Resource<Device>,
+        query_set: Resource<webgpu::GpuQuerySet>,
+        first_query: webgpu::GpuSize32,
+        query_count: webgpu::GpuSize32,
+    ) -> Result<Vec<u64>, String> {
+        let device = self.0.table().get(&device).unwrap();
+        let (device, queue) = (device.device, device.queue);
+        let query_set = self.0.table().get(&query_set).unwrap().clone();
+        validate_query_range(&query_set, first_query, query_count)
+            .map_err(|e| format!("can't read query results: {e}"))?;
+        self.check_owner(query_set.id, device, "query set")
+            .map_err(|e| format!("can't read query results: {e}"))?;
+        let instance = self.0.instance();
+        let read = move || {
+            read_queries(
+                &instance,
+                device,
+                queue,
+                query_set,
+                first_query,
+                query_count,
+            )
+        };
+        let values = match self.0.gpu_worker() {
+            // Waits on the GPU, so let the worker wait rather than the executor.
+            Some(worker) => worker.run(read).await,
+            None => read(),
+        };
+        values.map_err(|e| format!("can't read query results: {e}"))
+    }
+
+    async fn read_buffer(
+        &mut self,
+        device: Resource<Device>,
+        buffer: Resource<webgpu::GpuBuffer>,
+        offset: webgpu::GpuSize64,
+        length: webgpu::GpuSize64,
+    ) -> Result<Vec<u8>, String> {
+        let device = self.0.table().get(&device).unwrap();
+        let (device, queue) = (device.device, device.queue);
+        let buffer = self
+            .live_buffer(&buffer)
+            .and_then(|buffer| {
+                validate_buffer_read(buffer, offset, length).map_err(wasmtime::Error::msg)?;
+                Ok(buffer.buffer)
+            })
+            .map_err(|e| format!("can't read buffer: {e}"))?;
+        self.check_owner(buffer, device, "buffer")
+            .map_err(|e| format!("can't read buffer: {e}"))?;
+        let instance = self.0.instance();
+        let write_batches = self.0.write_batches();
+        let read = move || {
+            // Writes still batched up would be missing from the copy.
+            if let Some(write_batches) = write_batches {
+                write_batches.flush(&instance, queue);
+            }
+            read_buffer(&instance, device, queue, buffer, offset, length)
+        };
+        let bytes = match self.0.gpu_worker() {
+            // Waits on the GPU, so let the worker wait rather than the executor.
+            Some(worker) => worker.run(read).await,
+            None => read(),
+        };
+        bytes.map_err(|e| format!("can't read buffer: {e}"))
+    }
+
+    fn set_frame_readback(
+        &mut self,
+        _device: Resource<Device>,
+        context: Resource<GraphicsContext>,
+        buffer: Option<Resource<webgpu::GpuBuffer>>,
+    ) -> Result<(), String> {
+        let readback = buffer.map(|buffer| {
+            let buffer = self.0.table().get(&buffer).unwrap();
+            FrameReadback::new(buffer.buffer, buffer.size, buffer.usage)
+        });
+        let context = self.0.table().get_mut(&context).unwrap();
+        context.set_frame_readback(
+            readback.map(|readback| Box::new(readback) as Box<dyn std::any::Any + Send + Sync>),
+        )
+    }
+
     fn create_command_encoder(
         &mut self,
         device: Resource<Device>,
@@ -347,12 +2073,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
                 .device_create_command_encoder::<crate::Backend>(
                     device,
                     &descriptor
-                        .map(|d| d.to_core(&self.0.table()))
+                        .map(|d| d.to_core(self.0.table()))
                         .unwrap_or_default(),
                     None,
                 ),
         )
         .unwrap();
+        self.record_owner(command_encoder, device);
 
         self.0.table().push(command_encoder).unwrap()
     }
@@ -371,12 +2098,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
                 .instance()
                 .device_create_shader_module::<crate::Backend>(
                     device,
-                    &descriptor.to_core(&self.0.table()),
+                    &descriptor.to_core(self.0.table()),
                     code,
                     None,
                 ),
         )
         .unwrap();
+        self.record_owner(shader, device);
 
         self.0.table().push(shader).unwrap()
     }
@@ -385,10 +2113,32 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
         &mut self,
         device: Resource<Device>,
         descriptor: webgpu::GpuRenderPipelineDescriptor,
-    ) -> Resource<wgpu_core::id::RenderPipelineId> {
+    ) -> wasmtime::Result<Resource<RenderPipeline>> {
         let host_device = self.0.table().get(&device).unwrap().device;
-
-        let descriptor = descriptor.to_core(&self.0.table());
+        let features = self
+            .0
+            .instance()
+            .device_features::<crate::Backend>(host_device)?;
+        validate_primitive_state(descriptor.primitive.as_ref(), features)
+            .and_then(|()| validate_multiview(descriptor.multiview, features))
+            .map_err(|e| wasmtime::Error::msg(format!("can't create render pipeline: {e}")))?;
+
+        self.check_pipeline_cache(descriptor.cache.as_ref(), host_device)
+            .map_err(|e| wasmtime::Error::msg(format!("can't create render pipeline: {e}")))?;
+
+        let vertex_buffers = descriptor.vertex.buffers.as_ref().map_or(0, Vec::len) as u32;
+        let descriptor = descriptor.to_core(self.0.table());
+        if let Some(layout) = descriptor.layout {
+            self.check_owner(layout, host_device, "render pipeline layout")?;
+        }
+        self.check_owner(
+            descriptor.vertex.stage.module,
+            host_device,
+            "vertex shader module",
+        )?;
+        if let Some(fragment) = &descriptor.fragment {
+            self.check_owner(fragment.stage.module, host_device, "fragment shader module")?;
+        }
 
         let implicit_pipeline_ids = match descriptor.layout {
             Some(_) => None,
@@ -406,10 +2156,25 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
                     None,
                     implicit_pipeline_ids,
                 ),
-        )
-        .unwrap();
+        )?;
+        self.record_owner(render_pipeline, host_device);
+        #[cfg(feature = "resource-counts")]
+        self.resource_created(ResourceKind::RenderPipeline);
+        let freed = Freed::new(
+            self.0.instance(),
+            render_pipeline,
+            wgpu_core::global::Global::render_pipeline_drop::<crate::Backend>,
+        );
+        #[cfg(feature = "resource-counts")]
+        let freed = self.count_drop(freed, ResourceKind::RenderPipeline);
 
-        self.0.table().push_child(render_pipeline, &device).unwrap()
+        let render_pipeline = RenderPipeline {
+            id: render_pipeline,
+            vertex_buffers,
+            freed: Arc::new(freed),
+        };
+        // Not a child of the device, so guests can drop the device first.
+        Ok(self.0.table().push(render_pipeline).unwrap())
     }
 
     fn queue(&mut self, device: Resource<Device>) -> Resource<wgpu_core::id::QueueId> {
@@ -440,68 +2205,194 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
             .instance()
             .device_limits::<crate::Backend>(device)
             .unwrap();
-        self.0.table().push(limits).unwrap()
+        let downlevel = self
+            .0
+            .instance()
+            .device_downlevel_properties::<crate::Backend>(device)
+            .unwrap();
+        self.0
+            .table()
+            .push(SupportedLimits::new(limits, &downlevel))
+            .unwrap()
     }
 
-    fn destroy(&mut self, _device: Resource<webgpu::GpuDevice>) {
-        todo!()
+    fn destroy(&mut self, device: Resource<webgpu::GpuDevice>) {
+        let device = self.0.table().get(&device).unwrap().device;
+        self.0.instance().device_destroy::<crate::Backend>(device);
+        if let Some(owners) = self.0.device_owners() {
+            owners.forget_device(device);
+        }
     }
 
     fn create_buffer(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuBufferDescriptor,
-    ) -> Resource<webgpu::GpuBuffer> {
-        let device = self.0.table().get(&device).unwrap().device;
-
-        let buffer = core_result(self.0.instance().device_create_buffer::<crate::Backend>(
-            device,
-            &descriptor.to_core(&self.0.table()),
-            None,
-        ))
-        .unwrap();
+    ) -> wasmtime::Result<Resource<webgpu::GpuBuffer>> {
+        let device_resource = device;
+        let Device { device, queue, .. } = *self.0.table().get(&device_resource)?;
+        let limits = self.0.instance().device_limits::<crate::Backend>(device)?;
+        let features = self
+            .0
+            .instance()
+            .device_features::<crate::Backend>(device)?;
+        if let Err(e) = validate_buffer_descriptor(&descriptor, &limits, features) {
+            let error = GpuError::validation(format!("can't create buffer: {e}"));
+            return self.invalid_buffer(&device_resource, None, error);
+        }
 
+        let budget = self.0.memory_budget();
+        let size = descriptor.size;
+        // Checked in `validate_buffer_descriptor`.
+        let usage = wgpu_types::BufferUsages::from_bits_truncate(descriptor.usage);
+        if let Some(budget) = &budget {
+            if let Err(e) = budget.reserve(size) {
+                let error = GpuError::out_of_memory(format!("can't create buffer: {e}"));
+                return self.invalid_buffer(&device_resource, None, error);
+            }
+        }
+        // Empty buffers have nothing to clear.
+        let clear_on_create = descriptor.clear_on_create.unwrap_or_default() && size > 0;
+        let mapped_at_creation = descriptor.mapped_at_creation.unwrap_or_default();
+        let mut descriptor = descriptor.to_core(self.0.table());
+        // Clear-buffer needs copy-dst, where mapping at creation works whatever the usage.
+        let map_to_clear = clear_on_create
+            && !mapped_at_creation
+            && !usage.contains(wgpu_types::BufferUsages::COPY_DST);
+        descriptor.mapped_at_creation |= map_to_clear;
+        let (buffer, error) =
+            self.0
+                .instance()
+                .device_create_buffer::<crate::Backend>(device, &descriptor, None);
+        if let Some(e) = error {
+            if let Some(budget) = &budget {
+                budget.release_reserved(size);
+            }
+            let error = GpuError::validation(format!("buffer is invalid: {e}"));
+            return self.invalid_buffer(&device_resource, Some(buffer), error);
+        }
+        if let Some(budget) = &budget {
+            budget.allocated(Allocation::Buffer(buffer), size);
+        }
+        if clear_on_create {
+            zero_new_buffer(
+                self.0.instance().as_ref(),
+                device,
+                queue,
+                buffer,
+                descriptor.mapped_at_creation,
+                mapped_at_creation,
+            )?;
+        }
+
+        self.record_owner(buffer, device);
+        #[cfg(feature = "resource-counts")]
+        self.resource_created(ResourceKind::Buffer);
+        let freed = Freed::new(self.0.instance(), buffer, |instance, buffer| {
+            instance.buffer_drop::<crate::Backend>(buffer, false)
+        });
+        #[cfg(feature = "resource-counts")]
+        let freed = self.count_drop(freed, ResourceKind::Buffer);
         let buffer = Buffer {
             buffer,
-            mapped: None,
+            size,
+            usage,
+            mapping: Arc::new(BufferMapping {
+                buffer: freed,
+                range: Mutex::new(None),
+            }),
+            destroyed: false,
         };
 
-        self.0.table().push(buffer).unwrap()
+        Ok(self.0.table().push(buffer).unwrap())
     }
 
     fn create_texture(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuTextureDescriptor,
-    ) -> Resource<webgpu::GpuTexture> {
-        let device = self.0.table().get(&device).unwrap().device;
-        let texture = core_result(self.0.instance().device_create_texture::<crate::Backend>(
-            device,
-            &descriptor.to_core(&self.0.table()),
-            None,
-        ))
-        .unwrap();
+    ) -> wasmtime::Result<Resource<webgpu::GpuTexture>> {
+        let device_resource = device;
+        let device = self.0.table().get(&device_resource)?.device;
+        let limits = self.0.instance().device_limits::<crate::Backend>(device)?;
+        let valid = validate_extent("size", &descriptor.size).and_then(|()| {
+            let size = descriptor.size.clone().to_core(self.0.table());
+            validate_texture_descriptor(&descriptor, size, &limits)
+        });
+        if let Err(e) = valid {
+            let error = GpuError::validation(format!("can't create texture: {e}"));
+            return self.invalid_texture(&device_resource, None, error);
+        }
+        // wgpu checks that the usage fits the format, sample count and so on. Whether
+        // a texture can be rendered to or sampled from is checked where it's used:
+        // in the render pass (reported by `end`) and in `create-bind-group`.
+        let descriptor = descriptor.to_core(self.0.table());
+        let budget = self.0.memory_budget();
+        let size = texture_size(&descriptor);
+        if let Some(budget) = &budget {
+            if let Err(e) = budget.reserve(size) {
+                let error = GpuError::out_of_memory(format!("can't create texture: {e}"));
+                return self.invalid_texture(&device_resource, None, error);
+            }
+        }
+        let (texture, error) =
+            self.0
+                .instance()
+                .device_create_texture::<crate::Backend>(device, &descriptor, None);
+        if let Some(e) = error {
+            if let Some(budget) = &budget {
+                budget.release_reserved(size);
+            }
+            let error = GpuError::validation(format!("texture is invalid: {e}"));
+            return self.invalid_texture(&device_resource, Some(texture), error);
+        }
+        if let Some(budget) = &budget {
+            budget.allocated(Allocation::Texture(texture), size);
+        }
+        if let Some(descriptors) = self.0.texture_descriptors() {
+            descriptors.insert(texture, descriptor.map_label(|_| ()));
+        }
+        self.record_owner(texture, device);
+        #[cfg(feature = "resource-counts")]
+        if let Some(counts) = self.0.resource_counts() {
+            counts.texture_created(texture);
+        }
 
-        self.0.table().push(texture).unwrap()
+        Ok(self
+            .0
+            .table()
+            .push(Texture {
+                id: texture,
+                owned: true,
+                destroyed: false,
+            })
+            .unwrap())
     }
 
     fn create_sampler(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: Option<webgpu::GpuSamplerDescriptor>,
-    ) -> Resource<webgpu::GpuSampler> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuSampler>> {
         let device = self.0.table().get(&device).unwrap().device;
 
-        let descriptor = descriptor.unwrap();
+        let mut descriptor = descriptor.unwrap();
+        validate_sampler_descriptor(&descriptor)
+            .map_err(|e| wasmtime::Error::msg(format!("can't create sampler: {e}")))?;
+        let downlevel = self
+            .0
+            .instance()
+            .device_downlevel_properties::<crate::Backend>(device)?;
+        descriptor.max_anisotropy = clamp_anisotropy(descriptor.max_anisotropy, &downlevel);
 
         let sampler = core_result(self.0.instance().device_create_sampler::<crate::Backend>(
             device,
-            &descriptor.to_core(&self.0.table()),
+            &descriptor.to_core(self.0.table()),
             None,
-        ))
-        .unwrap();
+        ))?;
+        self.record_owner(sampler, device);
 
-        self.0.table().push(sampler).unwrap()
+        Ok(self.0.table().push(sampler)?)
     }
 
     fn import_external_texture(
@@ -516,70 +2407,149 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuBindGroupLayoutDescriptor,
-    ) -> Resource<webgpu::GpuBindGroupLayout> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuBindGroupLayout>> {
         let device = self.0.table().get(&device).unwrap().device;
 
-        let bind_group_layout = core_result(
-            self.0
+        let counts = descriptor.entries.iter().filter_map(|entry| entry.count);
+        if counts.clone().any(|count| count == 0) {
+            return Err(wasmtime::Error::msg(
+                "binding array count must be at least 1",
+            ));
+        }
+        if counts.count() > 0 {
+            let features = self
+                .0
                 .instance()
-                .device_create_bind_group_layout::<crate::Backend>(
-                    device,
-                    &descriptor.to_core(&self.0.table()),
-                    None,
-                ),
-        )
-        .unwrap();
+                .device_features::<crate::Backend>(device)?;
+            if !features.contains(wgpu_types::Features::TEXTURE_BINDING_ARRAY) {
+                return Err(wasmtime::Error::msg(
+                    "binding arrays require the texture-binding-array feature, which this device doesn't have enabled",
+                ));
+            }
+        }
 
-        self.0.table().push(bind_group_layout).unwrap()
+        let descriptor = descriptor.to_core(self.0.table());
+        let instance = self.0.instance();
+        let create = || {
+            core_result(instance.device_create_bind_group_layout::<crate::Backend>(
+                device,
+                &descriptor,
+                None,
+            ))
+        };
+        let bind_group_layout = match self.0.bind_group_layout_cache() {
+            Some(cache) => cache.get_or_create(device, &descriptor.entries, create)?,
+            None => create()?,
+        };
+        self.record_owner(bind_group_layout, device);
+
+        Ok(self.0.table().push(bind_group_layout).unwrap())
     }
 
     fn create_pipeline_layout(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuPipelineLayoutDescriptor,
-    ) -> Resource<webgpu::GpuPipelineLayout> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuPipelineLayout>> {
         let device = self.0.table().get(&device).unwrap().device;
 
+        let descriptor = descriptor.to_core(self.0.table());
+        for bind_group_layout in descriptor.bind_group_layouts.iter() {
+            self.check_owner(*bind_group_layout, device, "bind group layout")?;
+        }
         let pipeline_layout = core_result(
             self.0
                 .instance()
-                .device_create_pipeline_layout::<crate::Backend>(
-                    device,
-                    &descriptor.to_core(&self.0.table()),
-                    None,
-                ),
-        )
-        .unwrap();
+                .device_create_pipeline_layout::<crate::Backend>(device, &descriptor, None),
+        )?;
+        self.record_owner(pipeline_layout, device);
 
-        self.0.table().push(pipeline_layout).unwrap()
+        Ok(self.0.table().push(pipeline_layout).unwrap())
     }
 
     fn create_bind_group(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuBindGroupDescriptor,
-    ) -> Resource<webgpu::GpuBindGroup> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuBindGroup>> {
         let device = self.0.table().get(&device).unwrap().device;
 
-        let bind_group = core_result(
-            self.0
-                .instance()
-                .device_create_bind_group::<crate::Backend>(
-                    device,
-                    &descriptor.to_core(&self.0.table()),
-                    None,
-                ),
-        )
-        .unwrap();
+        let limits = self.0.instance().device_limits::<crate::Backend>(device)?;
+        for entry in descriptor.entries.iter() {
+            if let webgpu::GpuBindingResource::GpuBufferBinding(binding) = &entry.resource {
+                let usage = self.0.table().get(&binding.buffer)?.usage;
+                let offset = binding.offset.unwrap_or_default();
+                validate_buffer_binding(entry.binding, offset, usage, &limits)
+                    .map_err(|e| wasmtime::Error::msg(format!("can't create bind group: {e}")))?;
+            }
+        }
+        let descriptor = descriptor.to_core(self.0.table());
+        self.check_owner(descriptor.layout, device, "bind group layout")?;
+        for entry in descriptor.entries.iter() {
+            let what = format!("resource at binding {}", entry.binding);
+            match &entry.resource {
+                wgpu_core::binding_model::BindingResource::Buffer(buffer) => {
+                    self.check_owner(buffer.buffer_id, device, &what)?;
+                }
+                wgpu_core::binding_model::BindingResource::BufferArray(buffers) => {
+                    for buffer in buffers.iter() {
+                        self.check_owner(buffer.buffer_id, device, &what)?;
+                    }
+                }
+                wgpu_core::binding_model::BindingResource::Sampler(sampler) => {
+                    self.check_owner(*sampler, device, &what)?;
+                }
+                wgpu_core::binding_model::BindingResource::SamplerArray(samplers) => {
+                    for sampler in samplers.iter() {
+                        self.check_owner(*sampler, device, &what)?;
+                    }
+                }
+                wgpu_core::binding_model::BindingResource::TextureView(view) => {
+                    self.check_owner(*view, device, &what)?;
+                }
+                wgpu_core::binding_model::BindingResource::TextureViewArray(views) => {
+                    for view in views.iter() {
+                        self.check_owner(*view, device, &what)?;
+                    }
+                }
+            }
+        }
+        let instance = self.0.instance();
+        let bind_group = core_result(instance.device_create_bind_group::<crate::Backend>(
+            device,
+            &descriptor,
+            None,
+        ))
+        .map_err(|e| {
+            let features = instance.device_features::<crate::Backend>(device).unwrap();
+            explain_bind_group_error(e, features)
+        })?;
+        self.record_owner(bind_group, device);
+        #[cfg(feature = "resource-counts")]
+        self.resource_created(ResourceKind::BindGroup);
+        let freed = Freed::new(
+            instance,
+            bind_group,
+            wgpu_core::global::Global::bind_group_drop::<crate::Backend>,
+        );
+        #[cfg(feature = "resource-counts")]
+        let freed = self.count_drop(freed, ResourceKind::BindGroup);
 
-        self.0.table().push(bind_group).unwrap()
+        Ok(self
+            .0
+            .table()
+            .push(BindGroup {
+                id: bind_group,
+                freed: Arc::new(freed),
+            })
+            .unwrap())
     }
 
     fn create_compute_pipeline(
         &mut self,
         device: Resource<webgpu::GpuDevice>,
         descriptor: webgpu::GpuComputePipelineDescriptor,
-    ) -> Resource<webgpu::GpuComputePipeline> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuComputePipeline>> {
         let device = self.0.table().get(&device).unwrap().device;
 
         let implicit_pipeline_ids = match &descriptor.layout {
@@ -592,18 +2562,34 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
             },
         };
 
+        self.check_pipeline_cache(descriptor.cache.as_ref(), device)
+            .map_err(|e| wasmtime::Error::msg(format!("can't create compute pipeline: {e}")))?;
+        let descriptor = descriptor.to_core(self.0.table());
+        if let Some(layout) = descriptor.layout {
+            self.check_owner(layout, device, "compute pipeline layout")?;
+        }
+        self.check_owner(descriptor.stage.module, device, "compute shader module")?;
         let compute_pipeline = core_result(
             self.0
                 .instance()
                 .device_create_compute_pipeline::<crate::Backend>(
                     device,
-                    &descriptor.to_core(&self.0.table()),
+                    &descriptor,
                     None,
                     implicit_pipeline_ids,
                 ),
-        )
-        .unwrap();
-        self.0.table().push(compute_pipeline).unwrap()
+        )?;
+        self.record_owner(compute_pipeline, device);
+        Ok(self.0.table().push(compute_pipeline).unwrap())
+    }
+
+    fn create_pipeline_cache(
+        &mut self,
+        device: Resource<webgpu::GpuDevice>,
+        _descriptor: webgpu::GpuPipelineCacheDescriptor,
+    ) -> Resource<PipelineCache> {
+        let device = self.0.table().get(&device).unwrap().device;
+        self.0.table().push(PipelineCache { device }).unwrap()
     }
 
     // fn create_compute_pipeline_async(
@@ -618,7 +2604,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
     //     &mut self,
     //     self_: Resource<webgpu::GpuDevice>,
     //     descriptor: webgpu::GpuRenderPipelineDescriptor,
-    // ) -> Resource<wgpu_core::id::RenderPipelineId> {
+    // ) -> Resource<RenderPipeline> {
     //     todo!()
     // }
 
@@ -632,17 +2618,40 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
 
     fn create_query_set(
         &mut self,
-        _device: Resource<webgpu::GpuDevice>,
-        _descriptor: webgpu::GpuQuerySetDescriptor,
-    ) -> Resource<webgpu::GpuQuerySet> {
-        todo!()
+        device: Resource<webgpu::GpuDevice>,
+        descriptor: webgpu::GpuQuerySetDescriptor,
+    ) -> wasmtime::Result<Resource<webgpu::GpuQuerySet>> {
+        let device = self.0.table().get(&device).unwrap().device;
+        let query_set = core_result(self.0.instance().device_create_query_set::<crate::Backend>(
+            device,
+            &wgpu_types::QuerySetDescriptor {
+                label: descriptor.label.map(|l| l.into()),
+                ty: descriptor.type_.into(),
+                count: descriptor.count,
+            },
+            None,
+        ))
+        .map_err(|e| wasmtime::Error::msg(format!("can't create query set: {e}")))?;
+        self.record_owner(query_set, device);
+        let freed = Freed::new(
+            self.0.instance(),
+            query_set,
+            wgpu_core::global::Global::query_set_drop::<crate::Backend>,
+        );
+
+        Ok(self.0.table().push(QuerySet {
+            id: query_set,
+            type_: descriptor.type_,
+            count: descriptor.count,
+            freed: Arc::new(freed),
+        })?)
     }
 
     fn label(&mut self, _device: Resource<webgpu::GpuDevice>) -> String {
         todo!()
     }
 
-    fn set_label(&mut self, _device: Resource<webgpu::GpuDevice>, _label: String) -> () {
+    fn set_label(&mut self, _device: Resource<webgpu::GpuDevice>, _label: String) {
         todo!()
     }
 
@@ -655,17 +2664,22 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
 
     fn push_error_scope(
         &mut self,
-        _device: Resource<webgpu::GpuDevice>,
-        _filter: webgpu::GpuErrorFilter,
-    ) -> () {
-        todo!()
+        device: Resource<webgpu::GpuDevice>,
+        filter: webgpu::GpuErrorFilter,
+    ) {
+        let device = self.0.table().get_mut(&device).unwrap();
+        device.error_scopes.push(filter);
     }
 
     fn pop_error_scope(
         &mut self,
-        _device: Resource<webgpu::GpuDevice>,
-    ) -> Resource<webgpu::GpuError> {
-        todo!()
+        device: Resource<webgpu::GpuDevice>,
+    ) -> Result<Option<Resource<webgpu::GpuError>>, String> {
+        let device = self.0.table().get_mut(&device).unwrap();
+        match device.error_scopes.pop()? {
+            Some(error) => Ok(Some(self.0.table().push(error).unwrap())),
+            None => Ok(None),
+        }
     }
 
     fn onuncapturederror(
@@ -675,7 +2689,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuDevice for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuDevice>) -> wasmtime::Result<()> {
+    fn drop(&mut self, rep: Resource<webgpu::GpuDevice>) -> wasmtime::Result<()> {
+        let device = self.0.table().delete(rep)?;
+        if let Some(owners) = self.0.device_owners() {
+            owners.forget_device(device.device);
+        }
         Ok(())
     }
 }
@@ -684,38 +2702,100 @@ impl<T: WasiWebGpuView> webgpu::HostGpuTexture for WasiWebGpuImpl<T> {
     fn from_graphics_buffer(
         &mut self,
         buffer: Resource<GraphicsContextBuffer>,
-    ) -> Resource<wgpu_core::id::TextureId> {
+    ) -> Resource<Texture> {
         let host_buffer = self.0.table().delete(buffer).unwrap();
         let host_buffer: wgpu_core::id::TextureId = host_buffer.inner_type();
-        self.0.table().push(host_buffer).unwrap()
+        self.0
+            .table()
+            .push(Texture {
+                id: host_buffer,
+                owned: false,
+                destroyed: false,
+            })
+            .unwrap()
     }
 
     fn create_view(
         &mut self,
-        texture: Resource<wgpu_core::id::TextureId>,
+        texture: Resource<Texture>,
         descriptor: Option<webgpu::GpuTextureViewDescriptor>,
-    ) -> Resource<wgpu_core::id::TextureViewId> {
-        let texture_id = *self.0.table().get(&texture).unwrap();
+    ) -> wasmtime::Result<Resource<wgpu_core::id::TextureViewId>> {
+        let texture_id = self.live_texture(&texture)?.id;
         let texture_view = core_result(
             self.0.instance().texture_create_view::<crate::Backend>(
                 texture_id,
                 &descriptor
-                    .map(|d| d.to_core(&self.0.table()))
+                    .map(|d| d.to_core(self.0.table()))
                     .unwrap_or_default(),
                 None,
             ),
         )
-        .unwrap();
-        self.0.table().push(texture_view).unwrap()
+        .map_err(|e| match e {
+            wgpu_core::resource::CreateTextureViewError::FormatReinterpretation {
+                texture,
+                view,
+            } => wasmtime::Error::msg(format!(
+                "texture view format {view:?} has to be the texture's format {texture:?} or one of its view-formats"
+            )),
+            wgpu_core::resource::CreateTextureViewError::InvalidAspect {
+                texture_format,
+                requested_aspect,
+            } => wasmtime::Error::msg(format!(
+                "texture view aspect {requested_aspect:?} isn't part of the texture's format {texture_format:?}, only combined depth-stencil formats have both depth-only and stencil-only"
+            )),
+            wgpu_core::resource::CreateTextureViewError::InvalidTexture => wasmtime::Error::msg(
+                "texture was destroyed, or is invalid, so views of it can't be created",
+            ),
+            e => wasmtime::Error::msg(format!("texture view is invalid: {e}")),
+        })?;
+        if let Some(device) = self.owner(texture_id) {
+            self.record_owner(texture_view, device);
+        }
+        Ok(self.0.table().push(texture_view).unwrap())
     }
 
-    fn drop(&mut self, _rep: Resource<wgpu_core::id::TextureId>) -> wasmtime::Result<()> {
-        // TODO:
+    fn drop(&mut self, rep: Resource<Texture>) -> wasmtime::Result<()> {
+        let Texture {
+            id: texture, owned, ..
+        } = self.0.table().delete(rep)?;
+        if owned {
+            self.0
+                .instance()
+                .texture_drop::<crate::Backend>(texture, false);
+        }
+        if let Some(budget) = self.0.memory_budget() {
+            budget.free(Allocation::Texture(texture));
+        }
+        if let Some(descriptors) = self.0.texture_descriptors() {
+            descriptors.remove(texture);
+        }
+        #[cfg(feature = "resource-counts")]
+        if let Some(counts) = self.0.resource_counts() {
+            counts.texture_dropped(texture);
+        }
         Ok(())
     }
 
-    fn destroy(&mut self, _self_: Resource<webgpu::GpuTexture>) {
-        todo!()
+    fn destroy(&mut self, texture: Resource<webgpu::GpuTexture>) -> wasmtime::Result<()> {
+        let texture = self.0.table().get_mut(&texture)?;
+        // Destroying twice is fine, as in WebGPU.
+        if std::mem::replace(&mut texture.destroyed, true) {
+            return Ok(());
+        }
+        let texture = texture.id;
+        // Invalid textures, from failed creations, have nothing to free.
+        match self.0.instance().texture_destroy::<crate::Backend>(texture) {
+            Ok(())
+            | Err(
+                wgpu_core::resource::DestroyError::AlreadyDestroyed
+                | wgpu_core::resource::DestroyError::Invalid,
+            ) => {}
+            Err(e) => return Err(wasmtime::Error::msg(format!("can't destroy texture: {e}"))),
+        }
+        if let Some(budget) = self.0.memory_budget() {
+            budget.free(Allocation::Texture(texture));
+        }
+        Ok(())
     }
 
     fn width(&mut self, _self_: Resource<webgpu::GpuTexture>) -> webgpu::GpuIntegerCoordinateOut {
@@ -817,22 +2897,23 @@ impl<T: WasiWebGpuView> webgpu::HostGpuShaderModule for WasiWebGpuImpl<T> {
 }
 
 impl<T: WasiWebGpuView> webgpu::HostGpuRenderPipeline for WasiWebGpuImpl<T> {
-    fn drop(&mut self, _rep: Resource<webgpu::GpuRenderPipeline>) -> wasmtime::Result<()> {
-        // TODO:
+    fn drop(&mut self, rep: Resource<webgpu::GpuRenderPipeline>) -> wasmtime::Result<()> {
+        // Freed now, or once the render passes using it end.
+        self.0.table().delete(rep)?;
         Ok(())
     }
 
-    fn label(&mut self, _self_: Resource<wgpu_core::id::RenderPipelineId>) -> String {
+    fn label(&mut self, _self_: Resource<RenderPipeline>) -> String {
         todo!()
     }
 
-    fn set_label(&mut self, _self_: Resource<wgpu_core::id::RenderPipelineId>, _label: String) {
+    fn set_label(&mut self, _self_: Resource<RenderPipeline>, _label: String) {
         todo!()
     }
 
     fn get_bind_group_layout(
         &mut self,
-        _self_: Resource<wgpu_core::id::RenderPipelineId>,
+        _self_: Resource<RenderPipeline>,
         _index: u32,
     ) -> Resource<webgpu::GpuBindGroupLayout> {
         todo!()
@@ -844,21 +2925,61 @@ impl<T: WasiWebGpuView> webgpu::HostGpuAdapter for WasiWebGpuImpl<T> {
         &mut self,
         adapter: Resource<wgpu_core::id::AdapterId>,
         descriptor: Option<webgpu::GpuDeviceDescriptor>,
-    ) -> Resource<webgpu::GpuDevice> {
+    ) -> Result<Resource<webgpu::GpuDevice>, String> {
         let adapter_id = *self.0.table().get(&adapter).unwrap();
+        let instance = self.0.instance();
+
+        let mut descriptor = descriptor;
+        let required_features = descriptor
+            .as_ref()
+            .and_then(|d| d.required_features.clone())
+            .unwrap_or_default();
+        let required_limits = descriptor
+            .as_mut()
+            .and_then(|d| d.required_limits.take())
+            .unwrap_or_default();
+
+        let adapter_features = instance
+            .adapter_features::<crate::Backend>(adapter_id)
+            .unwrap();
+        check_required_features(&required_features, adapter_features)?;
+
+        let adapter_limits = instance
+            .adapter_limits::<crate::Backend>(adapter_id)
+            .unwrap();
+        let required_limits = required_limits_to_core(required_limits)?;
+        let mut exceeded_limits = Vec::new();
+        required_limits.check_limits_with_fail_fn(
+            &adapter_limits,
+            false,
+            |name, requested, allowed| {
+                exceeded_limits.push(format!(
+                    "{name} (requested {requested}, adapter allows {allowed})"
+                ))
+            },
+        );
+        if !exceeded_limits.is_empty() {
+            return Err(format!(
+                "adapter can't meet required limits: {}",
+                exceeded_limits.join(", ")
+            ));
+        }
 
-        let (device_id, queue_id) = core_results_2(
-            self.0.instance().adapter_request_device::<crate::Backend>(
+        let descriptor = wgpu_types::DeviceDescriptor {
+            required_limits,
+            ..descriptor
+                .map(|d| d.to_core(self.0.table()))
+                .unwrap_or_default()
+        };
+        let (device_id, queue_id) =
+            core_results_2(instance.adapter_request_device::<crate::Backend>(
                 adapter_id,
-                &descriptor
-                    .map(|d| d.to_core(&self.0.table()))
-                    .unwrap_or_default(),
+                &descriptor,
                 None,
                 None,
                 None,
-            ),
-        )
-        .unwrap();
+            ))
+            .map_err(|e| e.to_string())?;
 
         let device = self
             .0
@@ -867,10 +2988,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuAdapter for WasiWebGpuImpl<T> {
                 device: device_id,
                 queue: queue_id,
                 adapter: adapter_id,
+                error_scopes: ErrorScopes::default(),
             })
             .unwrap();
 
-        device
+        Ok(device)
     }
 
     fn drop(&mut self, _adapter: Resource<webgpu::GpuAdapter>) -> wasmtime::Result<()> {
@@ -894,7 +3016,15 @@ impl<T: WasiWebGpuView> webgpu::HostGpuAdapter for WasiWebGpuImpl<T> {
             .instance()
             .adapter_limits::<crate::Backend>(adapter)
             .unwrap();
-        self.0.table().push(limits).unwrap()
+        let downlevel = self
+            .0
+            .instance()
+            .adapter_downlevel_capabilities::<crate::Backend>(adapter)
+            .unwrap();
+        self.0
+            .table()
+            .push(SupportedLimits::new(limits, &downlevel))
+            .unwrap()
     }
 
     fn is_fallback_adapter(
@@ -917,24 +3047,75 @@ impl<T: WasiWebGpuView> webgpu::HostGpuAdapter for WasiWebGpuImpl<T> {
         let info = self.0.table().push(info).unwrap();
         info
     }
+
+    fn get_supported_sample_counts(
+        &mut self,
+        adapter: Resource<wgpu_core::id::AdapterId>,
+        format: webgpu::GpuTextureFormat,
+    ) -> Vec<u32> {
+        let adapter = *self.0.table().get(&adapter).unwrap();
+        let instance = self.0.instance();
+        let format: wgpu_types::TextureFormat = format.into();
+        let adapter_features = instance
+            .adapter_features::<crate::Backend>(adapter)
+            .unwrap();
+        if !adapter_features.contains(format.required_features()) {
+            return Vec::new();
+        }
+        instance
+            .adapter_get_texture_format_features::<crate::Backend>(adapter, format)
+            .unwrap()
+            .flags
+            .supported_sample_counts()
+    }
 }
 
+#[async_trait::async_trait]
 impl<T: WasiWebGpuView> webgpu::HostGpuQueue for WasiWebGpuImpl<T> {
-    fn submit(
+    async fn submit(
         &mut self,
         queue: Resource<wgpu_core::id::QueueId>,
         val: Vec<Resource<webgpu::GpuCommandBuffer>>,
-    ) {
+    ) -> wasmtime::Result<()> {
         let command_buffers = val
             .into_iter()
-            .map(|buffer| self.0.table().delete(buffer).unwrap())
-            .collect::<Vec<_>>();
-
-        let queue = *self.0.table().get(&queue).unwrap();
-        self.0
-            .instance()
-            .queue_submit::<crate::Backend>(queue, &command_buffers)
-            .unwrap();
+            .map(|buffer| self.0.table().delete(buffer))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let queue = *self.0.table().get(&queue)?;
+        let span = tracing::info_span!(
+            "queue_submit",
+            ?queue,
+            command_buffers = ?command_buffers
+        );
+        let instance = self.0.instance();
+        let in_flight_limit = self.0.in_flight_limit();
+        let frame_timings = self.0.frame_timings();
+        let write_batches = self.0.write_batches();
+        run_on_worker(self.0.gpu_worker(), move || -> wasmtime::Result<()> {
+            if let Some(limit) = &in_flight_limit {
+                limit.wait_for_slot(&instance)?;
+            }
+            if let Some(write_batches) = &write_batches {
+                write_batches.flush(&instance, queue);
+            }
+            let submitted_at = std::time::Instant::now();
+            instance.queue_submit::<crate::Backend>(queue, &command_buffers)?;
+            if let Some(limit) = &in_flight_limit {
+                limit.submitted(&instance, queue)?;
+            }
+            if let Some(frame_timings) = frame_timings {
+                instance.queue_on_submitted_work_done::<crate::Backend>(
+                    queue,
+                    wgpu_core::device::queue::SubmittedWorkDoneClosure::from_rust(Box::new(
+                        move || frame_timings.record_gpu_time(submitted_at.elapsed()),
+                    )),
+                )?;
+            }
+            Ok(())
+        })
+        .instrument(span)
+        .await
     }
 
     fn drop(&mut self, _rep: Resource<wgpu_core::id::QueueId>) -> wasmtime::Result<()> {
@@ -954,9 +3135,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuQueue for WasiWebGpuImpl<T> {
         data_offset: Option<webgpu::GpuSize64>,
         data: Vec<u8>,
         size: Option<webgpu::GpuSize64>,
-    ) {
+    ) -> wasmtime::Result<()> {
         let queue = *self.0.table().get(&queue).unwrap();
-        let buffer = self.0.table().get(&buffer).unwrap().buffer;
+        let buffer = self
+            .live_buffer(&buffer)
+            .map_err(|e| wasmtime::Error::msg(format!("can't write buffer: {e}")))?;
+        let mapping = Arc::clone(&buffer.mapping);
+        let buffer = buffer.buffer;
         let mut data = &data[..];
         if let Some(data_offset) = data_offset {
             let data_offset = data_offset as usize;
@@ -966,10 +3151,60 @@ impl<T: WasiWebGpuView> webgpu::HostGpuQueue for WasiWebGpuImpl<T> {
             let size = size as usize;
             data = &data[..size];
         }
-        self.0
-            .instance()
-            .queue_write_buffer::<crate::Backend>(queue, buffer, buffer_offset, &data)
-            .unwrap();
+        let instance = self.0.instance();
+        match self.0.write_batches() {
+            Some(write_batches) => {
+                // Checked now, so errors point at this call rather than the submit.
+                instance
+                    .queue_validate_write_buffer::<crate::Backend>(
+                        queue,
+                        buffer,
+                        buffer_offset,
+                        data.len() as u64,
+                    )
+                    .map_err(|e| wasmtime::Error::msg(format!("can't write buffer: {e}")))?;
+                write_batches.write(queue, &mapping, buffer_offset, data);
+            }
+            None => instance
+                .queue_write_buffer::<crate::Backend>(queue, buffer, buffer_offset, data)
+                .map_err(|e| wasmtime::Error::msg(format!("can't write buffer: {e}")))?,
+        }
+        Ok(())
+    }
+
+    fn write_buffer_with(
+        &mut self,
+        queue: Resource<wgpu_core::id::QueueId>,
+        buffer: Resource<webgpu::GpuBuffer>,
+        buffer_offset: webgpu::GpuSize64,
+        data: Vec<u8>,
+    ) -> Result<(), webgpu::GpuWriteBufferError> {
+        let queue = *self.0.table().get(&queue).unwrap();
+        let buffer = self
+            .live_buffer(&buffer)
+            .map_err(|_| webgpu::GpuWriteBufferError::DestroyedBuffer)?;
+        let mapping = Arc::clone(&buffer.mapping);
+        let buffer = buffer.buffer;
+        let instance = self.0.instance();
+        // Checks alignment, bounds, and usage.
+        instance.queue_validate_write_buffer::<crate::Backend>(
+            queue,
+            buffer,
+            buffer_offset,
+            data.len() as u64,
+        )?;
+        match self.0.write_batches() {
+            Some(write_batches) => write_batches.write(queue, &mapping, buffer_offset, &data),
+            // The bindings already copied `data` out of guest memory, so this is the
+            // only other copy, into wgpu's staging buffer.
+            None => instance.queue_write_buffer::<crate::Backend>(
+                queue,
+                buffer,
+                buffer_offset,
+                &data,
+            )?,
+        }
+        Ok(())
     }
 
     fn write_texture(
@@ -979,18 +3214,23 @@ impl<T: WasiWebGpuView> webgpu::HostGpuQueue for WasiWebGpuImpl<T> {
         data: Vec<u8>,
         data_layout: webgpu::GpuImageDataLayout,
         size: webgpu::GpuExtent3D,
-    ) {
-        let queue = *self.0.table().get(&queue).unwrap();
+    ) -> wasmtime::Result<()> {
+        let queue = *self.0.table().get(&queue)?;
+        let error = |e| wasmtime::Error::msg(format!("can't write texture: {e}"));
+        validate_extent("size", &size)
+            .and_then(|()| validate_origin("destination.origin", destination.origin.as_ref()))
+            .map_err(error)?;
+        self.live_texture(&destination.texture)?;
         self.0
             .instance()
             .queue_write_texture::<crate::Backend>(
                 queue,
-                &destination.to_core(&self.0.table()),
+                &destination.to_core(self.0.table()),
                 &data,
-                &data_layout.to_core(&self.0.table()),
-                &size.to_core(&self.0.table()),
+                &data_layout.to_core(self.0.table()),
+                &size.to_core(self.0.table()),
             )
-            .unwrap();
+            .map_err(|e| error(e.to_string()))
     }
 
     fn copy_external_image_to_texture(
@@ -1017,53 +3257,82 @@ impl<T: WasiWebGpuView> webgpu::HostGpuCommandEncoder for WasiWebGpuImpl<T> {
         &mut self,
         command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
         descriptor: webgpu::GpuRenderPassDescriptor,
-    ) -> Resource<webgpu::GpuRenderPassEncoder> {
+    ) -> wasmtime::Result<Resource<webgpu::GpuRenderPassEncoder>> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        let mut keep_alive: KeepAlive = Vec::new();
+        if let Some(timestamp_writes) = &descriptor.timestamp_writes {
+            let query_set = self.0.table().get(&timestamp_writes.query_set)?;
+            validate_timestamp_writes(
+                query_set,
+                timestamp_writes.beginning_of_pass_write_index,
+                timestamp_writes.end_of_pass_write_index,
+            )
+            .map_err(|e| wasmtime::Error::msg(format!("can't begin render pass: {e}")))?;
+            keep_alive.push(query_set.freed.clone());
+        }
         // can't use to_core because depth_stencil_attachment is Option<&x>.
         let depth_stencil_attachment = descriptor
             .depth_stencil_attachment
-            .map(|d| d.to_core(&self.0.table()));
+            .map(|d| d.to_core(self.0.table()));
+        let timestamp_writes = descriptor
+            .timestamp_writes
+            .map(|t| t.to_core(self.0.table()));
         let descriptor = wgpu_core::command::RenderPassDescriptor {
             label: descriptor.label.map(|l| l.into()),
             color_attachments: descriptor
                 .color_attachments
                 .into_iter()
-                .map(|c| Some(c.to_core(&self.0.table())))
+                .map(|c| c.map(|c| c.to_core(self.0.table())))
                 .collect::<Vec<_>>()
                 .into(),
             depth_stencil_attachment: depth_stencil_attachment.as_ref(),
-            // timestamp_writes: self.timestamp_writes,
+            timestamp_writes: timestamp_writes.as_ref(),
             // occlusion_query_set: self.occlusion_query_set,
             // TODO: self.max_draw_count not used
             // TODO: remove default
             ..Default::default()
         };
-        let render_pass = wgpu_core::command::RenderPass::new(
-            command_encoder.to_core(&self.0.table()),
-            &descriptor,
-        );
+        let render_pass = wgpu_core::command::RenderPass::new(command_encoder, &descriptor);
+        let max_vertex_buffers = self.owner(command_encoder).and_then(|device| {
+            let limits = self.0.instance().device_limits::<crate::Backend>(device);
+            limits.ok().map(|limits| limits.max_vertex_buffers)
+        });
 
-        self.0.table().push(render_pass).unwrap()
+        Ok(self.0.table().push(RenderPass {
+            pass: render_pass,
+            max_vertex_buffers,
+            pipeline_vertex_buffers: None,
+            vertex_buffer_slots: BTreeSet::new(),
+            keep_alive,
+        })?)
     }
 
     fn finish(
         &mut self,
         command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
         descriptor: Option<webgpu::GpuCommandBufferDescriptor>,
-    ) -> Resource<webgpu::GpuCommandBuffer> {
-        let command_encoder = self.0.table().delete(command_encoder).unwrap();
+    ) -> wasmtime::Result<Resource<webgpu::GpuCommandBuffer>> {
+        // The guest gave up its handle, so the encoder can't be used again.
+        let command_encoder = self.0.table().delete(command_encoder)?;
+        let _span = tracing::info_span!("command_encoder_finish", ?command_encoder).entered();
         let command_buffer = core_result(
             self.0.instance().command_encoder_finish::<crate::Backend>(
                 command_encoder,
                 &descriptor
-                    .map(|d| d.to_core(&self.0.table()))
+                    .map(|d| d.to_core(self.0.table()))
                     .unwrap_or_default(),
             ),
         )
-        .unwrap();
-        self.0.table().push(command_buffer).unwrap()
+        .map_err(|e| wasmtime::Error::msg(format!("can't finish command encoder: {e}")))?;
+        Ok(self.0.table().push(command_buffer)?)
     }
 
-    fn drop(&mut self, _rep: Resource<wgpu_core::id::CommandEncoderId>) -> wasmtime::Result<()> {
+    fn drop(&mut self, rep: Resource<wgpu_core::id::CommandEncoderId>) -> wasmtime::Result<()> {
+        // Only unfinished encoders get here, `finish` takes the handle.
+        let command_encoder = self.0.table().delete(rep)?;
+        self.0
+            .instance()
+            .command_encoder_drop::<crate::Backend>(command_encoder);
         Ok(())
     }
 
@@ -1071,19 +3340,36 @@ impl<T: WasiWebGpuView> webgpu::HostGpuCommandEncoder for WasiWebGpuImpl<T> {
         &mut self,
         command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
         descriptor: Option<webgpu::GpuComputePassDescriptor>,
-    ) -> Resource<webgpu::GpuComputePassEncoder> {
-        let command_encoder = self.0.table().get(&command_encoder).unwrap();
+    ) -> wasmtime::Result<Resource<webgpu::GpuComputePassEncoder>> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        let (label, timestamp_writes) = match descriptor {
+            Some(d) => (d.label, d.timestamp_writes),
+            None => (None, None),
+        };
+        let mut keep_alive: KeepAlive = Vec::new();
+        if let Some(timestamp_writes) = &timestamp_writes {
+            let query_set = self.0.table().get(&timestamp_writes.query_set)?;
+            validate_timestamp_writes(
+                query_set,
+                timestamp_writes.beginning_of_pass_write_index,
+                timestamp_writes.end_of_pass_write_index,
+            )
+            .map_err(|e| wasmtime::Error::msg(format!("can't begin compute pass: {e}")))?;
+            keep_alive.push(query_set.freed.clone());
+        }
         let compute_pass = wgpu_core::command::ComputePass::new(
-            *command_encoder,
+            command_encoder,
             &wgpu_core::command::ComputePassDescriptor {
-                label: Default::default(),
-                timestamp_writes: descriptor
-                    .map(|d| d.timestamp_writes.map(|tw| tw.to_core(&self.0.table())))
-                    .flatten()
+                label: label.map(|l| l.into()),
+                timestamp_writes: timestamp_writes
+                    .map(|tw| tw.to_core(self.0.table()))
                     .as_ref(),
             },
         );
-        self.0.table().push(compute_pass).unwrap()
+        Ok(self.0.table().push(ComputePass {
+            pass: compute_pass,
+            keep_alive,
+        })?)
     }
 
     fn copy_buffer_to_buffer(
@@ -1094,10 +3380,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuCommandEncoder for WasiWebGpuImpl<T> {
         destination: Resource<webgpu::GpuBuffer>,
         destination_offset: webgpu::GpuSize64,
         size: webgpu::GpuSize64,
-    ) {
-        let command_encoder = *self.0.table().get(&command_encoder).unwrap();
-        let source = self.0.table().get(&source).unwrap().buffer;
-        let destination = self.0.table().get(&destination).unwrap().buffer;
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        let error = |e| wasmtime::Error::msg(format!("can't copy buffer to buffer: {e}"));
+        let source = self.live_buffer(&source).map_err(error)?.buffer;
+        let destination = self.live_buffer(&destination).map_err(error)?.buffer;
         self.0
             .instance()
             .command_encoder_copy_buffer_to_buffer::<crate::Backend>(
@@ -1107,66 +3394,158 @@ impl<T: WasiWebGpuView> webgpu::HostGpuCommandEncoder for WasiWebGpuImpl<T> {
                 destination,
                 destination_offset,
                 size,
-            )
-            .unwrap();
+            )?;
+        Ok(())
     }
 
     fn copy_buffer_to_texture(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _source: webgpu::GpuImageCopyBuffer,
-        _destination: webgpu::GpuImageCopyTexture,
-        _copy_size: webgpu::GpuExtent3D,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        source: webgpu::GpuImageCopyBuffer,
+        destination: webgpu::GpuImageCopyTexture,
+        copy_size: webgpu::GpuExtent3D,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.live_texture(&destination.texture)?;
+        let error = |e| wasmtime::Error::msg(format!("can't copy buffer to texture: {e}"));
+        validate_extent("copy-size", &copy_size)
+            .and_then(|()| validate_origin("destination.origin", destination.origin.as_ref()))
+            .map_err(error)?;
+        let source = source.to_core(self.0.table());
+        let destination = destination.to_core(self.0.table());
+        let copy_size = copy_size.to_core(self.0.table());
+        self.validate_image_copy("destination", &destination, copy_size)
+            .map_err(error)?;
+        self.0
+            .instance()
+            .command_encoder_copy_buffer_to_texture::<crate::Backend>(
+                command_encoder,
+                &source,
+                &destination,
+                &copy_size,
+            )
+            .map_err(|e| error(e.to_string()))
     }
 
     fn copy_texture_to_buffer(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _source: webgpu::GpuImageCopyTexture,
-        _destination: webgpu::GpuImageCopyBuffer,
-        _copy_size: webgpu::GpuExtent3D,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        source: webgpu::GpuImageCopyTexture,
+        destination: webgpu::GpuImageCopyBuffer,
+        copy_size: webgpu::GpuExtent3D,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.live_texture(&source.texture)?;
+        let error = |e| wasmtime::Error::msg(format!("can't copy texture to buffer: {e}"));
+        validate_extent("copy-size", &copy_size)
+            .and_then(|()| validate_origin("source.origin", source.origin.as_ref()))
+            .map_err(error)?;
+        let source = source.to_core(self.0.table());
+        let destination = destination.to_core(self.0.table());
+        let copy_size = copy_size.to_core(self.0.table());
+        self.validate_image_copy("source", &source, copy_size)
+            .map_err(error)?;
+        self.0
+            .instance()
+            .command_encoder_copy_texture_to_buffer::<crate::Backend>(
+                command_encoder,
+                &source,
+                &destination,
+                &copy_size,
+            )
+            .map_err(|e| error(e.to_string()))
     }
 
     fn copy_texture_to_texture(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _source: webgpu::GpuImageCopyTexture,
-        _destination: webgpu::GpuImageCopyTexture,
-        _copy_size: webgpu::GpuExtent3D,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        source: webgpu::GpuImageCopyTexture,
+        destination: webgpu::GpuImageCopyTexture,
+        copy_size: webgpu::GpuExtent3D,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.live_texture(&source.texture)?;
+        self.live_texture(&destination.texture)?;
+        let error = |e| wasmtime::Error::msg(format!("can't copy texture to texture: {e}"));
+        validate_extent("copy-size", &copy_size)
+            .and_then(|()| validate_origin("source.origin", source.origin.as_ref()))
+            .and_then(|()| validate_origin("destination.origin", destination.origin.as_ref()))
+            .map_err(error)?;
+        let source = source.to_core(self.0.table());
+        let destination = destination.to_core(self.0.table());
+        let copy_size = copy_size.to_core(self.0.table());
+        self.validate_image_copy("source", &source, copy_size)
+            .and_then(|()| self.validate_image_copy("destination", &destination, copy_size))
+            .map_err(error)?;
+        self.0
+            .instance()
+            .command_encoder_copy_texture_to_texture::<crate::Backend>(
+                command_encoder,
+                &source,
+                &destination,
+                &copy_size,
+            )
+            .map_err(|e| error(e.to_string()))
     }
 
     fn clear_buffer(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _buffer: Resource<webgpu::GpuBuffer>,
-        _offset: Option<webgpu::GpuSize64>,
-        _size: Option<webgpu::GpuSize64>,
-    ) {
-        todo!()
-    }
-
-    fn resolve_query_set(
-        &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _query_set: Resource<webgpu::GpuQuerySet>,
-        _first_query: webgpu::GpuSize32,
-        _query_count: webgpu::GpuSize32,
-        _destination: Resource<webgpu::GpuBuffer>,
-        _destination_offset: webgpu::GpuSize64,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        buffer: Resource<webgpu::GpuBuffer>,
+        offset: Option<webgpu::GpuSize64>,
+        size: Option<webgpu::GpuSize64>,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        let buffer = self
+            .live_buffer(&buffer)
+            .map_err(|e| wasmtime::Error::msg(format!("can't clear buffer: {e}")))?
+            .buffer;
+        let offset = offset.unwrap_or(0);
+        validate_clear_buffer(offset, size)
+            .map_err(|e| wasmtime::Error::msg(format!("can't clear buffer: {e}")))?;
+        // A missing size clears the rest of the buffer.
+        self.0
+            .instance()
+            .command_encoder_clear_buffer::<crate::Backend>(command_encoder, buffer, offset, size)
+            .map_err(|e| wasmtime::Error::msg(format!("can't clear buffer: {e}")))?;
+        Ok(())
+    }
+
+    fn resolve_query_set(
+        &mut self,
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        query_set: Resource<webgpu::GpuQuerySet>,
+        first_query: webgpu::GpuSize32,
+        query_count: webgpu::GpuSize32,
+        destination: Resource<webgpu::GpuBuffer>,
+        destination_offset: webgpu::GpuSize64,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        let query_set = self.0.table().get(&query_set)?.id;
+        let destination = self.0.table().get(&destination)?.buffer;
+        self.check_encoder_owner(query_set, command_encoder, "query set")
+            .and_then(|()| self.check_encoder_owner(destination, command_encoder, "buffer"))
+            .map_err(|e| wasmtime::Error::msg(format!("can't resolve query set: {e}")))?;
+        self.0
+            .instance()
+            .command_encoder_resolve_query_set::<crate::Backend>(
+                command_encoder,
+                query_set,
+                first_query,
+                query_count,
+                destination,
+                destination_offset,
+            )
+            .map_err(|e| wasmtime::Error::msg(format!("can't resolve query set: {e}")))
     }
 
-    fn label(&mut self, command_encoder: Resource<wgpu_core::id::CommandEncoderId>) -> String {
-        let _command_encoder = self.0.table().get(&command_encoder).unwrap();
+    fn label(
+        &mut self,
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+    ) -> wasmtime::Result<String> {
+        let _command_encoder = self.command_encoder(&command_encoder)?;
         // TODO: return real label
-        String::new()
+        Ok(String::new())
     }
 
     fn set_label(&mut self, _self_: Resource<wgpu_core::id::CommandEncoderId>, _label: String) {
@@ -1175,45 +3554,77 @@ impl<T: WasiWebGpuView> webgpu::HostGpuCommandEncoder for WasiWebGpuImpl<T> {
 
     fn push_debug_group(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _group_label: String,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        group_label: String,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.0
+            .instance()
+            .command_encoder_push_debug_group::<crate::Backend>(command_encoder, &group_label)?;
+        Ok(())
     }
 
-    fn pop_debug_group(&mut self, _self_: Resource<wgpu_core::id::CommandEncoderId>) {
-        todo!()
+    fn pop_debug_group(
+        &mut self,
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.0
+            .instance()
+            .command_encoder_pop_debug_group::<crate::Backend>(command_encoder)?;
+        Ok(())
     }
 
     fn insert_debug_marker(
         &mut self,
-        _self_: Resource<wgpu_core::id::CommandEncoderId>,
-        _marker_label: String,
-    ) {
-        todo!()
+        command_encoder: Resource<wgpu_core::id::CommandEncoderId>,
+        marker_label: String,
+    ) -> wasmtime::Result<()> {
+        let command_encoder = self.command_encoder(&command_encoder)?;
+        self.0
+            .instance()
+            .command_encoder_insert_debug_marker::<crate::Backend>(
+                command_encoder,
+                &marker_label,
+            )?;
+        Ok(())
     }
 }
 
 impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
     fn set_pipeline(
         &mut self,
-        render_pass: Resource<wgpu_core::command::RenderPass>,
+        render_pass: Resource<RenderPass>,
         pipeline: Resource<webgpu::GpuRenderPipeline>,
-    ) {
-        let pipeline = pipeline.to_core(&self.0.table());
+    ) -> wasmtime::Result<()> {
+        let pipeline = self.0.table().get(&pipeline)?;
+        let (id, vertex_buffers, freed) = (
+            pipeline.id,
+            pipeline.vertex_buffers,
+            Arc::clone(&pipeline.freed),
+        );
+        let encoder = self.0.table().get(&render_pass).unwrap().parent_id();
+        self.check_encoder_owner(id, encoder, "render pipeline")?;
         let render_pass = self.0.table().get_mut(&render_pass).unwrap();
-        wgpu_core::command::render_commands::wgpu_render_pass_set_pipeline(render_pass, pipeline);
+        render_pass.pipeline_vertex_buffers = Some(vertex_buffers);
+        render_pass.keep_alive.push(freed);
+        wgpu_core::command::render_commands::wgpu_render_pass_set_pipeline(render_pass, id);
+        Ok(())
     }
 
     fn draw(
         &mut self,
-        rpass: Resource<wgpu_core::command::RenderPass>,
+        rpass: Resource<RenderPass>,
         vertex_count: webgpu::GpuSize32,
         instance_count: webgpu::GpuSize32,
         first_vertex: webgpu::GpuSize32,
         first_instance: webgpu::GpuSize32,
-    ) {
+    ) -> wasmtime::Result<()> {
         let rpass = self.0.table().get_mut(&rpass).unwrap();
+        if let Some(vertex_buffers) = rpass.pipeline_vertex_buffers {
+            validate_vertex_buffers_bound(vertex_buffers, &rpass.vertex_buffer_slots)
+                .map_err(|e| wasmtime::Error::msg(format!("can't draw: {e}")))?;
+        }
 
         wgpu_core::command::render_commands::wgpu_render_pass_draw(
             rpass,
@@ -1222,29 +3633,32 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
             first_vertex,
             first_instance,
         );
+        Ok(())
     }
 
     fn end(
         &mut self,
-        rpass: Resource<wgpu_core::command::RenderPass>,
+        rpass: Resource<RenderPass>,
         non_standard_encoder: Resource<wgpu_core::id::CommandEncoderId>,
-    ) {
-        let encoder = *self.0.table().get(&non_standard_encoder).unwrap();
+    ) -> wasmtime::Result<()> {
+        let encoder = self.command_encoder(&non_standard_encoder)?;
         let rpass = self.0.table().delete(rpass).unwrap();
+        // This is where wgpu validates the pass, e.g. that the pipeline's color targets
+        // match the pass's color attachments.
         self.0
             .instance()
             .command_encoder_run_render_pass::<crate::Backend>(encoder, &rpass)
-            .unwrap();
+            .map_err(|e| wasmtime::Error::msg(format!("render pass is invalid: {e}")))
     }
 
-    fn drop(&mut self, cwr: Resource<wgpu_core::command::RenderPass>) -> wasmtime::Result<()> {
+    fn drop(&mut self, cwr: Resource<RenderPass>) -> wasmtime::Result<()> {
         self.0.table().delete(cwr).unwrap();
         Ok(())
     }
 
     fn set_viewport(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _x: f32,
         _y: f32,
         _width: f32,
@@ -1257,7 +3671,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
 
     fn set_scissor_rect(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _x: webgpu::GpuIntegerCoordinate,
         _y: webgpu::GpuIntegerCoordinate,
         _width: webgpu::GpuIntegerCoordinate,
@@ -1268,90 +3682,118 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
 
     fn set_blend_constant(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
-        _color: webgpu::GpuColor,
-    ) {
-        todo!()
+        rpass: Resource<RenderPass>,
+        color: webgpu::GpuColor,
+    ) -> wasmtime::Result<()> {
+        validate_color("blend constant", &color).map_err(wasmtime::Error::msg)?;
+        let rpass = self.0.table().get_mut(&rpass).unwrap();
+        // Only used by pipelines with a `constant` or `one-minus-constant` blend factor.
+        // wgpu checks that it was set before drawing with such a pipeline, and reports
+        // it when the pass ends.
+        wgpu_core::command::render_commands::wgpu_render_pass_set_blend_constant(
+            rpass,
+            &color.into(),
+        );
+        Ok(())
     }
 
     fn set_stencil_reference(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _reference: webgpu::GpuStencilValue,
     ) {
         todo!()
     }
 
+    fn set_predication(
+        &mut self,
+        _rpass: Resource<RenderPass>,
+        _buffer: Option<Resource<webgpu::GpuBuffer>>,
+        _offset: webgpu::GpuSize64,
+    ) -> Result<(), String> {
+        // wgpu 0.20 has no conditional rendering on any backend, nor a feature to
+        // check for it, so there's nothing to forward this to yet.
+        Err("predicated rendering isn't supported by this runtime's wgpu version".to_string())
+    }
+
     fn begin_occlusion_query(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _query_index: webgpu::GpuSize32,
     ) {
         todo!()
     }
 
-    fn end_occlusion_query(&mut self, _self_: Resource<wgpu_core::command::RenderPass>) {
+    fn end_occlusion_query(&mut self, _self_: Resource<RenderPass>) {
         todo!()
     }
 
     fn execute_bundles(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _bundles: Vec<Resource<webgpu::GpuRenderBundle>>,
     ) {
         todo!()
     }
 
-    fn label(&mut self, _self_: Resource<wgpu_core::command::RenderPass>) -> String {
+    fn label(&mut self, _self_: Resource<RenderPass>) -> String {
         todo!()
     }
 
-    fn set_label(&mut self, _self_: Resource<wgpu_core::command::RenderPass>, _label: String) {
+    fn set_label(&mut self, _self_: Resource<RenderPass>, _label: String) {
         todo!()
     }
 
-    fn push_debug_group(
-        &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
-        _group_label: String,
-    ) {
-        todo!()
+    fn push_debug_group(&mut self, render_pass: Resource<RenderPass>, group_label: String) {
+        let render_pass = self.0.table().get_mut(&render_pass).unwrap();
+        wgpu_core::command::render_commands::wgpu_render_pass_push_debug_group(
+            render_pass,
+            &group_label,
+            0,
+        );
     }
 
-    fn pop_debug_group(&mut self, _self_: Resource<wgpu_core::command::RenderPass>) {
-        todo!()
+    fn pop_debug_group(&mut self, render_pass: Resource<RenderPass>) {
+        let render_pass = self.0.table().get_mut(&render_pass).unwrap();
+        wgpu_core::command::render_commands::wgpu_render_pass_pop_debug_group(render_pass);
     }
 
-    fn insert_debug_marker(
-        &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
-        _marker_label: String,
-    ) {
-        todo!()
+    fn insert_debug_marker(&mut self, render_pass: Resource<RenderPass>, marker_label: String) {
+        let render_pass = self.0.table().get_mut(&render_pass).unwrap();
+        wgpu_core::command::render_commands::wgpu_render_pass_insert_debug_marker(
+            render_pass,
+            &marker_label,
+            0,
+        );
     }
 
     fn set_bind_group(
         &mut self,
-        render_pass: Resource<wgpu_core::command::RenderPass>,
+        render_pass: Resource<RenderPass>,
         index: webgpu::GpuIndex32,
         bind_group: Resource<webgpu::GpuBindGroup>,
         dynamic_offsets: Option<Vec<webgpu::GpuBufferDynamicOffset>>,
-    ) {
-        let bind_group = *self.0.table().get(&bind_group).unwrap();
-        let mut render_pass = self.0.table().get_mut(&render_pass).unwrap();
+    ) -> wasmtime::Result<()> {
+        let bind_group = self.0.table().get(&bind_group)?;
+        let (bind_group, freed) = (bind_group.id, Arc::clone(&bind_group.freed));
+        let encoder = self.0.table().get(&render_pass).unwrap().parent_id();
+        self.check_encoder_owner(bind_group, encoder, "bind group")?;
+        let render_pass = self.0.table().get_mut(&render_pass).unwrap();
+        render_pass.keep_alive.push(freed);
 
         let dynamic_offsets = dynamic_offsets.unwrap();
         wgpu_core::command::render_commands::wgpu_render_pass_set_bind_group(
-            &mut render_pass,
+            render_pass,
             index,
             bind_group,
             &dynamic_offsets,
-        )
+        );
+        Ok(())
     }
 
     fn set_index_buffer(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _buffer: Resource<webgpu::GpuBuffer>,
         _index_format: webgpu::GpuIndexFormat,
         _offset: webgpu::GpuSize64,
@@ -1362,27 +3804,39 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
 
     fn set_vertex_buffer(
         &mut self,
-        render_pass: Resource<wgpu_core::command::RenderPass>,
+        render_pass: Resource<RenderPass>,
         slot: webgpu::GpuIndex32,
         buffer: Resource<webgpu::GpuBuffer>,
         offset: webgpu::GpuSize64,
         size: webgpu::GpuSize64,
-    ) {
-        let buffer_id = self.0.table().get(&buffer).unwrap().buffer;
-        let mut render_pass = self.0.table().get_mut(&render_pass).unwrap();
+    ) -> wasmtime::Result<()> {
+        let buffer = self.0.table().get(&buffer)?;
+        let (buffer_id, mapping) = (buffer.buffer, Arc::clone(&buffer.mapping));
+        let encoder = self.0.table().get(&render_pass).unwrap().parent_id();
+        self.check_encoder_owner(buffer_id, encoder, "vertex buffer")?;
+        let render_pass = self.0.table().get_mut(&render_pass).unwrap();
+        render_pass.keep_alive.push(mapping);
+        validate_vertex_buffer_slot(
+            slot,
+            render_pass.max_vertex_buffers,
+            render_pass.pipeline_vertex_buffers,
+        )
+        .map_err(|e| wasmtime::Error::msg(format!("can't set vertex buffer: {e}")))?;
+        render_pass.vertex_buffer_slots.insert(slot);
 
         wgpu_core::command::render_commands::wgpu_render_pass_set_vertex_buffer(
-            &mut render_pass,
+            render_pass,
             slot,
             buffer_id,
             offset,
             Some(size.try_into().unwrap()),
         );
+        Ok(())
     }
 
     fn draw_indexed(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _index_count: webgpu::GpuSize32,
         _instance_count: webgpu::GpuSize32,
         _first_index: webgpu::GpuSize32,
@@ -1394,7 +3848,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
 
     fn draw_indirect(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _indirect_buffer: Resource<webgpu::GpuBuffer>,
         _indirect_offset: webgpu::GpuSize64,
     ) {
@@ -1403,7 +3857,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderPassEncoder for WasiWebGpuImpl<T> {
 
     fn draw_indexed_indirect(
         &mut self,
-        _self_: Resource<wgpu_core::command::RenderPass>,
+        _self_: Resource<RenderPass>,
         _indirect_buffer: Resource<webgpu::GpuBuffer>,
         _indirect_offset: webgpu::GpuSize64,
     ) {
@@ -1471,12 +3925,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuValidationError for WasiWebGpuImpl<T> {
     }
 }
 impl<T: WasiWebGpuView> webgpu::HostGpuError for WasiWebGpuImpl<T> {
-    fn message(&mut self, _self_: Resource<webgpu::GpuError>) -> String {
-        todo!()
+    fn message(&mut self, error: Resource<webgpu::GpuError>) -> String {
+        self.0.table().get(&error).unwrap().message.clone()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuError>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, rep: Resource<webgpu::GpuError>) -> wasmtime::Result<()> {
+        self.0.table().delete(rep)?;
+        Ok(())
     }
 }
 impl<T: WasiWebGpuView> webgpu::HostGpuDeviceLostInfo for WasiWebGpuImpl<T> {
@@ -1544,10 +3999,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuComputePassEncoder for WasiWebGpuImpl<T>
         &mut self,
         encoder: Resource<webgpu::GpuComputePassEncoder>,
         pipeline: Resource<webgpu::GpuComputePipeline>,
-    ) {
+    ) -> wasmtime::Result<()> {
         let pipeline = *self.0.table().get(&pipeline).unwrap();
+        let command_encoder = self.0.table().get(&encoder).unwrap().parent_id();
+        self.check_encoder_owner(pipeline, command_encoder, "compute pipeline")?;
         let encoder = self.0.table().get_mut(&encoder).unwrap();
         wgpu_core::command::compute_commands::wgpu_compute_pass_set_pipeline(encoder, pipeline);
+        Ok(())
     }
 
     fn dispatch_workgroups(
@@ -1568,24 +4026,45 @@ impl<T: WasiWebGpuView> webgpu::HostGpuComputePassEncoder for WasiWebGpuImpl<T>
 
     fn dispatch_workgroups_indirect(
         &mut self,
-        _self_: Resource<webgpu::GpuComputePassEncoder>,
-        _indirect_buffer: Resource<webgpu::GpuBuffer>,
-        _indirect_offset: webgpu::GpuSize64,
-    ) {
-        todo!()
+        encoder: Resource<webgpu::GpuComputePassEncoder>,
+        indirect_buffer: Resource<webgpu::GpuBuffer>,
+        indirect_offset: webgpu::GpuSize64,
+    ) -> wasmtime::Result<()> {
+        let indirect_buffer = self
+            .live_buffer(&indirect_buffer)
+            .map_err(|e| wasmtime::Error::msg(format!("can't dispatch indirectly: {e}")))?;
+        let (buffer, size, usage, mapping) = (
+            indirect_buffer.buffer,
+            indirect_buffer.size,
+            indirect_buffer.usage,
+            Arc::clone(&indirect_buffer.mapping),
+        );
+        validate_indirect_dispatch(usage, size, indirect_offset)
+            .map_err(|e| wasmtime::Error::msg(format!("can't dispatch indirectly: {e}")))?;
+        let command_encoder = self.0.table().get(&encoder).unwrap().parent_id();
+        self.check_encoder_owner(buffer, command_encoder, "indirect buffer")?;
+        let encoder = self.0.table().get_mut(&encoder).unwrap();
+        encoder.keep_alive.push(mapping);
+        wgpu_core::command::compute_commands::wgpu_compute_pass_dispatch_workgroups_indirect(
+            encoder,
+            buffer,
+            indirect_offset,
+        );
+        Ok(())
     }
 
     fn end(
         &mut self,
-        cpass: Resource<wgpu_core::command::ComputePass>,
+        cpass: Resource<ComputePass>,
         non_standard_encoder: Resource<wgpu_core::id::CommandEncoderId>,
-    ) {
-        let encoder = *self.0.table().get(&non_standard_encoder).unwrap();
-        let cpass = self.0.table().delete(cpass).unwrap();
+    ) -> wasmtime::Result<()> {
+        let encoder = self.command_encoder(&non_standard_encoder)?;
+        let cpass = self.0.table().delete(cpass)?;
+        // This is where wgpu validates the pass, e.g. the pipeline's bind groups.
         self.0
             .instance()
             .command_encoder_run_compute_pass::<crate::Backend>(encoder, &cpass)
-            .unwrap();
+            .map_err(|e| wasmtime::Error::msg(format!("compute pass is invalid: {e}")))
     }
 
     fn label(&mut self, _self_: Resource<webgpu::GpuComputePassEncoder>) -> String {
@@ -1598,14 +4077,20 @@ impl<T: WasiWebGpuView> webgpu::HostGpuComputePassEncoder for WasiWebGpuImpl<T>
 
     fn push_debug_group(
         &mut self,
-        _self_: Resource<webgpu::GpuComputePassEncoder>,
-        _group_label: String,
+        cpass: Resource<webgpu::GpuComputePassEncoder>,
+        group_label: String,
     ) {
-        todo!()
+        let cpass = self.0.table().get_mut(&cpass).unwrap();
+        wgpu_core::command::compute_commands::wgpu_compute_pass_push_debug_group(
+            cpass,
+            &group_label,
+            0,
+        );
     }
 
-    fn pop_debug_group(&mut self, _self_: Resource<webgpu::GpuComputePassEncoder>) {
-        todo!()
+    fn pop_debug_group(&mut self, cpass: Resource<webgpu::GpuComputePassEncoder>) {
+        let cpass = self.0.table().get_mut(&cpass).unwrap();
+        wgpu_core::command::compute_commands::wgpu_compute_pass_pop_debug_group(cpass);
     }
 
     fn insert_debug_marker(
@@ -1625,16 +4110,21 @@ impl<T: WasiWebGpuView> webgpu::HostGpuComputePassEncoder for WasiWebGpuImpl<T>
         index: webgpu::GpuIndex32,
         bind_group: Resource<webgpu::GpuBindGroup>,
         dynamic_offsets: Option<Vec<webgpu::GpuBufferDynamicOffset>>,
-    ) {
-        let bind_group = *self.0.table().get(&bind_group).unwrap();
+    ) -> wasmtime::Result<()> {
+        let bind_group = self.0.table().get(&bind_group)?;
+        let (bind_group, freed) = (bind_group.id, Arc::clone(&bind_group.freed));
+        let command_encoder = self.0.table().get(&encoder).unwrap().parent_id();
+        self.check_encoder_owner(bind_group, command_encoder, "bind group")?;
         let encoder = self.0.table().get_mut(&encoder).unwrap();
+        encoder.keep_alive.push(freed);
         let dynamic_offsets = dynamic_offsets.unwrap();
         wgpu_core::command::compute_commands::wgpu_compute_pass_set_bind_group(
             encoder,
             index,
             bind_group,
             &dynamic_offsets,
-        )
+        );
+        Ok(())
     }
 
     fn drop(&mut self, _rep: Resource<webgpu::GpuComputePassEncoder>) -> wasmtime::Result<()> {
@@ -1703,12 +4193,12 @@ impl<T: WasiWebGpuView> webgpu::HostGpuQuerySet for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn type_(&mut self, _self_: Resource<webgpu::GpuQuerySet>) -> webgpu::GpuQueryType {
-        todo!()
+    fn type_(&mut self, query_set: Resource<webgpu::GpuQuerySet>) -> webgpu::GpuQueryType {
+        self.0.table().get(&query_set).unwrap().type_
     }
 
-    fn count(&mut self, _self_: Resource<webgpu::GpuQuerySet>) -> webgpu::GpuSize32Out {
-        todo!()
+    fn count(&mut self, query_set: Resource<webgpu::GpuQuerySet>) -> webgpu::GpuSize32Out {
+        self.0.table().get(&query_set).unwrap().count
     }
 
     fn label(&mut self, _self_: Resource<webgpu::GpuQuerySet>) -> String {
@@ -1719,8 +4209,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuQuerySet for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
-        todo!()
+    fn drop(&mut self, rep: Resource<webgpu::GpuQuerySet>) -> wasmtime::Result<()> {
+        // The wgpu query set is dropped once passes writing timestamps to it,
+        // and reads of its results, are done too.
+        self.0.table().delete(rep)?;
+        Ok(())
     }
 }
 impl<T: WasiWebGpuView> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuImpl<T> {
@@ -1773,7 +4266,7 @@ impl<T: WasiWebGpuView> webgpu::HostGpuRenderBundleEncoder for WasiWebGpuImpl<T>
     fn set_pipeline(
         &mut self,
         _self_: Resource<webgpu::GpuRenderBundleEncoder>,
-        _pipeline: Resource<wgpu_core::id::RenderPipelineId>,
+        _pipeline: Resource<RenderPipeline>,
     ) {
         todo!()
     }
@@ -1874,6 +4367,16 @@ impl<T: WasiWebGpuView> webgpu::HostGpuComputePipeline for WasiWebGpuImpl<T> {
         Ok(())
     }
 }
+impl<T: WasiWebGpuView> webgpu::HostGpuPipelineCache for WasiWebGpuImpl<T> {
+    fn get_data(&mut self, _cache: Resource<PipelineCache>) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn drop(&mut self, cache: Resource<PipelineCache>) -> wasmtime::Result<()> {
+        self.0.table().delete(cache)?;
+        Ok(())
+    }
+}
 impl<T: WasiWebGpuView> webgpu::HostGpuBindGroup for WasiWebGpuImpl<T> {
     fn label(&mut self, _self_: Resource<webgpu::GpuBindGroup>) -> String {
         todo!()
@@ -1883,7 +4386,9 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBindGroup for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuBindGroup>) -> wasmtime::Result<()> {
+    fn drop(&mut self, rep: Resource<webgpu::GpuBindGroup>) -> wasmtime::Result<()> {
+        // Freed now, or once the passes using it end.
+        self.0.table().delete(rep)?;
         Ok(())
     }
 }
@@ -1909,8 +4414,18 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBindGroupLayout for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuBindGroupLayout>) -> wasmtime::Result<()> {
-        // TODO:
+    fn drop(&mut self, rep: Resource<webgpu::GpuBindGroupLayout>) -> wasmtime::Result<()> {
+        let bind_group_layout = self.0.table().delete(rep)?;
+        // Cached layouts may have other handles still using them.
+        let last_handle = match self.0.bind_group_layout_cache() {
+            Some(cache) => cache.release(bind_group_layout),
+            None => true,
+        };
+        if last_handle {
+            self.0
+                .instance()
+                .bind_group_layout_drop::<crate::Backend>(bind_group_layout);
+        }
         Ok(())
     }
 }
@@ -1962,19 +4477,25 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBuffer for WasiWebGpuImpl<T> {
         mode: webgpu::GpuMapModeFlags,
         offset: Option<webgpu::GpuSize64>,
         size: Option<webgpu::GpuSize64>,
-    ) {
+    ) -> Result<(), String> {
         let buffer = self.0.table().get(&buffer).unwrap().buffer;
+        // source: https://www.w3.org/TR/webgpu/#typedefdef-gpumapmodeflags
+        let host = match mode {
+            0 => wgpu_core::device::HostMap::Read,
+            1 => wgpu_core::device::HostMap::Write,
+            _ => return Err(format!("can't map buffer: {mode} isn't a map mode")),
+        };
         let instance = self.0.instance();
-        CallbackFuture::new(Box::new(
+        if let Some(write_batches) = self.0.write_batches() {
+            // Writes to the buffer have to land before it's read.
+            write_batches.flush_all(&instance);
+        }
+        let worker = self.0.gpu_worker();
+        let span = tracing::info_span!("buffer_map", ?buffer, mode, ?offset, ?size);
+        let result = CallbackFuture::new(Box::new(
             move |resolve: Box<
                 dyn FnOnce(Box<Result<(), wgpu_core::resource::BufferAccessError>>) + Send,
             >| {
-                // source: https://www.w3.org/TR/webgpu/#typedefdef-gpumapmodeflags
-                let host = match mode {
-                    0 => wgpu_core::device::HostMap::Read,
-                    1 => wgpu_core::device::HostMap::Write,
-                    _ => panic!(),
-                };
                 let op = wgpu_core::resource::BufferMapOperation {
                     host,
                     callback: Some(wgpu_core::resource::BufferMapCallback::from_rust(Box::new(
@@ -1984,16 +4505,29 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBuffer for WasiWebGpuImpl<T> {
                     ))),
                 };
 
-                let offset = offset.unwrap();
-                instance
-                    .buffer_map_async::<crate::Backend>(buffer, offset, size, op)
-                    .unwrap();
-                // TODO: only poll this device.
-                instance.poll_all_devices(true).unwrap();
+                let map = move || {
+                    // wgpu resolves with the error itself when mapping fails.
+                    if instance
+                        .buffer_map_async::<crate::Backend>(buffer, offset.unwrap_or(0), size, op)
+                        .is_ok()
+                    {
+                        // TODO: only poll this device.
+                        if let Err(e) = instance.poll_all_devices(true) {
+                            tracing::error!("can't poll devices for a buffer map: {e}");
+                        }
+                    }
+                };
+                match worker {
+                    // Waiting on the GPU here would block the executor, so let the
+                    // worker wait and resolve the future when it's done.
+                    Some(worker) => worker.execute(map),
+                    None => map(),
+                }
             },
         ))
-        .await
-        .unwrap();
+        .instrument(span)
+        .await;
+        (*result).map_err(|e| format!("can't map buffer: {e}"))
     }
 
     fn get_mapped_range(
@@ -2001,23 +4535,20 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBuffer for WasiWebGpuImpl<T> {
         buffer: Resource<webgpu::GpuBuffer>,
         offset: Option<webgpu::GpuSize64>,
         size: Option<webgpu::GpuSize64>,
-    ) -> Resource<webgpu::GpuBuffer> {
-        let buffer_rep = buffer.rep();
-        let buffer_id = self.0.table().get(&buffer).unwrap().buffer;
-        let (ptr, len) = self
-            .0
-            .instance()
-            .buffer_get_mapped_range::<crate::Backend>(buffer_id, offset.unwrap_or(0), size)
+    ) -> Resource<webgpu::RemoteBuffer> {
+        let instance = self.0.instance();
+        let buffer = self.0.table().get(&buffer).unwrap();
+        let (ptr, len) = instance
+            .buffer_get_mapped_range::<crate::Backend>(buffer.buffer, offset.unwrap_or(0), size)
             .unwrap();
-        let remote_buffer = BufferPtr { ptr, len };
-        let buffer = self.0.table().get_mut(&buffer).unwrap();
-        buffer.mapped = Some(remote_buffer);
-        Resource::new_own(buffer_rep)
+        *buffer.mapping.range.lock().unwrap() = Some(BufferPtr { ptr, len });
+        let remote_buffer = RemoteBuffer(Arc::clone(&buffer.mapping));
+        self.0.table().push(remote_buffer).unwrap()
     }
 
     fn unmap(&mut self, buffer: Resource<webgpu::GpuBuffer>) {
         let buffer = self.0.table().get_mut(&buffer).unwrap();
-        buffer.mapped.take().unwrap();
+        buffer.mapping.range.lock().unwrap().take().unwrap();
         let buffer_id = buffer.buffer;
         self.0
             .instance()
@@ -2025,8 +4556,27 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBuffer for WasiWebGpuImpl<T> {
             .unwrap();
     }
 
-    fn destroy(&mut self, _self_: Resource<webgpu::GpuBuffer>) {
-        todo!()
+    fn destroy(&mut self, buffer: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<()> {
+        let buffer = self.0.table().get_mut(&buffer)?;
+        if std::mem::replace(&mut buffer.destroyed, true) {
+            return Ok(());
+        }
+        // wgpu unmaps the buffer, so the guest's view of it is gone.
+        *buffer.mapping.range.lock().unwrap() = None;
+        let buffer = buffer.buffer;
+        // Writes from before the destroy still land, as they would have unbatched.
+        if let Some(write_batches) = self.0.write_batches() {
+            write_batches.flush_buffer(&self.0.instance(), buffer);
+        }
+        // Invalid buffers, from failed creations, have nothing to free.
+        match self.0.instance().buffer_destroy::<crate::Backend>(buffer) {
+            Ok(()) | Err(wgpu_core::resource::DestroyError::Invalid) => {}
+            Err(e) => return Err(wasmtime::Error::msg(format!("can't destroy buffer: {e}"))),
+        }
+        if let Some(budget) = self.0.memory_budget() {
+            budget.free(Allocation::Buffer(buffer));
+        }
+        Ok(())
     }
 
     fn label(&mut self, _self_: Resource<webgpu::GpuBuffer>) -> String {
@@ -2037,7 +4587,13 @@ impl<T: WasiWebGpuView> webgpu::HostGpuBuffer for WasiWebGpuImpl<T> {
         todo!()
     }
 
-    fn drop(&mut self, _rep: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<()> {
+    fn drop(&mut self, rep: Resource<webgpu::GpuBuffer>) -> wasmtime::Result<()> {
+        // The wgpu buffer is dropped along with its mapping, once remote buffers
+        // from `get-mapped-range` and passes using it are gone too.
+        let buffer = self.0.table().delete(rep)?;
+        if let Some(budget) = self.0.memory_budget() {
+            budget.free(Allocation::Buffer(buffer.buffer));
+        }
         Ok(())
     }
 }
@@ -2047,14 +4603,10 @@ impl<T: WasiWebGpuView> webgpu::HostGpu for WasiWebGpuImpl<T> {
         _self_: Resource<webgpu::Gpu>,
         _options: Option<webgpu::GpuRequestAdapterOptions>,
     ) -> Resource<wgpu_core::id::AdapterId> {
-        let adapter = self
-            .0
-            .instance()
-            .request_adapter(
-                &Default::default(),
-                wgpu_core::instance::AdapterInputs::Mask(wgpu_types::Backends::all(), |_| None),
-            )
-            .unwrap();
+        if let Some(adapter) = self.0.adapter() {
+            return self.0.table().push(adapter).unwrap();
+        }
+        let adapter = default_adapter(&self.0.instance()).unwrap();
         self.0.table().push(adapter).unwrap()
     }
 
@@ -2093,6 +4645,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuAdapterInfo for WasiWebGpuImpl<T> {
         todo!()
     }
 
+    fn device_type(&mut self, info: Resource<webgpu::GpuAdapterInfo>) -> webgpu::GpuDeviceType {
+        let info = self.0.table().get(&info).unwrap();
+        info.device_type.into()
+    }
+
     fn drop(&mut self, _rep: Resource<webgpu::GpuAdapterInfo>) -> wasmtime::Result<()> {
         // TODO:
         Ok(())
@@ -2133,7 +4690,14 @@ impl<T: WasiWebGpuView> webgpu::HostGpuSupportedFeatures for WasiWebGpuImpl<T> {
                 features.contains(wgpu_types::Features::RG11B10UFLOAT_RENDERABLE)
             }
             "bgra8unorm-storage" => features.contains(wgpu_types::Features::BGRA8UNORM_STORAGE),
-            // "float32-filterable" => features.contains(wgpu_types::Features::FLOAT32_FILTERABLE),
+            "texture-binding-array" => {
+                features.contains(wgpu_types::Features::TEXTURE_BINDING_ARRAY)
+            }
+            "sampled-texture-and-storage-buffer-array-non-uniform-indexing" => features.contains(
+                wgpu_types::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            ),
+            "multiview" => features.contains(wgpu_types::Features::MULTIVIEW),
+            "float32-filterable" => features.contains(wgpu_types::Features::FLOAT32_FILTERABLE),
             _ => todo!(),
         }
     }
@@ -2143,6 +4707,11 @@ impl<T: WasiWebGpuView> webgpu::HostGpuSupportedFeatures for WasiWebGpuImpl<T> {
     }
 }
 impl<T: WasiWebGpuView> webgpu::HostGpuSupportedLimits for WasiWebGpuImpl<T> {
+    fn max_sampler_anisotropy(&mut self, limits: Resource<webgpu::GpuSupportedLimits>) -> u16 {
+        let limits = self.0.table().get(&limits).unwrap();
+        limits.max_anisotropy
+    }
+
     fn max_texture_dimension1_d(&mut self, limits: Resource<webgpu::GpuSupportedLimits>) -> u32 {
         let limits = self.0.table().get(&limits).unwrap();
         limits.max_texture_dimension_1d
@@ -2443,6 +5012,79 @@ where
     }
 }
 
+/// Starts from the WebGPU default limits and raises the ones listed, keyed by
+/// their WebGPU name.
+/// Fails listing the features in `required_features` the adapter lacks.
+fn check_required_features(
+    required_features: &[webgpu::GpuFeatureName],
+    adapter_features: wgpu_types::Features,
+) -> Result<(), String> {
+    let missing_features = required_features
+        .iter()
+        .filter(|feature| !adapter_features.contains((**feature).into()))
+        .collect::<Vec<_>>();
+    if !missing_features.is_empty() {
+        return Err(format!(
+            "adapter doesn't support required features: {missing_features:?}"
+        ));
+    }
+    Ok(())
+}
+
+fn required_limits_to_core(
+    required_limits: Vec<(String, webgpu::GpuSize64)>,
+) -> Result<wgpu_types::Limits, String> {
+    let mut limits = wgpu_types::Limits::default();
+    for (name, value) in required_limits {
+        let limit = match name.as_str() {
+            "maxTextureDimension1D" => &mut limits.max_texture_dimension_1d,
+            "maxTextureDimension2D" => &mut limits.max_texture_dimension_2d,
+            "maxTextureDimension3D" => &mut limits.max_texture_dimension_3d,
+            "maxTextureArrayLayers" => &mut limits.max_texture_array_layers,
+            "maxBindGroups" => &mut limits.max_bind_groups,
+            "maxBindingsPerBindGroup" => &mut limits.max_bindings_per_bind_group,
+            "maxDynamicUniformBuffersPerPipelineLayout" => {
+                &mut limits.max_dynamic_uniform_buffers_per_pipeline_layout
+            }
+            "maxDynamicStorageBuffersPerPipelineLayout" => {
+                &mut limits.max_dynamic_storage_buffers_per_pipeline_layout
+            }
+            "maxSampledTexturesPerShaderStage" => &mut limits.max_sampled_textures_per_shader_stage,
+            "maxSamplersPerShaderStage" => &mut limits.max_samplers_per_shader_stage,
+            "maxStorageBuffersPerShaderStage" => &mut limits.max_storage_buffers_per_shader_stage,
+            "maxStorageTexturesPerShaderStage" => &mut limits.max_storage_textures_per_shader_stage,
+            "maxUniformBuffersPerShaderStage" => &mut limits.max_uniform_buffers_per_shader_stage,
+            "maxUniformBufferBindingSize" => &mut limits.max_uniform_buffer_binding_size,
+            "maxStorageBufferBindingSize" => &mut limits.max_storage_buffer_binding_size,
+            "minUniformBufferOffsetAlignment" => &mut limits.min_uniform_buffer_offset_alignment,
+            "minStorageBufferOffsetAlignment" => &mut limits.min_storage_buffer_offset_alignment,
+            "maxVertexBuffers" => &mut limits.max_vertex_buffers,
+            "maxBufferSize" => {
+                limits.max_buffer_size = value;
+                continue;
+            }
+            "maxVertexAttributes" => &mut limits.max_vertex_attributes,
+            "maxVertexBufferArrayStride" => &mut limits.max_vertex_buffer_array_stride,
+            "maxInterStageShaderComponents" => &mut limits.max_inter_stage_shader_components,
+            "maxColorAttachments" => &mut limits.max_color_attachments,
+            "maxColorAttachmentBytesPerSample" => &mut limits.max_color_attachment_bytes_per_sample,
+            "maxComputeWorkgroupStorageSize" => &mut limits.max_compute_workgroup_storage_size,
+            "maxComputeInvocationsPerWorkgroup" => {
+                &mut limits.max_compute_invocations_per_workgroup
+            }
+            "maxComputeWorkgroupSizeX" => &mut limits.max_compute_workgroup_size_x,
+            "maxComputeWorkgroupSizeY" => &mut limits.max_compute_workgroup_size_y,
+            "maxComputeWorkgroupSizeZ" => &mut limits.max_compute_workgroup_size_z,
+            "maxComputeWorkgroupsPerDimension" => &mut limits.max_compute_workgroups_per_dimension,
+            _ => return Err(format!("unknown or unsupported limit {name:?}")),
+        };
+        *limit = value
+            .try_into()
+            .map_err(|_| format!("{name} of {value} doesn't fit in 32 bits"))?;
+    }
+    Ok(limits)
+}
+
 // same as core_result, but handles tuple of two ids for Ok.
 fn core_results_2<I1, I2, E>(
     (a, b, error): (wgpu_core::id::Id<I1>, wgpu_core::id::Id<I2>, Option<E>),
@@ -2456,3 +5098,2643 @@ where
         None => Ok((a, b)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapters_match_by_index_or_name() {
+        let names = [
+            "NVIDIA GeForce RTX 4090",
+            "llvmpipe (LLVM 17.0.6, 256 bits)",
+        ];
+        assert_eq!(match_adapter("1", &names), Some(1));
+        assert_eq!(match_adapter("2", &names), None);
+        assert_eq!(match_adapter("geforce", &names), Some(0));
+        assert_eq!(match_adapter("LLVMPIPE", &names), Some(1));
+        assert_eq!(match_adapter("radeon", &names), None);
+    }
+
+    /// A surface on `gpu`'s device, not connected to a display yet.
+    fn test_surface(
+        gpu: &test_gpu::TestGpu,
+    ) -> WebGpuSurface<
+        impl Fn() -> Arc<wgpu_core::global::Global>,
+        impl Fn(&(dyn DisplayApi + Send + Sync)) -> SurfaceId,
+        Arc<wgpu_core::global::Global>,
+    > {
+        let instance = gpu.instance.clone();
+        WebGpuSurface {
+            get_instance: {
+                let instance = instance.clone();
+                move || instance.clone()
+            },
+            create_surface: move |display: &(dyn DisplayApi + Send + Sync)| unsafe {
+                instance
+                    .instance_create_surface(
+                        display.display_handle().unwrap().as_raw(),
+                        display.window_handle().unwrap().as_raw(),
+                        None,
+                    )
+                    .unwrap()
+            },
+            device_id: gpu.device,
+            queue_id: gpu.queue,
+            adapter_id: gpu.adapter,
+            surface_id: None,
+            config: None,
+            frame_latency: 2,
+            render_scale: 1.0,
+            blitter: None,
+            scaled_target: None,
+            sample_count: 1,
+            msaa_target: None,
+            mirrors: Vec::new(),
+            readback: None,
+            color_space: ColorSpace::Srgb,
+            acquired: None,
+            reconfigured: false,
+        }
+    }
+
+    #[test]
+    fn required_features_the_adapter_lacks_are_listed() {
+        let adapter_features = wgpu_types::Features::TIMESTAMP_QUERY;
+        assert_eq!(
+            check_required_features(&[webgpu::GpuFeatureName::TimestampQuery], adapter_features),
+            Ok(())
+        );
+        let error = check_required_features(
+            &[
+                webgpu::GpuFeatureName::TimestampQuery,
+                webgpu::GpuFeatureName::ShaderF16,
+                webgpu::GpuFeatureName::Float32Filterable,
+            ],
+            adapter_features,
+        )
+        .unwrap_err();
+        assert!(error.contains("ShaderF16"), "{error}");
+        assert!(error.contains("Float32Filterable"), "{error}");
+        assert!(!error.contains("TimestampQuery"), "{error}");
+    }
+
+    #[test]
+    fn required_limits_are_converted_by_their_webgpu_names() {
+        let limits = required_limits_to_core(vec![
+            ("maxBindGroups".to_string(), 6),
+            ("maxBufferSize".to_string(), 1 << 33),
+        ])
+        .unwrap();
+        assert_eq!(limits.max_bind_groups, 6);
+        assert_eq!(limits.max_buffer_size, 1 << 33);
+        // Limits not asked for keep their defaults.
+        assert_eq!(
+            limits.max_vertex_buffers,
+            wgpu_types::Limits::default().max_vertex_buffers
+        );
+        let error = required_limits_to_core(vec![("maxWidgets".to_string(), 1)]).unwrap_err();
+        assert!(error.contains("maxWidgets"), "{error}");
+        let error =
+            required_limits_to_core(vec![("maxBindGroups".to_string(), 1 << 32)]).unwrap_err();
+        assert!(error.contains("32 bits"), "{error}");
+    }
+
+    #[test]
+    fn buffer_counts_other_than_2_or_3_are_rejected() {
+        assert_eq!(frame_latency_for_buffer_count(2), Ok(1));
+        assert_eq!(frame_latency_for_buffer_count(3), Ok(2));
+        for count in [0, 1, 4] {
+            assert!(frame_latency_for_buffer_count(count).is_err());
+        }
+    }
+
+    #[test]
+    fn the_last_buffer_count_or_latency_set_wins() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut surface = test_surface(&gpu);
+        surface.set_buffer_count(2).unwrap();
+        assert_eq!(surface.frame_latency, 1);
+        surface.set_desired_maximum_frame_latency(3).unwrap();
+        assert_eq!(surface.frame_latency, 3);
+        assert!(surface.set_buffer_count(5).is_err());
+        assert_eq!(surface.frame_latency, 3);
+        surface.set_buffer_count(3).unwrap();
+        assert_eq!(surface.frame_latency, 2);
+    }
+
+    #[test]
+    fn color_spaces_are_picked_from_what_the_surface_supports() {
+        use wgpu_types::TextureFormat::{Bgra8Unorm, Bgra8UnormSrgb, Rgba16Float};
+
+        let capabilities =
+            |formats: &[wgpu_types::TextureFormat]| wgpu_types::SurfaceCapabilities {
+                formats: formats.to_vec(),
+                present_modes: vec![wgpu_types::PresentMode::Fifo],
+                alpha_modes: vec![wgpu_types::CompositeAlphaMode::Opaque],
+                usages: wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+            };
+        let sdr = capabilities(&[Bgra8UnormSrgb, Bgra8Unorm]);
+        let hdr = capabilities(&[Bgra8UnormSrgb, Rgba16Float]);
+        let hdr_only = capabilities(&[Rgba16Float]);
+        for space in [ColorSpace::Srgb, ColorSpace::ExtendedSrgbLinear] {
+            for capabilities in [&sdr, &hdr, &hdr_only] {
+                let (format, chosen) = pick_format(space, capabilities);
+                assert!(capabilities.formats.contains(&format));
+                assert_eq!(
+                    format == Rgba16Float,
+                    chosen == ColorSpace::ExtendedSrgbLinear
+                );
+            }
+        }
+        assert_eq!(
+            pick_format(ColorSpace::ExtendedSrgbLinear, &hdr),
+            (Rgba16Float, ColorSpace::ExtendedSrgbLinear)
+        );
+        assert_eq!(
+            pick_format(ColorSpace::ExtendedSrgbLinear, &sdr),
+            (Bgra8UnormSrgb, ColorSpace::Srgb)
+        );
+        assert_eq!(
+            pick_format(ColorSpace::Srgb, &hdr),
+            (Bgra8UnormSrgb, ColorSpace::Srgb)
+        );
+    }
+
+    #[test]
+    fn frames_are_acquired_back_to_back_up_to_the_buffer_count() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let Some(window) = test_gpu::TestWindow::lock() else {
+            return;
+        };
+        let mut surface = test_surface(&gpu);
+        surface.display_api_ready(&*window);
+        for count in [2, 3] {
+            surface.set_buffer_count(count).unwrap();
+            // wgpu hands out one surface texture at a time, so each is
+            // presented before acquiring the next, without waiting on the GPU.
+            for _ in 0..count {
+                surface.get_current_buffer().unwrap();
+                let result = surface.present().unwrap();
+                assert!(!matches!(result, PresentResult::Failed(_)), "{result:?}");
+            }
+        }
+        surface.display_api_released();
+    }
+
+    #[test]
+    fn surface_capabilities_include_the_configured_format() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let Some(window) = test_gpu::TestWindow::lock() else {
+            return;
+        };
+        let mut surface = test_surface(&gpu);
+        assert!(surface.surface_capabilities().is_err());
+        surface.display_api_ready(&*window);
+        let capabilities = surface
+            .surface_capabilities()
+            .unwrap()
+            .downcast::<wgpu_types::SurfaceCapabilities>()
+            .unwrap();
+        let formats: Vec<_> = capabilities
+            .formats
+            .iter()
+            .copied()
+            .filter_map(webgpu_texture_format)
+            .collect();
+        assert!(!formats.is_empty());
+        let configured = surface.config.as_ref().unwrap().format;
+        assert!(formats.contains(&webgpu_texture_format(configured).unwrap()));
+        surface.display_api_released();
+    }
+
+    #[test]
+    fn frames_are_presented_with_a_frame_latency_of_1() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let Some(window) = test_gpu::TestWindow::lock() else {
+            return;
+        };
+        let mut surface = test_surface(&gpu);
+        surface.display_api_ready(&*window);
+        surface.set_desired_maximum_frame_latency(1).unwrap();
+        for _ in 0..3 {
+            surface.get_current_buffer().unwrap();
+            let result = surface.present().unwrap();
+            assert!(!matches!(result, PresentResult::Failed(_)), "{result:?}");
+        }
+        surface.display_api_released();
+    }
+
+    #[test]
+    fn outdated_surfaces_are_presented_as_reconfigured() {
+        use wgpu_types::SurfaceStatus;
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut surface = test_surface(&gpu);
+        assert!(matches!(
+            surface.present_result(SurfaceStatus::Good),
+            PresentResult::Presented
+        ));
+        assert!(matches!(
+            surface.present_result(SurfaceStatus::Outdated),
+            PresentResult::Reconfigured
+        ));
+        // Reconfigured while acquiring the frame's texture.
+        surface.reconfigured = true;
+        assert!(matches!(
+            surface.present_result(SurfaceStatus::Good),
+            PresentResult::Reconfigured
+        ));
+        assert!(matches!(
+            surface.present_result(SurfaceStatus::Good),
+            PresentResult::Presented
+        ));
+        assert!(matches!(
+            surface.present_result(SurfaceStatus::Timeout),
+            PresentResult::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn buffers_cleared_on_create_read_back_as_zeros() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let instance = gpu.instance.as_ref();
+        // Cleared on the GPU.
+        let cleared = gpu.create_buffer(
+            64,
+            wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::COPY_SRC,
+        );
+        zero_new_buffer(instance, gpu.device, gpu.queue, cleared, false, false).unwrap();
+        assert_eq!(gpu.read(cleared, 0, 64), vec![0; 64]);
+        // Zeroed through its mapping.
+        let mapped = core_result(instance.device_create_buffer::<crate::Backend>(
+            gpu.device,
+            &wgpu_types::BufferDescriptor {
+                label: None,
+                size: 64,
+                usage: wgpu_types::BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            },
+            None,
+        ))
+        .unwrap();
+        zero_new_buffer(instance, gpu.device, gpu.queue, mapped, true, false).unwrap();
+        assert_eq!(gpu.read(mapped, 0, 64), vec![0; 64]);
+    }
+
+    /// Names and fields of the spans entered.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = String::new();
+            attrs.record(
+                &mut |field: &tracing::field::Field, value: &dyn std::fmt::Debug| {
+                    fields += &format!(" {field}={value:?}");
+                },
+            );
+            let name = attrs.metadata().name();
+            self.0.lock().unwrap().push(format!("{name}{fields}"));
+        }
+    }
+
+    #[test]
+    fn submitting_records_finish_and_submit_spans() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder = view.table().push(encoder).unwrap();
+        let queue = view.table().push(gpu.queue).unwrap();
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut host = view.host();
+            let command_buffer =
+                webgpu::HostGpuCommandEncoder::finish(&mut host, encoder, None).unwrap();
+            block_on(webgpu::HostGpuQueue::submit(
+                &mut host,
+                queue,
+                vec![command_buffer],
+            ))
+            .unwrap();
+        });
+        let spans = recorder.0.lock().unwrap();
+        assert_eq!(spans.len(), 2, "{spans:?}");
+        assert!(spans[0].starts_with("command_encoder_finish command_encoder="));
+        assert!(spans[1].starts_with(&format!("queue_submit queue={:?}", gpu.queue)));
+    }
+
+    #[test]
+    fn render_passes_write_to_every_color_attachment() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (width, height) = (8, 4);
+        let usage =
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC;
+        let albedo =
+            gpu.create_texture(wgpu_types::TextureFormat::Rgba8Unorm, width, height, usage);
+        let normal =
+            gpu.create_texture(wgpu_types::TextureFormat::Rgba8Unorm, width, height, usage);
+        let mut attachment = |texture, [r, g, b, a]: [f64; 4]| {
+            let texture_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+                texture,
+                &Default::default(),
+                None,
+            ))
+            .unwrap();
+            Some(webgpu::GpuRenderPassColorAttachment {
+                view: view.table().push(texture_view).unwrap(),
+                depth_slice: None,
+                resolve_target: None,
+                clear_value: Some(webgpu::GpuColorDictOrListF64::GpuColorDict(
+                    webgpu::GpuColorDict { r, g, b, a },
+                )),
+                load_op: webgpu::GpuLoadOp::Clear,
+                store_op: webgpu::GpuStoreOp::Store,
+            })
+        };
+        let color_attachments = vec![
+            attachment(albedo, [1.0, 0.0, 0.0, 1.0]),
+            attachment(normal, [0.0, 0.0, 1.0, 1.0]),
+        ];
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let pass = webgpu::HostGpuCommandEncoder::begin_render_pass(
+            &mut host,
+            Resource::new_borrow(encoder_resource.rep()),
+            webgpu::GpuRenderPassDescriptor {
+                color_attachments,
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                max_draw_count: None,
+                label: None,
+            },
+        )
+        .unwrap();
+        webgpu::HostGpuRenderPassEncoder::end(&mut host, pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+
+        let pixels = |texture| gpu.read_texture(texture, width, height);
+        assert!(pixels(albedo)
+            .chunks(4)
+            .all(|pixel| pixel == [255, 0, 0, 255]));
+        assert!(pixels(normal)
+            .chunks(4)
+            .all(|pixel| pixel == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn render_passes_are_timed_with_timestamp_writes() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let Some(gpu) = test_gpu::TestGpu::with_features(wgpu_types::Features::TIMESTAMP_QUERY)
+        else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        // Big enough that clearing it takes more than a tick.
+        let texture = gpu.create_texture(
+            wgpu_types::TextureFormat::Rgba8Unorm,
+            2048,
+            2048,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let texture_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let texture_view = view.table().push(texture_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let query_set = host
+            .create_query_set(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuQuerySetDescriptor {
+                    type_: webgpu::GpuQueryType::Timestamp,
+                    count: 2,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: texture_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: Some(webgpu::GpuRenderPassTimestampWrites {
+                        query_set: Resource::new_borrow(query_set.rep()),
+                        beginning_of_pass_write_index: Some(0),
+                        end_of_pass_write_index: Some(1),
+                    }),
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+
+        let timestamps = block_on(host.read_query_results(device, query_set, 0, 2)).unwrap();
+        assert!(timestamps[1] > timestamps[0], "{timestamps:?}");
+    }
+
+    #[test]
+    fn blend_constants_can_change_mid_pass() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let (width, height) = (8, 4);
+        let target = gpu.create_texture(
+            wgpu_types::TextureFormat::Rgba8Unorm,
+            width,
+            height,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC,
+        );
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        // Vertices 0 to 2 make a triangle covering the left half, 3 to 5 one
+        // covering the right half, drawn over the first where they overlap.
+        // Every fragment is white, so the blend leaves just the constant.
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let corner = index % 3u;
+                        let x = f32(index / 3u) - 1.0 + select(0.0, 2.0, corner == 2u);
+                        let y = select(-1.0, 3.0, corner == 1u);
+                        return vec4<f32>(x, y, 0.0, 1.0);
+                    }
+
+                    @fragment
+                    fn fs_main() -> @location(0) vec4<f32> {
+                        return vec4<f32>(1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let blend_component = |src_factor| webgpu::GpuBlendComponent {
+            operation: Some(webgpu::GpuBlendOperation::Add),
+            src_factor: Some(src_factor),
+            dst_factor: Some(webgpu::GpuBlendFactor::Zero),
+        };
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: Some(webgpu::GpuFragmentState {
+                        targets: vec![Some(webgpu::GpuColorTargetState {
+                            format: webgpu::GpuTextureFormat::Rgba8unorm,
+                            blend: Some(webgpu::GpuBlendState {
+                                color: blend_component(webgpu::GpuBlendFactor::Constant),
+                                alpha: blend_component(webgpu::GpuBlendFactor::One),
+                            }),
+                            write_mask: None,
+                        })],
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "fs_main".to_string(),
+                    }),
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let rgba =
+            |components: [f64; 4]| webgpu::GpuColorDictOrListF64::ListF64(components.to_vec());
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.set_blend_constant(Resource::new_borrow(pass.rep()), rgba([1.0, 0.0, 0.0, 1.0]))
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.set_blend_constant(Resource::new_borrow(pass.rep()), rgba([0.0, 1.0, 0.0, 1.0]))
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 3, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+
+        let pixels = gpu.read_texture(target, width, height);
+        for (i, pixel) in pixels.chunks(4).enumerate() {
+            let expected = match i as u32 % width < width / 2 {
+                true => [255, 0, 0, 255],
+                false => [0, 255, 0, 255],
+            };
+            assert_eq!(pixel, expected, "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn edges_drawn_at_4x_msaa_resolve_to_partly_covered_pixels() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let (width, height) = (8, 8);
+        let format = wgpu_types::TextureFormat::Rgba8Unorm;
+        let target =
+            msaa::MsaaTarget::new(&gpu.instance, gpu.device, format, 4, width, height).unwrap();
+        let resolved = gpu.create_texture(
+            format,
+            width,
+            height,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC,
+        );
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target.texture,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        // A white triangle over the bottom left half, its edge running through
+        // the centers of the pixels on the diagonal.
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let x = select(-1.0, 1.0, index == 1u);
+                        let y = select(-1.0, 1.0, index == 2u);
+                        return vec4<f32>(x, y, 0.0, 1.0);
+                    }
+
+                    @fragment
+                    fn fs_main() -> @location(0) vec4<f32> {
+                        return vec4<f32>(1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: Some(webgpu::GpuMultisampleState {
+                        count: Some(4),
+                        mask: None,
+                        alpha_to_coverage_enabled: None,
+                    }),
+                    fragment: Some(webgpu::GpuFragmentState {
+                        targets: vec![Some(webgpu::GpuColorTargetState {
+                            format: webgpu::GpuTextureFormat::Rgba8unorm,
+                            blend: None,
+                            write_mask: None,
+                        })],
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "fs_main".to_string(),
+                    }),
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+        target
+            .resolve(&gpu.instance, gpu.device, gpu.queue, resolved)
+            .unwrap();
+        target.destroy(&gpu.instance);
+
+        let pixels = gpu.read_texture(resolved, width, height);
+        let red = |x: u32, y: u32| pixels[((y * width + x) * 4) as usize];
+        assert_eq!(red(0, height - 1), 255);
+        assert_eq!(red(width - 1, 0), 0);
+        // Without multisampling, every pixel would be either covered or not.
+        for i in 0..width {
+            let edge = red(i, i);
+            assert!(0 < edge && edge < 255, "pixel {i}, {i} is {edge}");
+        }
+    }
+
+    #[test]
+    fn set_predication_reports_it_is_unsupported() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let target = gpu.create_texture(
+            wgpu_types::TextureFormat::Rgba8Unorm,
+            4,
+            4,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let predicate = host
+            .create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size: 4,
+                    usage: wgpu_types::BufferUsages::INDIRECT.bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: Some(true),
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+
+        let error = host
+            .set_predication(
+                Resource::new_borrow(pass.rep()),
+                Some(Resource::new_borrow(predicate.rep())),
+                0,
+            )
+            .unwrap_err();
+        assert!(error.contains("isn't supported"), "{error}");
+        host.end(pass, encoder_resource).unwrap();
+    }
+
+    /// Draws a white triangle covering a 4x4 target, but past the far plane,
+    /// and reads the target back.
+    fn draw_beyond_the_far_plane(gpu: &test_gpu::TestGpu, unclipped_depth: bool) -> Vec<u8> {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let mut view = test_gpu::TestView::new(gpu);
+        let (device, _) = view.device(gpu);
+        let (width, height) = (4, 4);
+        let target = gpu.create_texture(
+            wgpu_types::TextureFormat::Rgba8Unorm,
+            width,
+            height,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC,
+        );
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let x = select(-1.0, 3.0, index == 2u);
+                        let y = select(-1.0, 3.0, index == 1u);
+                        return vec4<f32>(x, y, 2.0, 1.0);
+                    }
+
+                    @fragment
+                    fn fs_main() -> @location(0) vec4<f32> {
+                        return vec4<f32>(1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: Some(webgpu::GpuPrimitiveState {
+                        topology: None,
+                        strip_index_format: None,
+                        front_face: None,
+                        cull_mode: None,
+                        unclipped_depth: Some(unclipped_depth),
+                    }),
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: Some(webgpu::GpuFragmentState {
+                        targets: vec![Some(webgpu::GpuColorTargetState {
+                            format: webgpu::GpuTextureFormat::Rgba8unorm,
+                            blend: None,
+                            write_mask: None,
+                        })],
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "fs_main".to_string(),
+                    }),
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+        gpu.read_texture(target, width, height)
+    }
+
+    #[test]
+    fn geometry_past_the_far_plane_is_drawn_with_unclipped_depth() {
+        let Some(gpu) = test_gpu::TestGpu::with_features(wgpu_types::Features::DEPTH_CLIP_CONTROL)
+        else {
+            return;
+        };
+        assert!(draw_beyond_the_far_plane(&gpu, false)
+            .iter()
+            .all(|&byte| byte == 0));
+        assert!(draw_beyond_the_far_plane(&gpu, true)
+            .iter()
+            .all(|&byte| byte == 255));
+    }
+
+    #[test]
+    fn multiview_draws_reach_every_array_layer() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder};
+
+        let Some(gpu) = test_gpu::TestGpu::with_features(wgpu_types::Features::MULTIVIEW) else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let (width, height) = (4, 4);
+        let target = core_result(gpu.instance.device_create_texture::<crate::Backend>(
+            gpu.device,
+            &wgpu_core::resource::TextureDescriptor {
+                label: None,
+                size: wgpu_types::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 2,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu_types::TextureDimension::D2,
+                format: wgpu_types::TextureFormat::Rgba8Unorm,
+                usage: wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu_types::TextureUsages::COPY_SRC,
+                view_formats: vec![],
+            },
+            None,
+        ))
+        .unwrap();
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target,
+            &wgpu_core::resource::TextureViewDescriptor {
+                dimension: Some(wgpu_types::TextureViewDimension::D2Array),
+                ..Default::default()
+            },
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        // Red in the first view, green in the second.
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let x = select(-1.0, 3.0, index == 2u);
+                        let y = select(-1.0, 3.0, index == 1u);
+                        return vec4<f32>(x, y, 0.0, 1.0);
+                    }
+
+                    @fragment
+                    fn fs_main(@builtin(view_index) view: i32) -> @location(0) vec4<f32> {
+                        return select(vec4<f32>(1.0, 0.0, 0.0, 1.0), vec4<f32>(0.0, 1.0, 0.0, 1.0), view == 1);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: Some(webgpu::GpuFragmentState {
+                        targets: vec![Some(webgpu::GpuColorTargetState {
+                            format: webgpu::GpuTextureFormat::Rgba8unorm,
+                            blend: None,
+                            write_mask: None,
+                        })],
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "fs_main".to_string(),
+                    }),
+                    layout: None,
+                    label: None,
+                    multiview: Some(2),
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+        let first = gpu.read_texture_layer(target, 0, width, height);
+        assert!(first.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+        let second = gpu.read_texture_layer(target, 1, width, height);
+        assert!(second.chunks(4).all(|pixel| pixel == [0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn depth_is_read_back_where_geometry_was_drawn() {
+        use webgpu::{
+            HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder, HostGpuTexture,
+        };
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let (width, height) = (8, 4);
+        let depth = host
+            .create_texture(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuTextureDescriptor {
+                    size: webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![width, height]),
+                    mip_level_count: None,
+                    sample_count: None,
+                    dimension: webgpu::GpuTextureDimension::TwoD,
+                    format: webgpu::GpuTextureFormat::Depth32float,
+                    usage: (wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu_types::TextureUsages::COPY_SRC)
+                        .bits(),
+                    view_formats: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let depth_view = host
+            .create_view(Resource::new_borrow(depth.rep()), None)
+            .unwrap();
+        // A triangle covering the left half, at a depth of 0.25.
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let x = select(-1.0, 1.0, index == 2u);
+                        let y = select(-1.0, 3.0, index == 1u);
+                        return vec4<f32>(x, y, 0.25, 1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: Some(webgpu::GpuDepthStencilState {
+                        format: webgpu::GpuTextureFormat::Depth32float,
+                        depth_write_enabled: Some(true),
+                        depth_compare: Some(webgpu::GpuCompareFunction::Less),
+                        stencil_front: None,
+                        stencil_back: None,
+                        stencil_read_mask: None,
+                        stencil_write_mask: None,
+                        depth_bias: None,
+                        depth_bias_slope_scale: None,
+                        depth_bias_clamp: None,
+                    }),
+                    multisample: None,
+                    fragment: None,
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![],
+                    depth_stencil_attachment: Some(webgpu::GpuRenderPassDepthStencilAttachment {
+                        view: depth_view,
+                        depth_clear_value: Some(1.0),
+                        depth_load_op: Some(webgpu::GpuLoadOp::Clear),
+                        depth_store_op: Some(webgpu::GpuStoreOp::Store),
+                        depth_read_only: None,
+                        stencil_clear_value: None,
+                        stencil_load_op: None,
+                        stencil_store_op: None,
+                        stencil_read_only: None,
+                    }),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+
+        let depths = futures::executor::block_on(host.read_depth_texture(
+            Resource::new_borrow(device.rep()),
+            Resource::new_borrow(depth.rep()),
+            webgpu::GpuTextureRegion {
+                x: 0,
+                y: 0,
+                width,
+                height,
+                mip_level: 0,
+                array_layer: 0,
+            },
+        ))
+        .unwrap();
+        assert_eq!(depths.len(), (width * height) as usize);
+        for (i, depth) in depths.into_iter().enumerate() {
+            let expected = match i as u32 % width < width / 2 {
+                true => 0.25,
+                false => 1.0,
+            };
+            assert_eq!(depth, expected, "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn depth_only_views_of_depth_stencil_textures_can_be_sampled() {
+        use webgpu::{
+            HostGpuCommandEncoder, HostGpuDevice, HostGpuRenderPassEncoder, HostGpuTexture,
+        };
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let (width, height) = (4, 4);
+        let target = gpu.create_texture(
+            wgpu_types::TextureFormat::Rgba8Unorm,
+            width,
+            height,
+            wgpu_types::TextureUsages::RENDER_ATTACHMENT | wgpu_types::TextureUsages::COPY_SRC,
+        );
+        let target_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            target,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let target_view = view.table().push(target_view).unwrap();
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let depth_stencil = host
+            .create_texture(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuTextureDescriptor {
+                    size: webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![width, height]),
+                    mip_level_count: None,
+                    sample_count: None,
+                    dimension: webgpu::GpuTextureDimension::TwoD,
+                    format: webgpu::GpuTextureFormat::Depth24plusStencil8,
+                    usage: (wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu_types::TextureUsages::TEXTURE_BINDING)
+                        .bits(),
+                    view_formats: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let aspect_view = |aspect| webgpu::GpuTextureViewDescriptor {
+            format: None,
+            dimension: None,
+            aspect: Some(aspect),
+            base_mip_level: None,
+            mip_level_count: None,
+            base_array_layer: None,
+            array_layer_count: None,
+            label: None,
+        };
+        let attachment_view = host
+            .create_view(Resource::new_borrow(depth_stencil.rep()), None)
+            .unwrap();
+        let depth_view = host
+            .create_view(
+                Resource::new_borrow(depth_stencil.rep()),
+                Some(aspect_view(webgpu::GpuTextureAspect::DepthOnly)),
+            )
+            .unwrap();
+        assert!(host
+            .create_view(
+                Resource::new_borrow(depth_stencil.rep()),
+                Some(aspect_view(webgpu::GpuTextureAspect::StencilOnly)),
+            )
+            .is_ok());
+
+        // Clears the depth to 0.25, with the stencil alongside it.
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![],
+                    depth_stencil_attachment: Some(webgpu::GpuRenderPassDepthStencilAttachment {
+                        view: attachment_view,
+                        depth_clear_value: Some(0.25),
+                        depth_load_op: Some(webgpu::GpuLoadOp::Clear),
+                        depth_store_op: Some(webgpu::GpuStoreOp::Store),
+                        depth_read_only: None,
+                        stencil_clear_value: Some(0),
+                        stencil_load_op: Some(webgpu::GpuLoadOp::Clear),
+                        stencil_store_op: Some(webgpu::GpuStoreOp::Store),
+                        stencil_read_only: None,
+                    }),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.end(pass, Resource::new_borrow(encoder_resource.rep()))
+            .unwrap();
+
+        // Then draws the depth it samples as gray over the whole target.
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @group(0) @binding(0) var depth: texture_depth_2d;
+
+                    @vertex
+                    fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+                        let x = select(-1.0, 3.0, index == 2u);
+                        let y = select(-1.0, 3.0, index == 1u);
+                        return vec4<f32>(x, y, 0.0, 1.0);
+                    }
+
+                    @fragment
+                    fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+                        let d = textureLoad(depth, vec2<i32>(position.xy), 0);
+                        return vec4<f32>(d, d, d, 1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let bind_group_layout = host
+            .create_bind_group_layout(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupLayoutDescriptor {
+                    entries: vec![webgpu::GpuBindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu_types::ShaderStages::FRAGMENT.bits(),
+                        buffer: None,
+                        sampler: None,
+                        texture: Some(webgpu::GpuTextureBindingLayout {
+                            sample_type: Some(webgpu::GpuTextureSampleType::Depth),
+                            view_dimension: webgpu::GpuTextureViewDimension::TwoD,
+                            multisampled: Some(false),
+                        }),
+                        storage_texture: None,
+                        external_texture: None,
+                        count: None,
+                    }],
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pipeline_layout = host
+            .create_pipeline_layout(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuPipelineLayoutDescriptor {
+                    bind_group_layouts: vec![Resource::new_borrow(bind_group_layout.rep())],
+                    label: None,
+                },
+            )
+            .unwrap();
+        let bind_group = host
+            .create_bind_group(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupDescriptor {
+                    layout: bind_group_layout,
+                    entries: vec![webgpu::GpuBindGroupEntry {
+                        binding: 0,
+                        resource: webgpu::GpuBindingResource::GpuTextureView(depth_view),
+                    }],
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: Some(webgpu::GpuFragmentState {
+                        targets: vec![Some(webgpu::GpuColorTargetState {
+                            format: webgpu::GpuTextureFormat::Rgba8unorm,
+                            blend: None,
+                            write_mask: None,
+                        })],
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "fs_main".to_string(),
+                    }),
+                    layout: Some(Resource::new_borrow(pipeline_layout.rep())),
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view: target_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: None,
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.set_bind_group(
+            Resource::new_borrow(pass.rep()),
+            0,
+            Resource::new_borrow(bind_group.rep()),
+            None,
+        )
+        .unwrap();
+        host.draw(Resource::new_borrow(pass.rep()), 3, 1, 0, 0)
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+
+        let pixels = gpu.read_texture(target, width, height);
+        for pixel in pixels.chunks(4) {
+            // 0.25 of 255, give or take rounding.
+            assert!(pixel[..3].iter().all(|&c| c.abs_diff(64) <= 1), "{pixel:?}");
+            assert_eq!(pixel[3], 255);
+        }
+    }
+
+    /// Clears all of `view` to a gray of 0.5 in a pass of its own.
+    fn clear_to_gray(
+        gpu: &test_gpu::TestGpu,
+        host: &mut WasiWebGpuImpl<&mut test_gpu::TestView>,
+        view: Resource<wgpu_core::id::TextureViewId>,
+    ) {
+        use webgpu::{HostGpuCommandEncoder, HostGpuRenderPassEncoder};
+
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = host.0.table().push(encoder).unwrap();
+        let pass = host
+            .begin_render_pass(
+                Resource::new_borrow(encoder_resource.rep()),
+                webgpu::GpuRenderPassDescriptor {
+                    color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
+                        view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        clear_value: Some(webgpu::GpuColorDictOrListF64::ListF64(vec![
+                            0.5, 0.5, 0.5, 1.0,
+                        ])),
+                        load_op: webgpu::GpuLoadOp::Clear,
+                        store_op: webgpu::GpuStoreOp::Store,
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                    max_draw_count: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+    }
+
+    #[test]
+    fn srgb_views_of_linear_textures_store_colors_srgb_encoded() {
+        use webgpu::{HostGpuDevice, HostGpuTexture};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let mut host = view.host();
+        let texture = host
+            .create_texture(
+                device,
+                webgpu::GpuTextureDescriptor {
+                    size: webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![4, 4]),
+                    mip_level_count: None,
+                    sample_count: None,
+                    dimension: webgpu::GpuTextureDimension::TwoD,
+                    format: webgpu::GpuTextureFormat::Rgba8unorm,
+                    usage: (wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu_types::TextureUsages::COPY_SRC)
+                        .bits(),
+                    view_formats: Some(vec![webgpu::GpuTextureFormat::Rgba8unormSrgb]),
+                    label: None,
+                },
+            )
+            .unwrap();
+        let descriptor = |format| webgpu::GpuTextureViewDescriptor {
+            format: Some(format),
+            dimension: None,
+            aspect: None,
+            base_mip_level: None,
+            mip_level_count: None,
+            base_array_layer: None,
+            array_layer_count: None,
+            label: None,
+        };
+        let view_as = |host: &mut WasiWebGpuImpl<_>, format| {
+            host.create_view(
+                Resource::new_borrow(texture.rep()),
+                Some(descriptor(format)),
+            )
+        };
+        // Only the texture's own format and its view formats can be viewed as.
+        let error = view_as(&mut host, webgpu::GpuTextureFormat::Bgra8unorm).unwrap_err();
+        assert!(error.to_string().contains("view-formats"), "{error}");
+        let linear = view_as(&mut host, webgpu::GpuTextureFormat::Rgba8unorm).unwrap();
+        let srgb = view_as(&mut host, webgpu::GpuTextureFormat::Rgba8unormSrgb).unwrap();
+        let texture = host.0.table().get(&texture).unwrap().id;
+        let first_pixel = || gpu.read_texture(texture, 4, 4)[..4].to_vec();
+
+        clear_to_gray(&gpu, &mut host, linear);
+        assert_eq!(first_pixel(), [128, 128, 128, 255]);
+        // Linear 0.5 is about 0.735 sRGB encoded.
+        clear_to_gray(&gpu, &mut host, srgb);
+        let pixel = first_pixel();
+        assert!(
+            pixel[..3].iter().all(|channel| channel.abs_diff(188) <= 1) && pixel[3] == 255,
+            "{pixel:?}"
+        );
+    }
+
+    #[test]
+    fn buffer_clears_zero_just_their_range() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuQueue};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, queue) = view.device(&gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let buffer = host
+            .create_buffer(
+                device,
+                webgpu::GpuBufferDescriptor {
+                    size: 16,
+                    usage: (wgpu_types::BufferUsages::COPY_DST
+                        | wgpu_types::BufferUsages::COPY_SRC)
+                        .bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.write_buffer_with(queue, Resource::new_borrow(buffer.rep()), 0, vec![0xff; 16])
+            .unwrap();
+        let clear = |host: &mut WasiWebGpuImpl<_>, offset, size| {
+            host.clear_buffer(
+                Resource::new_borrow(encoder_resource.rep()),
+                Resource::new_borrow(buffer.rep()),
+                Some(offset),
+                size,
+            )
+        };
+        clear(&mut host, 4, Some(4)).unwrap();
+        // Without a size, clears to the end.
+        clear(&mut host, 12, None).unwrap();
+        assert!(clear(&mut host, 2, Some(4)).is_err());
+        gpu.submit(encoder);
+
+        let id = host.0.table().get(&buffer).unwrap().buffer;
+        let mut expected = vec![0xff; 16];
+        expected[4..8].fill(0);
+        expected[12..].fill(0);
+        assert_eq!(gpu.read(id, 0, 16), expected);
+    }
+
+    #[test]
+    fn anisotropy_is_clamped_to_the_device_maximum() {
+        let anisotropic = wgpu_types::DownlevelCapabilities::default();
+        let isotropic = wgpu_types::DownlevelCapabilities {
+            flags: wgpu_types::DownlevelFlags::empty(),
+            ..Default::default()
+        };
+        assert_eq!(clamp_anisotropy(Some(32), &anisotropic), Some(16));
+        assert_eq!(clamp_anisotropy(Some(4), &anisotropic), Some(4));
+        assert_eq!(clamp_anisotropy(Some(16), &isotropic), Some(1));
+        assert_eq!(clamp_anisotropy(None, &anisotropic), None);
+    }
+
+    #[test]
+    fn samplers_are_created_with_16x_anisotropy() {
+        use webgpu::{HostGpuDevice, HostGpuSupportedLimits};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let mut host = view.host();
+        let downlevel = gpu
+            .instance
+            .device_downlevel_properties::<crate::Backend>(gpu.device)
+            .unwrap();
+        let limits = host.limits(Resource::new_borrow(device.rep()));
+        let device_max = host.max_sampler_anisotropy(limits);
+        assert_eq!(clamp_anisotropy(Some(16), &downlevel), Some(device_max));
+        let sampler = |filter| webgpu::GpuSamplerDescriptor {
+            address_mode_u: None,
+            address_mode_v: None,
+            address_mode_w: None,
+            mag_filter: Some(filter),
+            min_filter: Some(filter),
+            mipmap_filter: Some(match filter {
+                webgpu::GpuFilterMode::Linear => webgpu::GpuMipmapFilterMode::Linear,
+                webgpu::GpuFilterMode::Nearest => webgpu::GpuMipmapFilterMode::Nearest,
+            }),
+            lod_min_clamp: None,
+            lod_max_clamp: None,
+            compare: None,
+            max_anisotropy: Some(16),
+            label: None,
+        };
+        assert!(host
+            .create_sampler(
+                Resource::new_borrow(device.rep()),
+                Some(sampler(webgpu::GpuFilterMode::Linear)),
+            )
+            .is_ok());
+        // Anisotropy needs linear filtering all round.
+        assert!(host
+            .create_sampler(
+                Resource::new_borrow(device.rep()),
+                Some(sampler(webgpu::GpuFilterMode::Nearest)),
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn adapter_info_reports_the_adapters_device_type() {
+        use webgpu::{HostGpuAdapter, HostGpuAdapterInfo};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let adapter = view.table().push(gpu.adapter).unwrap();
+        let mut host = view.host();
+        let info = host.request_adapter_info(adapter);
+        let device_type = host.device_type(info);
+        let expected = gpu
+            .instance
+            .adapter_get_info::<crate::Backend>(gpu.adapter)
+            .unwrap()
+            .device_type;
+        assert_eq!(device_type, expected.into());
+    }
+
+    #[test]
+    fn common_color_formats_support_4x_msaa() {
+        use webgpu::HostGpuAdapter;
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let adapter = view.table().push(gpu.adapter).unwrap();
+        let counts = view
+            .host()
+            .get_supported_sample_counts(adapter, webgpu::GpuTextureFormat::Rgba8unorm);
+        assert!(counts.contains(&1), "{counts:?}");
+        assert!(counts.contains(&4), "{counts:?}");
+        assert!(counts.iter().all(|count| [1, 2, 4, 8, 16].contains(count)));
+    }
+
+    /// Writes `data` at `offset` into a new 16 byte buffer with `usage`, and
+    /// returns how that went with what the buffer holds after.
+    fn write_to_new_buffer(
+        usage: wgpu_types::BufferUsages,
+        offset: u64,
+        data: &[u8],
+    ) -> Option<(Result<(), webgpu::GpuWriteBufferError>, Vec<u8>)> {
+        use webgpu::{HostGpuDevice, HostGpuQueue};
+
+        let gpu = test_gpu::TestGpu::new()?;
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, queue) = view.device(&gpu);
+        let mut host = view.host();
+        let buffer = host
+            .create_buffer(
+                device,
+                webgpu::GpuBufferDescriptor {
+                    size: 16,
+                    usage: (usage | wgpu_types::BufferUsages::COPY_SRC).bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let result = host.write_buffer_with(
+            queue,
+            Resource::new_borrow(buffer.rep()),
+            offset,
+            data.to_vec(),
+        );
+        let id = host.0.table().get(&buffer).unwrap().buffer;
+        Some((result, gpu.read(id, 0, 16)))
+    }
+
+    #[test]
+    fn writes_at_unaligned_offsets_fail() {
+        let usage = wgpu_types::BufferUsages::COPY_DST;
+        if let Some((result, _)) = write_to_new_buffer(usage, 2, &[1; 4]) {
+            assert_eq!(result, Err(webgpu::GpuWriteBufferError::UnalignedOffset));
+        }
+    }
+
+    #[test]
+    fn writes_of_unaligned_sizes_fail() {
+        let usage = wgpu_types::BufferUsages::COPY_DST;
+        if let Some((result, _)) = write_to_new_buffer(usage, 0, &[1; 3]) {
+            assert_eq!(result, Err(webgpu::GpuWriteBufferError::UnalignedSize));
+        }
+    }
+
+    #[test]
+    fn writes_past_the_end_fail() {
+        let usage = wgpu_types::BufferUsages::COPY_DST;
+        if let Some((result, bytes)) = write_to_new_buffer(usage, 12, &[1; 8]) {
+            assert_eq!(result, Err(webgpu::GpuWriteBufferError::OutOfBounds));
+            assert_eq!(bytes, [0; 16]);
+        }
+    }
+
+    #[test]
+    fn writes_without_copy_dst_fail() {
+        let usage = wgpu_types::BufferUsages::empty();
+        if let Some((result, _)) = write_to_new_buffer(usage, 0, &[1; 4]) {
+            assert_eq!(
+                result,
+                Err(webgpu::GpuWriteBufferError::MissingCopyDstUsage)
+            );
+        }
+    }
+
+    #[test]
+    fn sub_range_writes_land_where_asked() {
+        let usage = wgpu_types::BufferUsages::COPY_DST;
+        if let Some((result, bytes)) = write_to_new_buffer(usage, 4, &[1, 2, 3, 4, 5, 6, 7, 8]) {
+            assert_eq!(result, Ok(()));
+            assert_eq!(bytes, [0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn devices_can_be_dropped_before_their_pipelines() {
+        use webgpu::{HostGpuDevice, HostGpuRenderPipeline};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let mut host = view.host();
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main() -> @builtin(position) vec4<f32> {
+                        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: None,
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        // Like a guest struct holding the device before its pipelines.
+        HostGpuDevice::drop(&mut host, device).unwrap();
+        HostGpuRenderPipeline::drop(&mut host, pipeline).unwrap();
+    }
+
+    #[test]
+    fn pipeline_caches_only_work_with_their_own_device() {
+        use webgpu::{HostGpuDevice, HostGpuPipelineCache};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let mut host = view.host();
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @vertex
+                    fn vs_main() -> @builtin(position) vec4<f32> {
+                        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let create_pipeline = |host: &mut WasiWebGpuImpl<_>, cache: &Resource<PipelineCache>| {
+            host.create_render_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuRenderPipelineDescriptor {
+                    vertex: webgpu::GpuVertexState {
+                        buffers: None,
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: "vs_main".to_string(),
+                    },
+                    primitive: None,
+                    depth_stencil: None,
+                    multisample: None,
+                    fragment: None,
+                    layout: None,
+                    label: None,
+                    multiview: None,
+                    cache: Some(Resource::new_borrow(cache.rep())),
+                },
+            )
+        };
+        // Data from an earlier run is taken, though there's nothing to seed.
+        let cache = host.create_pipeline_cache(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuPipelineCacheDescriptor {
+                data: Some(vec![1, 2, 3, 4]),
+                label: None,
+            },
+        );
+        create_pipeline(&mut host, &cache).unwrap();
+        create_pipeline(&mut host, &cache).unwrap();
+        // wgpu-core 0.20 has no cache to save.
+        assert_eq!(host.get_data(Resource::new_borrow(cache.rep())), None);
+        HostGpuPipelineCache::drop(&mut host, cache).unwrap();
+
+        let other_device = wgpu_core::id::DeviceId::zip(u32::MAX, 1, wgpu_types::Backend::Empty);
+        let other_cache = host
+            .0
+            .table()
+            .push(PipelineCache {
+                device: other_device,
+            })
+            .unwrap();
+        let error = create_pipeline(&mut host, &other_cache).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("pipeline cache was created on device"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn writes_to_destroyed_buffers_fail() {
+        use webgpu::{HostGpuBuffer, HostGpuDevice, HostGpuQueue};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, queue) = view.device(&gpu);
+        let mut host = view.host();
+        let buffer = host
+            .create_buffer(
+                device,
+                webgpu::GpuBufferDescriptor {
+                    size: 16,
+                    usage: wgpu_types::BufferUsages::COPY_DST.bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        HostGpuBuffer::destroy(&mut host, Resource::new_borrow(buffer.rep())).unwrap();
+        // Destroying twice is fine.
+        HostGpuBuffer::destroy(&mut host, Resource::new_borrow(buffer.rep())).unwrap();
+        let error = host
+            .write_buffer(
+                Resource::new_borrow(queue.rep()),
+                Resource::new_borrow(buffer.rep()),
+                0,
+                None,
+                vec![1; 4],
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "can't write buffer: buffer was destroyed, so it can't be used anymore"
+        );
+        assert_eq!(
+            host.write_buffer_with(queue, buffer, 0, vec![1; 4]),
+            Err(webgpu::GpuWriteBufferError::DestroyedBuffer)
+        );
+    }
+
+    #[test]
+    fn buffers_are_read_back_a_slice_at_a_time() {
+        use webgpu::{HostGpuDevice, HostGpuQueue};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, queue) = view.device(&gpu);
+        let mut host = view.host();
+        let buffer = host
+            .create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size: 16,
+                    usage: (wgpu_types::BufferUsages::COPY_DST
+                        | wgpu_types::BufferUsages::COPY_SRC)
+                        .bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pattern: Vec<u8> = (0..16).collect();
+        host.write_buffer_with(queue, Resource::new_borrow(buffer.rep()), 0, pattern)
+            .unwrap();
+        let mut read = |offset, length| {
+            block_on(host.read_buffer(
+                Resource::new_borrow(device.rep()),
+                Resource::new_borrow(buffer.rep()),
+                offset,
+                length,
+            ))
+        };
+        assert_eq!(read(4, 8), Ok((4..12).collect()));
+        assert!(read(2, 8).is_err());
+        assert!(read(4, 6).is_err());
+        assert!(read(8, 12).is_err());
+    }
+
+    #[test]
+    fn format_features_follow_the_devices_features() {
+        use webgpu::HostGpuDevice;
+
+        let format_features = |gpu: &test_gpu::TestGpu, format| {
+            let mut view = test_gpu::TestView::new(gpu);
+            let (device, _queue) = view.device(gpu);
+            view.host().get_texture_format_features(device, format)
+        };
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let rgba8 = format_features(&gpu, webgpu::GpuTextureFormat::Rgba8unorm);
+        assert!(rgba8.supported);
+        assert!(rgba8.filterable);
+        assert!(rgba8.renderable);
+        assert!(rgba8.blendable);
+        assert!(rgba8.sample_counts.contains(&1));
+        let r32float = format_features(&gpu, webgpu::GpuTextureFormat::R32float);
+        assert!(r32float.supported);
+        assert!(!r32float.filterable);
+
+        let Some(gpu) = test_gpu::TestGpu::with_features(wgpu_types::Features::FLOAT32_FILTERABLE)
+        else {
+            return;
+        };
+        let r32float = format_features(&gpu, webgpu::GpuTextureFormat::R32float);
+        assert!(r32float.filterable);
+    }
+
+    #[test]
+    fn copies_land_in_the_mip_level_asked_for() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice, HostGpuQueue};
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, queue) = view.device(&gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let texture = host
+            .create_texture(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuTextureDescriptor {
+                    size: webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![8, 8]),
+                    mip_level_count: Some(2),
+                    sample_count: None,
+                    dimension: webgpu::GpuTextureDimension::TwoD,
+                    format: webgpu::GpuTextureFormat::Rgba8unorm,
+                    usage: (wgpu_types::TextureUsages::COPY_DST
+                        | wgpu_types::TextureUsages::COPY_SRC)
+                        .bits(),
+                    view_formats: None,
+                    label: None,
+                },
+            )
+            .unwrap();
+        // Rows of 4 pixels are padded to the 256 bytes copies need.
+        let row = wgpu_types::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = |host: &mut WasiWebGpuImpl<&mut test_gpu::TestView>| {
+            host.create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size: row as u64 * 8,
+                    usage: (wgpu_types::BufferUsages::COPY_DST
+                        | wgpu_types::BufferUsages::COPY_SRC)
+                        .bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap()
+        };
+        let source = buffer(&mut host);
+        let mip_1 = buffer(&mut host);
+        let mip_0 = buffer(&mut host);
+        let pixels: Vec<u8> = (0..row * 4).map(|i| (i % 251) as u8 + 1).collect();
+        host.write_buffer(
+            queue,
+            Resource::new_borrow(source.rep()),
+            0,
+            None,
+            pixels.clone(),
+            None,
+        )
+        .unwrap();
+        let image_copy_buffer = |buffer: &Resource<webgpu::GpuBuffer>| webgpu::GpuImageCopyBuffer {
+            buffer: Resource::new_borrow(buffer.rep()),
+            offset: None,
+            bytes_per_row: Some(row),
+            rows_per_image: None,
+        };
+        let image_copy_texture = |mip_level| webgpu::GpuImageCopyTexture {
+            texture: Resource::new_borrow(texture.rep()),
+            mip_level: Some(mip_level),
+            origin: None,
+            aspect: None,
+        };
+        let size = |side| webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![side, side]);
+        let encoder_borrow = || Resource::new_borrow(encoder_resource.rep());
+        host.copy_buffer_to_texture(
+            encoder_borrow(),
+            image_copy_buffer(&source),
+            image_copy_texture(1),
+            size(4),
+        )
+        .unwrap();
+        host.copy_texture_to_buffer(
+            encoder_borrow(),
+            image_copy_texture(1),
+            image_copy_buffer(&mip_1),
+            size(4),
+        )
+        .unwrap();
+        host.copy_texture_to_buffer(
+            encoder_borrow(),
+            image_copy_texture(0),
+            image_copy_buffer(&mip_0),
+            size(8),
+        )
+        .unwrap();
+        // Mip level 1 of an 8x8 texture is only 4x4.
+        assert!(host
+            .copy_buffer_to_texture(
+                encoder_borrow(),
+                image_copy_buffer(&source),
+                image_copy_texture(1),
+                size(8),
+            )
+            .is_err());
+        gpu.submit(encoder);
+
+        let mut read = |buffer: &Resource<webgpu::GpuBuffer>, rows: u32| {
+            let id = host.0.table().get(buffer).unwrap().buffer;
+            gpu.read(id, 0, row as u64 * rows as u64)
+        };
+        let row_pixels = |bytes: &[u8], width: usize| -> Vec<u8> {
+            bytes
+                .chunks(row as usize)
+                .flat_map(|padded| padded[..width * 4].to_vec())
+                .collect()
+        };
+        assert_eq!(row_pixels(&read(&mip_1, 4), 4), row_pixels(&pixels, 4));
+        assert!(read(&mip_0, 8)
+            .chunks(row as usize)
+            .all(|padded| padded[..8 * 4].iter().all(|&byte| byte == 0)));
+    }
+
+    /// Dispatches 3x2x1 workgroups that each count themselves in a 64 u32
+    /// buffer, directly or through an indirect buffer, and reads the counts back.
+    fn count_workgroups(gpu: &test_gpu::TestGpu, indirect: bool) -> Vec<u8> {
+        use webgpu::{
+            HostGpuCommandEncoder, HostGpuComputePassEncoder, HostGpuComputePipeline,
+            HostGpuDevice, HostGpuQueue,
+        };
+
+        let mut view = test_gpu::TestView::new(gpu);
+        let (device, queue) = view.device(gpu);
+        let encoder = core_result(
+            gpu.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    gpu.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        let encoder_resource = view.table().push(encoder).unwrap();
+        let mut host = view.host();
+        let buffer = |host: &mut WasiWebGpuImpl<&mut test_gpu::TestView>,
+                      size,
+                      usage: wgpu_types::BufferUsages| {
+            host.create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size,
+                    usage: (usage | wgpu_types::BufferUsages::COPY_DST).bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: None,
+                },
+            )
+            .unwrap()
+        };
+        let counts = buffer(&mut host, 256, wgpu_types::BufferUsages::STORAGE);
+        let args = buffer(&mut host, 12, wgpu_types::BufferUsages::INDIRECT);
+        let args_bytes = [3u32, 2, 1].iter().flat_map(|n| n.to_le_bytes()).collect();
+        host.write_buffer(
+            queue,
+            Resource::new_borrow(args.rep()),
+            0,
+            None,
+            args_bytes,
+            None,
+        )
+        .unwrap();
+        let shader = host.create_shader_module(
+            Resource::new_borrow(device.rep()),
+            webgpu::GpuShaderModuleDescriptor {
+                code: "
+                    @group(0) @binding(0) var<storage, read_write> counts: array<atomic<u32>, 64>;
+
+                    @compute @workgroup_size(1)
+                    fn main(@builtin(workgroup_id) id: vec3<u32>) {
+                        atomicAdd(&counts[id.x + id.y * 4u + id.z * 16u], 1u);
+                    }
+                "
+                .to_string(),
+                compilation_hints: None,
+                label: None,
+            },
+        );
+        let pipeline = host
+            .create_compute_pipeline(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuComputePipelineDescriptor {
+                    compute: webgpu::GpuProgrammableStage {
+                        module: Resource::new_borrow(shader.rep()),
+                        entry_point: Some("main".to_string()),
+                    },
+                    layout: webgpu::GpuPipelineLayoutOrGpuAutoLayoutMode::GpuAutoLayoutMode(
+                        webgpu::GpuAutoLayoutMode::Auto,
+                    ),
+                    label: None,
+                    cache: None,
+                },
+            )
+            .unwrap();
+        let layout = host.get_bind_group_layout(Resource::new_borrow(pipeline.rep()), 0);
+        let bind_group = host
+            .create_bind_group(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupDescriptor {
+                    layout,
+                    entries: vec![webgpu::GpuBindGroupEntry {
+                        binding: 0,
+                        resource: webgpu::GpuBindingResource::GpuBufferBinding(
+                            webgpu::GpuBufferBinding {
+                                buffer: Resource::new_borrow(counts.rep()),
+                                offset: None,
+                                size: None,
+                            },
+                        ),
+                    }],
+                    label: None,
+                },
+            )
+            .unwrap();
+        let pass = host
+            .begin_compute_pass(Resource::new_borrow(encoder_resource.rep()), None)
+            .unwrap();
+        host.set_pipeline(Resource::new_borrow(pass.rep()), pipeline)
+            .unwrap();
+        host.set_bind_group(Resource::new_borrow(pass.rep()), 0, bind_group, None)
+            .unwrap();
+        if indirect {
+            // Only the first 12 bytes hold workgroup counts.
+            assert!(host
+                .dispatch_workgroups_indirect(
+                    Resource::new_borrow(pass.rep()),
+                    Resource::new_borrow(args.rep()),
+                    4,
+                )
+                .is_err());
+            host.dispatch_workgroups_indirect(
+                Resource::new_borrow(pass.rep()),
+                Resource::new_borrow(args.rep()),
+                0,
+            )
+            .unwrap();
+        } else {
+            host.dispatch_workgroups(Resource::new_borrow(pass.rep()), 3, Some(2), Some(1));
+        }
+        host.end(pass, encoder_resource).unwrap();
+        gpu.submit(encoder);
+        let id = host.0.table().get(&counts).unwrap().buffer;
+        gpu.read(id, 0, 256)
+    }
+
+    #[test]
+    fn indirect_dispatches_match_direct_ones() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let direct = count_workgroups(&gpu, false);
+        let indirect = count_workgroups(&gpu, true);
+        assert_eq!(indirect, direct);
+        let counts: Vec<u32> = direct
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        // Each of the 3x2 workgroups ran once.
+        for (i, count) in counts.into_iter().enumerate() {
+            let expected = (i % 4 < 3 && i / 4 < 2) as u32;
+            assert_eq!(count, expected, "workgroup {i}");
+        }
+    }
+
+    /// A layout entry for `count` 2D float textures at binding 0.
+    fn texture_array_entry(count: u32) -> webgpu::GpuBindGroupLayoutEntry {
+        webgpu::GpuBindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu_types::ShaderStages::FRAGMENT.bits(),
+            buffer: None,
+            sampler: None,
+            texture: Some(webgpu::GpuTextureBindingLayout {
+                sample_type: Some(webgpu::GpuTextureSampleType::Float),
+                view_dimension: webgpu::GpuTextureViewDimension::TwoD,
+                multisampled: Some(false),
+            }),
+            storage_texture: None,
+            external_texture: None,
+            count: Some(count),
+        }
+    }
+
+    #[test]
+    fn bind_groups_take_an_array_of_four_textures() {
+        use webgpu::HostGpuDevice;
+
+        let Some(gpu) =
+            test_gpu::TestGpu::with_features(wgpu_types::Features::TEXTURE_BINDING_ARRAY)
+        else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let texture_views = (0..4)
+            .map(|_| {
+                let texture = gpu.create_texture(
+                    wgpu_types::TextureFormat::Rgba8Unorm,
+                    4,
+                    4,
+                    wgpu_types::TextureUsages::TEXTURE_BINDING,
+                );
+                let texture_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+                    texture,
+                    &Default::default(),
+                    None,
+                ))
+                .unwrap();
+                view.table().push(texture_view).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let mut host = view.host();
+        let layout = host
+            .create_bind_group_layout(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupLayoutDescriptor {
+                    entries: vec![texture_array_entry(4)],
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.create_bind_group(
+            device,
+            webgpu::GpuBindGroupDescriptor {
+                layout,
+                entries: vec![webgpu::GpuBindGroupEntry {
+                    binding: 0,
+                    resource: webgpu::GpuBindingResource::GpuTextureViewArray(texture_views),
+                }],
+                label: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn binding_arrays_need_the_texture_binding_array_feature() {
+        use webgpu::HostGpuDevice;
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let error = view
+            .host()
+            .create_bind_group_layout(
+                device,
+                webgpu::GpuBindGroupLayoutDescriptor {
+                    entries: vec![texture_array_entry(4)],
+                    label: None,
+                },
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("texture-binding-array"));
+    }
+
+    /// Binds an r32float texture where the layout takes a filterable one.
+    fn bind_r32float_as_filterable(gpu: &test_gpu::TestGpu) -> wasmtime::Result<()> {
+        use webgpu::HostGpuDevice;
+
+        let mut view = test_gpu::TestView::new(gpu);
+        let (device, _) = view.device(gpu);
+        let texture = gpu.create_texture(
+            wgpu_types::TextureFormat::R32Float,
+            4,
+            4,
+            wgpu_types::TextureUsages::TEXTURE_BINDING,
+        );
+        let texture_view = core_result(gpu.instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let texture_view = view.table().push(texture_view).unwrap();
+        let mut host = view.host();
+        let layout = host
+            .create_bind_group_layout(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBindGroupLayoutDescriptor {
+                    entries: vec![webgpu::GpuBindGroupLayoutEntry {
+                        count: None,
+                        ..texture_array_entry(1)
+                    }],
+                    label: None,
+                },
+            )
+            .unwrap();
+        host.create_bind_group(
+            device,
+            webgpu::GpuBindGroupDescriptor {
+                layout,
+                entries: vec![webgpu::GpuBindGroupEntry {
+                    binding: 0,
+                    resource: webgpu::GpuBindingResource::GpuTextureView(texture_view),
+                }],
+                label: None,
+            },
+        )
+        .map(drop)
+    }
+
+    #[test]
+    fn filtering_float32_textures_needs_float32_filterable() {
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let error = bind_r32float_as_filterable(&gpu).unwrap_err();
+        assert!(error.to_string().contains("float32-filterable"), "{error}");
+
+        let Some(gpu) = test_gpu::TestGpu::with_features(wgpu_types::Features::FLOAT32_FILTERABLE)
+        else {
+            return;
+        };
+        bind_r32float_as_filterable(&gpu).unwrap();
+    }
+
+    #[test]
+    fn labeled_resources_are_reported_with_their_names() {
+        use webgpu::{HostGpuCommandEncoder, HostGpuDevice};
+        use wgpu_core::hal_api::HalApi;
+
+        let Some(gpu) = test_gpu::TestGpu::new() else {
+            return;
+        };
+        let mut view = test_gpu::TestView::new(&gpu);
+        let (device, _) = view.device(&gpu);
+        let mut host = view.host();
+        let buffer = host
+            .create_buffer(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuBufferDescriptor {
+                    size: 16,
+                    usage: wgpu_types::BufferUsages::COPY_DST.bits(),
+                    mapped_at_creation: None,
+                    clear_on_create: None,
+                    label: Some("vertices".to_string()),
+                },
+            )
+            .unwrap();
+        let texture = host
+            .create_texture(
+                Resource::new_borrow(device.rep()),
+                webgpu::GpuTextureDescriptor {
+                    size: webgpu::GpuExtent3D::ListGpuIntegerCoordinate(vec![4, 4]),
+                    mip_level_count: None,
+                    sample_count: None,
+                    dimension: webgpu::GpuTextureDimension::TwoD,
+                    format: webgpu::GpuTextureFormat::Rgba8unorm,
+                    usage: wgpu_types::TextureUsages::TEXTURE_BINDING.bits(),
+                    view_formats: None,
+                    label: Some("albedo".to_string()),
+                },
+            )
+            .unwrap();
+        let sampler = host
+            .create_sampler(
+                Resource::new_borrow(device.rep()),
+                Some(webgpu::GpuSamplerDescriptor {
+                    address_mode_u: None,
+                    address_mode_v: None,
+                    address_mode_w: None,
+                    mag_filter: None,
+                    min_filter: None,
+                    mipmap_filter: None,
+                    lod_min_clamp: None,
+                    lod_max_clamp: None,
+                    compare: None,
+                    max_anisotropy: None,
+                    label: Some("linear".to_string()),
+                }),
+            )
+            .unwrap();
+        let encoder = host.create_command_encoder(
+            device,
+            Some(webgpu::GpuCommandEncoderDescriptor {
+                label: Some("frame".to_string()),
+            }),
+        );
+        let command_buffer = host.finish(encoder, None).unwrap();
+
+        let table = host.0.table();
+        let buffer = table.get(&buffer).unwrap().buffer;
+        let texture = table.get(&texture).unwrap().id;
+        let sampler = *table.get(&sampler).unwrap();
+        let command_buffer = *table.get(&command_buffer).unwrap();
+        let instance = &gpu.instance;
+        assert_eq!(instance.buffer_label::<crate::Backend>(buffer), "vertices");
+        assert_eq!(instance.texture_label::<crate::Backend>(texture), "albedo");
+        assert_eq!(instance.sampler_label::<crate::Backend>(sampler), "linear");
+        assert_eq!(
+            instance.command_buffer_label::<crate::Backend>(command_buffer),
+            "frame"
+        );
+        let report = instance.generate_report();
+        let hub = report.hub_report(crate::Backend::VARIANT);
+        assert_eq!(hub.buffers.num_kept_from_user, 1);
+        assert_eq!(hub.textures.num_kept_from_user, 1);
+        assert_eq!(hub.samplers.num_kept_from_user, 1);
+    }
+
+    #[test]
+    fn devices_are_created_on_the_adapter_picked_by_index() {
+        let instance = wgpu_core::global::Global::new(
+            "webgpu-test",
+            wgpu_types::InstanceDescriptor {
+                backends: primary_backends(),
+                ..Default::default()
+            },
+        );
+        let adapters = instance.enumerate_adapters(wgpu_core::instance::AdapterInputs::Mask(
+            primary_backends(),
+            |_| None,
+        ));
+        let Some(last) = adapters.len().checked_sub(1) else {
+            eprintln!("skipping: no adapter");
+            return;
+        };
+        let adapter = find_adapter(&instance, &last.to_string()).unwrap();
+        assert_eq!(adapter, adapters[last]);
+        let info = instance
+            .adapter_get_info::<crate::Backend>(adapter)
+            .unwrap();
+        assert_eq!(
+            info.backend,
+            <Backend as wgpu_core::hal_api::HalApi>::VARIANT
+        );
+        core_results_2(instance.adapter_request_device::<crate::Backend>(
+            adapter,
+            &Default::default(),
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use wgpu_core::id::{BufferId, TextureId};
+
+/// Caps how much memory the guest's buffers and textures may take up.
+///
+/// wgpu-core 0.20 has no memory hints or budgets of its own, so this is
+/// tracked here from the size of each resource the guest creates. Creating a
+/// resource that doesn't fit fails with an out of memory error.
+pub struct MemoryBudget {
+    max: u64,
+    allocations: Mutex<Allocations>,
+}
+
+#[derive(Default)]
+struct Allocations {
+    used: u64,
+    sizes: HashMap<Allocation, u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Allocation {
+    Buffer(BufferId),
+    Texture(TextureId),
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max: max_bytes,
+            allocations: Default::default(),
+        }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Bytes taken up by the buffers and textures the guest still holds.
+    pub fn used(&self) -> u64 {
+        self.allocations.lock().unwrap().used
+    }
+
+    /// Takes `size` bytes out of the budget, or fails if there aren't enough left.
+    /// Call `allocated` once the resource exists, or `release_reserved` if creating it failed.
+    pub(crate) fn reserve(&self, size: u64) -> Result<(), String> {
+        let mut allocations = self.allocations.lock().unwrap();
+        match allocations.used.checked_add(size) {
+            Some(used) if used <= self.max => {
+                allocations.used = used;
+                Ok(())
+            }
+            _ => Err(format!(
+                "out of memory: {size} bytes don't fit in the memory budget of {} bytes, {} are in use",
+                self.max, allocations.used
+            )),
+        }
+    }
+
+    pub(crate) fn release_reserved(&self, size: u64) {
+        self.allocations.lock().unwrap().used -= size;
+    }
+
+    pub(crate) fn allocated(&self, allocation: Allocation, size: u64) {
+        self.allocations
+            .lock()
+            .unwrap()
+            .sizes
+            .insert(allocation, size);
+    }
+
+    /// Gives back the memory of a resource the guest dropped. Resources that
+    /// weren't counted, like surface textures, are ignored.
+    pub(crate) fn free(&self, allocation: Allocation) {
+        let mut allocations = self.allocations.lock().unwrap();
+        if let Some(size) = allocations.sizes.remove(&allocation) {
+            allocations.used -= size;
+        }
+    }
+}
+
+/// Bytes a texture takes up, counting every mip level and sample.
+pub(crate) fn texture_size(descriptor: &wgpu_core::resource::TextureDescriptor) -> u64 {
+    let format = descriptor.format;
+    let (block_width, block_height) = format.block_dimensions();
+    // Combined depth-stencil formats have no single block size, 8 bytes covers all of them.
+    let block_size = format.block_copy_size(None).unwrap_or(8) as u64;
+    (0..descriptor.mip_level_count)
+        .filter_map(|level| descriptor.mip_level_size(level))
+        .map(|size| {
+            size.width.div_ceil(block_width) as u64
+                * size.height.div_ceil(block_height) as u64
+                * size.depth_or_array_layers as u64
+                * block_size
+        })
+        .sum::<u64>()
+        * descriptor.sample_count as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(index: u32) -> Allocation {
+        Allocation::Buffer(BufferId::zip(index, 1, wgpu_types::Backend::Empty))
+    }
+
+    fn texture(
+        format: wgpu_types::TextureFormat,
+        width: u32,
+        height: u32,
+        mip_level_count: u32,
+        sample_count: u32,
+    ) -> wgpu_core::resource::TextureDescriptor<'static> {
+        wgpu_types::TextureDescriptor {
+            label: None,
+            size: wgpu_types::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count,
+            dimension: wgpu_types::TextureDimension::D2,
+            format,
+            usage: wgpu_types::TextureUsages::TEXTURE_BINDING,
+            view_formats: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reserving_past_the_budget_fails() {
+        let budget = MemoryBudget::new(100);
+        budget.reserve(60).unwrap();
+        assert!(budget.reserve(41).is_err());
+        budget.reserve(40).unwrap();
+        assert_eq!(budget.used(), 100);
+    }
+
+    #[test]
+    fn freeing_gives_memory_back() {
+        let budget = MemoryBudget::new(100);
+        budget.reserve(60).unwrap();
+        budget.allocated(buffer(0), 60);
+        budget.reserve(10).unwrap();
+        budget.release_reserved(10);
+        budget.free(buffer(0));
+        assert_eq!(budget.used(), 0);
+        // Freeing twice, or something never counted, changes nothing.
+        budget.free(buffer(0));
+        budget.free(buffer(1));
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn texture_size_counts_mips_and_samples() {
+        let format = wgpu_types::TextureFormat::Rgba8Unorm;
+        assert_eq!(texture_size(&texture(format, 4, 4, 1, 1)), 64);
+        // 4x4, 2x2 and 1x1.
+        assert_eq!(texture_size(&texture(format, 4, 4, 3, 1)), 84);
+        assert_eq!(texture_size(&texture(format, 4, 4, 1, 4)), 256);
+    }
+
+    #[test]
+    fn texture_size_rounds_up_to_whole_blocks() {
+        // 4x4 blocks of 8 bytes.
+        let format = wgpu_types::TextureFormat::Bc1RgbaUnorm;
+        assert_eq!(texture_size(&texture(format, 5, 5, 1, 1)), 32);
+    }
+}
@@ -0,0 +1,117 @@
+//! Multisampled rendering to a surface.
+//!
+//! With a sample count above 1 set, the guest draws into a multisampled texture
+//! sized like the surface (or its render scale target), which gets resolved
+//! into it on present.
+
+use std::borrow::Cow;
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{DeviceId, QueueId, TextureId, TextureViewId};
+
+use crate::core_result;
+
+/// The texture the guest renders into while multisampling.
+pub(crate) struct MsaaTarget {
+    pub(crate) texture: TextureId,
+    view: TextureViewId,
+    pub(crate) sample_count: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl MsaaTarget {
+    pub(crate) fn new(
+        instance: &Global,
+        device: DeviceId,
+        format: wgpu_types::TextureFormat,
+        sample_count: u32,
+        width: u32,
+        height: u32,
+    ) -> wasmtime::Result<Self> {
+        let texture = core_result(instance.device_create_texture::<crate::Backend>(
+            device,
+            &wgpu_types::TextureDescriptor {
+                label: Some("msaa target".into()),
+                size: wgpu_types::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu_types::TextureDimension::D2,
+                format,
+                usage: wgpu_types::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: vec![],
+            },
+            None,
+        ))?;
+        let view = core_result(instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))?;
+        Ok(Self {
+            texture,
+            view,
+            sample_count,
+            width,
+            height,
+        })
+    }
+
+    /// Resolves the target into `destination`, a single sampled texture of the
+    /// same size and format.
+    pub(crate) fn resolve(
+        &self,
+        instance: &Global,
+        device: DeviceId,
+        queue: QueueId,
+        destination: TextureId,
+    ) -> wasmtime::Result<()> {
+        let resolve_view = core_result(instance.texture_create_view::<crate::Backend>(
+            destination,
+            &Default::default(),
+            None,
+        ))?;
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            device,
+            &Default::default(),
+            None,
+        ))?;
+        let render_pass = wgpu_core::command::RenderPass::new(
+            encoder,
+            &wgpu_core::command::RenderPassDescriptor {
+                label: Some("msaa resolve".into()),
+                color_attachments: Cow::Borrowed(&[Some(
+                    wgpu_core::command::RenderPassColorAttachment {
+                        view: self.view,
+                        resolve_target: Some(resolve_view),
+                        channel: wgpu_core::command::PassChannel {
+                            load_op: wgpu_core::command::LoadOp::Load,
+                            store_op: wgpu_core::command::StoreOp::Store,
+                            clear_value: wgpu_types::Color::BLACK,
+                            read_only: false,
+                        },
+                    },
+                )]),
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        );
+        instance.command_encoder_run_render_pass::<crate::Backend>(encoder, &render_pass)?;
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )?;
+        instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+        let _ = instance.texture_view_drop::<crate::Backend>(resolve_view, false);
+        Ok(())
+    }
+
+    pub(crate) fn destroy(self, instance: &Global) {
+        let _ = instance.texture_view_drop::<crate::Backend>(self.view, false);
+        instance.texture_drop::<crate::Backend>(self.texture, false);
+    }
+}
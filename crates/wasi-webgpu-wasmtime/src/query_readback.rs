@@ -0,0 +1,211 @@
+//! Reading query results back for `read-query-results`.
+
+use std::sync::{Arc, Mutex};
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{BufferId, DeviceId, QuerySetId, QueueId};
+
+use crate::wasi::webgpu::webgpu::GpuQueryType;
+use crate::{core_result, QuerySet};
+
+const QUERY_SIZE: u64 = wgpu_types::QUERY_SIZE as u64;
+
+pub(crate) fn validate_query_range(
+    query_set: &QuerySet,
+    first: u32,
+    count: u32,
+) -> Result<(), String> {
+    if count == 0 {
+        return Err("query count must be greater than 0".to_string());
+    }
+    match first.checked_add(count) {
+        Some(end) if end <= query_set.count => Ok(()),
+        _ => Err(format!(
+            "queries {first} to {} are out of the query set's {} queries",
+            first as u64 + count as u64,
+            query_set.count
+        )),
+    }
+}
+
+/// Resolves `count` queries of `query_set` from `first` on, and waits for the
+/// GPU to get their values. Timestamps are turned into nanoseconds.
+pub(crate) fn read_queries(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    query_set: QuerySet,
+    first: u32,
+    count: u32,
+) -> wasmtime::Result<Vec<u64>> {
+    let size = count as u64 * QUERY_SIZE;
+    // Queries resolve into a buffer that can't be mapped, so they're copied
+    // on into one that can.
+    let resolved = core_result(instance.device_create_buffer::<crate::Backend>(
+        device,
+        &wgpu_types::BufferDescriptor {
+            label: Some("query resolve".into()),
+            size,
+            usage: wgpu_types::BufferUsages::QUERY_RESOLVE | wgpu_types::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        },
+        None,
+    ))?;
+    let readback = core_result(instance.device_create_buffer::<crate::Backend>(
+        device,
+        &wgpu_types::BufferDescriptor {
+            label: Some("query readback".into()),
+            size,
+            usage: wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        },
+        None,
+    ));
+    let values = readback.map_err(Into::into).and_then(|readback| {
+        let values = read_into(
+            instance,
+            device,
+            queue,
+            query_set.id,
+            first,
+            count,
+            resolved,
+            readback,
+        );
+        instance.buffer_drop::<crate::Backend>(readback, false);
+        values
+    });
+    instance.buffer_drop::<crate::Backend>(resolved, false);
+    let mut values = values?;
+
+    if query_set.type_ == GpuQueryType::Timestamp {
+        let period = instance.queue_get_timestamp_period::<crate::Backend>(queue)?;
+        for value in &mut values {
+            *value = (*value as f64 * period as f64) as u64;
+        }
+    }
+    Ok(values)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_into(
+    instance: &Global,
+    device: DeviceId,
+    queue: QueueId,
+    query_set: QuerySetId,
+    first: u32,
+    count: u32,
+    resolved: BufferId,
+    readback: BufferId,
+) -> wasmtime::Result<Vec<u64>> {
+    let size = count as u64 * QUERY_SIZE;
+    let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+        device,
+        &Default::default(),
+        None,
+    ))?;
+    instance.command_encoder_resolve_query_set::<crate::Backend>(
+        encoder, query_set, first, count, resolved, 0,
+    )?;
+    instance.command_encoder_copy_buffer_to_buffer::<crate::Backend>(
+        encoder, resolved, 0, readback, 0, size,
+    )?;
+    let command_buffer = core_result(
+        instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+    )?;
+    instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+
+    let mapped = Arc::new(Mutex::new(None));
+    let op = wgpu_core::resource::BufferMapOperation {
+        host: wgpu_core::device::HostMap::Read,
+        callback: Some(wgpu_core::resource::BufferMapCallback::from_rust(Box::new(
+            {
+                let mapped = Arc::clone(&mapped);
+                move |result| *mapped.lock().unwrap() = Some(result)
+            },
+        ))),
+    };
+    instance.buffer_map_async::<crate::Backend>(readback, 0, Some(size), op)?;
+    instance.device_poll::<crate::Backend>(device, wgpu_types::Maintain::wait())?;
+    let mapped = mapped.lock().unwrap().take();
+    mapped.ok_or_else(|| wasmtime::Error::msg("query readback wasn't mapped"))??;
+
+    let (ptr, len) = instance.buffer_get_mapped_range::<crate::Backend>(readback, 0, Some(size))?;
+    // Safety: the buffer stays mapped, and isn't written to, until the unmap below.
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len as usize) };
+    let values = bytes
+        .chunks_exact(QUERY_SIZE as usize)
+        .map(|value| u64::from_le_bytes(value.try_into().unwrap()))
+        .collect();
+    instance.buffer_unmap::<crate::Backend>(readback)?;
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_gpu::TestGpu;
+    use crate::Freed;
+
+    #[test]
+    fn two_timestamps_read_back_in_order() {
+        let Some(gpu) = TestGpu::with_features(wgpu_types::Features::TIMESTAMP_QUERY) else {
+            return;
+        };
+        let instance = &gpu.instance;
+        let id = core_result(instance.device_create_query_set::<crate::Backend>(
+            gpu.device,
+            &wgpu_types::QuerySetDescriptor {
+                label: None,
+                ty: wgpu_types::QueryType::Timestamp,
+                count: 2,
+            },
+            None,
+        ))
+        .unwrap();
+        let query_set = QuerySet {
+            id,
+            type_: GpuQueryType::Timestamp,
+            count: 2,
+            freed: Arc::new(Freed::new(
+                Arc::clone(instance),
+                id,
+                Global::query_set_drop::<crate::Backend>,
+            )),
+        };
+
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            gpu.device,
+            &Default::default(),
+            None,
+        ))
+        .unwrap();
+        let pass = wgpu_core::command::ComputePass::new(
+            encoder,
+            &wgpu_core::command::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: Some(&wgpu_core::command::ComputePassTimestampWrites {
+                    query_set: id,
+                    beginning_of_pass_write_index: Some(0),
+                    end_of_pass_write_index: Some(1),
+                }),
+            },
+        );
+        instance
+            .command_encoder_run_compute_pass::<crate::Backend>(encoder, &pass)
+            .unwrap();
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )
+        .unwrap();
+        instance
+            .queue_submit::<crate::Backend>(gpu.queue, &[command_buffer])
+            .unwrap();
+
+        let values = read_queries(instance, gpu.device, gpu.queue, query_set, 0, 2).unwrap();
+        assert_eq!(values.len(), 2);
+        assert!(values[0] > 0);
+        // An empty pass can start and end on the same tick.
+        assert!(values[1] >= values[0]);
+    }
+}
@@ -0,0 +1,401 @@
+//! Renders a surface's frames at a different resolution than its window.
+//!
+//! With a render scale set, the guest draws into an intermediate texture
+//! sized `window * scale`, which gets upscaled (or downscaled) onto the
+//! swapchain texture on present.
+
+use std::borrow::Cow;
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{
+    BindGroupId, BindGroupLayoutId, CommandEncoderId, DeviceId, PipelineLayoutId, QueueId,
+    RenderPipelineId, SamplerId, ShaderModuleId, SurfaceId, TextureId, TextureViewId,
+};
+
+use crate::core_result;
+
+const BLIT_SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// A single triangle covering the whole target.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source, source_sampler, in.uv);
+}
+"#;
+
+/// Pipelines that copy a texture onto another with linear filtering, one per
+/// target format.
+pub(crate) struct Blitter {
+    bind_group_layout: BindGroupLayoutId,
+    sampler: SamplerId,
+    pipelines: Vec<(wgpu_types::TextureFormat, RenderPipelineId)>,
+}
+
+impl Blitter {
+    pub(crate) fn new(instance: &Global, device: DeviceId) -> wasmtime::Result<Self> {
+        let bind_group_layout =
+            core_result(instance.device_create_bind_group_layout::<crate::Backend>(
+                device,
+                &wgpu_core::binding_model::BindGroupLayoutDescriptor {
+                    label: Some("render scale blit".into()),
+                    entries: Cow::Owned(vec![
+                        wgpu_types::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu_types::ShaderStages::FRAGMENT,
+                            ty: wgpu_types::BindingType::Texture {
+                                sample_type: wgpu_types::TextureSampleType::Float {
+                                    filterable: true,
+                                },
+                                view_dimension: wgpu_types::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu_types::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu_types::ShaderStages::FRAGMENT,
+                            ty: wgpu_types::BindingType::Sampler(
+                                wgpu_types::SamplerBindingType::Filtering,
+                            ),
+                            count: None,
+                        },
+                    ]),
+                },
+                None,
+            ))?;
+        let sampler = core_result(instance.device_create_sampler::<crate::Backend>(
+            device,
+            &wgpu_core::resource::SamplerDescriptor {
+                label: Some("render scale blit".into()),
+                address_modes: [wgpu_types::AddressMode::ClampToEdge; 3],
+                mag_filter: wgpu_types::FilterMode::Linear,
+                min_filter: wgpu_types::FilterMode::Linear,
+                mipmap_filter: wgpu_types::FilterMode::Nearest,
+                lod_min_clamp: 0.0,
+                lod_max_clamp: 32.0,
+                compare: None,
+                anisotropy_clamp: 1,
+                border_color: None,
+            },
+            None,
+        ));
+        let sampler = match sampler {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                instance.bind_group_layout_drop::<crate::Backend>(bind_group_layout);
+                return Err(e.into());
+            }
+        };
+        Ok(Self {
+            bind_group_layout,
+            sampler,
+            pipelines: Vec::new(),
+        })
+    }
+
+    /// The pipeline for drawing onto textures of `format`, created on first use.
+    fn pipeline(
+        &mut self,
+        instance: &Global,
+        device: DeviceId,
+        format: wgpu_types::TextureFormat,
+    ) -> wasmtime::Result<RenderPipelineId> {
+        if let Some((_, pipeline)) = self.pipelines.iter().find(|(f, _)| *f == format) {
+            return Ok(*pipeline);
+        }
+        // The pipeline keeps what it needs of the shader and layout alive, so
+        // they're dropped as soon as it's created.
+        let shader = core_result(instance.device_create_shader_module::<crate::Backend>(
+            device,
+            &wgpu_core::pipeline::ShaderModuleDescriptor {
+                label: Some("render scale blit".into()),
+                shader_bound_checks: Default::default(),
+            },
+            wgpu_core::pipeline::ShaderModuleSource::Wgsl(Cow::Borrowed(BLIT_SHADER)),
+            None,
+        ))?;
+        let pipeline_layout =
+            core_result(instance.device_create_pipeline_layout::<crate::Backend>(
+                device,
+                &wgpu_core::binding_model::PipelineLayoutDescriptor {
+                    label: Some("render scale blit".into()),
+                    bind_group_layouts: Cow::Owned(vec![self.bind_group_layout]),
+                    push_constant_ranges: Cow::Borrowed(&[]),
+                },
+                None,
+            ));
+        let pipeline = match pipeline_layout {
+            Ok(pipeline_layout) => {
+                let pipeline = create_pipeline(instance, device, shader, pipeline_layout, format);
+                instance.pipeline_layout_drop::<crate::Backend>(pipeline_layout);
+                pipeline
+            }
+            Err(e) => Err(e.into()),
+        };
+        instance.shader_module_drop::<crate::Backend>(shader);
+        let pipeline = pipeline?;
+        self.pipelines.push((format, pipeline));
+        Ok(pipeline)
+    }
+
+    pub(crate) fn destroy(self, instance: &Global) {
+        for (_, pipeline) in self.pipelines {
+            instance.render_pipeline_drop::<crate::Backend>(pipeline);
+        }
+        instance.sampler_drop::<crate::Backend>(self.sampler);
+        instance.bind_group_layout_drop::<crate::Backend>(self.bind_group_layout);
+    }
+}
+
+fn create_pipeline(
+    instance: &Global,
+    device: DeviceId,
+    shader: ShaderModuleId,
+    pipeline_layout: PipelineLayoutId,
+    format: wgpu_types::TextureFormat,
+) -> wasmtime::Result<RenderPipelineId> {
+    let stage = |entry_point: &'static str| wgpu_core::pipeline::ProgrammableStageDescriptor {
+        module: shader,
+        entry_point: Some(entry_point.into()),
+        constants: Default::default(),
+        zero_initialize_workgroup_memory: true,
+    };
+    let pipeline = core_result(instance.device_create_render_pipeline::<crate::Backend>(
+        device,
+        &wgpu_core::pipeline::RenderPipelineDescriptor {
+            label: Some("render scale blit".into()),
+            layout: Some(pipeline_layout),
+            vertex: wgpu_core::pipeline::VertexState {
+                stage: stage("vs_main"),
+                buffers: Cow::Borrowed(&[]),
+            },
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(wgpu_core::pipeline::FragmentState {
+                stage: stage("fs_main"),
+                targets: Cow::Owned(vec![Some(wgpu_types::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu_types::ColorWrites::ALL,
+                })]),
+            }),
+            multiview: None,
+        },
+        None,
+        None,
+    ))?;
+    Ok(pipeline)
+}
+
+/// The size frames are rendered at for a `width` by `height` window at
+/// `scale`, never smaller than 1x1.
+pub(crate) fn scaled_size(width: u32, height: u32, scale: f64) -> (u32, u32) {
+    let scaled = |length: u32| ((length as f64 * scale) as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// The texture the guest renders into while a render scale is set.
+pub(crate) struct ScaledTarget {
+    pub(crate) texture: TextureId,
+    view: TextureViewId,
+    bind_group: BindGroupId,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl ScaledTarget {
+    pub(crate) fn new(
+        instance: &Global,
+        device: DeviceId,
+        blitter: &Blitter,
+        format: wgpu_types::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> wasmtime::Result<Self> {
+        let texture = core_result(instance.device_create_texture::<crate::Backend>(
+            device,
+            &wgpu_types::TextureDescriptor {
+                label: Some("render scale target".into()),
+                size: wgpu_types::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu_types::TextureDimension::D2,
+                format,
+                usage: wgpu_types::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu_types::TextureUsages::TEXTURE_BINDING
+                    | wgpu_types::TextureUsages::COPY_SRC,
+                view_formats: vec![],
+            },
+            None,
+        ))?;
+        let view = core_result(instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))?;
+        let bind_group = core_result(instance.device_create_bind_group::<crate::Backend>(
+            device,
+            &wgpu_core::binding_model::BindGroupDescriptor {
+                label: Some("render scale blit".into()),
+                layout: blitter.bind_group_layout,
+                entries: Cow::Owned(vec![
+                    wgpu_core::binding_model::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu_core::binding_model::BindingResource::TextureView(view),
+                    },
+                    wgpu_core::binding_model::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu_core::binding_model::BindingResource::Sampler(
+                            blitter.sampler,
+                        ),
+                    },
+                ]),
+            },
+            None,
+        ))?;
+        Ok(Self {
+            texture,
+            view,
+            bind_group,
+            width,
+            height,
+        })
+    }
+
+    /// Draws the target onto the surface's current texture and presents it.
+    /// Returns the surface's status; nothing is presented if it had no texture.
+    pub(crate) fn present(
+        &self,
+        instance: &Global,
+        device: DeviceId,
+        queue: QueueId,
+        surface: SurfaceId,
+        format: wgpu_types::TextureFormat,
+        blitter: &mut Blitter,
+    ) -> wasmtime::Result<wgpu_types::SurfaceStatus> {
+        let output = instance.surface_get_current_texture::<crate::Backend>(surface, None)?;
+        let Some(texture) = output.texture_id else {
+            return Ok(output.status);
+        };
+        let view = core_result(instance.texture_create_view::<crate::Backend>(
+            texture,
+            &Default::default(),
+            None,
+        ))?;
+        let encoder = core_result(instance.device_create_command_encoder::<crate::Backend>(
+            device,
+            &Default::default(),
+            None,
+        ))?;
+        self.encode_blit(instance, device, encoder, view, format, blitter)?;
+        let command_buffer = core_result(
+            instance.command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )?;
+        instance.queue_submit::<crate::Backend>(queue, &[command_buffer])?;
+        let status = instance.surface_present::<crate::Backend>(surface)?;
+        let _ = instance.texture_view_drop::<crate::Backend>(view, false);
+        Ok(status)
+    }
+
+    /// Records a pass drawing the target onto `view`, a view of a `format` texture.
+    pub(crate) fn encode_blit(
+        &self,
+        instance: &Global,
+        device: DeviceId,
+        encoder: CommandEncoderId,
+        view: TextureViewId,
+        format: wgpu_types::TextureFormat,
+        blitter: &mut Blitter,
+    ) -> wasmtime::Result<()> {
+        let pipeline = blitter.pipeline(instance, device, format)?;
+        let mut render_pass = wgpu_core::command::RenderPass::new(
+            encoder,
+            &wgpu_core::command::RenderPassDescriptor {
+                label: Some("render scale blit".into()),
+                color_attachments: Cow::Borrowed(&[Some(
+                    wgpu_core::command::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        channel: wgpu_core::command::PassChannel {
+                            load_op: wgpu_core::command::LoadOp::Clear,
+                            store_op: wgpu_core::command::StoreOp::Store,
+                            clear_value: wgpu_types::Color::BLACK,
+                            read_only: false,
+                        },
+                    },
+                )]),
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            },
+        );
+        wgpu_core::command::render_commands::wgpu_render_pass_set_pipeline(
+            &mut render_pass,
+            pipeline,
+        );
+        wgpu_core::command::render_commands::wgpu_render_pass_set_bind_group(
+            &mut render_pass,
+            0,
+            self.bind_group,
+            &[],
+        );
+        wgpu_core::command::render_commands::wgpu_render_pass_draw(&mut render_pass, 3, 1, 0, 0);
+        instance.command_encoder_run_render_pass::<crate::Backend>(encoder, &render_pass)?;
+        Ok(())
+    }
+
+    pub(crate) fn destroy(self, instance: &Global) {
+        instance.bind_group_drop::<crate::Backend>(self.bind_group);
+        let _ = instance.texture_view_drop::<crate::Backend>(self.view, false);
+        instance.texture_drop::<crate::Backend>(self.texture, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scaled_size;
+
+    #[test]
+    fn unscaled_size_is_the_window_size() {
+        assert_eq!(scaled_size(800, 600, 1.0), (800, 600));
+    }
+
+    #[test]
+    fn sizes_scale_both_ways() {
+        assert_eq!(scaled_size(800, 600, 0.5), (400, 300));
+        assert_eq!(scaled_size(800, 600, 2.0), (1600, 1200));
+    }
+
+    #[test]
+    fn fractional_sizes_round_down() {
+        assert_eq!(scaled_size(801, 601, 0.5), (400, 300));
+        assert_eq!(scaled_size(3, 3, 1.5), (4, 4));
+    }
+
+    #[test]
+    fn sizes_never_reach_zero() {
+        assert_eq!(scaled_size(5, 1, 0.1), (1, 1));
+        assert_eq!(scaled_size(0, 0, 1.0), (1, 1));
+    }
+}
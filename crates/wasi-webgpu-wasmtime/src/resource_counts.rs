@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use wgpu_core::id::TextureId;
+
+/// The kinds of resources `ResourceCounts` keeps track of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    RenderPipeline,
+    BindGroup,
+}
+
+/// Counts the guest's live buffers, textures, render pipelines and bind groups,
+/// so tests can check that dropping them frees them.
+///
+/// A resource is live from when the guest gets its handle until its wgpu id is
+/// freed. That's when the guest drops it, or for buffers, render pipelines and
+/// bind groups, once the passes and remote buffers using it are done too.
+/// Textures of a graphics context, gotten through `from-graphics-buffer`, aren't
+/// counted.
+#[derive(Default)]
+pub struct ResourceCounts {
+    live: [AtomicU64; 4],
+    // Graphics context textures have the same resource type, so the ones
+    // counted are remembered to tell them apart when dropped.
+    textures: Mutex<HashSet<TextureId>>,
+    on_drop: Option<Box<dyn Fn(ResourceKind) + Send + Sync>>,
+}
+
+impl ResourceCounts {
+    /// Also calls `on_drop` with the kind of each resource dropped.
+    pub fn with_drop_callback(on_drop: impl Fn(ResourceKind) + Send + Sync + 'static) -> Self {
+        Self {
+            live: Default::default(),
+            textures: Default::default(),
+            on_drop: Some(Box::new(on_drop)),
+        }
+    }
+
+    /// How many resources of `kind` the guest holds.
+    pub fn live(&self, kind: ResourceKind) -> u64 {
+        self.live[kind as usize].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn created(&self, kind: ResourceKind) {
+        self.live[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dropped(&self, kind: ResourceKind) {
+        self.live[kind as usize].fetch_sub(1, Ordering::Relaxed);
+        if let Some(on_drop) = &self.on_drop {
+            on_drop(kind);
+        }
+    }
+
+    pub(crate) fn texture_created(&self, texture: TextureId) {
+        self.textures.lock().unwrap().insert(texture);
+        self.created(ResourceKind::Texture);
+    }
+
+    pub(crate) fn texture_dropped(&self, texture: TextureId) {
+        if self.textures.lock().unwrap().remove(&texture) {
+            self.dropped(ResourceKind::Texture);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn counts_live_resources_and_calls_back_on_drop() {
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let counts = ResourceCounts::with_drop_callback({
+            let dropped = Arc::clone(&dropped);
+            move |kind| dropped.lock().unwrap().push(kind)
+        });
+        counts.created(ResourceKind::Buffer);
+        counts.created(ResourceKind::Buffer);
+        counts.created(ResourceKind::BindGroup);
+        counts.dropped(ResourceKind::Buffer);
+        assert_eq!(counts.live(ResourceKind::Buffer), 1);
+        assert_eq!(counts.live(ResourceKind::BindGroup), 1);
+        assert_eq!(*dropped.lock().unwrap(), [ResourceKind::Buffer]);
+    }
+
+    #[test]
+    fn only_counted_textures_are_dropped() {
+        let counts = ResourceCounts::default();
+        let texture = |index| TextureId::zip(index, 1, wgpu_types::Backend::Empty);
+        counts.texture_created(texture(0));
+        // A graphics context texture, never counted.
+        counts.texture_dropped(texture(1));
+        assert_eq!(counts.live(ResourceKind::Texture), 1);
+        counts.texture_dropped(texture(0));
+        assert_eq!(counts.live(ResourceKind::Texture), 0);
+    }
+}
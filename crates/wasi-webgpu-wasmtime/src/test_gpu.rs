@@ -0,0 +1,419 @@
+//! A device for tests that need a GPU, and a window for those that present.
+//! Machines without an adapter for this platform's backend or a display, like
+//! most CI runners, skip those tests. Canvases for input need neither.
+
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, OnceLock};
+
+use wasmtime::component::Resource;
+use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+use wgpu_core::global::Global;
+use wgpu_core::id::{AdapterId, BufferId, CommandEncoderId, DeviceId, QueueId, TextureId};
+
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+use wasi_graphics_context_wasmtime::DisplayApi;
+use wasi_mini_canvas_wasmtime::{
+    CanvasWindow, MiniCanvas, MiniCanvasArc, MiniCanvasDesc, WasiMiniCanvasView,
+};
+use winit::window::Window;
+
+use crate::{
+    buffer_readback, core_result, core_results_2, Device, MainThreadSpawner, TextureDescriptors,
+    WasiWebGpuImpl, WasiWebGpuView,
+};
+
+pub(crate) struct TestGpu {
+    pub(crate) instance: Arc<Global>,
+    pub(crate) adapter: AdapterId,
+    pub(crate) device: DeviceId,
+    pub(crate) queue: QueueId,
+}
+
+impl TestGpu {
+    /// A device with `features`, or `None` if there's no adapter that has them.
+    pub(crate) fn with_features(features: wgpu_types::Features) -> Option<Self> {
+        let instance = Global::new(
+            "webgpu-test",
+            wgpu_types::InstanceDescriptor {
+                backends: crate::primary_backends(),
+                ..Default::default()
+            },
+        );
+        let Ok(adapter) = instance.request_adapter(
+            &Default::default(),
+            wgpu_core::instance::AdapterInputs::Mask(crate::primary_backends(), |_| None),
+        ) else {
+            eprintln!("skipping: no adapter");
+            return None;
+        };
+        let adapter_features = instance
+            .adapter_features::<crate::Backend>(adapter)
+            .unwrap();
+        if !adapter_features.contains(features) {
+            eprintln!("skipping: adapter lacks {:?}", features - adapter_features);
+            return None;
+        }
+        let (device, queue) = core_results_2(instance.adapter_request_device::<crate::Backend>(
+            adapter,
+            &wgpu_types::DeviceDescriptor {
+                required_features: features,
+                ..Default::default()
+            },
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+        Some(Self {
+            instance: Arc::new(instance),
+            adapter,
+            device,
+            queue,
+        })
+    }
+
+    pub(crate) fn new() -> Option<Self> {
+        Self::with_features(wgpu_types::Features::empty())
+    }
+
+    pub(crate) fn create_buffer(&self, size: u64, usage: wgpu_types::BufferUsages) -> BufferId {
+        core_result(self.instance.device_create_buffer::<crate::Backend>(
+            self.device,
+            &wgpu_types::BufferDescriptor {
+                label: None,
+                size,
+                usage,
+                mapped_at_creation: false,
+            },
+            None,
+        ))
+        .unwrap()
+    }
+
+    pub(crate) fn create_texture(
+        &self,
+        format: wgpu_types::TextureFormat,
+        width: u32,
+        height: u32,
+        usage: wgpu_types::TextureUsages,
+    ) -> TextureId {
+        core_result(self.instance.device_create_texture::<crate::Backend>(
+            self.device,
+            &wgpu_core::resource::TextureDescriptor {
+                label: None,
+                size: wgpu_types::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu_types::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: vec![],
+            },
+            None,
+        ))
+        .unwrap()
+    }
+
+    /// The pixels of `texture`'s first mip level, which has 4 bytes per pixel,
+    /// row after row.
+    pub(crate) fn read_texture(&self, texture: TextureId, width: u32, height: u32) -> Vec<u8> {
+        self.read_texture_layer(texture, 0, width, height)
+    }
+
+    /// Like `read_texture`, for array layer `layer`.
+    pub(crate) fn read_texture_layer(
+        &self,
+        texture: TextureId,
+        layer: u32,
+        width: u32,
+        height: u32,
+    ) -> Vec<u8> {
+        let row = width * 4;
+        let padded_row = row.next_multiple_of(wgpu_types::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let size = padded_row as u64 * height as u64;
+        let buffer = self.create_buffer(
+            size,
+            wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::COPY_SRC,
+        );
+        let encoder = core_result(
+            self.instance
+                .device_create_command_encoder::<crate::Backend>(
+                    self.device,
+                    &Default::default(),
+                    None,
+                ),
+        )
+        .unwrap();
+        self.instance
+            .command_encoder_copy_texture_to_buffer::<crate::Backend>(
+                encoder,
+                &wgpu_types::ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu_types::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: wgpu_types::TextureAspect::All,
+                },
+                &wgpu_types::ImageCopyBuffer {
+                    buffer,
+                    layout: wgpu_types::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_row),
+                        rows_per_image: None,
+                    },
+                },
+                &wgpu_types::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            )
+            .unwrap();
+        self.submit(encoder);
+        let bytes = self.read(buffer, 0, size);
+        self.instance.buffer_drop::<crate::Backend>(buffer, false);
+        bytes
+            .chunks(padded_row as usize)
+            .flat_map(|padded| &padded[..row as usize])
+            .copied()
+            .collect()
+    }
+
+    /// Finishes `encoder` and submits it.
+    pub(crate) fn submit(&self, encoder: CommandEncoderId) {
+        let command_buffer = core_result(
+            self.instance
+                .command_encoder_finish::<crate::Backend>(encoder, &Default::default()),
+        )
+        .unwrap();
+        self.instance
+            .queue_submit::<crate::Backend>(self.queue, &[command_buffer])
+            .unwrap();
+    }
+
+    /// `length` bytes of `buffer` from `offset` on, once the GPU is done with it.
+    pub(crate) fn read(&self, buffer: BufferId, offset: u64, length: u64) -> Vec<u8> {
+        buffer_readback::read_buffer(
+            &self.instance,
+            self.device,
+            self.queue,
+            buffer,
+            offset,
+            length,
+        )
+        .unwrap()
+    }
+}
+
+/// A host for calling the webgpu host functions directly, without a guest.
+pub(crate) struct TestView {
+    table: ResourceTable,
+    ctx: WasiCtx,
+    instance: Arc<Global>,
+    texture_descriptors: Arc<TextureDescriptors>,
+}
+
+impl TestView {
+    pub(crate) fn new(gpu: &TestGpu) -> Self {
+        Self {
+            table: ResourceTable::new(),
+            ctx: WasiCtxBuilder::new().build(),
+            instance: Arc::clone(&gpu.instance),
+            texture_descriptors: Default::default(),
+        }
+    }
+
+    /// `gpu`'s device and queue, as the guest would hold them.
+    pub(crate) fn device(&mut self, gpu: &TestGpu) -> (Resource<Device>, Resource<QueueId>) {
+        let device = self
+            .table
+            .push(Device {
+                device: gpu.device,
+                queue: gpu.queue,
+                adapter: gpu.adapter,
+                error_scopes: Default::default(),
+            })
+            .unwrap();
+        let queue = self.table.push(gpu.queue).unwrap();
+        (device, queue)
+    }
+
+    /// The host functions, called as if from a guest.
+    pub(crate) fn host(&mut self) -> WasiWebGpuImpl<&mut Self> {
+        WasiWebGpuImpl(self)
+    }
+
+    /// The mini-canvas host functions, called as if from a guest.
+    pub(crate) fn canvas_host(&mut self) -> &mut dyn WasiMiniCanvasView {
+        self
+    }
+
+    /// A canvas on a `FakeWindow`, as the guest would hold it.
+    pub(crate) fn canvas(&mut self) -> Resource<MiniCanvasArc> {
+        let canvas = MiniCanvas::new(Box::new(FakeWindow));
+        self.table.push(MiniCanvasArc(Arc::new(canvas))).unwrap()
+    }
+}
+
+impl WasiView for TestView {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+impl WasiWebGpuView for TestView {
+    fn instance(&self) -> Arc<Global> {
+        Arc::clone(&self.instance)
+    }
+
+    fn ui_thread_spawner(&self) -> Box<impl MainThreadSpawner + 'static> {
+        Box::new(InPlaceSpawner)
+    }
+
+    fn texture_descriptors(&self) -> Option<Arc<TextureDescriptors>> {
+        Some(Arc::clone(&self.texture_descriptors))
+    }
+}
+
+impl WasiMiniCanvasView for TestView {
+    fn create_canvas(&self, _desc: MiniCanvasDesc) -> MiniCanvas {
+        MiniCanvas::new(Box::new(FakeWindow))
+    }
+}
+
+/// Tests have no UI thread, so runs closures in place.
+struct InPlaceSpawner;
+
+impl MainThreadSpawner for InPlaceSpawner {
+    async fn spawn<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        f()
+    }
+}
+
+/// A window to present to. There's one per test run, on a thread of its own
+/// running its event loop, since winit only lets a process create one.
+pub(crate) struct TestWindow(Window);
+
+impl TestWindow {
+    /// The window, locked for the caller since it can only back one surface
+    /// at a time, or `None` if there's no display to open it on.
+    pub(crate) fn lock() -> Option<MutexGuard<'static, TestWindow>> {
+        static WINDOW: OnceLock<Option<Mutex<TestWindow>>> = OnceLock::new();
+        let window = WINDOW.get_or_init(|| Self::open().map(Mutex::new));
+        match window {
+            // A test that panicked while holding the window leaves it usable.
+            Some(window) => Some(window.lock().unwrap_or_else(|e| e.into_inner())),
+            None => {
+                eprintln!("skipping: no display");
+                None
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    fn open() -> Option<Self> {
+        #[cfg(target_os = "windows")]
+        use winit::platform::windows::EventLoopBuilderExtWindows;
+        #[cfg(target_os = "linux")]
+        use winit::platform::x11::EventLoopBuilderExtX11;
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let Ok(event_loop) = winit::event_loop::EventLoop::builder()
+                .with_any_thread(true)
+                .build()
+            else {
+                sender.send(None).unwrap();
+                return;
+            };
+            #[allow(deprecated)]
+            let window = event_loop
+                .create_window(
+                    Window::default_attributes()
+                        .with_title("webgpu test")
+                        .with_inner_size(winit::dpi::PhysicalSize::new(64, 64)),
+                )
+                .ok();
+            let opened = window.is_some();
+            sender.send(window).unwrap();
+            if opened {
+                #[allow(deprecated)]
+                let _ = event_loop.run(|_, _| {});
+            }
+        });
+        receiver.recv().unwrap().map(Self)
+    }
+
+    /// The event loop has to run on the main thread elsewhere, which tests
+    /// don't get.
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    fn open() -> Option<Self> {
+        None
+    }
+}
+
+impl HasDisplayHandle for TestWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        self.0.display_handle()
+    }
+}
+
+impl HasWindowHandle for TestWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        self.0.window_handle()
+    }
+}
+
+impl DisplayApi for TestWindow {
+    fn height(&self) -> u32 {
+        self.0.inner_size().height
+    }
+
+    fn width(&self) -> u32 {
+        self.0.inner_size().width
+    }
+}
+
+/// A 640 by 480 window with no window system behind it, for canvases that
+/// only deliver input.
+struct FakeWindow;
+
+impl HasDisplayHandle for FakeWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl HasWindowHandle for FakeWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl DisplayApi for FakeWindow {
+    fn height(&self) -> u32 {
+        480
+    }
+
+    fn width(&self) -> u32 {
+        640
+    }
+}
+
+impl CanvasWindow for FakeWindow {}
@@ -0,0 +1,29 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use wgpu_core::id::TextureId;
+
+pub(crate) type TextureDescriptor =
+    wgpu_types::TextureDescriptor<(), Vec<wgpu_types::TextureFormat>>;
+
+/// What each texture was created with.
+///
+/// wgpu-core can't be asked about a texture once it's created, so this keeps
+/// what's needed to check reads from and copies to it. Surface textures aren't in here.
+#[derive(Default)]
+pub struct TextureDescriptors {
+    descriptors: Mutex<HashMap<TextureId, TextureDescriptor>>,
+}
+
+impl TextureDescriptors {
+    pub(crate) fn insert(&self, texture: TextureId, descriptor: TextureDescriptor) {
+        self.descriptors.lock().unwrap().insert(texture, descriptor);
+    }
+
+    pub(crate) fn get(&self, texture: TextureId) -> Option<TextureDescriptor> {
+        self.descriptors.lock().unwrap().get(&texture).cloned()
+    }
+
+    pub(crate) fn remove(&self, texture: TextureId) {
+        self.descriptors.lock().unwrap().remove(&texture);
+    }
+}
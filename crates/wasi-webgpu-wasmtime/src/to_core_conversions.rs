@@ -15,15 +15,27 @@ where
     }
 }
 
+impl ToCore<wgpu_core::id::TextureId> for wasmtime::component::Resource<crate::Texture> {
+    fn to_core(self, table: &ResourceTable) -> wgpu_core::id::TextureId {
+        table.get(&self).unwrap().id
+    }
+}
+
 impl ToCore<wgpu_types::Extent3d> for webgpu::GpuExtent3D {
     fn to_core(self, _table: &ResourceTable) -> wgpu_types::Extent3d {
         match self {
+            // https://www.w3.org/TR/webgpu/#dictdef-gpuextent3ddict
             webgpu::GpuExtent3D::GpuExtent3DDict(extent_dict) => wgpu_types::Extent3d {
                 width: extent_dict.width,
-                height: extent_dict.height.unwrap(),
-                depth_or_array_layers: extent_dict.depth_or_array_layers.unwrap(),
+                height: extent_dict.height.unwrap_or(1),
+                depth_or_array_layers: extent_dict.depth_or_array_layers.unwrap_or(1),
+            },
+            // `validate_extent` rejects lists without a width.
+            webgpu::GpuExtent3D::ListGpuIntegerCoordinate(coordinates) => wgpu_types::Extent3d {
+                width: coordinates.first().copied().unwrap_or(0),
+                height: coordinates.get(1).copied().unwrap_or(1),
+                depth_or_array_layers: coordinates.get(2).copied().unwrap_or(1),
             },
-            webgpu::GpuExtent3D::ListGpuIntegerCoordinate(_coordinates) => todo!(),
         }
     }
 }
@@ -65,11 +77,29 @@ impl<'a> ToCore<wgpu_core::binding_model::BindingResource<'a>> for webgpu::GpuBi
             webgpu::GpuBindingResource::GpuTextureView(texture_view) => {
                 wgpu_core::binding_model::BindingResource::TextureView(texture_view.to_core(table))
             }
+            webgpu::GpuBindingResource::GpuSamplerArray(samplers) => {
+                wgpu_core::binding_model::BindingResource::SamplerArray(
+                    samplers
+                        .into_iter()
+                        .map(|sampler| sampler.to_core(table))
+                        .collect::<Vec<_>>()
+                        .into(),
+                )
+            }
+            webgpu::GpuBindingResource::GpuTextureViewArray(texture_views) => {
+                wgpu_core::binding_model::BindingResource::TextureViewArray(
+                    texture_views
+                        .into_iter()
+                        .map(|texture_view| texture_view.to_core(table))
+                        .collect::<Vec<_>>()
+                        .into(),
+                )
+            }
         }
     }
 }
 
-impl<'a> ToCore<wgpu_core::binding_model::BufferBinding> for webgpu::GpuBufferBinding {
+impl ToCore<wgpu_core::binding_model::BufferBinding> for webgpu::GpuBufferBinding {
     fn to_core(self, table: &ResourceTable) -> wgpu_core::binding_model::BufferBinding {
         let buffer = table.get(&self.buffer).unwrap();
         wgpu_core::binding_model::BufferBinding {
@@ -112,15 +142,13 @@ impl<'a> ToCore<wgpu_core::resource::TextureViewDescriptor<'a>>
             label: self.label.map(|l| l.into()),
             format: self.format.map(|f| f.into()),
             dimension: self.dimension.map(|d| d.into()),
-            // TODO: Don't default
-            range: Default::default(),
-            // range: wgpu_types::ImageSubresourceRange {
-            //     aspect: self.aspect,
-            //     base_mip_level: self.base_mip_level,
-            //     mip_level_count: self.mip_level_count,
-            //     base_array_layer: self.base_array_layer,
-            //     array_layer_count: self.array_layer_count,
-            // }
+            range: wgpu_types::ImageSubresourceRange {
+                aspect: self.aspect.map(|a| a.into()).unwrap_or_default(),
+                base_mip_level: self.base_mip_level.unwrap_or(0),
+                mip_level_count: self.mip_level_count,
+                base_array_layer: self.base_array_layer.unwrap_or(0),
+                array_layer_count: self.array_layer_count,
+            },
         }
     }
 }
@@ -150,8 +178,7 @@ impl<'a> ToCore<wgpu_core::pipeline::RenderPipelineDescriptor<'a>>
 {
     fn to_core(self, table: &ResourceTable) -> wgpu_core::pipeline::RenderPipelineDescriptor<'a> {
         wgpu_core::pipeline::RenderPipelineDescriptor {
-            // TODO: remove defaults
-            label: Default::default(),
+            label: self.label.map(|l| l.into()),
             layout: self.layout.map(|l| l.to_core(table)),
             vertex: self.vertex.to_core(table),
             primitive: self.primitive.map(|p| p.to_core(table)).unwrap(),
@@ -161,7 +188,8 @@ impl<'a> ToCore<wgpu_core::pipeline::RenderPipelineDescriptor<'a>>
                 .map(|ms| ms.to_core(table))
                 .unwrap_or_default(),
             fragment: self.fragment.map(|f| f.to_core(table)),
-            multiview: Default::default(),
+            // Checked in `create_render_pipeline`.
+            multiview: self.multiview.and_then(std::num::NonZeroU32::new),
         }
     }
 }
@@ -180,7 +208,7 @@ impl ToCore<wgpu_types::DepthStencilState> for webgpu::GpuDepthStencilState {
     fn to_core(self, table: &ResourceTable) -> wgpu_types::DepthStencilState {
         wgpu_types::DepthStencilState {
             format: self.format.into(),
-            depth_write_enabled: self.depth_write_enabled.unwrap().into(),
+            depth_write_enabled: self.depth_write_enabled.unwrap(),
             depth_compare: self.depth_compare.unwrap().into(),
             stencil: wgpu_types::StencilState {
                 front: self
@@ -353,11 +381,13 @@ impl<'a> ToCore<wgpu_types::TextureDescriptor<wgpu_core::Label<'a>, Vec<wgpu_typ
         wgpu_types::TextureDescriptor {
             label: self.label.map(|l| l.into()),
             size: self.size.to_core(table),
-            mip_level_count: self.mip_level_count.unwrap(),
-            sample_count: self.sample_count.unwrap(),
+            // https://www.w3.org/TR/webgpu/#dictdef-gputexturedescriptor
+            mip_level_count: self.mip_level_count.unwrap_or(1),
+            sample_count: self.sample_count.unwrap_or(1),
             dimension: self.dimension.into(),
             format: self.format.into(),
-            usage: wgpu_types::TextureUsages::from_bits(self.usage).unwrap(),
+            // Checked in `create_texture`.
+            usage: wgpu_types::TextureUsages::from_bits_truncate(self.usage),
             view_formats: self
                 .view_formats
                 .map(|view_formats| {
@@ -386,8 +416,7 @@ impl<'a> ToCore<wgpu_core::resource::SamplerDescriptor<'a>> for webgpu::GpuSampl
             lod_min_clamp: self.lod_min_clamp.unwrap(),
             lod_max_clamp: self.lod_max_clamp.unwrap(),
             compare: self.compare.map(|compare| compare.into()),
-            // TODO: should this be coming from self.anisotropy_clamp?
-            anisotropy_clamp: 1,
+            anisotropy_clamp: self.max_anisotropy.unwrap_or(1),
             border_color: Default::default(),
         }
     }
@@ -426,7 +455,7 @@ impl<'a> ToCore<wgpu_core::binding_model::BindGroupLayoutDescriptor<'a>>
 impl ToCore<wgpu_types::BindGroupLayoutEntry> for webgpu::GpuBindGroupLayoutEntry {
     fn to_core(self, table: &ResourceTable) -> wgpu_types::BindGroupLayoutEntry {
         wgpu_types::BindGroupLayoutEntry {
-            binding: self.binding.into(),
+            binding: self.binding,
             // TODO:
             visibility: wgpu_types::ShaderStages::from_bits(self.visibility).unwrap(),
             ty: match (
@@ -444,8 +473,8 @@ impl ToCore<wgpu_types::BindGroupLayoutEntry> for webgpu::GpuBindGroupLayoutEntr
                 (None, None, None, None, None) => todo!(),
                 _ => panic!("Can't have multiple ..."),
             },
-            // TODO:
-            count: Default::default(),
+            // Zero is rejected in `create_bind_group_layout`.
+            count: self.count.map(|count| count.try_into().unwrap()),
         }
     }
 }
@@ -520,14 +549,14 @@ impl ToCore<wgpu_core::command::RenderPassDepthStencilAttachment>
             depth: pass_channel_from_options(
                 self.depth_load_op.map(|x| x.into()),
                 self.depth_store_op.map(|x| x.into()),
-                self.depth_clear_value.map(|x| x.into()),
-                self.depth_read_only.map(|x| x.into()),
+                self.depth_clear_value,
+                self.depth_read_only,
             ),
             stencil: pass_channel_from_options(
                 self.stencil_load_op.map(|x| x.into()),
                 self.stencil_store_op.map(|x| x.into()),
-                self.stencil_clear_value.map(|x| x.into()),
-                self.stencil_read_only.map(|x| x.into()),
+                self.stencil_clear_value,
+                self.stencil_read_only,
             ),
         }
     }
@@ -586,7 +615,8 @@ impl<'a> ToCore<wgpu_types::BufferDescriptor<wgpu_core::Label<'a>>>
         wgpu_types::BufferDescriptor {
             label: self.label.map(|l| l.into()),
             size: self.size,
-            usage: wgpu_types::BufferUsages::from_bits(self.usage).unwrap(),
+            // Checked in `create_buffer`.
+            usage: wgpu_types::BufferUsages::from_bits_truncate(self.usage),
             mapped_at_creation: self.mapped_at_creation.unwrap_or_default(),
         }
     }
@@ -598,6 +628,12 @@ impl<'a> ToCore<wgpu_types::DeviceDescriptor<wgpu_core::Label<'a>>>
     fn to_core(self, _table: &ResourceTable) -> wgpu_types::DeviceDescriptor<wgpu_core::Label<'a>> {
         wgpu_types::DeviceDescriptor {
             label: self.label.map(|l| l.into()),
+            required_features: self
+                .required_features
+                .into_iter()
+                .flatten()
+                .map(wgpu_types::Features::from)
+                .collect(),
             // TODO: Don't default
             ..Default::default()
         }
@@ -613,9 +649,31 @@ impl ToCore<wgpu_types::ImageCopyTexture<wgpu_core::id::TextureId>>
     ) -> wgpu_types::ImageCopyTexture<wgpu_core::id::TextureId> {
         wgpu_types::ImageCopyTexture {
             texture: self.texture.to_core(table),
-            mip_level: self.mip_level.unwrap(),
-            origin: self.origin.unwrap().to_core(table),
-            aspect: self.aspect.unwrap().into(),
+            mip_level: self.mip_level.unwrap_or(0),
+            origin: self
+                .origin
+                .map(|origin| origin.to_core(table))
+                .unwrap_or_default(),
+            aspect: self
+                .aspect
+                .map(Into::into)
+                .unwrap_or(wgpu_types::TextureAspect::All),
+        }
+    }
+}
+
+impl ToCore<wgpu_types::ImageCopyBuffer<wgpu_core::id::BufferId>> for webgpu::GpuImageCopyBuffer {
+    fn to_core(
+        self,
+        table: &ResourceTable,
+    ) -> wgpu_types::ImageCopyBuffer<wgpu_core::id::BufferId> {
+        wgpu_types::ImageCopyBuffer {
+            buffer: table.get(&self.buffer).unwrap().buffer,
+            layout: wgpu_types::ImageDataLayout {
+                offset: self.offset.unwrap_or(0),
+                bytes_per_row: self.bytes_per_row,
+                rows_per_image: self.rows_per_image,
+            },
         }
     }
 }
@@ -625,14 +683,18 @@ impl ToCore<wgpu_types::Origin3d> for webgpu::GpuOrigin3DDictOrListGpuIntegerCoo
         match self {
             webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate::GpuOrigin3DDict(dict) => {
                 wgpu_types::Origin3d {
-                    x: dict.x.unwrap(),
-                    y: dict.y.unwrap(),
-                    z: dict.z.unwrap(),
+                    x: dict.x.unwrap_or(0),
+                    y: dict.y.unwrap_or(0),
+                    z: dict.z.unwrap_or(0),
                 }
             }
-            webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate::ListGpuIntegerCoordinate(_) => {
-                todo!()
-            }
+            webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate::ListGpuIntegerCoordinate(
+                coordinates,
+            ) => wgpu_types::Origin3d {
+                x: coordinates.first().copied().unwrap_or(0),
+                y: coordinates.get(1).copied().unwrap_or(0),
+                z: coordinates.get(2).copied().unwrap_or(0),
+            },
         }
     }
 }
@@ -652,7 +714,7 @@ impl<'a> ToCore<wgpu_core::pipeline::ComputePipelineDescriptor<'a>>
 {
     fn to_core(self, table: &ResourceTable) -> wgpu_core::pipeline::ComputePipelineDescriptor<'a> {
         wgpu_core::pipeline::ComputePipelineDescriptor {
-            label: Default::default(), //TODO: enable: self.compute.label.map(|l| l.into()),
+            label: self.label.map(|l| l.into()),
             layout: match self.layout {
                 webgpu::GpuPipelineLayoutOrGpuAutoLayoutMode::GpuPipelineLayout(layout) => {
                     Some(layout.to_core(table))
@@ -688,7 +750,19 @@ impl ToCore<wgpu_core::command::ComputePassTimestampWrites>
 {
     fn to_core(self, table: &ResourceTable) -> wgpu_core::command::ComputePassTimestampWrites {
         wgpu_core::command::ComputePassTimestampWrites {
-            query_set: self.query_set.to_core(table),
+            query_set: table.get(&self.query_set).unwrap().id,
+            beginning_of_pass_write_index: self.beginning_of_pass_write_index,
+            end_of_pass_write_index: self.end_of_pass_write_index,
+        }
+    }
+}
+
+impl ToCore<wgpu_core::command::RenderPassTimestampWrites>
+    for webgpu::GpuRenderPassTimestampWrites
+{
+    fn to_core(self, table: &ResourceTable) -> wgpu_core::command::RenderPassTimestampWrites {
+        wgpu_core::command::RenderPassTimestampWrites {
+            query_set: table.get(&self.query_set).unwrap().id,
             beginning_of_pass_write_index: self.beginning_of_pass_write_index,
             end_of_pass_write_index: self.end_of_pass_write_index,
         }
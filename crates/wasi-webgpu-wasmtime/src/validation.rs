@@ -0,0 +1,738 @@
+//! Checks for common mistakes in guest descriptors, done before handing them to
+//! wgpu so the error names the parameter at fault. wgpu still validates
+//! everything else.
+
+use std::collections::BTreeSet;
+
+use crate::texture_descriptors::TextureDescriptor;
+use crate::wasi::webgpu::webgpu;
+use crate::QuerySet;
+
+pub(crate) fn validate_buffer_descriptor(
+    descriptor: &webgpu::GpuBufferDescriptor,
+    limits: &wgpu_types::Limits,
+    features: wgpu_types::Features,
+) -> Result<(), String> {
+    let Some(usage) = wgpu_types::BufferUsages::from_bits(descriptor.usage) else {
+        return Err(format!("usage has unknown flags: {:#x}", descriptor.usage));
+    };
+    if usage.is_empty() {
+        return Err("usage can't be empty".to_string());
+    }
+    let size = descriptor.size;
+    if size == 0 {
+        return Err("size must be greater than 0".to_string());
+    }
+    if size > limits.max_buffer_size {
+        return Err(format!(
+            "size of {size} is more than the device's maxBufferSize of {}",
+            limits.max_buffer_size
+        ));
+    }
+    if descriptor.mapped_at_creation.unwrap_or_default()
+        && !size.is_multiple_of(wgpu_types::COPY_BUFFER_ALIGNMENT)
+    {
+        return Err(format!(
+            "size of {size} must be a multiple of {} to map at creation",
+            wgpu_types::COPY_BUFFER_ALIGNMENT
+        ));
+    }
+    // Cleared with clear-buffer or by mapping them at creation, which both need
+    // aligned sizes.
+    if descriptor.clear_on_create.unwrap_or_default()
+        && !size.is_multiple_of(wgpu_types::COPY_BUFFER_ALIGNMENT)
+    {
+        return Err(format!(
+            "size of {size} must be a multiple of {} to clear on create",
+            wgpu_types::COPY_BUFFER_ALIGNMENT
+        ));
+    }
+    // As in wgpu-core, devices with mappable primary buffers can combine map
+    // usages with any other.
+    if features.contains(wgpu_types::Features::MAPPABLE_PRIMARY_BUFFERS) {
+        return Ok(());
+    }
+    if usage.contains(wgpu_types::BufferUsages::MAP_READ)
+        && !(wgpu_types::BufferUsages::MAP_READ | wgpu_types::BufferUsages::COPY_DST)
+            .contains(usage)
+    {
+        return Err("usage map-read can only be combined with copy-dst".to_string());
+    }
+    if usage.contains(wgpu_types::BufferUsages::MAP_WRITE)
+        && !(wgpu_types::BufferUsages::MAP_WRITE | wgpu_types::BufferUsages::COPY_SRC)
+            .contains(usage)
+    {
+        return Err("usage map-write can only be combined with copy-src".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_texture_descriptor(
+    descriptor: &webgpu::GpuTextureDescriptor,
+    size: wgpu_types::Extent3d,
+    limits: &wgpu_types::Limits,
+) -> Result<(), String> {
+    let Some(usage) = wgpu_types::TextureUsages::from_bits(descriptor.usage) else {
+        return Err(format!("usage has unknown flags: {:#x}", descriptor.usage));
+    };
+    if usage.is_empty() {
+        return Err("usage can't be empty".to_string());
+    }
+    for (name, value) in [
+        ("size.width", size.width),
+        ("size.height", size.height),
+        ("size.depth-or-array-layers", size.depth_or_array_layers),
+    ] {
+        if value == 0 {
+            return Err(format!("{name} must be greater than 0"));
+        }
+    }
+    let (max_dimension, limit_name, max_layers) = match descriptor.dimension {
+        webgpu::GpuTextureDimension::OneD => {
+            (limits.max_texture_dimension_1d, "maxTextureDimension1D", 1)
+        }
+        webgpu::GpuTextureDimension::TwoD => (
+            limits.max_texture_dimension_2d,
+            "maxTextureDimension2D",
+            limits.max_texture_array_layers,
+        ),
+        webgpu::GpuTextureDimension::ThreeD => (
+            limits.max_texture_dimension_3d,
+            "maxTextureDimension3D",
+            limits.max_texture_dimension_3d,
+        ),
+    };
+    for (name, value) in [("size.width", size.width), ("size.height", size.height)] {
+        if value > max_dimension {
+            return Err(format!(
+                "{name} of {value} is more than the device's {limit_name} of {max_dimension}"
+            ));
+        }
+    }
+    if size.depth_or_array_layers > max_layers {
+        return Err(format!(
+            "size.depth-or-array-layers of {} is more than the device allows, {max_layers}",
+            size.depth_or_array_layers
+        ));
+    }
+    let format: wgpu_types::TextureFormat = descriptor.format.into();
+    for view_format in descriptor.view_formats.iter().flatten() {
+        let view_format: wgpu_types::TextureFormat = (*view_format).into();
+        if view_format.remove_srgb_suffix() != format.remove_srgb_suffix() {
+            return Err(format!(
+                "view-formats entry {view_format:?} doesn't match format {format:?}, only srgb-ness can differ"
+            ));
+        }
+    }
+    let mip_level_count = descriptor.mip_level_count.unwrap_or(1);
+    let max_mip_level_count = size.max_mips(descriptor.dimension.into()).max(1);
+    if mip_level_count == 0 || mip_level_count > max_mip_level_count {
+        return Err(format!(
+            "mip-level-count of {mip_level_count} must be between 1 and {max_mip_level_count} for this size"
+        ));
+    }
+    Ok(())
+}
+
+/// Bounds are left to wgpu, which knows the buffer's size.
+pub(crate) fn validate_clear_buffer(offset: u64, size: Option<u64>) -> Result<(), String> {
+    let alignment = wgpu_types::COPY_BUFFER_ALIGNMENT;
+    if !offset.is_multiple_of(alignment) {
+        return Err(format!(
+            "offset of {offset} must be a multiple of {alignment}"
+        ));
+    }
+    if let Some(size) = size.filter(|size| !size.is_multiple_of(alignment)) {
+        return Err(format!("size of {size} must be a multiple of {alignment}"));
+    }
+    Ok(())
+}
+
+/// The buffer has to hold the three workgroup counts, x, y and z, as u32s at
+/// `offset`.
+pub(crate) fn validate_indirect_dispatch(
+    usage: wgpu_types::BufferUsages,
+    size: u64,
+    offset: u64,
+) -> Result<(), String> {
+    if !usage.contains(wgpu_types::BufferUsages::INDIRECT) {
+        return Err("indirect buffer needs indirect usage".to_string());
+    }
+    if !offset.is_multiple_of(4) {
+        return Err(format!(
+            "indirect offset of {offset} must be a multiple of 4"
+        ));
+    }
+    let args_size = 3 * std::mem::size_of::<u32>() as u64;
+    if offset.checked_add(args_size).is_none_or(|end| end > size) {
+        return Err(format!(
+            "indirect buffer of {size} bytes doesn't have the {args_size} bytes of workgroup counts at offset {offset}"
+        ));
+    }
+    Ok(())
+}
+
+/// Turning off depth clipping needs the device to have the feature for it.
+pub(crate) fn validate_primitive_state(
+    primitive: Option<&webgpu::GpuPrimitiveState>,
+    features: wgpu_types::Features,
+) -> Result<(), String> {
+    let unclipped_depth = primitive.and_then(|p| p.unclipped_depth) == Some(true);
+    if unclipped_depth && !features.contains(wgpu_types::Features::DEPTH_CLIP_CONTROL) {
+        return Err(
+            "unclipped-depth needs the depth-clip-control feature, request it in request-device"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Multiview needs the device to have the feature for it. Whether the
+/// attachments have as many array layers is left to wgpu, which knows the views.
+pub(crate) fn validate_multiview(
+    multiview: Option<u32>,
+    features: wgpu_types::Features,
+) -> Result<(), String> {
+    match multiview {
+        None => Ok(()),
+        Some(0) => Err("multiview must be at least 1, or none to render one view".to_string()),
+        Some(_) if !features.contains(wgpu_types::Features::MULTIVIEW) => {
+            Err("multiview needs the multiview feature, request it in request-device".to_string())
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// The slot has to be one the device has, and one the current pipeline, if any,
+/// takes a vertex buffer in.
+pub(crate) fn validate_vertex_buffer_slot(
+    slot: u32,
+    max_vertex_buffers: Option<u32>,
+    pipeline_vertex_buffers: Option<u32>,
+) -> Result<(), String> {
+    if let Some(max) = max_vertex_buffers.filter(|max| slot >= *max) {
+        return Err(format!(
+            "slot {slot} is past the device's max-vertex-buffers of {max}"
+        ));
+    }
+    if let Some(count) = pipeline_vertex_buffers.filter(|count| slot >= *count) {
+        return Err(format!(
+            "slot {slot} is past the {count} vertex buffers the current pipeline declares"
+        ));
+    }
+    Ok(())
+}
+
+/// Every vertex buffer the pipeline declares needs a buffer bound to its slot.
+pub(crate) fn validate_vertex_buffers_bound(
+    pipeline_vertex_buffers: u32,
+    bound: &BTreeSet<u32>,
+) -> Result<(), String> {
+    match (0..pipeline_vertex_buffers).find(|slot| !bound.contains(slot)) {
+        Some(slot) => Err(format!(
+            "the pipeline declares {pipeline_vertex_buffers} vertex buffers, but slot {slot} has none bound"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Anisotropic filtering only works with linear filtering, as in WebGPU.
+pub(crate) fn validate_sampler_descriptor(
+    descriptor: &webgpu::GpuSamplerDescriptor,
+) -> Result<(), String> {
+    let max_anisotropy = descriptor.max_anisotropy.unwrap_or(1);
+    if max_anisotropy == 0 {
+        return Err("max-anisotropy must be at least 1".to_string());
+    }
+    let linear = descriptor.mag_filter == Some(webgpu::GpuFilterMode::Linear)
+        && descriptor.min_filter == Some(webgpu::GpuFilterMode::Linear)
+        && descriptor.mipmap_filter == Some(webgpu::GpuMipmapFilterMode::Linear);
+    if max_anisotropy > 1 && !linear {
+        return Err(format!(
+            "max-anisotropy of {max_anisotropy} needs mag-filter, min-filter and mipmap-filter to be linear"
+        ));
+    }
+    Ok(())
+}
+
+/// Only timestamp query sets can be written to, and those can only be made on
+/// devices with timestamp-query, so that's checked too.
+pub(crate) fn validate_timestamp_writes(
+    query_set: &QuerySet,
+    beginning_of_pass_write_index: Option<u32>,
+    end_of_pass_write_index: Option<u32>,
+) -> Result<(), String> {
+    if !matches!(query_set.type_, webgpu::GpuQueryType::Timestamp) {
+        return Err("timestamp writes need a timestamp query set".to_string());
+    }
+    let indices = [beginning_of_pass_write_index, end_of_pass_write_index];
+    if indices == [None, None] {
+        return Err(
+            "timestamp writes need a beginning or end of pass write index, or both".to_string(),
+        );
+    }
+    for index in indices.into_iter().flatten() {
+        if index >= query_set.count {
+            return Err(format!(
+                "write index {index} is out of the query set's {} queries",
+                query_set.count
+            ));
+        }
+    }
+    if beginning_of_pass_write_index.is_some()
+        && beginning_of_pass_write_index == end_of_pass_write_index
+    {
+        return Err("beginning and end of pass write indices must differ".to_string());
+    }
+    Ok(())
+}
+
+/// A buffer bound for uniform or storage reads has to start at an offset
+/// aligned to the device's limit for that kind of binding. Buffers that can be
+/// bound either way are left to wgpu, which knows how the layout binds them.
+pub(crate) fn validate_buffer_binding(
+    binding: u32,
+    offset: u64,
+    usage: wgpu_types::BufferUsages,
+    limits: &wgpu_types::Limits,
+) -> Result<(), String> {
+    let uniform = usage.contains(wgpu_types::BufferUsages::UNIFORM);
+    let storage = usage.contains(wgpu_types::BufferUsages::STORAGE);
+    let (kind, name, alignment) = match (uniform, storage) {
+        (true, false) => (
+            "uniform",
+            "minUniformBufferOffsetAlignment",
+            limits.min_uniform_buffer_offset_alignment,
+        ),
+        (false, true) => (
+            "storage",
+            "minStorageBufferOffsetAlignment",
+            limits.min_storage_buffer_offset_alignment,
+        ),
+        _ => return Ok(()),
+    };
+    if !offset.is_multiple_of(alignment as u64) {
+        return Err(format!(
+            "offset of {offset} for the {kind} buffer at binding {binding} must be a multiple \
+             of the device's {name} of {alignment}"
+        ));
+    }
+    Ok(())
+}
+
+/// wgpu's error for binding a float32 texture where a filterable one is
+/// expected doesn't say it's only unfilterable for want of float32-filterable.
+pub(crate) fn explain_bind_group_error(
+    error: wgpu_core::binding_model::CreateBindGroupError,
+    features: wgpu_types::Features,
+) -> wasmtime::Error {
+    if let wgpu_core::binding_model::CreateBindGroupError::InvalidTextureSampleType {
+        binding,
+        layout_sample_type: wgpu_types::TextureSampleType::Float { filterable: true },
+        view_format:
+            view_format @ (wgpu_types::TextureFormat::R32Float
+            | wgpu_types::TextureFormat::Rg32Float
+            | wgpu_types::TextureFormat::Rgba32Float),
+    } = error
+    {
+        if !features.contains(wgpu_types::Features::FLOAT32_FILTERABLE) {
+            return wasmtime::Error::msg(format!(
+                "texture at binding {binding} is {view_format:?}, which is only filterable on \
+                 devices with the float32-filterable feature; request it, or bind the texture \
+                 as unfilterable-float and sample it with a non-filtering sampler"
+            ));
+        }
+    }
+    error.into()
+}
+
+/// A color given as a list needs exactly 4 components, as in WebGPU.
+pub(crate) fn validate_color(name: &str, color: &webgpu::GpuColor) -> Result<(), String> {
+    match color {
+        webgpu::GpuColorDictOrListF64::ListF64(components) if components.len() != 4 => Err(
+            format!("{name} must have 4 components, got {}", components.len()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// A size given as a list needs 1 to 3 coordinates, as in WebGPU.
+pub(crate) fn validate_extent(name: &str, extent: &webgpu::GpuExtent3D) -> Result<(), String> {
+    match extent {
+        webgpu::GpuExtent3D::ListGpuIntegerCoordinate(coordinates)
+            if !(1..=3).contains(&coordinates.len()) =>
+        {
+            Err(format!(
+                "{name} has {} coordinates, but needs 1 to 3",
+                coordinates.len()
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// An origin given as a list has at most 3 coordinates, as in WebGPU.
+pub(crate) fn validate_origin(
+    name: &str,
+    origin: Option<&webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate>,
+) -> Result<(), String> {
+    match origin {
+        Some(webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate::ListGpuIntegerCoordinate(
+            coordinates,
+        )) if coordinates.len() > 3 => Err(format!(
+            "{name} has {} coordinates, but can have at most 3",
+            coordinates.len()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `name` is the copy parameter, e.g. source, for the error.
+pub(crate) fn validate_image_copy_texture(
+    name: &str,
+    copy: &wgpu_types::ImageCopyTexture<wgpu_core::id::TextureId>,
+    copy_size: wgpu_types::Extent3d,
+    descriptor: &TextureDescriptor,
+) -> Result<(), String> {
+    let mip_level = copy.mip_level;
+    let Some(mip_size) = descriptor.mip_level_size(mip_level) else {
+        return Err(format!(
+            "{name}.mip-level of {mip_level} is past the texture's {} mip levels",
+            descriptor.mip_level_count
+        ));
+    };
+    let format = descriptor.format;
+    let aspect_missing = match copy.aspect {
+        wgpu_types::TextureAspect::DepthOnly => !format.has_depth_aspect(),
+        wgpu_types::TextureAspect::StencilOnly => !format.has_stencil_aspect(),
+        _ => false,
+    };
+    if aspect_missing {
+        return Err(format!(
+            "{name}.aspect {:?} isn't part of the texture's format {format:?}",
+            copy.aspect
+        ));
+    }
+    for (axis, origin, size, extent) in [
+        ("x", copy.origin.x, copy_size.width, mip_size.width),
+        ("y", copy.origin.y, copy_size.height, mip_size.height),
+        (
+            "z",
+            copy.origin.z,
+            copy_size.depth_or_array_layers,
+            mip_size.depth_or_array_layers,
+        ),
+    ] {
+        if origin as u64 + size as u64 > extent as u64 {
+            return Err(format!(
+                "{name}.origin.{axis} of {origin} plus {size} copied is past the {extent} mip level {mip_level} has there"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(size: u64, usage: wgpu_types::BufferUsages) -> webgpu::GpuBufferDescriptor {
+        webgpu::GpuBufferDescriptor {
+            size,
+            usage: usage.bits(),
+            mapped_at_creation: None,
+            clear_on_create: None,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn colors_given_as_lists_need_4_components() {
+        let list = |components: &[f64]| webgpu::GpuColorDictOrListF64::ListF64(components.to_vec());
+        assert_eq!(
+            validate_color("blend constant", &list(&[1.0, 0.5, 0.0])),
+            Err("blend constant must have 4 components, got 3".to_string())
+        );
+        assert!(validate_color("blend constant", &list(&[1.0; 5])).is_err());
+        assert_eq!(
+            validate_color("blend constant", &list(&[1.0, 0.5, 0.0, 1.0])),
+            Ok(())
+        );
+        let dict = webgpu::GpuColorDictOrListF64::GpuColorDict(webgpu::GpuColorDict {
+            r: 1.0,
+            g: 0.5,
+            b: 0.0,
+            a: 1.0,
+        });
+        assert_eq!(validate_color("blend constant", &dict), Ok(()));
+    }
+
+    #[test]
+    fn buffer_clears_need_4_byte_alignment() {
+        assert_eq!(validate_clear_buffer(4, Some(8)), Ok(()));
+        assert_eq!(validate_clear_buffer(0, None), Ok(()));
+        assert_eq!(
+            validate_clear_buffer(2, Some(8)),
+            Err("offset of 2 must be a multiple of 4".to_string())
+        );
+        assert_eq!(
+            validate_clear_buffer(4, Some(6)),
+            Err("size of 6 must be a multiple of 4".to_string())
+        );
+    }
+
+    #[test]
+    fn zero_size_buffers_are_rejected() {
+        let descriptor = buffer(0, wgpu_types::BufferUsages::UNIFORM);
+        assert_eq!(
+            validate_buffer_descriptor(
+                &descriptor,
+                &wgpu_types::Limits::default(),
+                wgpu_types::Features::empty(),
+            ),
+            Err("size must be greater than 0".to_string())
+        );
+    }
+
+    #[test]
+    fn buffers_over_the_size_limit_are_rejected() {
+        let limits = wgpu_types::Limits {
+            max_buffer_size: 1024,
+            ..Default::default()
+        };
+        let descriptor = buffer(1025, wgpu_types::BufferUsages::STORAGE);
+        assert_eq!(
+            validate_buffer_descriptor(&descriptor, &limits, wgpu_types::Features::empty()),
+            Err("size of 1025 is more than the device's maxBufferSize of 1024".to_string())
+        );
+        let descriptor = buffer(1024, wgpu_types::BufferUsages::STORAGE);
+        assert_eq!(
+            validate_buffer_descriptor(&descriptor, &limits, wgpu_types::Features::empty()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn only_buffers_mapped_at_creation_need_aligned_sizes() {
+        let limits = wgpu_types::Limits::default();
+        let none = wgpu_types::Features::empty();
+        let readback = buffer(
+            6,
+            wgpu_types::BufferUsages::MAP_READ | wgpu_types::BufferUsages::COPY_DST,
+        );
+        assert_eq!(validate_buffer_descriptor(&readback, &limits, none), Ok(()));
+        let mapped = webgpu::GpuBufferDescriptor {
+            mapped_at_creation: Some(true),
+            ..buffer(6, wgpu_types::BufferUsages::COPY_SRC)
+        };
+        assert_eq!(
+            validate_buffer_descriptor(&mapped, &limits, none),
+            Err("size of 6 must be a multiple of 4 to map at creation".to_string())
+        );
+    }
+
+    #[test]
+    fn map_usages_combine_freely_with_mappable_primary_buffers() {
+        let limits = wgpu_types::Limits::default();
+        let descriptor = buffer(
+            16,
+            wgpu_types::BufferUsages::MAP_READ | wgpu_types::BufferUsages::STORAGE,
+        );
+        assert_eq!(
+            validate_buffer_descriptor(&descriptor, &limits, wgpu_types::Features::empty()),
+            Err("usage map-read can only be combined with copy-dst".to_string())
+        );
+        assert_eq!(
+            validate_buffer_descriptor(
+                &descriptor,
+                &limits,
+                wgpu_types::Features::MAPPABLE_PRIMARY_BUFFERS
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn misaligned_uniform_bindings_are_rejected() {
+        let limits = wgpu_types::Limits::default();
+        let uniform = wgpu_types::BufferUsages::UNIFORM | wgpu_types::BufferUsages::COPY_DST;
+        assert_eq!(
+            validate_buffer_binding(2, 64, uniform, &limits),
+            Err(
+                "offset of 64 for the uniform buffer at binding 2 must be a multiple of the \
+                 device's minUniformBufferOffsetAlignment of 256"
+                    .to_string()
+            )
+        );
+        assert_eq!(validate_buffer_binding(2, 512, uniform, &limits), Ok(()));
+        let either = uniform | wgpu_types::BufferUsages::STORAGE;
+        assert_eq!(validate_buffer_binding(2, 64, either, &limits), Ok(()));
+    }
+
+    #[test]
+    fn extent_lists_need_one_to_three_coordinates() {
+        let list = |coordinates: &[u32]| {
+            webgpu::GpuExtent3D::ListGpuIntegerCoordinate(coordinates.to_vec())
+        };
+        assert!(validate_extent("size", &list(&[])).is_err());
+        assert!(validate_extent("size", &list(&[4])).is_ok());
+        assert!(validate_extent("size", &list(&[4, 4, 1])).is_ok());
+        assert!(validate_extent("size", &list(&[4, 4, 1, 1])).is_err());
+    }
+
+    #[test]
+    fn origin_lists_have_at_most_three_coordinates() {
+        let list = |coordinates: &[u32]| {
+            webgpu::GpuOrigin3DDictOrListGpuIntegerCoordinate::ListGpuIntegerCoordinate(
+                coordinates.to_vec(),
+            )
+        };
+        assert!(validate_origin("origin", None).is_ok());
+        assert!(validate_origin("origin", Some(&list(&[]))).is_ok());
+        assert!(validate_origin("origin", Some(&list(&[1, 2, 3]))).is_ok());
+        assert!(validate_origin("origin", Some(&list(&[1, 2, 3, 4]))).is_err());
+    }
+
+    #[test]
+    fn unclipped_depth_needs_depth_clip_control() {
+        let primitive = |unclipped_depth| webgpu::GpuPrimitiveState {
+            topology: None,
+            strip_index_format: None,
+            front_face: None,
+            cull_mode: None,
+            unclipped_depth,
+        };
+        let none = wgpu_types::Features::empty();
+        let depth_clip_control = wgpu_types::Features::DEPTH_CLIP_CONTROL;
+        assert!(validate_primitive_state(None, none).is_ok());
+        assert!(validate_primitive_state(Some(&primitive(Some(false))), none).is_ok());
+        assert!(validate_primitive_state(Some(&primitive(Some(true))), none).is_err());
+        assert!(validate_primitive_state(Some(&primitive(Some(true))), depth_clip_control).is_ok());
+    }
+
+    #[test]
+    fn multiview_needs_the_multiview_feature() {
+        let none = wgpu_types::Features::empty();
+        let multiview = wgpu_types::Features::MULTIVIEW;
+        assert!(validate_multiview(None, none).is_ok());
+        assert!(validate_multiview(Some(2), none).is_err());
+        assert!(validate_multiview(Some(2), multiview).is_ok());
+        assert!(validate_multiview(Some(0), multiview).is_err());
+    }
+
+    #[test]
+    fn vertex_buffer_slots_stay_within_the_device_and_pipeline() {
+        assert_eq!(validate_vertex_buffer_slot(1, Some(8), Some(2)), Ok(()));
+        assert_eq!(validate_vertex_buffer_slot(5, Some(8), None), Ok(()));
+        assert_eq!(
+            validate_vertex_buffer_slot(8, Some(8), None),
+            Err("slot 8 is past the device's max-vertex-buffers of 8".to_string())
+        );
+        assert_eq!(
+            validate_vertex_buffer_slot(2, Some(8), Some(2)),
+            Err("slot 2 is past the 2 vertex buffers the current pipeline declares".to_string())
+        );
+
+        assert_eq!(
+            validate_vertex_buffers_bound(2, &BTreeSet::from([0, 1])),
+            Ok(())
+        );
+        assert_eq!(
+            validate_vertex_buffers_bound(2, &BTreeSet::from([0])),
+            Err("the pipeline declares 2 vertex buffers, but slot 1 has none bound".to_string())
+        );
+    }
+
+    #[test]
+    fn image_copies_stay_within_their_mip_level() {
+        let descriptor = TextureDescriptor {
+            label: (),
+            size: wgpu_types::Extent3d {
+                width: 8,
+                height: 8,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 2,
+            sample_count: 1,
+            dimension: wgpu_types::TextureDimension::D2,
+            format: wgpu_types::TextureFormat::Rgba8Unorm,
+            usage: wgpu_types::TextureUsages::COPY_DST,
+            view_formats: Vec::new(),
+        };
+        let copy = |mip_level, x, aspect| wgpu_types::ImageCopyTexture {
+            texture: wgpu_core::id::TextureId::zip(0, 1, wgpu_types::Backend::Empty),
+            mip_level,
+            origin: wgpu_types::Origin3d { x, y: 0, z: 0 },
+            aspect,
+        };
+        let size = |width, height| wgpu_types::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let all = wgpu_types::TextureAspect::All;
+        // Mip level 1 of an 8x8 texture is 4x4.
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(1, 0, all),
+            size(4, 4),
+            &descriptor
+        )
+        .is_ok());
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(1, 2, all),
+            size(2, 4),
+            &descriptor
+        )
+        .is_ok());
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(1, 1, all),
+            size(4, 4),
+            &descriptor
+        )
+        .is_err());
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(1, 0, all),
+            size(8, 8),
+            &descriptor
+        )
+        .is_err());
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(0, 0, all),
+            size(8, 8),
+            &descriptor
+        )
+        .is_ok());
+        let error =
+            validate_image_copy_texture("destination", &copy(2, 0, all), size(1, 1), &descriptor)
+                .unwrap_err();
+        assert!(error.contains("mip-level"), "{error}");
+        let depth = wgpu_types::TextureAspect::DepthOnly;
+        assert!(validate_image_copy_texture(
+            "destination",
+            &copy(0, 0, depth),
+            size(1, 1),
+            &descriptor
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn indirect_dispatches_need_three_counts_in_an_indirect_buffer() {
+        let indirect = wgpu_types::BufferUsages::INDIRECT;
+        assert_eq!(validate_indirect_dispatch(indirect, 12, 0), Ok(()));
+        assert_eq!(validate_indirect_dispatch(indirect, 16, 4), Ok(()));
+        assert!(validate_indirect_dispatch(indirect, 12, 4).is_err());
+        assert!(validate_indirect_dispatch(indirect, 16, 2).is_err());
+        assert!(validate_indirect_dispatch(indirect, 8, 0).is_err());
+        assert!(validate_indirect_dispatch(indirect, 12, u64::MAX - 3).is_err());
+        assert_eq!(
+            validate_indirect_dispatch(wgpu_types::BufferUsages::STORAGE, 12, 0),
+            Err("indirect buffer needs indirect usage".to_string())
+        );
+    }
+}
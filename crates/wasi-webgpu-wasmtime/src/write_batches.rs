@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use wgpu_core::global::Global;
+use wgpu_core::id::{BufferId, QueueId};
+
+use crate::BufferMapping;
+
+/// Holds on to each queue's buffer writes until it's submitted.
+///
+/// wgpu-core stages and copies every `queue_write_buffer` call on its own, so
+/// a guest filling a buffer piece by piece, e.g. a uniform per object, pays for
+/// a copy per piece. Writes are kept here instead, with a write that touches or
+/// overlaps the previous one to the same buffer merged into it, and handed to
+/// wgpu right before the queue is submitted. Mapping a buffer flushes first, so
+/// the guest never sees a buffer without its earlier writes.
+///
+/// Pending writes hold on to their buffer, so a guest dropping it before the
+/// submit doesn't lose them, and destroying a buffer flushes its writes first.
+#[derive(Default)]
+pub struct WriteBatches {
+    queues: Mutex<HashMap<QueueId, Vec<PendingWrite>>>,
+    merged: AtomicU64,
+    copies: AtomicU64,
+}
+
+struct PendingWrite {
+    buffer: Arc<BufferMapping>,
+    offset: u64,
+    data: Vec<u8>,
+}
+
+impl PendingWrite {
+    fn buffer_id(&self) -> BufferId {
+        self.buffer.buffer.id
+    }
+
+    fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+
+    /// Takes in a later write to `offset` if it touches or overlaps this one.
+    fn merge(&mut self, offset: u64, data: &[u8]) -> bool {
+        let end = offset + data.len() as u64;
+        if offset > self.end() || end < self.offset {
+            return false;
+        }
+        if offset < self.offset {
+            let mut merged = vec![0; (self.offset - offset) as usize];
+            merged.append(&mut self.data);
+            self.data = merged;
+            self.offset = offset;
+        }
+        if end > self.end() {
+            self.data.resize((end - self.offset) as usize, 0);
+        }
+        let start = (offset - self.offset) as usize;
+        self.data[start..start + data.len()].copy_from_slice(data);
+        true
+    }
+}
+
+impl WriteBatches {
+    /// Writes that were merged into an earlier one, saving a copy each.
+    pub fn merged(&self) -> u64 {
+        self.merged.load(Ordering::Relaxed)
+    }
+
+    /// Writes handed to wgpu, each a copy.
+    pub fn copies(&self) -> u64 {
+        self.copies.load(Ordering::Relaxed)
+    }
+
+    /// Queues writing `data` to `buffer` at `offset` on the next submit of
+    /// `queue`. The write should have been validated already.
+    pub(crate) fn write(
+        &self,
+        queue: QueueId,
+        buffer: &Arc<BufferMapping>,
+        offset: u64,
+        data: &[u8],
+    ) {
+        let mut queues = self.queues.lock().unwrap();
+        let writes = queues.entry(queue).or_default();
+        if let Some(last) = writes
+            .last_mut()
+            .filter(|last| Arc::ptr_eq(&last.buffer, buffer))
+        {
+            if last.merge(offset, data) {
+                self.merged.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        writes.push(PendingWrite {
+            buffer: Arc::clone(buffer),
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Hands the writes queued for `queue` to wgpu, in order.
+    pub(crate) fn flush(&self, instance: &Global, queue: QueueId) {
+        let writes = self.queues.lock().unwrap().remove(&queue);
+        self.write_all(instance, queue, writes.into_iter().flatten());
+    }
+
+    /// Hands the writes of every queue to wgpu.
+    pub(crate) fn flush_all(&self, instance: &Global) {
+        let queues: Vec<_> = self.queues.lock().unwrap().keys().copied().collect();
+        for queue in queues {
+            self.flush(instance, queue);
+        }
+    }
+
+    /// Hands the writes to `buffer` to wgpu, e.g. before it's destroyed. Writes
+    /// to other buffers stay queued.
+    pub(crate) fn flush_buffer(&self, instance: &Global, buffer: BufferId) {
+        let mut flushed = Vec::new();
+        for (queue, writes) in self.queues.lock().unwrap().iter_mut() {
+            let (to_buffer, rest) = writes
+                .drain(..)
+                .partition(|write| write.buffer_id() == buffer);
+            *writes = rest;
+            flushed.push((*queue, to_buffer));
+        }
+        for (queue, writes) in flushed {
+            self.write_all(instance, queue, writes);
+        }
+    }
+
+    /// Writes were validated when queued, so one failing now is the host's
+    /// fault; it's logged and skipped rather than costing the guest the rest.
+    fn write_all(
+        &self,
+        instance: &Global,
+        queue: QueueId,
+        writes: impl IntoIterator<Item = PendingWrite>,
+    ) {
+        for write in writes {
+            let buffer = write.buffer_id();
+            match instance.queue_write_buffer::<crate::Backend>(
+                queue,
+                buffer,
+                write.offset,
+                &write.data,
+            ) {
+                Ok(()) => {
+                    self.copies.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => tracing::error!("dropped a batched write to {buffer:?}: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::OnceLock;
+
+    use super::*;
+    use crate::test_gpu::TestGpu;
+    use crate::Freed;
+
+    fn queue() -> QueueId {
+        QueueId::zip(0, 1, wgpu_types::Backend::Empty)
+    }
+
+    /// Buffers that are never freed, as there's nothing behind their ids.
+    fn buffer(index: u32) -> Arc<BufferMapping> {
+        static INSTANCE: OnceLock<Arc<Global>> = OnceLock::new();
+        let instance = INSTANCE.get_or_init(|| {
+            Arc::new(Global::new(
+                "write-batches-test",
+                wgpu_types::InstanceDescriptor {
+                    backends: wgpu_types::Backends::empty(),
+                    ..Default::default()
+                },
+            ))
+        });
+        let id = BufferId::zip(index, 1, wgpu_types::Backend::Empty);
+        Arc::new(BufferMapping {
+            buffer: Freed::new(Arc::clone(instance), id, |_, _| {}),
+            range: Mutex::new(None),
+        })
+    }
+
+    /// The writes pending on the test queue, as (offset, data).
+    fn pending(batches: &WriteBatches) -> Vec<(u64, Vec<u8>)> {
+        batches.queues.lock().unwrap()[&queue()]
+            .iter()
+            .map(|write| (write.offset, write.data.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn adjacent_writes_merge() {
+        let batches = WriteBatches::default();
+        let a = buffer(0);
+        batches.write(queue(), &a, 0, &[1, 2]);
+        batches.write(queue(), &a, 2, &[3, 4]);
+        assert_eq!(pending(&batches), [(0, vec![1, 2, 3, 4])]);
+        assert_eq!(batches.merged(), 1);
+    }
+
+    #[test]
+    fn overlapping_writes_merge_with_the_later_one_winning() {
+        let batches = WriteBatches::default();
+        let a = buffer(0);
+        batches.write(queue(), &a, 2, &[1, 2, 3]);
+        batches.write(queue(), &a, 3, &[4, 5, 6]);
+        assert_eq!(pending(&batches), [(2, vec![1, 4, 5, 6])]);
+        // Reaching in front of the earlier write.
+        batches.write(queue(), &a, 0, &[7, 8, 9]);
+        assert_eq!(pending(&batches), [(0, vec![7, 8, 9, 4, 5, 6])]);
+        // Inside it.
+        batches.write(queue(), &a, 1, &[0]);
+        assert_eq!(pending(&batches), [(0, vec![7, 0, 9, 4, 5, 6])]);
+        assert_eq!(batches.merged(), 3);
+    }
+
+    #[test]
+    fn disjoint_writes_stay_apart() {
+        let batches = WriteBatches::default();
+        let a = buffer(0);
+        batches.write(queue(), &a, 0, &[1, 2]);
+        batches.write(queue(), &a, 3, &[3, 4]);
+        assert_eq!(pending(&batches), [(0, vec![1, 2]), (3, vec![3, 4])]);
+        assert_eq!(batches.merged(), 0);
+    }
+
+    #[test]
+    fn writes_to_other_buffers_stay_apart() {
+        let batches = WriteBatches::default();
+        let a = buffer(0);
+        let b = buffer(1);
+        batches.write(queue(), &a, 0, &[1, 2]);
+        batches.write(queue(), &b, 2, &[3, 4]);
+        // Only the last write is merged into, so order between buffers holds.
+        batches.write(queue(), &a, 2, &[5, 6]);
+        assert_eq!(
+            pending(&batches),
+            [(0, vec![1, 2]), (2, vec![3, 4]), (2, vec![5, 6])]
+        );
+    }
+
+    /// The naive path copies once per `write_buffer`, so 1000 small writes
+    /// filling a buffer are 1000 copies. Batched, they're one.
+    #[test]
+    fn small_writes_filling_a_buffer_are_one_copy() {
+        let batches = WriteBatches::default();
+        let a = buffer(0);
+        for i in 0..1000u32 {
+            batches.write(queue(), &a, i as u64 * 4, &i.to_le_bytes());
+        }
+        let pending = pending(&batches);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].1.len(), 4000);
+        assert_eq!(&pending[0].1[3996..], &999u32.to_le_bytes());
+        assert_eq!(batches.merged(), 999);
+    }
+
+    #[test]
+    fn pending_writes_keep_their_buffer() {
+        let batches = WriteBatches::default();
+        let freed = Arc::new(AtomicBool::new(false));
+        let mut a = buffer(0);
+        Arc::get_mut(&mut a).unwrap().buffer.on_free = Some(Box::new({
+            let freed = Arc::clone(&freed);
+            move || freed.store(true, Ordering::Relaxed)
+        }));
+        batches.write(queue(), &a, 0, &[1, 2]);
+        drop(a);
+        assert!(!freed.load(Ordering::Relaxed));
+        drop(batches);
+        assert!(freed.load(Ordering::Relaxed));
+    }
+
+    fn gpu_buffer(gpu: &TestGpu, size: u64) -> Arc<BufferMapping> {
+        let usage = wgpu_types::BufferUsages::COPY_DST | wgpu_types::BufferUsages::COPY_SRC;
+        Arc::new(BufferMapping {
+            buffer: Freed::new(
+                Arc::clone(&gpu.instance),
+                gpu.create_buffer(size, usage),
+                |instance, buffer| instance.buffer_drop::<crate::Backend>(buffer, false),
+            ),
+            range: Mutex::new(None),
+        })
+    }
+
+    #[test]
+    fn small_writes_land_in_one_copy() {
+        let Some(gpu) = TestGpu::new() else {
+            return;
+        };
+        let batches = WriteBatches::default();
+        let a = gpu_buffer(&gpu, 4000);
+        for i in 0..1000u32 {
+            batches.write(gpu.queue, &a, i as u64 * 4, &i.to_le_bytes());
+        }
+        batches.flush(&gpu.instance, gpu.queue);
+        assert_eq!(batches.copies(), 1);
+        let expected: Vec<u8> = (0..1000u32).flat_map(u32::to_le_bytes).collect();
+        assert_eq!(gpu.read(a.buffer.id, 0, 4000), expected);
+    }
+
+    #[test]
+    fn flushing_a_buffer_leaves_other_writes_queued() {
+        let Some(gpu) = TestGpu::new() else {
+            return;
+        };
+        let batches = WriteBatches::default();
+        let a = gpu_buffer(&gpu, 4);
+        let b = gpu_buffer(&gpu, 4);
+        batches.write(gpu.queue, &a, 0, &[1, 2, 3, 4]);
+        batches.write(gpu.queue, &b, 0, &[5, 6, 7, 8]);
+        batches.flush_buffer(&gpu.instance, a.buffer.id);
+        assert_eq!(batches.copies(), 1);
+        assert_eq!(gpu.read(a.buffer.id, 0, 4), [1, 2, 3, 4]);
+        assert_eq!(batches.queues.lock().unwrap()[&gpu.queue].len(), 1);
+    }
+}
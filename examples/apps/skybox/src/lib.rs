@@ -76,7 +76,7 @@ impl Guest for ExampleSkybox {
 
             if pollables_res.contains(&5) {
                 frame_listener.get();
-                print(&format!("frame event"));
+                print("frame event");
                 example.render();
             }
         }
@@ -190,7 +190,10 @@ impl Example {
     }
 
     fn init(width: u32, height: u32) -> Self {
-        let device = webgpu::get_gpu().request_adapter(None).request_device(None);
+        let device = webgpu::get_gpu()
+            .request_adapter(None)
+            .request_device(None)
+            .unwrap();
         let canvas = mini_canvas::MiniCanvas::new(mini_canvas::CreateDesc {
             height,
             width,
@@ -255,6 +258,7 @@ impl Example {
                         texture: None,
                         storage_texture: None,
                         external_texture: None,
+                        count: None,
                     },
                     webgpu::GpuBindGroupLayoutEntry {
                         binding: 1,
@@ -268,6 +272,7 @@ impl Example {
                         }),
                         storage_texture: None,
                         external_texture: None,
+                        count: None,
                     },
                     webgpu::GpuBindGroupLayoutEntry {
                         binding: 2,
@@ -279,6 +284,7 @@ impl Example {
                         texture: None,
                         storage_texture: None,
                         external_texture: None,
+                        count: None,
                     },
                 ],
             });
@@ -313,8 +319,10 @@ impl Example {
         });
 
         let sky_pipeline = device.create_render_pipeline(&webgpu::GpuRenderPipelineDescriptor {
-            // label: Some("Sky"),
+            label: Some("Sky".into()),
             layout: Some(&pipeline_layout),
+            cache: None,
+            multiview: None,
             vertex: webgpu::GpuVertexState {
                 module: &shader,
                 entry_point: "vs_sky".into(),
@@ -357,9 +365,11 @@ impl Example {
         });
 
         let entity_pipeline = device.create_render_pipeline(&webgpu::GpuRenderPipelineDescriptor {
-            // label: Some("Entity"),
+            label: Some("Entity".into()),
             // layout: None,
             layout: Some(&pipeline_layout),
+            cache: None,
+            multiview: None,
             vertex: webgpu::GpuVertexState {
                 module: &shader,
                 entry_point: "vs_entity".into(),
@@ -586,7 +596,7 @@ impl Example {
         {
             let rpass = encoder.begin_render_pass(webgpu::GpuRenderPassDescriptor {
                 label: None,
-                color_attachments: vec![webgpu::GpuRenderPassColorAttachment {
+                color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
                     view,
                     resolve_target: None,
                     load_op: webgpu::GpuLoadOp::Clear,
@@ -598,7 +608,7 @@ impl Example {
                         b: 0.3,
                         a: 1.0,
                     })),
-                }],
+                })],
                 depth_stencil_attachment: Some(webgpu::GpuRenderPassDepthStencilAttachment {
                     view: &self.depth_view,
                     depth_load_op: Some(webgpu::GpuLoadOp::Clear),
@@ -782,8 +792,8 @@ fn physical_size(extend: &webgpu::GpuExtent3DDict) -> webgpu::GpuExtent3DDict {
     // let (block_width, block_height) = format.block_dimensions();
     let (block_width, block_height) = (1, 1);
 
-    let width = ((extend.width + block_width - 1) / block_width) * block_width;
-    let height = ((extend.height.unwrap() + block_height - 1) / block_height) * block_height;
+    let width = extend.width.div_ceil(block_width) * block_width;
+    let height = extend.height.unwrap().div_ceil(block_height) * block_height;
 
     webgpu::GpuExtent3DDict {
         width,
@@ -816,6 +826,7 @@ fn device_create_buffer_init(
             size: 0,
             usage: descriptor.usage.bits(),
             mapped_at_creation: Some(false),
+            clear_on_create: None,
         });
         MyBuffer { buffer, size: 0 }
     } else {
@@ -833,6 +844,7 @@ fn device_create_buffer_init(
             size: padded_size,
             usage: descriptor.usage.bits(),
             mapped_at_creation: Some(true),
+            clear_on_create: None,
         });
 
         let remote_buffer = buffer.get_mapped_range(None, None);
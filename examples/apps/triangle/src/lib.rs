@@ -38,7 +38,7 @@ fn fs_green() -> @location(0) vec4<f32> {
 fn draw_triangle() {
     let gpu = webgpu::get_gpu();
     let adapter = gpu.request_adapter(None);
-    let device = adapter.request_device(None);
+    let device = adapter.request_device(None).unwrap();
 
     let canvas = mini_canvas::MiniCanvas::new(mini_canvas::CreateDesc {
         height: 100,
@@ -116,6 +116,9 @@ fn draw_triangle() {
             depth_stencil: None,
             multisample: None,
             layout: None,
+            cache: None,
+            multiview: None,
+            label: None,
         };
         let render_pipeline = device.create_render_pipeline(&pipeline_description);
         let pollables_res = wasi::io::poll::poll(&pollables);
@@ -148,7 +151,7 @@ fn draw_triangle() {
 
         if pollables_res.contains(&6) {
             frame_listener.get();
-            print(&format!("frame event"));
+            print("frame event");
 
             let graphics_buffer = graphics_context.get_current_buffer();
             let texture = webgpu::GpuTexture::from_graphics_buffer(graphics_buffer);
@@ -156,7 +159,7 @@ fn draw_triangle() {
             let encoder = device.create_command_encoder(None);
             let render_pass_description = webgpu::GpuRenderPassDescriptor {
                 label: Some(String::from("fdsa")),
-                color_attachments: vec![webgpu::GpuRenderPassColorAttachment {
+                color_attachments: vec![Some(webgpu::GpuRenderPassColorAttachment {
                     view,
                     depth_slice: None,
                     resolve_target: None,
@@ -170,7 +173,7 @@ fn draw_triangle() {
                     )),
                     load_op: webgpu::GpuLoadOp::Clear,
                     store_op: webgpu::GpuStoreOp::Store,
-                }],
+                })],
                 depth_stencil_attachment: None,
                 occlusion_query_set: None,
                 timestamp_writes: None,
@@ -0,0 +1,79 @@
+//! The `clock` interface, for timing things outside the frame loop.
+//!
+//! Its monotonic time counts from when the runtime started, as frame event
+//! times do, though those also leave out time a canvas's frames were paused.
+
+use std::time::{Instant, SystemTime};
+
+use crate::example::example::clock::{self, Datetime};
+use crate::HostState;
+
+impl clock::Host for HostState {
+    fn monotonic_now(&mut self) -> u64 {
+        nanos_since(wasi_mini_canvas_wasmtime::time_origin())
+    }
+
+    fn wall_clock_now(&mut self) -> Datetime {
+        wall_clock_now()
+    }
+}
+
+fn nanos_since(start: Instant) -> u64 {
+    start.elapsed().as_nanos() as u64
+}
+
+fn wall_clock_now() -> Datetime {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    Datetime {
+        seconds: now.as_secs(),
+        nanoseconds: now.subsec_nanos(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::HostStateBuilder;
+
+    fn host() -> HostState {
+        HostStateBuilder::new()
+            .backends(wgpu_types::Backends::empty())
+            .build(None)
+            .unwrap()
+    }
+
+    #[test]
+    fn monotonic_time_increases_from_the_frame_time_origin() {
+        let origin = wasi_mini_canvas_wasmtime::time_origin();
+        let mut host = host();
+        let before = origin.elapsed().as_nanos() as u64;
+        let first = clock::Host::monotonic_now(&mut host);
+        std::thread::sleep(Duration::from_millis(2));
+        let second = clock::Host::monotonic_now(&mut host);
+        let after = origin.elapsed().as_nanos() as u64;
+        assert!(before <= first);
+        assert!(second >= first + 2_000_000);
+        assert!(second <= after);
+    }
+
+    #[test]
+    fn monotonic_time_keeps_its_origin_across_guest_starts() {
+        let first = clock::Host::monotonic_now(&mut host());
+        std::thread::sleep(Duration::from_millis(2));
+        // As on a `--watch` reload.
+        let second = clock::Host::monotonic_now(&mut host());
+        assert!(second >= first + 2_000_000);
+    }
+
+    #[test]
+    fn wall_clock_is_past_the_epoch() {
+        let now = clock::Host::wall_clock_now(&mut host());
+        // Some time after 2020.
+        assert!(now.seconds > 1_577_836_800);
+        assert!(now.nanoseconds < 1_000_000_000);
+    }
+}
@@ -0,0 +1,300 @@
+//! Reports which guest call was running when a host function panics.
+//!
+//! Host functions still `unwrap` in a lot of places. `GuestCallLayer` keeps the
+//! interface, function and, with `--trace`, arguments of each guest call in
+//! the span the bindings open around it, so a panic can be reported together
+//! with the guest call that triggered it instead of just a Rust backtrace.
+//!
+//! A panic ends the guest with an error, like a trap would, since wasm can't
+//! carry on from the middle of a call. Other guests keep running.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+};
+
+use futures::FutureExt;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn, layer::Context, registry::LookupSpan, Layer, Registry,
+};
+
+/// Name of the span the bindings open around every import call.
+const GUEST_CALL_SPAN: &str = "wit-bindgen import";
+
+tokio::task_local! {
+    /// The guest call that was running during the last panic of the guest
+    /// running on this task. Guests running side by side each have their own.
+    static PANICKED_CALL: RefCell<Option<String>>;
+}
+
+/// A guest call, kept in the extensions of its span.
+#[derive(Default)]
+struct GuestCall {
+    module: String,
+    function: String,
+    args: Option<String>,
+}
+
+impl fmt::Display for GuestCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.module, self.function)?;
+        if let Some(args) = &self.args {
+            write!(f, " {args}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Visit for GuestCall {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "module" => self.module = value.to_string(),
+            "function" => self.function = value.to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+/// Arguments of the event the bindings emit when a guest call starts.
+#[derive(Default)]
+struct CallArgs {
+    args: String,
+    is_call: bool,
+}
+
+impl Visit for CallArgs {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        use fmt::Write;
+        if field.name() == "message" {
+            self.is_call = format!("{value:?}") == "call";
+            return;
+        }
+        if !self.args.is_empty() {
+            self.args.push(' ');
+        }
+        let _ = write!(self.args, "{}={:?}", field.name(), value);
+    }
+}
+
+/// Keeps each guest call in its span, for the panic hook to find.
+struct GuestCallLayer;
+
+/// A `GuestCallLayer` that only sees the bindings' import spans, and their
+/// `TRACE` events when `capture_args` is set.
+pub fn guest_call_layer<S>(capture_args: bool) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let filter = filter_fn(move |metadata: &Metadata<'_>| match metadata.is_span() {
+        true => metadata.name() == GUEST_CALL_SPAN,
+        false => capture_args && *metadata.level() == Level::TRACE,
+    });
+    GuestCallLayer.with_filter(filter)
+}
+
+impl<S> Layer<S> for GuestCallLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut call = GuestCall::default();
+        attrs.record(&mut call);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(call);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(call) = extensions.get_mut::<GuestCall>() else {
+            return;
+        };
+        let mut args = CallArgs::default();
+        event.record(&mut args);
+        if args.is_call {
+            call.args = Some(args.args);
+        }
+    }
+}
+
+/// The innermost guest call the current span is in.
+fn current_call() -> Option<String> {
+    tracing::Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            let span = registry.span(id)?;
+            span.scope().find_map(|span| {
+                span.extensions()
+                    .get::<GuestCall>()
+                    .map(ToString::to_string)
+            })
+        })
+        .flatten()
+}
+
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let call = current_call();
+        if let Some(call) = &call {
+            log::error!("host panicked during guest call {call}");
+        }
+        // Panics outside `catch_host_panics`, e.g. on the event loop, have no
+        // guest to report them to.
+        let _ = PANICKED_CALL.try_with(|panicked| *panicked.borrow_mut() = call);
+        default_hook(info);
+    }));
+}
+
+/// Runs guest code, turning a panic in a host function into an error that
+/// names the guest call which caused it, so the runtime keeps running.
+pub async fn catch_host_panics<T>(
+    guest_call: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    PANICKED_CALL
+        .scope(RefCell::new(None), async {
+            match AssertUnwindSafe(guest_call).catch_unwind().await {
+                Ok(result) => result,
+                Err(payload) => {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "<non-string panic payload>".to_string());
+                    let call = PANICKED_CALL
+                        .with(|panicked| panicked.borrow_mut().take())
+                        .unwrap_or_else(|| "<unknown host call>".to_string());
+                    Err(anyhow::anyhow!("host panicked in {call}: {message}"))
+                }
+            }
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use tracing_subscriber::layer::SubscriberExt;
+    use wasmtime::{Config, Engine, Linker, Module, Store};
+
+    use super::*;
+
+    fn in_guest_call(capture_args: bool) -> Option<String> {
+        let subscriber = Registry::default().with(guest_call_layer(capture_args));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::span!(
+                Level::TRACE,
+                "wit-bindgen import",
+                module = "gpu",
+                function = "[method]gpu-device.create-buffer",
+            );
+            let _enter = span.enter();
+            tracing::event!(Level::TRACE, size = tracing::field::debug(&16), "call");
+            let _inner = tracing::info_span!("device_create_buffer").entered();
+            current_call()
+        })
+    }
+
+    #[test]
+    fn finds_the_guest_call_from_a_nested_span() {
+        assert_eq!(
+            in_guest_call(true).as_deref(),
+            Some("gpu.[method]gpu-device.create-buffer size=16")
+        );
+    }
+
+    #[test]
+    fn leaves_arguments_out_unless_asked_for() {
+        assert_eq!(
+            in_guest_call(false).as_deref(),
+            Some("gpu.[method]gpu-device.create-buffer")
+        );
+    }
+
+    #[test]
+    fn outside_a_guest_call_there_is_none() {
+        let subscriber = Registry::default().with(guest_call_layer(true));
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("present").entered();
+            assert_eq!(current_call(), None);
+        });
+    }
+
+    /// Runs a guest that calls a host function `function`, which panics.
+    async fn run_panicking_guest(engine: &Engine, function: &'static str) -> anyhow::Result<()> {
+        let module = Module::new(
+            engine,
+            r#"(module
+                (import "host" "boom" (func $boom))
+                (func (export "run") (call $boom)))"#,
+        )?;
+        let mut linker = Linker::new(engine);
+        linker.func_wrap("host", "boom", move || -> () {
+            // What the bindings open around every import call.
+            let _span =
+                tracing::trace_span!("wit-bindgen import", module = "host", function).entered();
+            panic!("{function} failed")
+        })?;
+        let mut store = Store::new(engine, ());
+        catch_host_panics(async {
+            let instance = linker.instantiate_async(&mut store, &module).await?;
+            let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+            run.call_async(&mut store, ()).await
+        })
+        .await
+    }
+
+    fn async_engine() -> Engine {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(install);
+        let mut config = Config::new();
+        config.async_support(true);
+        Engine::new(&config).unwrap()
+    }
+
+    #[test]
+    fn guests_get_an_error_naming_their_own_panicked_call() {
+        let engine = async_engine();
+        let subscriber = Registry::default().with(guest_call_layer(false));
+        let _subscriber = tracing::subscriber::set_default(subscriber);
+
+        let (first, second) = futures::executor::block_on(async {
+            futures::join!(
+                run_panicking_guest(&engine, "create-buffer"),
+                run_panicking_guest(&engine, "create-texture"),
+            )
+        });
+        assert_eq!(
+            first.unwrap_err().to_string(),
+            "host panicked in host.create-buffer: create-buffer failed"
+        );
+        assert_eq!(
+            second.unwrap_err().to_string(),
+            "host panicked in host.create-texture: create-texture failed"
+        );
+    }
+
+    #[test]
+    fn without_trace_the_error_still_names_the_panicked_call() {
+        let engine = async_engine();
+        let _subscriber = tracing::subscriber::set_default(crate::trace::subscriber(false));
+
+        let error = futures::executor::block_on(run_panicking_guest(&engine, "create-buffer"));
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "host panicked in host.create-buffer: create-buffer failed"
+        );
+    }
+}
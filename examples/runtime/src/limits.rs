@@ -0,0 +1,132 @@
+//! Caps on the wasm memory a guest may take up, for running untrusted components.
+
+use wasmtime::ResourceLimiter;
+
+/// Counts all of a store's linear memories and tables against one budget.
+///
+/// A component has a memory per core module, so capping each memory on its
+/// own would let a guest take up a multiple of the budget. Going past the
+/// budget traps, rather than failing the grow, so the guest's error says why.
+pub(crate) struct MemoryLimiter {
+    max: usize,
+    used: usize,
+}
+
+impl MemoryLimiter {
+    pub(crate) fn new(max: usize) -> Self {
+        Self { max, used: 0 }
+    }
+
+    fn grow(&mut self, bytes: usize) -> anyhow::Result<bool> {
+        let used = self.used.saturating_add(bytes);
+        if used > self.max {
+            anyhow::bail!(
+                "guest tried to use {} MiB of wasm memory, past --max-memory-mb of {}",
+                used.div_ceil(1024 * 1024),
+                self.max / (1024 * 1024)
+            );
+        }
+        self.used = used;
+        Ok(true)
+    }
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        self.grow(desired - current)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        // Each element is about a pointer on the host.
+        self.grow((desired - current) as usize * std::mem::size_of::<usize>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::{Config, Engine, Instance, Module, Store, Trap};
+
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+    const PAGE: usize = 64 * 1024;
+
+    #[test]
+    fn grows_count_against_one_budget() {
+        let mut limiter = MemoryLimiter::new(2 * MIB);
+        assert!(limiter.grow(MIB).unwrap());
+        assert!(limiter.grow(MIB).unwrap());
+        let error = limiter.grow(1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "guest tried to use 3 MiB of wasm memory, past --max-memory-mb of 2"
+        );
+        // A failed grow doesn't count.
+        assert_eq!(limiter.used, 2 * MIB);
+    }
+
+    #[test]
+    fn tables_count_a_pointer_per_element() {
+        let mut limiter = MemoryLimiter::new(1024);
+        let elements = (1024 / std::mem::size_of::<usize>()) as u32;
+        assert!(limiter.table_growing(0, elements, None).unwrap());
+        assert!(limiter.table_growing(elements, elements + 1, None).is_err());
+    }
+
+    #[test]
+    fn memory_hungry_guests_trap() {
+        let engine = Engine::default();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (memory 1)
+                (func (export "run")
+                    (drop (memory.grow (i32.const 32)))))"#,
+        )
+        .unwrap();
+        let mut store = Store::new(&engine, MemoryLimiter::new(MIB));
+        store.limiter(|limiter| limiter);
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let run = instance
+            .get_typed_func::<(), ()>(&mut store, "run")
+            .unwrap();
+        let error = run.call(&mut store, ()).unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "guest tried to use 3 MiB of wasm memory, past --max-memory-mb of 1"
+        );
+        assert_eq!(store.data().used, PAGE);
+    }
+
+    #[test]
+    fn busy_loops_run_out_of_fuel() {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).unwrap();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (func (export "run")
+                    (loop (br 0))))"#,
+        )
+        .unwrap();
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(10_000).unwrap();
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let run = instance
+            .get_typed_func::<(), ()>(&mut store, "run")
+            .unwrap();
+        let error = run.call(&mut store, ()).unwrap_err();
+        assert_eq!(error.downcast_ref::<Trap>(), Some(&Trap::OutOfFuel));
+    }
+}
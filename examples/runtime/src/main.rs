@@ -1,37 +1,304 @@
-use std::sync::Arc;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::Context;
 use clap::Parser;
 use futures::executor::block_on;
+use rand::{rngs::StdRng, SeedableRng};
 use wasi_frame_buffer_wasmtime::WasiFrameBufferView;
-use wasi_graphics_context_wasmtime::WasiGraphicsContextView;
-use wasi_mini_canvas_wasmtime::{MiniCanvas, MiniCanvasDesc, WasiMiniCanvasView};
+use wasi_graphics_context_wasmtime::{Color, ColorSpace, FrameTimings, WasiGraphicsContextView};
+use wasi_mini_canvas_wasmtime::{
+    CanvasSize, EventQueueCapacities, MiniCanvas, MiniCanvasDesc, WasiMiniCanvasView,
+};
 use wasi_webgpu_wasmtime::WasiWebGpuView;
 use wasmtime::{
     component::{Component, Linker},
-    Config, Engine, Store,
+    Config, Engine, Store, Trap,
 };
 
-use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi::{DirPerms, FilePerms, ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+
+mod clock;
+mod crash;
+mod limits;
+mod trace;
 
 #[derive(clap::Parser, Debug)]
 struct RuntimeArgs {
-    /// The example name
+    /// The example name. Repeat it, or add --component, to run several examples
+    /// side by side, sharing the GPU and the event loop
+    #[arg(
+        long,
+        value_name = "NAME",
+        required_unless_present_any = ["component", "dump_caps_only"]
+    )]
+    example: Vec<String>,
+
+    /// Run the component at PATH, on top of any --example
+    #[arg(long, value_name = "PATH")]
+    component: Vec<PathBuf>,
+
+    /// Call this export of the example instead of `start`. It must take no arguments and return nothing
+    #[arg(long, value_name = "NAME", default_value = "start")]
+    entry: String,
+
+    /// Log tracing spans around GPU submission and presentation
+    #[arg(long)]
+    trace: bool,
+
+    /// Use this adapter, given as an index or part of its name
+    #[arg(long, value_name = "INDEX|NAME")]
+    adapter: Option<String>,
+
+    /// Color canvases show before the guest presents its first frame, instead
+    /// of whatever the new surface holds
+    #[arg(
+        long,
+        visible_alias = "splash-color",
+        value_name = "R,G,B,A",
+        default_value = "0.1,0.1,0.1,1",
+        value_parser = parse_color
+    )]
+    default_clear_color: Color,
+
+    /// Leave canvases as the new surface has them until the guest's first frame
+    #[arg(long)]
+    no_splash: bool,
+
+    /// Show canvases in extended sRGB linear where the surface supports it, falling back to sRGB
+    #[arg(long)]
+    hdr: bool,
+
+    /// Open windows at exactly this size in physical pixels, whatever size the guest asks for
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_canvas_size)]
+    canvas_size: Option<CanvasSize>,
+
+    /// Only fire frames after a guest requests a redraw or input arrives
+    #[arg(long)]
+    on_demand: bool,
+
+    /// Input events, of each kind, a canvas queues for the guest before dropping
+    /// new ones. More drops less under bursts, but a slow guest then handles
+    /// older input first, adding latency
+    #[arg(long, value_name = "N", default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    input_queue_cap: u32,
+
+    /// Frame events a canvas queues for the guest before dropping new ones.
+    /// Past 1, a slow guest renders frames that are already late
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    frame_queue_cap: u32,
+
+    /// Give the guest read-only access to a host directory, mounted at GUEST_PATH
+    #[arg(long, value_name = "HOST_PATH:GUEST_PATH", value_parser = parse_preopen)]
+    preopen: Vec<Preopen>,
+
+    /// Seed wasi:random with SEED instead of the OS RNG, so runs are reproducible
+    #[arg(long, value_name = "SEED")]
+    random_seed: Option<u64>,
+
+    /// Most queue submissions the GPU may be behind on before submitting blocks
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..))]
+    max_in_flight: u32,
+
+    /// Most memory, in MiB, the guest's buffers and textures may take up.
+    /// Creating one past it fails with an out of memory error
+    #[arg(long, value_name = "MB")]
+    memory_budget_mb: Option<u64>,
+
+    /// Most wasm memory, in MiB, the guest's linear memories and tables may
+    /// take up together. Growing past it traps the guest
+    #[arg(long, value_name = "MB")]
+    max_memory_mb: Option<u64>,
+
+    /// Units of wasm fuel the guest may burn, roughly one per instruction.
+    /// Running out traps the guest
+    #[arg(long, value_name = "N")]
+    fuel: Option<u64>,
+
+    /// Warn about frames the guest takes longer than MS milliseconds to handle
+    #[arg(long, value_name = "MS")]
+    frame_budget_ms: Option<u64>,
+
+    /// Print a summary of frame and GPU timings on exit
+    #[arg(long)]
+    profile: bool,
+
+    /// Write each frame's time, from the present before it, to a CSV at PATH on exit
+    #[arg(long, value_name = "PATH")]
+    frametime_csv: Option<PathBuf>,
+
+    /// Exit once N frames have been timed, i.e. after N + 1 presents
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    frames: Option<u64>,
+
+    /// Reload the example, in the same window, whenever its wasm file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// wgpu backends to probe, e.g. "vulkan,gl", or "all".
+    /// Defaults to the platform's primary backend
+    #[arg(long, alias = "backend", value_name = "BACKENDS", value_parser = parse_backends)]
+    backends: Option<wgpu_types::Backends>,
+
+    /// Shader compiler the DX12 backend uses
+    #[arg(long, value_enum, default_value_t = Dx12Compiler::Fxc)]
+    dx12_compiler: Dx12Compiler,
+
+    /// OpenGL ES 3 minor version the GL backend requests
+    #[arg(long, value_enum, default_value_t = GlesMinorVersion::Auto)]
+    gles_minor_version: GlesMinorVersion,
+
+    /// Enable wgpu's debug and validation layers, even in release builds
+    #[arg(long)]
+    gpu_validation: bool,
+
+    /// Print what the adapter is and supports before running anything
     #[arg(long)]
-    example: String,
+    dump_caps: bool,
+
+    /// Print what the adapter is and supports, then exit without running anything
+    #[arg(long)]
+    dump_caps_only: bool,
+
+    /// Worker threads for the async runtime the guest runs on, 0 to run it on
+    /// a single thread. Defaults to one per CPU core
+    #[arg(long, value_name = "N")]
+    worker_threads: Option<usize>,
+}
+
+impl RuntimeArgs {
+    /// What canvases are cleared to before the guest's first frame, if anything.
+    fn splash_color(&self) -> Option<Color> {
+        (!self.no_splash).then_some(self.default_clear_color)
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Dx12Compiler {
+    Fxc,
+    /// Needs dxcompiler.dll and dxil.dll next to the runtime
+    Dxc,
+}
+
+impl From<Dx12Compiler> for wgpu_types::Dx12Compiler {
+    fn from(value: Dx12Compiler) -> Self {
+        match value {
+            Dx12Compiler::Fxc => wgpu_types::Dx12Compiler::Fxc,
+            Dx12Compiler::Dxc => wgpu_types::Dx12Compiler::Dxc {
+                dxil_path: None,
+                dxc_path: None,
+            },
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum GlesMinorVersion {
+    Auto,
+    #[value(name = "0")]
+    Version0,
+    #[value(name = "1")]
+    Version1,
+    #[value(name = "2")]
+    Version2,
+}
+
+impl From<GlesMinorVersion> for wgpu_types::Gles3MinorVersion {
+    fn from(value: GlesMinorVersion) -> Self {
+        match value {
+            GlesMinorVersion::Auto => wgpu_types::Gles3MinorVersion::Automatic,
+            GlesMinorVersion::Version0 => wgpu_types::Gles3MinorVersion::Version0,
+            GlesMinorVersion::Version1 => wgpu_types::Gles3MinorVersion::Version1,
+            GlesMinorVersion::Version2 => wgpu_types::Gles3MinorVersion::Version2,
+        }
+    }
+}
+
+fn parse_backends(s: &str) -> Result<wgpu_types::Backends, String> {
+    if s.trim().eq_ignore_ascii_case("all") {
+        return Ok(wgpu_types::Backends::all());
+    }
+    s.split(',')
+        .map(|name| match name.trim().to_lowercase().as_str() {
+            "vulkan" => Ok(wgpu_types::Backends::VULKAN),
+            "dx12" => Ok(wgpu_types::Backends::DX12),
+            "metal" => Ok(wgpu_types::Backends::METAL),
+            "gl" => Ok(wgpu_types::Backends::GL),
+            other => Err(format!(
+                "unknown backend {other:?}, expected vulkan, dx12, metal, gl or all"
+            )),
+        })
+        .try_fold(wgpu_types::Backends::empty(), |backends, backend| {
+            Ok(backends | backend?)
+        })
+}
+
+#[derive(Clone, Debug)]
+struct Preopen {
+    host_path: PathBuf,
+    guest_path: String,
+}
+
+fn parse_preopen(s: &str) -> Result<Preopen, String> {
+    let (host_path, guest_path) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected HOST_PATH:GUEST_PATH, got {s:?}"))?;
+    let host_path = PathBuf::from(host_path);
+    if !host_path.is_dir() {
+        return Err(format!("{} is not a directory", host_path.display()));
+    }
+    Ok(Preopen {
+        host_path,
+        guest_path: guest_path.to_string(),
+    })
+}
+
+fn parse_canvas_size(s: &str) -> Result<CanvasSize, String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {s:?}"))?;
+    let parse = |n: &str| match n.trim().parse::<u32>() {
+        Ok(0) => Err("size can't be 0".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("{n:?}: {e}")),
+    };
+    Ok(CanvasSize {
+        width: parse(width)?,
+        height: parse(height)?,
+    })
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    let channels = s
+        .split(',')
+        .map(|c| c.trim().parse::<f64>().map_err(|e| format!("{c:?}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    match channels[..] {
+        [r, g, b, a] => Ok(Color { r, g, b, a }),
+        _ => Err(format!("expected 4 channels, got {}", channels.len())),
+    }
 }
 
 wasmtime::component::bindgen!({
     path: "../../wit/",
     world: "example",
+    tracing: true,
     async: {
         only_imports: [],
     },
+    trappable_imports: ["abort"],
     with: {
         "wasi:webgpu/graphics-context": wasi_graphics_context_wasmtime,
         "wasi:webgpu/mini-canvas": wasi_mini_canvas_wasmtime,
         "wasi:webgpu/frame-buffer": wasi_frame_buffer_wasmtime,
         "wasi:webgpu/webgpu": wasi_webgpu_wasmtime,
+        "wasi:clocks": wasmtime_wasi::bindings::clocks,
     },
 });
 
@@ -39,25 +306,169 @@ struct HostState {
     pub table: ResourceTable,
     pub ctx: WasiCtx,
     pub instance: Arc<wgpu_core::global::Global>,
-    pub main_thread_proxy: wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy,
+    /// None where there's no event loop, for components that don't open windows.
+    pub main_thread_proxy: Option<wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy>,
+    pub adapter: Option<wgpu_core::id::AdapterId>,
+    pub default_clear_color: Option<Color>,
+    pub default_color_space: Option<ColorSpace>,
+    pub gpu_worker: wasi_webgpu_wasmtime::GpuWorker,
+    pub device_owners: Arc<wasi_webgpu_wasmtime::DeviceOwners>,
+    pub texture_descriptors: Arc<wasi_webgpu_wasmtime::TextureDescriptors>,
+    pub write_batches: Arc<wasi_webgpu_wasmtime::WriteBatches>,
+    pub bind_group_layout_cache: Arc<wasi_webgpu_wasmtime::BindGroupLayoutCache>,
+    pub in_flight_limit: Option<Arc<wasi_webgpu_wasmtime::InFlightLimit>>,
+    pub memory_budget: Option<Arc<wasi_webgpu_wasmtime::MemoryBudget>>,
+    pub frame_timings: Option<Arc<FrameTimings>>,
+    pub frame_budget: Option<Duration>,
+    pub memory_limiter: Option<limits::MemoryLimiter>,
 }
 
 impl HostState {
-    fn new(main_thread_proxy: wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy) -> Self {
+    /// A `HostState` with the builder's default options.
+    #[allow(dead_code)] // The runtime always sets options from its flags.
+    fn new(
+        main_thread_proxy: wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy,
+    ) -> anyhow::Result<Self> {
+        HostStateBuilder::new().build(Some(main_thread_proxy))
+    }
+}
+
+/// Builds a `HostState`, with control over how the wgpu instance is created.
+struct HostStateBuilder {
+    backends: wgpu_types::Backends,
+    instance_flags: wgpu_types::InstanceFlags,
+    dx12_compiler: wgpu_types::Dx12Compiler,
+    gles_minor_version: wgpu_types::Gles3MinorVersion,
+    preopens: Vec<Preopen>,
+    random_seed: Option<u64>,
+    memory_budget: Option<u64>,
+    instance: Option<Arc<wgpu_core::global::Global>>,
+}
+
+impl HostStateBuilder {
+    fn new() -> Self {
         Self {
+            backends: wasi_webgpu_wasmtime::primary_backends(),
+            instance_flags: wgpu_types::InstanceFlags::from_build_config(),
+            dx12_compiler: wgpu_types::Dx12Compiler::Fxc,
+            gles_minor_version: wgpu_types::Gles3MinorVersion::default(),
+            preopens: Vec::new(),
+            random_seed: None,
+            memory_budget: None,
+            instance: None,
+        }
+    }
+
+    /// Uses `instance` instead of creating a wgpu instance.
+    fn instance(mut self, instance: Option<Arc<wgpu_core::global::Global>>) -> Self {
+        self.instance = instance;
+        self
+    }
+
+    fn instance_descriptor(&self) -> wgpu_types::InstanceDescriptor {
+        wgpu_types::InstanceDescriptor {
+            backends: self.backends,
+            flags: self.instance_flags,
+            dx12_shader_compiler: self.dx12_compiler.clone(),
+            gles_minor_version: self.gles_minor_version,
+        }
+    }
+
+    /// Creates a wgpu instance with the builder's options.
+    fn build_instance(&self) -> wgpu_core::global::Global {
+        let instance_start = Instant::now();
+        let instance = wgpu_core::global::Global::new("webgpu", self.instance_descriptor());
+        log::info!(
+            "created wgpu instance with backends {:?} in {:?}",
+            self.backends,
+            instance_start.elapsed()
+        );
+        instance
+    }
+
+    fn backends(mut self, backends: wgpu_types::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    fn instance_flags(mut self, flags: wgpu_types::InstanceFlags) -> Self {
+        self.instance_flags = flags;
+        self
+    }
+
+    fn dx12_compiler(mut self, compiler: wgpu_types::Dx12Compiler) -> Self {
+        self.dx12_compiler = compiler;
+        self
+    }
+
+    fn gles_minor_version(mut self, version: wgpu_types::Gles3MinorVersion) -> Self {
+        self.gles_minor_version = version;
+        self
+    }
+
+    fn preopens(mut self, preopens: Vec<Preopen>) -> Self {
+        self.preopens = preopens;
+        self
+    }
+
+    /// Makes wasi:random deterministic, for reproducible runs. Never use this
+    /// where the guest needs unpredictable numbers.
+    fn random_seed(mut self, seed: Option<u64>) -> Self {
+        self.random_seed = seed;
+        self
+    }
+
+    /// Caps the bytes the guest's buffers and textures may take up.
+    fn memory_budget(mut self, max_bytes: Option<u64>) -> Self {
+        self.memory_budget = max_bytes;
+        self
+    }
+
+    fn build(
+        self,
+        main_thread_proxy: Option<wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy>,
+    ) -> anyhow::Result<HostState> {
+        let mut ctx = WasiCtxBuilder::new();
+        ctx.inherit_stdio();
+        for preopen in &self.preopens {
+            ctx.preopened_dir(
+                &preopen.host_path,
+                &preopen.guest_path,
+                DirPerms::READ,
+                FilePerms::READ,
+            )
+            .with_context(|| format!("can't preopen {}", preopen.host_path.display()))?;
+        }
+        if let Some(seed) = self.random_seed {
+            ctx.secure_random(StdRng::seed_from_u64(seed));
+            ctx.insecure_random(StdRng::seed_from_u64(seed.wrapping_add(1)));
+            ctx.insecure_random_seed(seed.into());
+        }
+        let instance = match &self.instance {
+            Some(instance) => Arc::clone(instance),
+            None => Arc::new(self.build_instance()),
+        };
+        Ok(HostState {
             table: ResourceTable::new(),
-            ctx: WasiCtxBuilder::new().inherit_stdio().build(),
-            instance: Arc::new(wgpu_core::global::Global::new(
-                "webgpu",
-                wgpu_types::InstanceDescriptor {
-                    backends: wgpu_types::Backends::all(),
-                    flags: wgpu_types::InstanceFlags::from_build_config(),
-                    dx12_shader_compiler: wgpu_types::Dx12Compiler::Fxc,
-                    gles_minor_version: wgpu_types::Gles3MinorVersion::default(),
-                },
-            )),
+            ctx: ctx.build(),
+            instance,
             main_thread_proxy,
-        }
+            adapter: None,
+            default_clear_color: None,
+            default_color_space: None,
+            gpu_worker: wasi_webgpu_wasmtime::GpuWorker::spawn(),
+            device_owners: Default::default(),
+            texture_descriptors: Default::default(),
+            write_batches: Default::default(),
+            bind_group_layout_cache: Default::default(),
+            in_flight_limit: None,
+            memory_budget: self
+                .memory_budget
+                .map(|max| Arc::new(wasi_webgpu_wasmtime::MemoryBudget::new(max))),
+            frame_timings: None,
+            frame_budget: None,
+            memory_limiter: None,
+        })
     }
 }
 
@@ -71,10 +482,23 @@ impl WasiView for HostState {
     }
 }
 
-impl WasiGraphicsContextView for HostState {}
+impl WasiGraphicsContextView for HostState {
+    fn default_clear_color(&self) -> Option<Color> {
+        self.default_clear_color
+    }
+
+    fn default_color_space(&self) -> Option<ColorSpace> {
+        self.default_color_space
+    }
+
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        self.frame_timings.clone()
+    }
+}
 impl WasiFrameBufferView for HostState {}
 
-struct UiThreadSpawner(wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy);
+/// Runs closures in place when there's no event loop, and so no UI thread.
+struct UiThreadSpawner(Option<wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy>);
 
 impl wasi_webgpu_wasmtime::MainThreadSpawner for UiThreadSpawner {
     async fn spawn<F, T>(&self, f: F) -> T
@@ -82,7 +506,10 @@ impl wasi_webgpu_wasmtime::MainThreadSpawner for UiThreadSpawner {
         F: FnOnce() -> T + Send + Sync + 'static,
         T: Send + Sync + 'static,
     {
-        self.0.spawn(f).await
+        match &self.0 {
+            Some(main_thread_proxy) => main_thread_proxy.spawn(f).await,
+            None => f(),
+        }
     }
 }
 
@@ -94,11 +521,54 @@ impl WasiWebGpuView for HostState {
     fn ui_thread_spawner(&self) -> Box<impl wasi_webgpu_wasmtime::MainThreadSpawner + 'static> {
         Box::new(UiThreadSpawner(self.main_thread_proxy.clone()))
     }
+
+    fn adapter(&self) -> Option<wgpu_core::id::AdapterId> {
+        self.adapter
+    }
+
+    fn gpu_worker(&self) -> Option<wasi_webgpu_wasmtime::GpuWorker> {
+        Some(self.gpu_worker.clone())
+    }
+
+    fn device_owners(&self) -> Option<Arc<wasi_webgpu_wasmtime::DeviceOwners>> {
+        Some(Arc::clone(&self.device_owners))
+    }
+
+    fn texture_descriptors(&self) -> Option<Arc<wasi_webgpu_wasmtime::TextureDescriptors>> {
+        Some(Arc::clone(&self.texture_descriptors))
+    }
+
+    fn write_batches(&self) -> Option<Arc<wasi_webgpu_wasmtime::WriteBatches>> {
+        Some(Arc::clone(&self.write_batches))
+    }
+
+    fn bind_group_layout_cache(&self) -> Option<Arc<wasi_webgpu_wasmtime::BindGroupLayoutCache>> {
+        Some(Arc::clone(&self.bind_group_layout_cache))
+    }
+
+    fn in_flight_limit(&self) -> Option<Arc<wasi_webgpu_wasmtime::InFlightLimit>> {
+        self.in_flight_limit.clone()
+    }
+
+    fn memory_budget(&self) -> Option<Arc<wasi_webgpu_wasmtime::MemoryBudget>> {
+        self.memory_budget.clone()
+    }
+
+    fn frame_timings(&self) -> Option<Arc<FrameTimings>> {
+        self.frame_timings.clone()
+    }
 }
 
 impl WasiMiniCanvasView for HostState {
     fn create_canvas(&self, desc: MiniCanvasDesc) -> MiniCanvas {
-        block_on(self.main_thread_proxy.create_window(desc))
+        // Only components that import the windowing interfaces can get here,
+        // and those always run with an event loop.
+        let main_thread_proxy = self.main_thread_proxy.as_ref().unwrap();
+        block_on(main_thread_proxy.create_window(desc))
+    }
+
+    fn frame_budget(&self) -> Option<Duration> {
+        self.frame_budget
     }
 }
 
@@ -106,26 +576,249 @@ impl ExampleImports for HostState {
     fn print(&mut self, s: String) {
         println!("{s}");
     }
+
+    fn abort(&mut self, message: String) -> wasmtime::Result<()> {
+        log::error!(target: "guest", "aborted: {message}");
+        Err(GuestAborted(message).into())
+    }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .init();
+/// The trap a guest stops itself with through `abort`.
+#[derive(Debug)]
+struct GuestAborted(String);
 
-    let args = RuntimeArgs::parse();
+impl std::fmt::Display for GuestAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "guest aborted: {}", self.0)
+    }
+}
 
-    let mut config = Config::default();
-    config.wasm_component_model(true);
-    config.async_support(true);
-    let engine = Engine::new(&config)?;
-    let mut linker: Linker<HostState> = Linker::new(&engine);
+impl std::error::Error for GuestAborted {}
+
+impl wasi::logging::logging::Host for HostState {
+    fn log(&mut self, level: wasi::logging::logging::Level, context: String, message: String) {
+        use wasi::logging::logging::Level;
+        let level = match level {
+            Level::Trace => log::Level::Trace,
+            Level::Debug => log::Level::Debug,
+            Level::Info => log::Level::Info,
+            Level::Warn => log::Level::Warn,
+            // `log` has no level above error.
+            Level::Error | Level::Critical => log::Level::Error,
+        };
+        if context.is_empty() {
+            log::log!(target: "guest", level, "{message}");
+        } else {
+            log::log!(target: "guest", level, "{context}: {message}");
+        }
+    }
+}
+
+/// What's needed to (re)start the example, in a fresh store.
+struct Guest {
+    engine: Engine,
+    args: Arc<RuntimeArgs>,
+    wasm_path: PathBuf,
+    /// The wgpu instance shared with the other components, if there are any.
+    shared_instance: Option<Arc<wgpu_core::global::Global>>,
+    /// Components without windows still running. Once none are, and no
+    /// component has windows, the runtime exits.
+    windowless_running: Arc<AtomicUsize>,
+    /// Set once any component fails, for the runtime's exit code.
+    failed: Arc<AtomicBool>,
+    /// None runs the example without an event loop, which only works for
+    /// components that don't open windows.
+    main_thread_proxy: Option<wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy>,
+    frame_timings: Option<Arc<FrameTimings>>,
+    /// The wgpu instance of the running example, to wait for its GPU work on exit.
+    instance: Arc<Mutex<Weak<wgpu_core::global::Global>>>,
+}
+
+/// A builder for the host state of each component, from the command line.
+fn host_state_builder(args: &RuntimeArgs) -> HostStateBuilder {
+    let mut builder = HostStateBuilder::new()
+        .dx12_compiler(args.dx12_compiler.into())
+        .gles_minor_version(args.gles_minor_version.into())
+        .preopens(args.preopen.clone())
+        .random_seed(args.random_seed)
+        .memory_budget(
+            args.memory_budget_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+        );
+    if let Some(backends) = args.backends {
+        builder = builder.backends(backends);
+    }
+    if args.gpu_validation {
+        builder = builder.instance_flags(wgpu_types::InstanceFlags::debugging());
+    }
+    builder
+}
+
+impl Guest {
+    /// Instantiates the example and runs it on a new task. Everything it
+    /// creates, down to its wgpu instance unless that's shared, is freed once
+    /// the task ends or is aborted.
+    async fn start(&self) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+        let args = &self.args;
+        let mut host_state = host_state_builder(args)
+            .instance(self.shared_instance.clone())
+            .build(self.main_thread_proxy.clone())?;
+        *self.instance.lock().unwrap() = Arc::downgrade(&host_state.instance);
+        if let Some(adapter) = &args.adapter {
+            host_state.adapter = Some(wasi_webgpu_wasmtime::find_adapter(
+                &host_state.instance,
+                adapter,
+            )?);
+        }
+        host_state.default_clear_color = args.splash_color();
+        host_state.default_color_space = args.hdr.then_some(ColorSpace::ExtendedSrgbLinear);
+        host_state.in_flight_limit = Some(Arc::new(wasi_webgpu_wasmtime::InFlightLimit::new(
+            args.max_in_flight,
+        )));
+        host_state.frame_timings = self.frame_timings.clone();
+        host_state.frame_budget = args.frame_budget_ms.map(Duration::from_millis);
+        host_state.memory_limiter = args.max_memory_mb.map(|mb| {
+            limits::MemoryLimiter::new(
+                mb.saturating_mul(1024 * 1024)
+                    .try_into()
+                    .unwrap_or(usize::MAX),
+            )
+        });
+
+        let mut store = Store::new(&self.engine, host_state);
+        if args.watch {
+            store.epoch_deadline_async_yield_and_update(1);
+        }
+        if store.data().memory_limiter.is_some() {
+            store.limiter(|state| state.memory_limiter.as_mut().unwrap());
+        }
+        if let Some(fuel) = args.fuel {
+            store.set_fuel(fuel)?;
+        }
+
+        let component = Component::from_file(&self.engine, &self.wasm_path)
+            .with_context(|| format!("can't load {}", self.wasm_path.display()))?;
+        let windowing = imports_windowing(&self.engine, &component);
+        let linker = linker(&self.engine, windowing)?;
+
+        // Runs the component's start function, which is guest code too.
+        let instance = crash::catch_host_panics(linker.instantiate_async(&mut store, &component))
+            .await
+            .context("can't instantiate the example")?;
+        let entry = instance
+            .get_func(&mut store, &args.entry)
+            .with_context(|| format!("the example has no `{}` export", args.entry))?
+            .typed::<(), ()>(&store)
+            .with_context(|| {
+                format!(
+                    "`{}` can't be an entry, it must take no arguments and return nothing",
+                    args.entry
+                )
+            })?;
+
+        // Nothing is left to show once the components without windows are
+        // done, unless another one has windows.
+        let fuel = args.fuel;
+        let failed = Arc::clone(&self.failed);
+        let exit_when_done = (!windowing && !args.watch).then(|| {
+            (
+                Arc::clone(&self.windowless_running),
+                self.main_thread_proxy.clone(),
+            )
+        });
+        Ok(tokio::spawn(async move {
+            let call = async {
+                entry.call_async(&mut store, ()).await?;
+                entry.post_return_async(&mut store).await
+            };
+            let result = crash::catch_host_panics(call).await;
+            match &result {
+                // Already logged by `abort`.
+                Err(e) if e.is::<GuestAborted>() => {}
+                Err(e) if e.downcast_ref() == Some(&Trap::OutOfFuel) => {
+                    log::error!(
+                        "guest ran out of fuel, it burned all {} of --fuel",
+                        fuel.unwrap()
+                    );
+                }
+                Err(e) => log::error!("guest failed: {e:?}"),
+                Ok(()) => {}
+            }
+            if result.is_err() {
+                failed.store(true, Ordering::Relaxed);
+            }
+            if let Some((windowless_running, main_thread_proxy)) = exit_when_done {
+                if windowless_running.fetch_sub(1, Ordering::Relaxed) == 1 {
+                    if let Some(main_thread_proxy) = main_thread_proxy {
+                        main_thread_proxy.exit();
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Restarts the example in the same windows whenever its wasm file changes.
+    async fn watch(self, running: tokio::task::JoinHandle<()>) {
+        let wasm_path = self.wasm_path.display().to_string();
+        let modified = || {
+            std::fs::metadata(&wasm_path)
+                .and_then(|m| m.modified())
+                .ok()
+        };
+        let mut last_modified = modified();
+        let mut running = Some(running);
+        loop {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let now = modified();
+            if now.is_none() || now == last_modified {
+                continue;
+            }
+            last_modified = now;
+            log::info!("{wasm_path} changed, reloading");
+            if let Some(running) = running.take() {
+                running.abort();
+                // Waits for the old store to be dropped.
+                let _ = running.await;
+            }
+            if let Some(main_thread_proxy) = &self.main_thread_proxy {
+                main_thread_proxy.release_windows();
+            }
+            match self.start().await {
+                Ok(started) => running = Some(started),
+                // Possibly caught mid-write, try again on the next change.
+                Err(e) => log::error!("can't reload {wasm_path}: {e:?}"),
+            }
+        }
+    }
+}
+
+/// Interfaces only components that open windows import.
+const WINDOWING_INTERFACES: &[&str] = &[
+    "wasi:webgpu/mini-canvas",
+    "wasi:webgpu/animation-frame",
+    "wasi:webgpu/pointer-events",
+    "wasi:webgpu/key-events",
+];
+
+/// Whether `component` imports any of the `WINDOWING_INTERFACES`. Components that
+/// don't, like compute only ones, still instantiate against the `example` world,
+/// since it only needs the imports a component actually has.
+fn imports_windowing(engine: &Engine, component: &Component) -> bool {
+    component.component_type().imports(engine).any(|(name, _)| {
+        WINDOWING_INTERFACES
+            .iter()
+            .any(|interface| name.starts_with(interface))
+    })
+}
+
+/// Links everything the runtime provides, leaving out the windowing interfaces
+/// unless `windowing` is set.
+fn linker(engine: &Engine, windowing: bool) -> anyhow::Result<Linker<HostState>> {
+    let mut linker: Linker<HostState> = Linker::new(engine);
 
     wasi_webgpu_wasmtime::add_to_linker(&mut linker)?;
     wasi_frame_buffer_wasmtime::add_to_linker(&mut linker)?;
     wasi_graphics_context_wasmtime::add_to_linker(&mut linker)?;
-    wasi_mini_canvas_wasmtime::add_to_linker(&mut linker)?;
 
     fn type_annotate<F>(val: F) -> F
     where
@@ -135,27 +828,901 @@ async fn main() -> anyhow::Result<()> {
     }
     let closure = type_annotate::<_>(|t| t);
     Example::add_to_linker_imports_get_host(&mut linker, closure)?;
+    example::example::clock::add_to_linker(&mut linker, |state| state)?;
+    wasi::logging::logging::add_to_linker(&mut linker, |state| state)?;
+
+    // Lets guests read files from the `--preopen` directories.
+    fn wasi_annotate<F>(val: F) -> F
+    where
+        F: Fn(&mut HostState) -> &mut HostState,
+    {
+        val
+    }
+    let closure = wasi_annotate(|t| t);
+    if windowing {
+        // Also links wasi:io/poll and wasi:io/streams.
+        wasi_mini_canvas_wasmtime::add_to_linker(&mut linker)?;
+    } else {
+        wasmtime_wasi::bindings::io::poll::add_to_linker_get_host(&mut linker, closure)?;
+        wasmtime_wasi::bindings::io::streams::add_to_linker_get_host(&mut linker, closure)?;
+    }
+    wasmtime_wasi::bindings::filesystem::types::add_to_linker_get_host(&mut linker, closure)?;
+    wasmtime_wasi::bindings::filesystem::preopens::add_to_linker_get_host(&mut linker, closure)?;
+    wasmtime_wasi::bindings::io::error::add_to_linker_get_host(&mut linker, closure)?;
+    // Backed by the host's clocks, like the runtime's own `clock` interface.
+    wasmtime_wasi::bindings::clocks::monotonic_clock::add_to_linker_get_host(&mut linker, closure)?;
+    wasmtime_wasi::bindings::clocks::wall_clock::add_to_linker_get_host(&mut linker, closure)?;
+    // Entropy for `getrandom` and the like, see `--random-seed`.
+    wasmtime_wasi::bindings::random::random::add_to_linker_get_host(&mut linker, closure)?;
+    wasmtime_wasi::bindings::random::insecure::add_to_linker_get_host(&mut linker, closure)?;
+    wasmtime_wasi::bindings::random::insecure_seed::add_to_linker_get_host(&mut linker, closure)?;
+    Ok(linker)
+}
+
+fn async_runtime(worker_threads: Option<usize>) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = match worker_threads {
+        Some(0) => tokio::runtime::Builder::new_current_thread(),
+        Some(worker_threads) => {
+            let mut builder = tokio::runtime::Builder::new_multi_thread();
+            builder.worker_threads(worker_threads);
+            builder
+        }
+        None => tokio::runtime::Builder::new_multi_thread(),
+    };
+    builder.enable_all().build()
+}
+
+/// The capabilities of the adapter guests will get, as `--dump-caps` prints them.
+fn dump_caps(args: &RuntimeArgs) -> anyhow::Result<String> {
+    let instance = host_state_builder(args).build_instance();
+    let adapter = match &args.adapter {
+        Some(adapter) => wasi_webgpu_wasmtime::find_adapter(&instance, adapter)?,
+        None => wasi_webgpu_wasmtime::default_adapter(&instance)?,
+    };
+    wasi_webgpu_wasmtime::capability_report(&instance, adapter)
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let args = RuntimeArgs::parse();
+    // Clock and frame event times count from here.
+    wasi_mini_canvas_wasmtime::time_origin();
 
-    let (main_thread_loop, main_thread_proxy) =
+    trace::install(args.trace);
+    crash::install();
+
+    if args.dump_caps || args.dump_caps_only {
+        println!("{}", dump_caps(&args)?);
+        if args.dump_caps_only {
+            return Ok(());
+        }
+    }
+
+    let runtime = async_runtime(args.worker_threads)?;
+    // The winit event loop has to be made and run on the main thread.
+    let (mut main_thread_loop, main_thread_proxy) =
         wasi_mini_canvas_wasmtime::create_wasi_winit_event_loop();
-    let host_state = HostState::new(main_thread_proxy);
+    main_thread_loop.set_on_demand(args.on_demand);
+    main_thread_loop.set_canvas_size(args.canvas_size);
+    main_thread_loop.set_queue_capacities(EventQueueCapacities {
+        input: args.input_queue_cap as usize,
+        frame: args.frame_queue_cap as usize,
+    });
+    let frame_timings = match args.frames {
+        Some(frames) => {
+            let main_thread_proxy = main_thread_proxy.clone();
+            Some(FrameTimings::with_frame_limit(frames as usize, move || {
+                main_thread_proxy.exit()
+            }))
+        }
+        None => (args.profile || args.frametime_csv.is_some()).then(FrameTimings::new),
+    }
+    .map(Arc::new);
+    let profile = args.profile;
+    let frametime_csv = args.frametime_csv.clone();
 
-    let mut store = Store::new(&engine, host_state);
+    let (instances, failed) =
+        runtime.block_on(start(args, main_thread_proxy, frame_timings.clone()))?;
+    // The event loop takes over the main thread, so tasks need another thread
+    // to run on. Only a current-thread runtime needs it, a multi-threaded one
+    // runs them on its workers anyway, but it also keeps the runtime alive.
+    std::thread::spawn(move || runtime.block_on(std::future::pending::<()>()));
 
-    let wasm_path = format!("./target/example-{}.wasm", args.example);
+    // Returns once the window is closed or on ctrl-c.
+    main_thread_loop.run();
 
-    let component =
-        Component::from_file(&engine, &wasm_path).context("Component file not found")?;
+    shut_down(&instances, frame_timings, profile, frametime_csv);
 
-    let (instance, _) = Example::instantiate_async(&mut store, &component, &linker)
-        .await
-        .unwrap();
+    // The guest may be blocked in a host call that never returns now, so
+    // don't wait for it to finish.
+    std::process::exit(if failed.load(Ordering::Relaxed) { 1 } else { 0 })
+}
+
+/// The engine every guest runs on.
+fn engine(args: &RuntimeArgs) -> anyhow::Result<Engine> {
+    let mut config = Config::default();
+    config.wasm_component_model(true);
+    config.async_support(true);
+    // Lets a reload stop the guest even while it's busy running wasm.
+    config.epoch_interruption(args.watch);
+    config.consume_fuel(args.fuel.is_some());
+    Engine::new(&config)
+}
+
+/// Starts the guests, each in its own store, and the tasks that watch them and
+/// ctrl-c. Returns where each guest's wgpu instance will be, and a flag set
+/// once any of them fails.
+async fn start(
+    args: RuntimeArgs,
+    main_thread_proxy: wasi_mini_canvas_wasmtime::WasiWinitEventLoopProxy,
+    frame_timings: Option<Arc<FrameTimings>>,
+) -> anyhow::Result<(
+    Vec<Arc<Mutex<Weak<wgpu_core::global::Global>>>>,
+    Arc<AtomicBool>,
+)> {
+    let wasm_paths: Vec<PathBuf> = args
+        .example
+        .iter()
+        .map(|example| format!("./target/example-{example}.wasm").into())
+        .chain(args.component.iter().cloned())
+        .collect();
+    if args.watch && wasm_paths.len() > 1 {
+        // Reloading releases every window, not only the reloaded guest's.
+        anyhow::bail!("--watch only works with a single example or component");
+    }
+
+    let engine = engine(&args)?;
+
+    // Guests share one wgpu instance when there are several. A single guest
+    // gets its own, so a reload frees everything the old one created.
+    let shared_instance =
+        (wasm_paths.len() > 1).then(|| Arc::new(host_state_builder(&args).build_instance()));
+    let args = Arc::new(args);
+    let windowless_running = Arc::new(AtomicUsize::new(wasm_paths.len()));
+    let failed = Arc::new(AtomicBool::new(false));
+    let mut instances = Vec::new();
+    for wasm_path in wasm_paths {
+        let guest = Guest {
+            engine: engine.clone(),
+            args: Arc::clone(&args),
+            wasm_path,
+            shared_instance: shared_instance.clone(),
+            windowless_running: Arc::clone(&windowless_running),
+            failed: Arc::clone(&failed),
+            main_thread_proxy: Some(main_thread_proxy.clone()),
+            frame_timings: frame_timings.clone(),
+            instance: Default::default(),
+        };
+        instances.push(guest.instance.clone());
+        let running = guest.start().await?;
+        if args.watch {
+            let engine = guest.engine.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(Duration::from_millis(10));
+                engine.increment_epoch();
+            });
+            tokio::spawn(guest.watch(running));
+        }
+    }
 
     tokio::spawn(async move {
-        instance.call_start(&mut store).await.unwrap();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            main_thread_proxy.exit();
+        }
     });
 
-    main_thread_loop.run();
+    Ok((instances, failed))
+}
+
+/// What's left to do once the event loop returned: waiting for the GPU, then
+/// reporting frame times.
+fn shut_down(
+    instances: &[Arc<Mutex<Weak<wgpu_core::global::Global>>>],
+    frame_timings: Option<Arc<FrameTimings>>,
+    profile: bool,
+    frametime_csv: Option<PathBuf>,
+) {
+    wait_for_gpu(instances);
+
+    if let Some(frame_timings) = frame_timings {
+        if profile {
+            print!("{}", frame_timings.summary());
+        }
+        if let Some(path) = frametime_csv {
+            let written = std::fs::File::create(&path)
+                .and_then(|file| frame_timings.write_csv(std::io::BufWriter::new(file)));
+            if let Err(e) = written {
+                log::error!("can't write frame times to {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Lets work the examples already submitted, like frames being captured,
+/// finish rather than be abandoned mid-submission.
+fn wait_for_gpu(instances: &[Arc<Mutex<Weak<wgpu_core::global::Global>>>]) {
+    for instance in instances {
+        let instance = instance.lock().unwrap().upgrade();
+        if let Some(instance) = instance {
+            if let Err(e) = instance.poll_all_devices(true) {
+                log::warn!("couldn't wait for the GPU to finish: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_parse_from_a_list() {
+        assert_eq!(
+            parse_backends("vulkan, GL"),
+            Ok(wgpu_types::Backends::VULKAN | wgpu_types::Backends::GL)
+        );
+        assert_eq!(parse_backends(" all "), Ok(wgpu_types::Backends::all()));
+        assert!(parse_backends("vulkan,webgpu").is_err());
+        assert!(parse_backends("").is_err());
+    }
 
-    Ok(())
+    #[test]
+    fn async_runtimes_get_the_requested_worker_threads() {
+        use tokio::runtime::RuntimeFlavor;
+
+        let runtime = async_runtime(Some(0)).unwrap();
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            RuntimeFlavor::CurrentThread
+        );
+        assert_eq!(runtime.block_on(async { 1 + 1 }), 2);
+
+        let runtime = async_runtime(Some(3)).unwrap();
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            RuntimeFlavor::MultiThread
+        );
+        // Each task blocks its worker until all 3 are running at once.
+        let barrier = Arc::new(std::sync::Barrier::new(3));
+        let threads = (0..3)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                runtime.spawn(async move {
+                    barrier.wait();
+                    std::thread::current().id()
+                })
+            })
+            .collect::<Vec<_>>();
+        let threads = runtime.block_on(async {
+            let mut ids = std::collections::HashSet::new();
+            for thread in threads {
+                ids.insert(thread.await.unwrap());
+            }
+            ids
+        });
+        assert_eq!(threads.len(), 3);
+        // And no more than 3 ever run tasks.
+        let threads = runtime.block_on(async {
+            let tasks = (0..30)
+                .map(|_| {
+                    tokio::spawn(async {
+                        std::thread::sleep(Duration::from_millis(1));
+                        std::thread::current().id()
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut ids = std::collections::HashSet::new();
+            for task in tasks {
+                ids.insert(task.await.unwrap());
+            }
+            ids
+        });
+        assert!(threads.len() <= 3, "{}", threads.len());
+    }
+
+    #[test]
+    fn dump_caps_names_the_adapter_and_its_limits() {
+        use wgpu_types as wgt;
+
+        let args = RuntimeArgs::try_parse_from(["runtime", "--dump-caps-only"]).unwrap();
+        let report = match dump_caps(&args) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("skipping: {e}");
+                return;
+            }
+        };
+        let instance = host_state_builder(&args).build_instance();
+        let adapter = wasi_webgpu_wasmtime::default_adapter(&instance).unwrap();
+        let info = wgpu_core::gfx_select!(adapter => instance.adapter_get_info(adapter)).unwrap();
+        let limits = wgpu_core::gfx_select!(adapter => instance.adapter_limits(adapter)).unwrap();
+        let lines: Vec<_> = report.lines().collect();
+        assert!(
+            lines.contains(&format!("adapter: {}", info.name).as_str()),
+            "{report}"
+        );
+        assert!(
+            lines.contains(&format!("max bind groups: {}", limits.max_bind_groups).as_str()),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn preopens_split_at_the_last_colon() {
+        let temp_dir = std::env::temp_dir();
+        let preopen = parse_preopen(&format!("{}:/data", temp_dir.display())).unwrap();
+        assert_eq!(preopen.host_path, temp_dir);
+        assert_eq!(preopen.guest_path, "/data");
+
+        // Host paths may have colons of their own, like Windows drive letters,
+        // which file names can only have on unix.
+        if cfg!(unix) {
+            let host_path = temp_dir.join(format!("preopen:{}", std::process::id()));
+            std::fs::create_dir_all(&host_path).unwrap();
+            let preopen = parse_preopen(&format!("{}:/data", host_path.display()));
+            std::fs::remove_dir(&host_path).unwrap();
+            assert_eq!(preopen.unwrap().host_path, host_path);
+        }
+    }
+
+    #[test]
+    fn preopens_need_a_guest_path_and_a_directory() {
+        assert!(parse_preopen("assets").is_err());
+        let file = std::env::current_exe().unwrap();
+        assert!(parse_preopen(&format!("{}:/data", file.display())).is_err());
+    }
+
+    #[test]
+    fn canvas_sizes_parse_as_width_x_height() {
+        let size = parse_canvas_size("1280x 720").unwrap();
+        assert_eq!((size.width, size.height), (1280, 720));
+        assert!(parse_canvas_size("1280").is_err());
+        assert!(parse_canvas_size("0x720").is_err());
+        assert!(parse_canvas_size("1280x-1").is_err());
+    }
+
+    #[test]
+    fn colors_need_four_channels() {
+        let color = parse_color("0.1, 0.2,0.3,1").unwrap();
+        assert_eq!((color.r, color.g, color.b, color.a), (0.1, 0.2, 0.3, 1.0));
+        assert_eq!(
+            parse_color("0.1,0.2,0.3").err(),
+            Some("expected 4 channels, got 3".to_string())
+        );
+        assert!(parse_color("0.1,0.2,blue,1").is_err());
+    }
+
+    #[test]
+    fn canvases_clear_to_dark_gray_unless_told_otherwise() {
+        let args = |extra: &[&str]| {
+            let base = ["runtime", "--example", "triangle"];
+            RuntimeArgs::try_parse_from(base.iter().chain(extra)).unwrap()
+        };
+        let splash = |args: RuntimeArgs| {
+            args.splash_color()
+                .map(|color| (color.r, color.g, color.b, color.a))
+        };
+        assert_eq!(splash(args(&[])), Some((0.1, 0.1, 0.1, 1.0)));
+        assert_eq!(
+            splash(args(&["--splash-color", "1,0,0,1"])),
+            Some((1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(splash(args(&["--no-splash"])), None);
+    }
+
+    #[test]
+    fn instance_descriptor_takes_the_builder_options() {
+        let builder = HostStateBuilder::new()
+            .backends(wgpu_types::Backends::GL)
+            .instance_flags(wgpu_types::InstanceFlags::VALIDATION)
+            .dx12_compiler(Dx12Compiler::Dxc.into())
+            .gles_minor_version(wgpu_types::Gles3MinorVersion::Version2);
+        let descriptor = builder.instance_descriptor();
+        assert_eq!(descriptor.backends, wgpu_types::Backends::GL);
+        assert_eq!(descriptor.flags, wgpu_types::InstanceFlags::VALIDATION);
+        assert!(matches!(
+            descriptor.dx12_shader_compiler,
+            wgpu_types::Dx12Compiler::Dxc { .. }
+        ));
+        assert_eq!(
+            descriptor.gles_minor_version,
+            wgpu_types::Gles3MinorVersion::Version2
+        );
+    }
+
+    #[test]
+    fn only_the_selected_backends_are_probed() {
+        let descriptor = |extra: &[&str]| {
+            let base = ["runtime", "--example", "triangle"];
+            let args = RuntimeArgs::try_parse_from(base.iter().chain(extra)).unwrap();
+            host_state_builder(&args).instance_descriptor()
+        };
+        let primary = wasi_webgpu_wasmtime::primary_backends();
+        assert_eq!(primary.bits().count_ones(), 1);
+        assert_eq!(descriptor(&[]).backends, primary);
+        assert_eq!(
+            descriptor(&["--backend", "gl"]).backends,
+            wgpu_types::Backends::GL
+        );
+        assert_eq!(
+            descriptor(&["--backend", "all"]).backends,
+            wgpu_types::Backends::all()
+        );
+        // The instance probes just the one backend.
+        let instance = wgpu_core::global::Global::new("webgpu-test", descriptor(&[]));
+        let adapters = instance.enumerate_adapters(wgpu_core::instance::AdapterInputs::Mask(
+            wgpu_types::Backends::all() - primary,
+            |_| None,
+        ));
+        assert!(adapters.is_empty());
+    }
+
+    #[test]
+    fn fixed_seeds_give_the_same_random_numbers_every_run() {
+        use wasmtime_wasi::bindings::random::{insecure, insecure_seed, random};
+
+        let instance = Arc::new(wgpu_core::global::Global::new(
+            "webgpu-test",
+            wgpu_types::InstanceDescriptor {
+                backends: wgpu_types::Backends::empty(),
+                ..Default::default()
+            },
+        ));
+        let draws = |extra: &[&str]| {
+            let base = ["runtime", "--example", "triangle"];
+            let args = RuntimeArgs::try_parse_from(base.iter().chain(extra)).unwrap();
+            let mut state = host_state_builder(&args)
+                .instance(Some(Arc::clone(&instance)))
+                .build(None)
+                .unwrap();
+            (
+                random::Host::get_random_bytes(&mut state, 16).unwrap(),
+                insecure::Host::get_insecure_random_bytes(&mut state, 16).unwrap(),
+                insecure_seed::Host::insecure_seed(&mut state).unwrap(),
+            )
+        };
+        let seeded = draws(&["--random-seed", "42"]);
+        assert_eq!(draws(&["--random-seed", "42"]), seeded);
+        assert_ne!(draws(&["--random-seed", "43"]), seeded);
+        assert_ne!(draws(&[]), seeded);
+    }
+
+    #[test]
+    fn exiting_waits_for_submitted_gpu_work() {
+        use wgpu_types as wgt;
+
+        let primary = wasi_webgpu_wasmtime::primary_backends();
+        let instance = Arc::new(wgpu_core::global::Global::new(
+            "webgpu-test",
+            wgpu_types::InstanceDescriptor {
+                backends: primary,
+                ..Default::default()
+            },
+        ));
+        let Ok(adapter) = instance.request_adapter(
+            &Default::default(),
+            wgpu_core::instance::AdapterInputs::Mask(primary, |_| None),
+        ) else {
+            eprintln!("skipping: no adapter");
+            return;
+        };
+        let (device, queue, error) = wgpu_core::gfx_select!(adapter => instance.adapter_request_device(
+            adapter,
+            &Default::default(),
+            None,
+            None,
+            None
+        ));
+        assert!(error.is_none(), "{error:?}");
+
+        // Copies 64 KiB from one buffer to another, as a guest's last frame
+        // might still be doing when the window closes.
+        let size = 1 << 16;
+        let buffer = |usage: wgt::BufferUsages| {
+            let (buffer, error) = wgpu_core::gfx_select!(device => instance.device_create_buffer(
+                device,
+                &wgt::BufferDescriptor {
+                    label: None,
+                    size,
+                    usage: usage | wgt::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+                None
+            ));
+            assert!(error.is_none(), "{error:?}");
+            buffer
+        };
+        let source = buffer(wgt::BufferUsages::COPY_SRC);
+        let destination = buffer(wgt::BufferUsages::MAP_READ);
+        let bytes = vec![7; size as usize];
+        wgpu_core::gfx_select!(queue => instance.queue_write_buffer(queue, source, 0, &bytes))
+            .unwrap();
+        let (encoder, error) = wgpu_core::gfx_select!(device =>
+            instance.device_create_command_encoder(device, &Default::default(), None));
+        assert!(error.is_none(), "{error:?}");
+        wgpu_core::gfx_select!(encoder => instance.command_encoder_copy_buffer_to_buffer(
+            encoder,
+            source,
+            0,
+            destination,
+            0,
+            size
+        ))
+        .unwrap();
+        let (command_buffer, error) = wgpu_core::gfx_select!(encoder =>
+            instance.command_encoder_finish(encoder, &Default::default()));
+        assert!(error.is_none(), "{error:?}");
+        wgpu_core::gfx_select!(queue => instance.queue_submit(queue, &[command_buffer])).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+        let on_done = wgpu_core::device::queue::SubmittedWorkDoneClosure::from_rust(Box::new({
+            let done = Arc::clone(&done);
+            move || done.store(true, Ordering::Relaxed)
+        }));
+        wgpu_core::gfx_select!(queue => instance.queue_on_submitted_work_done(queue, on_done))
+            .unwrap();
+
+        shut_down(
+            &[Arc::new(Mutex::new(Arc::downgrade(&instance)))],
+            None,
+            false,
+            None,
+        );
+        assert!(done.load(Ordering::Relaxed));
+    }
+
+    /// A component whose `start` returns, or traps if `traps` is set.
+    fn trivial_component(traps: bool) -> String {
+        let body = if traps { "unreachable" } else { "" };
+        format!(
+            r#"(component
+                (core module $m (func (export "start") {body}))
+                (core instance $i (instantiate $m))
+                (func (export "start") (canon lift (core func $i "start")))
+            )"#
+        )
+    }
+
+    /// A guest for the component at `path`, which must not open a window.
+    fn windowless_guest(path: &std::path::Path, extra: &[&str]) -> Guest {
+        let mut args: Vec<&std::ffi::OsStr> =
+            vec!["runtime".as_ref(), "--component".as_ref(), path.as_os_str()];
+        args.extend(extra.iter().map(std::ffi::OsStr::new));
+        let args = RuntimeArgs::try_parse_from(args).unwrap();
+        Guest {
+            engine: engine(&args).unwrap(),
+            args: Arc::new(args),
+            wasm_path: path.to_path_buf(),
+            shared_instance: None,
+            windowless_running: Arc::new(AtomicUsize::new(1)),
+            failed: Arc::new(AtomicBool::new(false)),
+            main_thread_proxy: None,
+            frame_timings: None,
+            instance: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn compute_only_components_run_without_windowing() {
+        // Calls into wasi:webgpu, but imports nothing that opens a window.
+        let component = r#"(component
+            (import "wasi:webgpu/webgpu" (instance $webgpu
+                (export "gpu" (type (sub resource)))
+                (export "get-gpu" (func (result (own 0))))
+            ))
+            (core func $get-gpu (canon lower (func $webgpu "get-gpu")))
+            (core module $m
+                (import "webgpu" "get-gpu" (func $get-gpu (result i32)))
+                (func (export "start") (drop (call $get-gpu)))
+            )
+            (core instance $i (instantiate $m
+                (with "webgpu" (instance (export "get-gpu" (func $get-gpu))))
+            ))
+            (func (export "start") (canon lift (core func $i "start")))
+        )"#;
+        let path = std::env::temp_dir().join(format!("runtime-compute-{}.wat", std::process::id()));
+        std::fs::write(&path, component).unwrap();
+        let guest = windowless_guest(&path, &[]);
+        let loaded = Component::from_file(&guest.engine, &path).unwrap();
+        assert!(!imports_windowing(&guest.engine, &loaded));
+        // With no event loop to fall back on, any windowing call would panic.
+        let running = guest.start().await;
+        std::fs::remove_file(&path).unwrap();
+        running.unwrap().await.unwrap();
+        assert!(!guest.failed.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn entries_other_than_start_can_be_called() {
+        // `start` traps, so only a guest that ran `run-once` succeeds.
+        let component = r#"(component
+            (core module $m
+                (func (export "start") unreachable)
+                (func (export "run-once"))
+            )
+            (core instance $i (instantiate $m))
+            (func (export "start") (canon lift (core func $i "start")))
+            (func (export "run-once") (canon lift (core func $i "run-once")))
+        )"#;
+        let path = std::env::temp_dir().join(format!("runtime-entry-{}.wat", std::process::id()));
+        std::fs::write(&path, component).unwrap();
+        let run = |entry: &'static str| {
+            let guest = windowless_guest(&path, &["--entry", entry]);
+            async move {
+                let running = guest.start().await?;
+                running.await.unwrap();
+                anyhow::Ok(guest.failed.load(Ordering::Relaxed))
+            }
+        };
+        let run_once = run("run-once").await;
+        let start = run("start").await;
+        let missing = run("run-twice").await;
+        std::fs::remove_file(&path).unwrap();
+        assert!(!run_once.unwrap());
+        assert!(start.unwrap());
+        let error = missing.unwrap_err().to_string();
+        assert!(error.contains("no `run-twice` export"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn guests_read_a_monotonic_clock() {
+        // Traps if the second reading is before the first.
+        let component = r#"(component
+            (import "wasi:clocks/monotonic-clock@0.2.0" (instance $clock
+                (export "now" (func (result u64)))
+            ))
+            (core func $now (canon lower (func $clock "now")))
+            (core module $m
+                (import "clock" "now" (func $now (result i64)))
+                (func (export "start")
+                    (local $first i64)
+                    (local.set $first (call $now))
+                    (if (i64.lt_u (call $now) (local.get $first))
+                        (then unreachable))
+                )
+            )
+            (core instance $i (instantiate $m
+                (with "clock" (instance (export "now" (func $now))))
+            ))
+            (func (export "start") (canon lift (core func $i "start")))
+        )"#;
+        let path = std::env::temp_dir().join(format!("runtime-clocks-{}.wat", std::process::id()));
+        std::fs::write(&path, component).unwrap();
+        let guest = windowless_guest(&path, &[]);
+        let running = guest.start().await;
+        std::fs::remove_file(&path).unwrap();
+        running.unwrap().await.unwrap();
+        assert!(!guest.failed.load(Ordering::Relaxed));
+    }
+
+    /// Keeps the records logged for the guest, for tests to look at.
+    struct GuestLogs(Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for GuestLogs {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "guest"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let entry = (record.level(), record.args().to_string());
+                self.0.lock().unwrap().push(entry);
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static GUEST_LOGS: GuestLogs = GuestLogs(Mutex::new(Vec::new()));
+
+    /// A component that logs `message` at warn, with "guest-ctx" as the context.
+    fn logging_component(message: &str) -> String {
+        format!(
+            r#"(component
+                (import "wasi:logging/logging" (instance $logging
+                    (type (enum "trace" "debug" "info" "warn" "error" "critical"))
+                    (export "level" (type (eq 0)))
+                    (export "log" (func (param "level" 1) (param "context" string) (param "message" string)))
+                ))
+                (core module $memory
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "guest-ctx{message}")
+                )
+                (core instance $memory (instantiate $memory))
+                (alias core export $memory "memory" (core memory $mem))
+                (core func $log (canon lower (func $logging "log") (memory $mem)))
+                (core module $m
+                    (import "logging" "log" (func $log (param i32 i32 i32 i32 i32)))
+                    (func (export "start")
+                        (call $log (i32.const 3) (i32.const 0) (i32.const 9) (i32.const 9) (i32.const {len}))
+                    )
+                )
+                (core instance $i (instantiate $m
+                    (with "logging" (instance (export "log" (func $log))))
+                ))
+                (func (export "start") (canon lift (core func $i "start")))
+            )"#,
+            len = message.len()
+        )
+    }
+
+    #[tokio::test]
+    async fn guest_logs_keep_their_level() {
+        let component = logging_component("hello");
+        if log::set_logger(&GUEST_LOGS).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+        let path = std::env::temp_dir().join(format!("runtime-logging-{}.wat", std::process::id()));
+        std::fs::write(&path, component).unwrap();
+        let guest = windowless_guest(&path, &[]);
+        let running = guest.start().await;
+        std::fs::remove_file(&path).unwrap();
+        running.unwrap().await.unwrap();
+        assert!(!guest.failed.load(Ordering::Relaxed));
+        let logs = GUEST_LOGS.0.lock().unwrap();
+        assert!(
+            logs.contains(&(log::Level::Warn, "guest-ctx: hello".to_string())),
+            "{logs:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn components_run_side_by_side_in_their_own_stores() {
+        if log::set_logger(&GUEST_LOGS).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+        let paths = ["first guest", "second guest"].map(|message| {
+            let path = std::env::temp_dir().join(format!(
+                "runtime-{}-{}.wat",
+                message.replace(' ', "-"),
+                std::process::id()
+            ));
+            std::fs::write(&path, logging_component(message)).unwrap();
+            path
+        });
+        let args = RuntimeArgs::try_parse_from([
+            "runtime".as_ref(),
+            "--component".as_ref(),
+            paths[0].as_os_str(),
+            "--component".as_ref(),
+            paths[1].as_os_str(),
+        ])
+        .unwrap();
+        // Set up the way `start` does for several components.
+        let shared_instance = Arc::new(host_state_builder(&args).build_instance());
+        let args = Arc::new(args);
+        let engine = engine(&args).unwrap();
+        let windowless_running = Arc::new(AtomicUsize::new(paths.len()));
+        let failed = Arc::new(AtomicBool::new(false));
+        let mut instances = Vec::new();
+        let mut running = Vec::new();
+        for path in &paths {
+            let guest = Guest {
+                engine: engine.clone(),
+                args: Arc::clone(&args),
+                wasm_path: path.clone(),
+                shared_instance: Some(Arc::clone(&shared_instance)),
+                windowless_running: Arc::clone(&windowless_running),
+                failed: Arc::clone(&failed),
+                main_thread_proxy: None,
+                frame_timings: None,
+                instance: Default::default(),
+            };
+            instances.push(guest.instance.clone());
+            running.push(guest.start().await);
+        }
+        for path in &paths {
+            std::fs::remove_file(path).unwrap();
+        }
+        for running in running {
+            running.unwrap().await.unwrap();
+        }
+        assert!(!failed.load(Ordering::Relaxed));
+        assert_eq!(windowless_running.load(Ordering::Relaxed), 0);
+        let logs = GUEST_LOGS.0.lock().unwrap();
+        for message in ["first guest", "second guest"] {
+            let entry = (log::Level::Warn, format!("guest-ctx: {message}"));
+            assert!(logs.contains(&entry), "{logs:?}");
+        }
+        // Each got a store of its own, on the one wgpu instance.
+        for instance in instances {
+            let instance = instance.lock().unwrap().upgrade().unwrap();
+            assert!(Arc::ptr_eq(&instance, &shared_instance));
+        }
+    }
+
+    #[tokio::test]
+    async fn aborting_guests_stop_with_their_message() {
+        // Aborts with "first", and would with "second" if it kept going.
+        let component = r#"(component
+            (import "abort" (func $abort (param "message" string)))
+            (core module $memory
+                (memory (export "memory") 1)
+                (data (i32.const 0) "firstsecond")
+            )
+            (core instance $memory (instantiate $memory))
+            (alias core export $memory "memory" (core memory $mem))
+            (core func $abort (canon lower (func $abort) (memory $mem)))
+            (core module $m
+                (import "host" "abort" (func $abort (param i32 i32)))
+                (func (export "start")
+                    (call $abort (i32.const 0) (i32.const 5))
+                    (call $abort (i32.const 5) (i32.const 6))
+                )
+            )
+            (core instance $i (instantiate $m
+                (with "host" (instance (export "abort" (func $abort))))
+            ))
+            (func (export "start") (canon lift (core func $i "start")))
+        )"#;
+        if log::set_logger(&GUEST_LOGS).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+        let path = std::env::temp_dir().join(format!("runtime-abort-{}.wat", std::process::id()));
+        std::fs::write(&path, component).unwrap();
+        let guest = windowless_guest(&path, &[]);
+        let running = guest.start().await;
+        std::fs::remove_file(&path).unwrap();
+        running.unwrap().await.unwrap();
+        assert!(guest.failed.load(Ordering::Relaxed));
+        let logs = GUEST_LOGS.0.lock().unwrap();
+        assert!(
+            logs.contains(&(log::Level::Error, "aborted: first".to_string())),
+            "{logs:?}"
+        );
+        assert!(
+            !logs.iter().any(|(_, message)| message.contains("second")),
+            "{logs:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn watched_components_are_swapped_for_their_new_version() {
+        let path = std::env::temp_dir().join(format!("runtime-watch-{}.wat", std::process::id()));
+        std::fs::write(&path, trivial_component(false)).unwrap();
+        let guest = windowless_guest(&path, &["--watch"]);
+        let failed = Arc::clone(&guest.failed);
+        let running = guest.start().await.unwrap();
+        while !running.is_finished() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!failed.load(Ordering::Relaxed));
+
+        let watching = tokio::spawn(guest.watch(running));
+        // Lets the watch take note of the first version's timestamp.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // The trap shows the new version is the one that ran.
+        std::fs::write(&path, trivial_component(true)).unwrap();
+        // File systems with coarse timestamps could otherwise miss the change.
+        let modified = std::time::SystemTime::now() + Duration::from_secs(2);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(modified))
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !failed.load(Ordering::Relaxed) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        watching.abort();
+        std::fs::remove_file(&path).unwrap();
+        assert!(failed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    #[cfg_attr(not(windows), ignore = "DXC is only used on Windows")]
+    fn builds_an_instance_with_dxc() {
+        let instance = HostStateBuilder::new()
+            .backends(wgpu_types::Backends::DX12)
+            .dx12_compiler(Dx12Compiler::Dxc.into())
+            .build_instance();
+        // wgpu falls back to FXC if the DXC libraries are missing, so this only
+        // checks the instance comes up.
+        let _ = instance.enumerate_adapters(wgpu_core::instance::AdapterInputs::Mask(
+            wgpu_types::Backends::DX12,
+            |_| None,
+        ));
+    }
 }
@@ -0,0 +1,36 @@
+//! Sets up `tracing` output.
+//!
+//! Spans are logged when they close, together with their fields and the time
+//! between creation and close. `--trace` shows `INFO` and up, otherwise only
+//! warnings, like slow frames, are shown.
+//!
+//! The subscriber is always installed, since `crash` keeps the guest call each
+//! import span belongs to in the span itself.
+
+use tracing::Subscriber;
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::format::FmtSpan, layer::SubscriberExt, Layer, Registry,
+};
+
+use crate::crash;
+
+pub fn install(log: bool) {
+    tracing::subscriber::set_global_default(subscriber(log))
+        .expect("tracing subscriber already installed");
+}
+
+pub fn subscriber(log: bool) -> impl Subscriber + Send + Sync {
+    let level = match log {
+        true => LevelFilter::INFO,
+        false => LevelFilter::WARN,
+    };
+    Registry::default()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_span_events(FmtSpan::CLOSE)
+                .with_filter(level),
+        )
+        // Argument values are only formatted with `--trace`, they'd cost every
+        // call otherwise.
+        .with(crash::guest_call_layer(log))
+}